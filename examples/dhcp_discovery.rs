@@ -0,0 +1,56 @@
+use netprobe::dhcp::DhcpDiscoverer;
+use netprobe::setting::ProbeSetting;
+use std::{env, process, thread};
+use xenet::net::interface::Interface;
+
+const USAGE: &str = "USAGE: dhcp_discovery <NETWORK INTERFACE> [-6]";
+
+fn main() {
+    let interface: Interface = match env::args().nth(1) {
+        Some(n) => {
+            // Use interface specified by user
+            let interfaces: Vec<Interface> = xenet::net::interface::get_interfaces();
+            let interface: Interface = interfaces
+                .into_iter()
+                .find(|interface| interface.name == n)
+                .expect("Failed to get interface information");
+            interface
+        }
+        None => {
+            println!("Failed to get default interface");
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+    let setting: ProbeSetting = match env::args().nth(2).as_deref() {
+        Some("-6") => ProbeSetting::dhcpv6_discovery(interface, 1).unwrap(),
+        _ => ProbeSetting::dhcp_discovery(interface, 1).unwrap(),
+    };
+    let discoverer: DhcpDiscoverer = DhcpDiscoverer::new(setting).unwrap();
+    let rx = discoverer.get_progress_receiver();
+    let handle = thread::spawn(move || discoverer.discover());
+    for r in rx.lock().unwrap().iter() {
+        println!(
+            "DHCP Server MAC Addr:{}, IP Addr:{}, Server ID:{}, Offered:{:?}, Lease:{:?}s, RTT:{:?}",
+            r.mac_addr, r.ip_addr, r.server_id, r.offered_addr, r.lease_time, r.rtt
+        );
+        for gateway in &r.gateways {
+            println!("    Gateway {}", gateway);
+        }
+        for dns in &r.dns_servers {
+            println!("    DNS {}", dns);
+        }
+    }
+    match handle.join() {
+        Ok(discovery_result) => match discovery_result {
+            Ok(r) => {
+                if r.multiple_servers {
+                    println!("Multiple DHCP servers found on the link");
+                }
+                println!("DHCP Discovery Result: {:?}", r);
+            }
+            Err(e) => println!("{:?}", e),
+        },
+        Err(e) => println!("{:?}", e),
+    }
+}
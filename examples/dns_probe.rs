@@ -0,0 +1,81 @@
+use hickory_resolver::proto::rr::RecordType;
+use netprobe::dns::probe::DnsProber;
+use netprobe::result::{DnsRecord, DnsResponse};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::{env, process, thread};
+
+const USAGE: &str = "USAGE: dns_probe <SERVER IP> <NAME> [RECORD TYPE] [PORT] [tcp]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let server: IpAddr = args[1].parse().expect("Invalid IP address");
+    let record_type: RecordType = match args.get(3) {
+        Some(record_type) => {
+            RecordType::from_str(&record_type.to_uppercase()).expect("Invalid record type")
+        }
+        None => RecordType::A,
+    };
+    let mut prober: DnsProber = DnsProber::new(server, &args[2])
+        .unwrap()
+        .with_record_type(record_type)
+        .with_tcp_only(args.get(5).is_some_and(|t| t == "tcp"));
+    if let Some(port) = args.get(4) {
+        prober = prober.with_port(port.parse().expect("Invalid port"));
+    }
+    let rx = prober.get_progress_receiver();
+    let handle = thread::spawn(move || prober.probe());
+    for r in rx.lock().unwrap().iter() {
+        print_response(&r);
+    }
+    match handle.join() {
+        Ok(Ok(result)) => println!(
+            "{} queries, {} responses, {:.1}% loss, RTT min/avg/max {:?}/{:?}/{:?}",
+            result.stat.transmitted_count,
+            result.stat.received_count,
+            result.stat.loss_rate(),
+            result.stat.min,
+            result.stat.avg,
+            result.stat.max
+        ),
+        Ok(Err(e)) => println!("{}", e),
+        Err(e) => println!("{:?}", e),
+    }
+}
+
+fn print_response(r: &DnsResponse) {
+    println!(
+        "seq={} {}:{} {:?} id={} RTT:{:?} {} [{}] {} bytes{} ({})",
+        r.seq,
+        r.ip_addr,
+        r.port,
+        r.protocol,
+        r.id,
+        r.rtt,
+        r.rcode_name,
+        r.flags.join(" "),
+        r.received_packet_size,
+        if r.truncated { " truncated" } else { "" },
+        r.probe_status.kind.name()
+    );
+    for (section, records) in [
+        ("ANSWER", &r.answers),
+        ("AUTHORITY", &r.authorities),
+        ("ADDITIONAL", &r.additionals),
+    ] {
+        for record in records.iter() {
+            print_record(section, record);
+        }
+    }
+}
+
+fn print_record(section: &str, record: &DnsRecord) {
+    println!(
+        "    {} {} {} {} {} {}",
+        section, record.name, record.ttl, record.class, record.record_type, record.data
+    );
+}
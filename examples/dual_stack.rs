@@ -0,0 +1,111 @@
+use netprobe::dns::{AddressPolicy, TargetResolver};
+use netprobe::dualstack::DualStackProber;
+use netprobe::result::{PingStat, ProbeResult};
+use std::{env, process, thread};
+
+const USAGE: &str = "USAGE: dual_stack <HOST NAME> [PORT]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let port: u16 = match args.get(2) {
+        Some(port) => port.parse().expect("Invalid port"),
+        None => 443,
+    };
+    for policy in [
+        AddressPolicy::Ipv4Only,
+        AddressPolicy::Ipv6Only,
+        AddressPolicy::PreferIpv6,
+        AddressPolicy::Rfc6724,
+        AddressPolicy::HappyEyeballs,
+    ] {
+        match TargetResolver::new(&args[1], policy)
+            .with_port(port)
+            .resolve()
+        {
+            Ok(target) => println!(
+                "{}: {} (lookup {:?}{}) candidates: {:?}",
+                policy.name(),
+                target.ip_addr,
+                target.lookup_time,
+                target
+                    .connect_time
+                    .map(|t| format!(", connected in {:?}", t))
+                    .unwrap_or_default(),
+                target.addresses
+            ),
+            Err(e) => println!("{}: {}", policy.name(), e),
+        }
+    }
+    let prober: DualStackProber = match DualStackProber::new(&args[1], port) {
+        Ok(prober) => prober,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    let rx = prober.get_progress_receiver();
+    let handle = thread::spawn(move || prober.probe());
+    for r in rx.lock().unwrap().iter() {
+        print_result(&r);
+    }
+    let result = match handle.join() {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+        Err(e) => {
+            println!("{:?}", e);
+            process::exit(1);
+        }
+    };
+    print_stat(
+        "IPv4",
+        &result.ipv4_stat,
+        result.ipv4_status.message.as_str(),
+    );
+    print_stat(
+        "IPv6",
+        &result.ipv6_stat,
+        result.ipv6_status.message.as_str(),
+    );
+    match (result.faster_addr(), result.rtt_difference_ms()) {
+        (Some(ip_addr), Some(difference)) => {
+            println!("Faster: {} (IPv6 - IPv4 = {:.3} ms)", ip_addr, difference)
+        }
+        (Some(ip_addr), None) => println!("Reachable: {}", ip_addr),
+        _ => println!("No reply"),
+    }
+}
+
+fn print_result(r: &ProbeResult) {
+    println!(
+        "seq={} {}:{} {:?} RTT:{:?} ({}{})",
+        r.seq,
+        r.ip_addr,
+        r.port_number.unwrap_or(0),
+        r.port_status,
+        r.rtt,
+        r.probe_status.kind.name(),
+        if r.probe_status.message.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", r.probe_status.message)
+        }
+    );
+}
+
+fn print_stat(family: &str, stat: &PingStat, message: &str) {
+    if stat.transmitted_count == 0 {
+        println!("{}: not probed ({})", family, message);
+        return;
+    }
+    println!(
+        "{}: {} sent, {} replies, RTT min/avg/max {:?}/{:?}/{:?}",
+        family, stat.transmitted_count, stat.received_count, stat.min, stat.avg, stat.max
+    );
+}
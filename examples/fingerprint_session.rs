@@ -0,0 +1,64 @@
+use default_net::Interface;
+use netprobe::fp::session::{FingerprintReport, FingerprintSession};
+use netprobe::fp::FingerprintType;
+use netprobe::setting::ProbeSetting;
+use std::net::IpAddr;
+use std::{env, process};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        println!("Usage: fingerprint_session <target> [open_port] [closed_port]");
+        process::exit(1);
+    }
+    let dst_ip: IpAddr = match args[1].parse::<IpAddr>() {
+        Ok(ip_addr) => ip_addr,
+        Err(_) => match netprobe::dns::lookup_host_name(args[1].clone()) {
+            Some(ip_addr) => ip_addr,
+            None => {
+                println!("Failed to resolve {}", args[1]);
+                process::exit(1);
+            }
+        },
+    };
+    let open_port: u16 = match args.get(2) {
+        Some(port) => port.parse::<u16>().expect("Invalid port"),
+        None => 80,
+    };
+    let interface: Interface = match Interface::default() {
+        Ok(interface) => interface,
+        Err(e) => {
+            println!("Failed to get default interface: {}", e);
+            process::exit(1);
+        }
+    };
+    let setting: ProbeSetting = ProbeSetting::fingerprinting(
+        interface,
+        dst_ip,
+        Some(open_port),
+        FingerprintType::TcpSynAck,
+    )
+    .unwrap();
+    let mut session: FingerprintSession = FingerprintSession::new(setting).unwrap();
+    if let Some(port) = args.get(3) {
+        session = session.with_closed_port(port.parse::<u16>().expect("Invalid port"));
+    }
+    let report: FingerprintReport = match session.run() {
+        Ok(report) => report,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    for (probe_type, fingerprint) in &report.fingerprints {
+        println!(
+            "{:?}: {:?} rtt: {:?}",
+            probe_type, fingerprint.probe_status.kind, fingerprint.rtt
+        );
+        if let Some(features) = &fingerprint.features {
+            println!("    {:?}", features);
+        }
+    }
+    println!("Replied: {:?}", report.replied());
+    println!("Elapsed time: {:?}", report.elapsed_time);
+}
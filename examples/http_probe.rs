@@ -0,0 +1,57 @@
+use netprobe::http::HttpProber;
+use netprobe::result::HttpResponse;
+use std::{env, process};
+
+const USAGE: &str = "USAGE: http_probe <URL> [GET|HEAD|POST...] [follow]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let prober: HttpProber = HttpProber::new(&args[1])
+        .unwrap()
+        .with_method(args.get(2).map(|m| m.as_str()).unwrap_or("GET"))
+        .with_follow_redirects(args.get(3).is_some_and(|f| f == "follow"));
+    match prober.probe() {
+        Ok(result) => {
+            for response in &result.responses {
+                print_response(response);
+            }
+            println!(
+                "HTTP probe finished in {:?} ({}: {})",
+                result.elapsed_time,
+                result.probe_status.kind.name(),
+                result.probe_status.message
+            );
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+fn print_response(response: &HttpResponse) {
+    println!(
+        "{} ({}:{}) {} {} {}",
+        response.url,
+        response.ip_addr,
+        response.port,
+        response.version,
+        response.status_code,
+        response.reason_phrase
+    );
+    for (name, value) in &response.headers {
+        println!("    {}: {}", name, value);
+    }
+    println!("    body: {} bytes", response.body_size);
+    let timing = &response.timing;
+    println!(
+        "    DNS:{:?} Connect:{:?} Send:{:?} TTFB:{:?} Transfer:{:?} Total:{:?}",
+        timing.dns_lookup,
+        timing.tcp_connect,
+        timing.request_send,
+        timing.first_byte,
+        timing.content_transfer,
+        timing.total
+    );
+}
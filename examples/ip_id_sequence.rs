@@ -0,0 +1,60 @@
+use default_net::Interface;
+use netprobe::fp::sequence::{SequenceProber, SequenceResult};
+use netprobe::fp::FingerprintType;
+use netprobe::setting::ProbeSetting;
+use std::net::IpAddr;
+use std::{env, process};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        println!("Usage: ip_id_sequence <target> [open_port|-] [other_ip...]");
+        process::exit(1);
+    }
+    let dst_ip: IpAddr = args[1].parse::<IpAddr>().expect("Invalid IP address");
+    // "-" to use ICMP echo instead of TCP
+    let port: Option<u16> = match args.get(2) {
+        Some(port) if port == "-" => None,
+        Some(port) => Some(port.parse::<u16>().expect("Invalid port")),
+        None => Some(80),
+    };
+    let additional_targets: Vec<IpAddr> = args
+        .iter()
+        .skip(3)
+        .map(|ip| ip.parse::<IpAddr>().expect("Invalid IP address"))
+        .collect();
+    let interface: Interface = match Interface::default() {
+        Ok(interface) => interface,
+        Err(e) => {
+            println!("Failed to get default interface: {}", e);
+            process::exit(1);
+        }
+    };
+    let setting: ProbeSetting =
+        ProbeSetting::fingerprinting(interface, dst_ip, port, FingerprintType::TcpSynAck).unwrap();
+    let prober: SequenceProber = SequenceProber::new(setting)
+        .unwrap()
+        .with_additional_targets(additional_targets);
+    let result: SequenceResult = match prober.probe() {
+        Ok(result) => result,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    for target in &result.targets {
+        println!(
+            "{} replies: {}/{} IP ID: {} timestamp: {:?} Hz uptime: {:?}",
+            target.ip_addr,
+            target.samples.len(),
+            target.sent_count,
+            target.ip_id.name(),
+            target.timestamp_hz,
+            target.uptime
+        );
+        let ip_ids: Vec<u16> = target.samples.iter().filter_map(|s| s.ip_id).collect();
+        println!("    IP IDs: {:?}", ip_ids);
+    }
+    println!("IP ID: {}", result.ip_id.name());
+    println!("Same host: {:?}", result.same_host);
+}
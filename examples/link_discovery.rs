@@ -0,0 +1,59 @@
+use netprobe::neighbor::LinkDiscoverer;
+use netprobe::setting::ProbeSetting;
+use std::{env, process, thread};
+use xenet::net::interface::Interface;
+
+const USAGE: &str = "USAGE: link_discovery <NETWORK INTERFACE>";
+
+fn main() {
+    let interface: Interface = match env::args().nth(1) {
+        Some(n) => {
+            // Use interface specified by user
+            let interfaces: Vec<Interface> = xenet::net::interface::get_interfaces();
+            let interface: Interface = interfaces
+                .into_iter()
+                .find(|interface| interface.name == n)
+                .expect("Failed to get interface information");
+            interface
+        }
+        None => {
+            println!("Failed to get default interface");
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+    let setting: ProbeSetting = ProbeSetting::link_discovery(interface).unwrap();
+    let discoverer: LinkDiscoverer = LinkDiscoverer::new(setting)
+        .unwrap()
+        .with_stop_on_first(true);
+    let rx = discoverer.get_progress_receiver();
+    let handle = thread::spawn(move || discoverer.discover());
+    for r in rx.lock().unwrap().iter() {
+        println!(
+            "[{:?}] MAC Addr:{}, Chassis ID:{}, Port ID:{}, System Name:{:?}, VLAN:{:?}, Capabilities:{:?}",
+            r.protocol, r.mac_addr, r.chassis_id, r.port_id, r.system_name, r.vlan_id, r.enabled_capabilities
+        );
+        for addr in &r.management_addrs {
+            println!("    Management Addr {}", addr);
+        }
+    }
+    match handle.join() {
+        Ok(discovery_result) => match discovery_result {
+            Ok(r) => {
+                if let Some(switch_port) = r.switch_port() {
+                    println!(
+                        "Connected to {} port {}",
+                        switch_port
+                            .system_name
+                            .clone()
+                            .unwrap_or(switch_port.chassis_id.clone()),
+                        switch_port.port_id
+                    );
+                }
+                println!("Link Discovery Result: {:?}", r);
+            }
+            Err(e) => println!("{:?}", e),
+        },
+        Err(e) => println!("{:?}", e),
+    }
+}
@@ -0,0 +1,70 @@
+use default_net::Interface;
+use netprobe::fp::os::OsFingerprinter;
+use netprobe::fp::FingerprintType;
+use netprobe::setting::ProbeSetting;
+use std::net::IpAddr;
+use std::{env, process};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        println!("Usage: os_fingerprinting <target> [open_port] [interface]");
+        process::exit(1);
+    }
+    let dst_ip: IpAddr = match args[1].parse::<IpAddr>() {
+        Ok(ip_addr) => ip_addr,
+        Err(_) => match netprobe::dns::lookup_host_name(args[1].clone()) {
+            Some(ip_addr) => ip_addr,
+            None => {
+                println!("Failed to resolve {}", args[1]);
+                process::exit(1);
+            }
+        },
+    };
+    let port: u16 = match args.get(2) {
+        Some(port) => port.parse::<u16>().expect("Invalid port"),
+        None => 80,
+    };
+    let interface: Interface = match args.get(3) {
+        Some(n) => {
+            // Use interface specified by user
+            let interfaces: Vec<Interface> = xenet::net::interface::get_interfaces();
+            let interface: Interface = interfaces
+                .into_iter()
+                .find(|interface| &interface.name == n)
+                .expect("Failed to get interface information");
+            interface
+        }
+        None => {
+            // Use default interface
+            match Interface::default() {
+                Ok(interface) => interface,
+                Err(e) => {
+                    println!("Failed to get default interface: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+    };
+    let setting: ProbeSetting =
+        ProbeSetting::fingerprinting(interface, dst_ip, Some(port), FingerprintType::TcpSynAck)
+            .unwrap();
+    let fingerprinter: OsFingerprinter = OsFingerprinter::new(setting).unwrap();
+    match fingerprinter.detect() {
+        Ok(result) => {
+            println!("Signature: {:?}", result.signature);
+            for os_match in result.matches.iter().take(5) {
+                println!(
+                    "{:5.1}% {} ({}) matched: {:?}, mismatched: {:?}",
+                    os_match.confidence * 100.0,
+                    os_match.name,
+                    os_match.class,
+                    os_match.matched_fields,
+                    os_match.mismatched_fields
+                );
+            }
+            println!("Elapsed time: {:?}", result.elapsed_time);
+        }
+        Err(e) => println!("{}", e),
+    }
+}
@@ -0,0 +1,72 @@
+use netprobe::packet::decode::IcmpError;
+use netprobe::setting::ProbeSetting;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use std::{env, process};
+use xenet::net::interface::Interface;
+
+const USAGE: &str = "USAGE: packet_api <NETWORK INTERFACE> <IP ADDRESS>";
+
+// Build a UDP probe with the packet API, send it on our own channel
+// and decode the ICMP error together with the quoted datagram.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let interface: Interface = xenet::net::interface::get_interfaces()
+        .into_iter()
+        .find(|interface| interface.name == args[1])
+        .expect("Failed to get interface information");
+    let dst_ip: IpAddr = args[2].parse().expect("Invalid IP address");
+    let setting: ProbeSetting = ProbeSetting::udp_ping(interface.clone(), dst_ip, 1).unwrap();
+
+    let probe: Vec<u8> = netprobe::packet::udp::build_udp_packet(setting.clone(), None);
+    println!("Built {} byte UDP probe", probe.len());
+
+    let config = xenet::datalink::Config {
+        read_timeout: Some(Duration::from_millis(200)),
+        ..Default::default()
+    };
+    let (mut tx, mut rx) = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+        Ok(_) => panic!("Unknown channel type"),
+        Err(e) => panic!("Failed to create channel: {}", e),
+    };
+    tx.send(&probe);
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(3) {
+        let packet = match rx.next() {
+            Ok(packet) => packet,
+            Err(_) => continue,
+        };
+        let frame = netprobe::packet::decode::parse_frame(packet, &setting);
+        let error: IcmpError = match IcmpError::from_frame(&frame) {
+            Some(error) if error.quotes_probe(&setting) => error,
+            _ => continue,
+        };
+        println!(
+            "ICMP type {} code {} from {} (port unreachable: {})",
+            error.icmp_type,
+            error.icmp_code,
+            error.src_ip,
+            error.is_port_unreachable()
+        );
+        if let Some(quoted) = &error.quoted {
+            println!(
+                "Quoted: {} -> {} proto {} ttl {} id {:?} ports {:?} -> {:?} udp length {:?}",
+                quoted.src_ip,
+                quoted.dst_ip,
+                quoted.next_protocol,
+                quoted.ttl,
+                quoted.ip_id,
+                quoted.src_port,
+                quoted.dst_port,
+                quoted.udp_length
+            );
+        }
+        return;
+    }
+    println!("No ICMP error received");
+}
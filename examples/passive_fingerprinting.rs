@@ -0,0 +1,73 @@
+use netprobe::fp::passive::{PassiveFingerprintResult, PassiveFingerprinter};
+use netprobe::setting::ProbeSetting;
+use std::time::Duration;
+use std::{env, process, thread};
+use xenet::net::interface::Interface;
+
+const USAGE: &str = "USAGE: passive_fingerprinting <NETWORK INTERFACE> [seconds] [port]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let interface: Interface = match args.get(1) {
+        Some(n) => {
+            let interfaces: Vec<Interface> = xenet::net::interface::get_interfaces();
+            interfaces
+                .into_iter()
+                .find(|interface| &interface.name == n)
+                .expect("Failed to get interface information")
+        }
+        None => {
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+    let seconds: u64 = match args.get(2) {
+        Some(seconds) => seconds.parse::<u64>().expect("Invalid duration"),
+        None => 30,
+    };
+    let mut setting: ProbeSetting =
+        ProbeSetting::passive_fingerprinting(interface, Duration::from_secs(seconds)).unwrap();
+    if let Some(port) = args.get(3) {
+        setting = setting.with_dst_port(port.parse::<u16>().expect("Invalid port"));
+    }
+    let fingerprinter: PassiveFingerprinter = PassiveFingerprinter::new(setting).unwrap();
+    let rx = fingerprinter.get_progress_receiver();
+    let handle = thread::spawn(move || fingerprinter.listen());
+    for o in rx.lock().unwrap().iter() {
+        let guess: String = match o.best_match() {
+            Some(m) => format!(
+                "{}{}",
+                m.label.full_name(),
+                if m.fuzzy { " (fuzzy)" } else { "" }
+            ),
+            None => String::from("unknown"),
+        };
+        println!(
+            "[{}] {}:{} -> {}:{} {} distance {} sig {}",
+            o.direction.name(),
+            o.src_ip,
+            o.src_port,
+            o.dst_ip,
+            o.dst_port,
+            guess,
+            o.distance,
+            o.signature.to_p0f_string()
+        );
+    }
+    match handle.join() {
+        Ok(result) => match result {
+            Ok(r) => print_summary(&r),
+            Err(e) => println!("{}", e),
+        },
+        Err(e) => println!("{:?}", e),
+    }
+}
+
+fn print_summary(result: &PassiveFingerprintResult) {
+    println!(
+        "Observed {} signatures in {:?} ({})",
+        result.observations.len(),
+        result.elapsed_time,
+        result.probe_status.kind.name()
+    );
+}
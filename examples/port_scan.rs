@@ -0,0 +1,73 @@
+use netprobe::result::PortScanResult;
+use netprobe::scan::PortScanner;
+use netprobe::setting::{ProbeSetting, ScanType};
+use std::net::IpAddr;
+use std::{env, process, thread};
+use xenet::net::interface::Interface;
+
+const USAGE: &str =
+    "USAGE: port_scan <NETWORK INTERFACE> <IP ADDRESS[,IP ADDRESS...]> <PORTS> [syn|connect|ack|udp]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 4 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let interface: Interface = xenet::net::interface::get_interfaces()
+        .into_iter()
+        .find(|interface| interface.name == args[1])
+        .expect("Failed to get interface information");
+    let targets: Vec<IpAddr> = args[2]
+        .split(',')
+        .map(|ip| ip.parse().expect("Invalid IP address"))
+        .collect();
+    let ports: Vec<u16> = netprobe::scan::parse_ports(&args[3]).expect("Invalid port list");
+    let scan_type: ScanType = match args.get(4).map(|s| s.as_str()) {
+        None | Some("syn") => ScanType::TcpSyn,
+        Some("connect") => ScanType::TcpConnect,
+        Some("ack") => ScanType::TcpAck,
+        Some("udp") => ScanType::Udp,
+        Some(_) => {
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+    let setting: ProbeSetting = ProbeSetting::port_scan(interface, targets[0], scan_type).unwrap();
+    let scanner: PortScanner = PortScanner::new(setting)
+        .unwrap()
+        .with_scan_type(scan_type)
+        .with_targets(targets)
+        .with_ports(ports)
+        .with_rate(1000);
+    let rx = scanner.get_progress_receiver();
+    let handle = thread::spawn(move || scanner.scan());
+    for r in rx.lock().unwrap().iter() {
+        println!(
+            "{}:{} {} RTT:{:?} TTL:{} attempts:{}",
+            r.ip_addr,
+            r.port,
+            r.port_status.name(),
+            r.rtt,
+            r.ttl,
+            r.attempts
+        );
+    }
+    match handle.join() {
+        Ok(Ok(result)) => print_summary(&result),
+        Ok(Err(e)) => println!("{}", e),
+        Err(e) => println!("{:?}", e),
+    }
+}
+
+fn print_summary(result: &PortScanResult) {
+    println!(
+        "{} scan finished in {:?} ({})",
+        result.scan_type.name(),
+        result.elapsed_time,
+        result.probe_status.kind.name()
+    );
+    for host in &result.hosts {
+        println!("{} open: {:?}", host.ip_addr, host.open_ports());
+    }
+}
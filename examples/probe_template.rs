@@ -0,0 +1,97 @@
+use netprobe::fp::{FingerprintType, Fingerprinter};
+use netprobe::ping::Pinger;
+use netprobe::setting::{ProbeSetting, Protocol};
+use netprobe::template::{ProbeTemplate, ReplyMatch, TemplateTcpOption};
+use std::net::IpAddr;
+use std::{env, process, thread};
+use xenet::net::interface::Interface;
+use xenet::packet::tcp::TcpFlags;
+
+const USAGE: &str = "USAGE: probe_template <NETWORK INTERFACE> <IP ADDRESS> [port]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let interface: Interface = xenet::net::interface::get_interfaces()
+        .into_iter()
+        .find(|interface| interface.name == args[1])
+        .expect("Failed to get interface information");
+    let dst_ip: IpAddr = args[2].parse().expect("Invalid IP address");
+    let dst_port: u16 = match args.get(3) {
+        Some(port) => port.parse::<u16>().expect("Invalid port"),
+        None => 80,
+    };
+
+    // SYN with custom TOS, DF and option layout, answered by SYN-ACK or RST
+    let syn: ProbeTemplate = ProbeTemplate::new(Protocol::TCP)
+        .with_ttl(48)
+        .with_tos(0x10)
+        .with_dont_fragment(true)
+        .with_tcp_window(5840)
+        .with_tcp_options(vec![
+            TemplateTcpOption::Mss(1360),
+            TemplateTcpOption::SackPermitted,
+            TemplateTcpOption::Timestamp(0xdead_beef, 0),
+            TemplateTcpOption::Nop,
+            TemplateTcpOption::WindowScale(10),
+        ]);
+    let setting: ProbeSetting =
+        ProbeSetting::tcp_ping(interface.clone(), dst_ip, dst_port, 3).unwrap();
+    let pinger: Pinger = Pinger::new(setting).unwrap().with_template(syn);
+    let rx = pinger.get_progress_receiver();
+    let handle = thread::spawn(move || pinger.ping());
+    for r in rx.lock().unwrap().iter() {
+        let flags: String = match r.reply_frame.as_ref().and_then(|f| f.transport.as_ref()) {
+            Some(transport) => match &transport.tcp {
+                Some(tcp) => netprobe::fp::features::tcp_flag_string(tcp.flags),
+                None => String::from("-"),
+            },
+            None => String::from("-"),
+        };
+        println!(
+            "{} {} Bytes from IP:{}, Port:{:?}, Status:{:?}, Flags:{}, TTL:{}, RTT:{:?}",
+            r.seq,
+            r.received_packet_size,
+            r.ip_addr,
+            r.port_number,
+            r.port_status,
+            flags,
+            r.ttl,
+            r.rtt
+        );
+    }
+    match handle.join() {
+        Ok(Ok(result)) => println!(
+            "Transmitted: {}, Received: {}",
+            result.stat.transmitted_count, result.stat.received_count
+        ),
+        Ok(Err(e)) => println!("{}", e),
+        Err(e) => println!("{:?}", e),
+    }
+
+    // ACK with payload, only a RST counts as the reply
+    let ack: ProbeTemplate = ProbeTemplate::new(Protocol::TCP)
+        .with_tcp_flags(TcpFlags::ACK)
+        .with_tcp_ack(1)
+        .with_payload(b"netprobe".to_vec())
+        .with_reply_match(ReplyMatch::tcp().with_tcp_flags_mask(TcpFlags::RST, TcpFlags::RST));
+    let setting: ProbeSetting = ProbeSetting::fingerprinting(
+        interface,
+        dst_ip,
+        Some(dst_port),
+        FingerprintType::TcpAckOpen,
+    )
+    .unwrap();
+    let fingerprinter: Fingerprinter =
+        Fingerprinter::new(setting, FingerprintType::TcpAckOpen).with_template(ack);
+    let fingerprint = fingerprinter.probe();
+    println!(
+        "ACK probe: {} RTT:{:?} Features:{:?}",
+        fingerprint.probe_status.kind.name(),
+        fingerprint.rtt,
+        fingerprint.features
+    );
+}
@@ -0,0 +1,56 @@
+use netprobe::neighbor::RouterDiscoverer;
+use netprobe::setting::ProbeSetting;
+use std::{env, process, thread};
+use xenet::net::interface::Interface;
+
+const USAGE: &str = "USAGE: router_discovery <NETWORK INTERFACE>";
+
+fn main() {
+    let interface: Interface = match env::args().nth(1) {
+        Some(n) => {
+            // Use interface specified by user
+            let interfaces: Vec<Interface> = xenet::net::interface::get_interfaces();
+            let interface: Interface = interfaces
+                .into_iter()
+                .find(|interface| interface.name == n)
+                .expect("Failed to get interface information");
+            interface
+        }
+        None => {
+            println!("Failed to get default interface");
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+    let setting: ProbeSetting = ProbeSetting::router_discovery(interface, 1).unwrap();
+    let discoverer: RouterDiscoverer = RouterDiscoverer::new(setting).unwrap();
+    let rx = discoverer.get_progress_receiver();
+    let handle = thread::spawn(move || discoverer.discover());
+    for r in rx.lock().unwrap().iter() {
+        println!(
+            "Router MAC Addr:{}, IP Addr:{}, Lifetime:{}s, M:{}, O:{}, MTU:{:?}, RTT:{:?}",
+            r.mac_addr, r.ip_addr, r.router_lifetime, r.managed, r.other_config, r.mtu, r.rtt
+        );
+        for prefix in &r.prefixes {
+            println!(
+                "    Prefix {}/{} valid:{}s preferred:{}s",
+                prefix.prefix, prefix.prefix_len, prefix.valid_lifetime, prefix.preferred_lifetime
+            );
+        }
+        for dns in &r.dns_servers {
+            println!("    RDNSS {}", dns);
+        }
+    }
+    match handle.join() {
+        Ok(discovery_result) => match discovery_result {
+            Ok(r) => {
+                if r.multiple_routers {
+                    println!("Multiple routers found on the link");
+                }
+                println!("Router Discovery Result: {:?}", r);
+            }
+            Err(e) => println!("{:?}", e),
+        },
+        Err(e) => println!("{:?}", e),
+    }
+}
@@ -0,0 +1,64 @@
+use netprobe::result::ServiceInfo;
+use netprobe::service::probes::ServiceDatabase;
+use netprobe::service::ServiceDetector;
+use netprobe::setting::ProbeSetting;
+use std::net::IpAddr;
+use std::time::Duration;
+use std::{env, process, thread};
+
+const USAGE: &str = "USAGE: service_detection <IP ADDRESS> <PORTS> [nmap-service-probes FILE]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let dst_ip: IpAddr = args[1].parse().expect("Invalid IP address");
+    let ports: Vec<u16> = netprobe::scan::parse_ports(&args[2]).expect("Invalid port list");
+    let setting: ProbeSetting = ProbeSetting::new()
+        .with_dst_ip(dst_ip)
+        .with_receive_timeout(Duration::from_secs(2));
+    let mut detector: ServiceDetector = ServiceDetector::new(setting).unwrap().with_ports(ports);
+    if let Some(path) = args.get(3) {
+        let database: ServiceDatabase = ServiceDatabase::load(path).unwrap();
+        println!(
+            "{} probes loaded, {} rules skipped",
+            database.probes.len(),
+            database.skipped_rules
+        );
+        detector = detector.with_database(database);
+    }
+    let rx = detector.get_progress_receiver();
+    let handle = thread::spawn(move || detector.detect());
+    for r in rx.lock().unwrap().iter() {
+        println!(
+            "{}:{} {} RTT:{:?} {}",
+            r.ip_addr,
+            r.port_number.unwrap_or(0),
+            r.port_status.as_ref().map(|s| s.name()).unwrap_or_default(),
+            r.rtt,
+            r.service.as_ref().map(describe).unwrap_or_default()
+        );
+    }
+    match handle.join() {
+        Ok(Ok(result)) => println!(
+            "Service detection finished in {:?} ({})",
+            result.elapsed_time,
+            result.probe_status.kind.name()
+        ),
+        Ok(Err(e)) => println!("{}", e),
+        Err(e) => println!("{:?}", e),
+    }
+}
+
+fn describe(service: &ServiceInfo) -> String {
+    let mut fields: Vec<String> = vec![service.name.clone()];
+    fields.extend(service.product.clone());
+    fields.extend(service.version.clone());
+    fields.extend(service.info.as_ref().map(|i| format!("({})", i)));
+    if service.name == "unknown" {
+        fields.push(format!("banner: {}", service.banner_text()));
+    }
+    format!("{} [probe {}]", fields.join(" "), service.probe)
+}
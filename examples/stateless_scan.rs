@@ -0,0 +1,68 @@
+use netprobe::scan::stateless::StatelessScanner;
+use netprobe::setting::{ProbeSetting, ScanType};
+use std::net::IpAddr;
+use std::{env, process, thread};
+use xenet::net::interface::Interface;
+use xenet::net::ipnet::IpNet;
+
+const USAGE: &str =
+    "USAGE: stateless_scan <NETWORK INTERFACE> <NETWORK[,NETWORK...]> <PORTS> [PACKETS PER SECOND]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 4 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let interface: Interface = xenet::net::interface::get_interfaces()
+        .into_iter()
+        .find(|interface| interface.name == args[1])
+        .expect("Failed to get interface information");
+    // 192.0.2.0/24 or a single address
+    let networks: Vec<IpNet> = args[2]
+        .split(',')
+        .map(|net| match net.split_once('/') {
+            Some((ip, prefix)) => IpNet::new(
+                ip.parse::<IpAddr>().expect("Invalid network"),
+                prefix.parse::<u8>().expect("Invalid prefix length"),
+            ),
+            None => IpNet::from(net.parse::<IpAddr>().expect("Invalid IP address")),
+        })
+        .collect();
+    let ports: Vec<u16> = netprobe::scan::parse_ports(&args[3]).expect("Invalid port list");
+    let rate: u32 = match args.get(4) {
+        Some(rate) => rate.parse().expect("Invalid rate"),
+        None => 1000,
+    };
+    let setting: ProbeSetting =
+        ProbeSetting::port_scan(interface, networks[0].addr(), ScanType::TcpSyn).unwrap();
+    let scanner: StatelessScanner = StatelessScanner::new(setting)
+        .unwrap()
+        .with_networks(networks)
+        .with_ports(ports)
+        .with_rate(rate);
+    let rx = scanner.get_progress_receiver();
+    let handle = thread::spawn(move || scanner.scan());
+    for r in rx.lock().unwrap().iter() {
+        println!(
+            "{}:{} {} TTL:{}",
+            r.ip_addr,
+            r.port,
+            r.port_status.name(),
+            r.ttl
+        );
+    }
+    match handle.join() {
+        Ok(Ok(result)) => println!(
+            "Sent {} probes at {:.0} pps, {} validated replies ({} open), {} invalid, in {:?}",
+            result.sent_count,
+            result.send_rate(),
+            result.validated_count,
+            result.open_count,
+            result.invalid_count,
+            result.elapsed_time
+        ),
+        Ok(Err(e)) => println!("{}", e),
+        Err(e) => println!("{:?}", e),
+    }
+}
@@ -36,13 +36,14 @@ fn main() {
     let handle = thread::spawn(move || pinger.ping());
     for r in rx.lock().unwrap().iter() {
         println!(
-            "{} [{:?}] {} Bytes from IP:{}, Port:{:?}, Status:{:?}, HOP:{}, TTL:{}, RTT:{:?}",
+            "{} [{:?}] {} Bytes from IP:{}, Port:{:?}, Status:{:?}, Reason:{:?}, HOP:{}, TTL:{}, RTT:{:?}",
             r.seq,
             r.protocol,
             r.received_packet_size,
             r.ip_addr,
             r.port_number,
             r.port_status,
+            r.unreachable_reason.map(|reason| reason.name()),
             r.hop,
             r.ttl,
             r.rtt
@@ -0,0 +1,63 @@
+use netprobe::tls::TlsProber;
+use std::{env, process};
+
+const USAGE: &str = "USAGE: tls_probe <HOST> [PORT] [SERVER NAME]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    }
+    let port: u16 = match args.get(2) {
+        Some(port) => port.parse().expect("Invalid port"),
+        None => netprobe::tls::TLS_DEFAULT_PORT,
+    };
+    let mut prober: TlsProber = TlsProber::new(&args[1], port)
+        .unwrap()
+        .with_alpn_protocols(vec![String::from("h2"), String::from("http/1.1")]);
+    if let Some(server_name) = args.get(3) {
+        prober = prober.with_server_name(server_name);
+    }
+    let result = match prober.probe() {
+        Ok(result) => result,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    println!(
+        "{}:{} ({}) {} {} ALPN:{}",
+        result.ip_addr,
+        result.port,
+        result.server_name,
+        result.version,
+        result.cipher_suite,
+        result.alpn_protocol.as_deref().unwrap_or("-")
+    );
+    println!(
+        "DNS:{:?} Connect:{:?} Handshake:{:?}",
+        result.dns_lookup, result.tcp_connect, result.handshake
+    );
+    match &result.verify_error {
+        None if result.verified => println!("Certificate chain verified"),
+        None => println!("Certificate chain not received"),
+        Some(e) => println!("Certificate chain not trusted: {}", e),
+    }
+    for (i, cert) in result.certificates.iter().enumerate() {
+        println!("[{}] Subject: {}", i, cert.subject);
+        println!("    Issuer: {}", cert.issuer);
+        println!("    Serial: {}", cert.serial_number);
+        println!("    SANs: {}", cert.subject_alt_names.join(", "));
+        println!(
+            "    Valid: {} to {} ({} days left)",
+            cert.not_before, cert.not_after, cert.days_to_expiry
+        );
+    }
+    println!(
+        "TLS probe finished in {:?} ({}: {})",
+        result.elapsed_time,
+        result.probe_status.kind.name(),
+        result.probe_status.message
+    );
+}
@@ -36,8 +36,16 @@ fn main() {
     let handle = thread::spawn(move || pinger.ping());
     for r in rx.lock().unwrap().iter() {
         println!(
-            "{} [{:?}] {} Bytes from IP:{}, HOP:{}, TTL:{}, RTT:{:?}",
-            r.seq, r.protocol, r.received_packet_size, r.ip_addr, r.hop, r.ttl, r.rtt
+            "{} [{:?}] {} Bytes from IP:{}, Status:{:?}, Reason:{:?}, HOP:{}, TTL:{}, RTT:{:?}",
+            r.seq,
+            r.protocol,
+            r.received_packet_size,
+            r.ip_addr,
+            r.port_status,
+            r.unreachable_reason.map(|reason| reason.name()),
+            r.hop,
+            r.ttl,
+            r.rtt
         );
     }
     match handle.join() {
@@ -60,6 +60,17 @@ fn main() {
         Ok(trace_result) => match trace_result {
             Ok(r) => {
                 println!("Traceroute Result: {:?}", r);
+                // Resolve hop names together, cached answers are reused by later traces
+                let hop_ips: Vec<IpAddr> = r.nodes.iter().map(|node| node.ip_addr).collect();
+                let hop_names = netprobe::dns::lookup_addrs(&hop_ips, 8);
+                for node in &r.nodes {
+                    let name: &str = hop_names
+                        .get(&node.ip_addr)
+                        .and_then(|names| names.first())
+                        .map(|name| name.as_str())
+                        .unwrap_or("-");
+                    println!("HOP:{} {} {}", node.hop, node.ip_addr, name);
+                }
             }
             Err(e) => println!("{:?}", e),
         },
@@ -0,0 +1,64 @@
+use netprobe::setting::ProbeSetting;
+use netprobe::wol::{WakeOnLan, WakeOnLanMethod};
+use std::net::IpAddr;
+use std::{env, process};
+use xenet::net::interface::Interface;
+use xenet::net::mac::MacAddr;
+
+const USAGE: &str =
+    "USAGE: wake_on_lan <NETWORK INTERFACE> <TARGET MAC Addr> [TARGET IP Addr] [--udp]";
+
+fn main() {
+    let interface: Interface = match env::args().nth(1) {
+        Some(n) => {
+            // Use interface specified by user
+            let interfaces: Vec<Interface> = xenet::net::interface::get_interfaces();
+            let interface: Interface = interfaces
+                .into_iter()
+                .find(|interface| interface.name == n)
+                .expect("Failed to get interface information");
+            interface
+        }
+        None => {
+            println!("Failed to get default interface");
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+    let target_mac: MacAddr = match env::args().nth(2) {
+        Some(mac) => MacAddr::from_hex_format(&mac),
+        None => {
+            println!("Failed to get target MAC address");
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+    let verify_ip: Option<IpAddr> = env::args()
+        .skip(3)
+        .find_map(|arg| arg.parse::<IpAddr>().ok());
+    let method: WakeOnLanMethod = if env::args().any(|arg| arg == "--udp") {
+        WakeOnLanMethod::Udp
+    } else {
+        WakeOnLanMethod::Ethernet
+    };
+    let setting: ProbeSetting = ProbeSetting::wake_on_lan(interface, 3).unwrap();
+    let mut wol: WakeOnLan = WakeOnLan::new(setting, target_mac)
+        .unwrap()
+        .with_method(method);
+    if let Some(ip) = verify_ip {
+        wol = wol.with_verify_ip(ip);
+    }
+    match wol.wake() {
+        Ok(r) => {
+            println!(
+                "Sent {} magic packet(s) to {} via {:?}",
+                r.sent_count, r.target_mac, r.method
+            );
+            if let Some(wake_time) = r.wake_time {
+                println!("Host is up after {:?}", wake_time);
+            }
+            println!("Wake-on-LAN Result: {:?}", r);
+        }
+        Err(e) => println!("{:?}", e),
+    }
+}
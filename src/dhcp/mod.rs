@@ -0,0 +1,145 @@
+pub(crate) mod v4;
+pub(crate) mod v6;
+
+use std::net::IpAddr;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use xenet::net::interface::Interface;
+
+use crate::result::{DhcpDiscoveryResult, DhcpServerInfo};
+use crate::setting::ProbeSetting;
+
+/// DHCP server discovery structure.
+///
+/// Broadcasts DHCPDISCOVER (or DHCPv6 Solicit) and collects every offer on the link,
+/// so unauthorized DHCP servers can be found.
+/// No lease is requested: DHCPREQUEST is never sent.
+#[derive(Clone, Debug)]
+pub struct DhcpDiscoverer {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Known legitimate servers. If not empty, servers not listed are reported as rogue
+    pub trusted_servers: Vec<IpAddr>,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<DhcpServerInfo>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<DhcpServerInfo>>>,
+}
+
+impl DhcpDiscoverer {
+    /// Create new DhcpDiscoverer instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<DhcpDiscoverer, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "DhcpDiscoverer::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let (tx, rx) = channel();
+        let discoverer = DhcpDiscoverer {
+            probe_setting: setting,
+            trusted_servers: Vec::new(),
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(discoverer)
+    }
+    /// Set known legitimate servers (IP source address or DHCPv4 Server Identifier)
+    pub fn with_trusted_servers(mut self, trusted_servers: Vec<IpAddr>) -> DhcpDiscoverer {
+        self.trusted_servers = trusted_servers;
+        self
+    }
+    /// Run DHCP server discovery
+    pub fn discover(&self) -> Result<DhcpDiscoveryResult, String> {
+        run_dhcp_discovery(&self.probe_setting, &self.trusted_servers, &self.tx)
+    }
+    /// Get progress receiver
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<DhcpServerInfo>>> {
+        self.rx.clone()
+    }
+}
+
+fn run_dhcp_discovery(
+    setting: &ProbeSetting,
+    trusted_servers: &[IpAddr],
+    msg_tx: &Arc<Mutex<Sender<DhcpServerInfo>>>,
+) -> Result<DhcpDiscoveryResult, String> {
+    let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
+        Some(interface) => interface,
+        None => {
+            return Err(format!(
+                "run_dhcp_discovery: unable to get interface by index {}",
+                setting.if_index
+            ))
+        }
+    };
+    let config = xenet::datalink::Config {
+        write_buffer_size: 4096,
+        read_buffer_size: 4096,
+        read_timeout: Some(setting.receive_timeout),
+        write_timeout: None,
+        channel_type: xenet::datalink::ChannelType::Layer2,
+        bpf_fd_attempts: 1000,
+        linux_fanout: None,
+        promiscuous: false,
+    };
+    // Create a channel to send/receive packet
+    let (mut tx, mut rx) = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+        Ok(_) => return Err("run_dhcp_discovery: unable to create channel".to_string()),
+        Err(e) => {
+            return Err(format!(
+                "run_dhcp_discovery: unable to create channel: {}",
+                e
+            ))
+        }
+    };
+    match setting.dst_ip {
+        IpAddr::V4(_) => Ok(v4::run_dhcp_discovery(
+            &mut tx,
+            &mut rx,
+            setting,
+            trusted_servers,
+            msg_tx,
+        )),
+        IpAddr::V6(_) => Ok(v6::run_dhcpv6_discovery(
+            &mut tx,
+            &mut rx,
+            setting,
+            trusted_servers,
+            msg_tx,
+        )),
+    }
+}
+
+/// Merge a newly received offer into the known servers.
+/// Returns the server if it was not seen before.
+pub(crate) fn merge_server(
+    servers: &mut Vec<DhcpServerInfo>,
+    mut server: DhcpServerInfo,
+    trusted_servers: &[IpAddr],
+) -> Option<DhcpServerInfo> {
+    server.rogue = !trusted_servers.is_empty()
+        && !trusted_servers.contains(&server.ip_addr)
+        && !trusted_servers
+            .iter()
+            .any(|ip| ip.to_string() == server.server_id);
+    match servers
+        .iter_mut()
+        .find(|s| s.mac_addr == server.mac_addr && s.server_id == server.server_id)
+    {
+        Some(known) => {
+            server.offer_count = known.offer_count + 1;
+            server.rtt = known.rtt;
+            *known = server;
+            None
+        }
+        None => {
+            servers.push(server.clone());
+            Some(server)
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::packet::dhcp::{
+    DHCP_CLIENT_PORT, DHCP_FIXED_LEN, DHCP_MAGIC_COOKIE, DHCP_OFFER, DHCP_OPT_DNS_SERVER,
+    DHCP_OPT_DOMAIN_NAME, DHCP_OPT_END, DHCP_OPT_LEASE_TIME, DHCP_OPT_MESSAGE_TYPE, DHCP_OPT_PAD,
+    DHCP_OPT_ROUTER, DHCP_OPT_SERVER_ID, DHCP_OPT_SUBNET_MASK, DHCP_SERVER_PORT,
+};
+use crate::result::{DhcpDiscoveryResult, DhcpServerInfo, ProbeStatus};
+use crate::setting::{ProbeSetting, Protocol};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use xenet::datalink::{DataLinkReceiver, DataLinkSender};
+use xenet::net::mac::MacAddr;
+use xenet::packet::frame::{Frame, ParseOption};
+
+pub(crate) fn run_dhcp_discovery(
+    tx: &mut Box<dyn DataLinkSender>,
+    rx: &mut Box<dyn DataLinkReceiver>,
+    setting: &ProbeSetting,
+    trusted_servers: &[IpAddr],
+    msg_tx: &Arc<Mutex<Sender<DhcpServerInfo>>>,
+) -> DhcpDiscoveryResult {
+    let mut result = DhcpDiscoveryResult::new();
+    result.protocol = Protocol::UDP;
+    let parse_option: ParseOption = ParseOption::default();
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let mut servers: Vec<DhcpServerInfo> = Vec::new();
+    let mut xids: Vec<u32> = Vec::new();
+    for seq in 1..setting.count + 1 {
+        let xid: u32 = rand::random::<u32>();
+        xids.push(xid);
+        let discover_packet: Vec<u8> = crate::packet::dhcp::build_dhcp_packet(setting.clone(), xid);
+        let send_time = Instant::now();
+        let _ = tx.send(&discover_packet);
+        // Every server on the link may answer. Keep listening for the whole receive window.
+        loop {
+            if let Ok(packet) = rx.next() {
+                let recv_time: Duration = Instant::now().duration_since(send_time);
+                let frame: Frame = Frame::from_bytes(packet, parse_option.clone());
+                if let Some(mut server) = parse_dhcp_offer_frame(&frame, setting, &xids) {
+                    server.rtt = recv_time;
+                    if let Some(server) = super::merge_server(&mut servers, server, trusted_servers)
+                    {
+                        if let Ok(lr) = msg_tx.lock() {
+                            let _ = lr.send(server);
+                        }
+                    }
+                }
+            }
+            let wait_time: Duration = Instant::now().duration_since(send_time);
+            if wait_time > setting.receive_timeout {
+                break;
+            }
+        }
+        if seq < setting.count {
+            std::thread::sleep(setting.send_rate);
+        }
+    }
+    let probe_time = Instant::now().duration_since(start_time);
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = probe_time;
+    result.multiple_servers = servers.len() > 1;
+    result.servers = servers;
+    result.probe_status = ProbeStatus::new();
+    result
+}
+
+fn parse_dhcp_offer_frame(
+    frame: &Frame,
+    setting: &ProbeSetting,
+    xids: &[u32],
+) -> Option<DhcpServerInfo> {
+    let ethernet_header = frame.datalink.as_ref()?.ethernet.as_ref()?;
+    let ipv4_header = frame.ip.as_ref()?.ipv4.as_ref()?;
+    let udp_header = frame.transport.as_ref()?.udp.as_ref()?;
+    if udp_header.source != DHCP_SERVER_PORT || udp_header.destination != DHCP_CLIENT_PORT {
+        return None;
+    }
+    let mut server = parse_dhcp_offer(&frame.payload, setting.src_mac, xids)?;
+    server.mac_addr = ethernet_header.source;
+    server.ip_addr = IpAddr::V4(ipv4_header.source);
+    if server.server_id.is_empty() {
+        server.server_id = ipv4_header.source.to_string();
+    }
+    Some(server)
+}
+
+/// Parse DHCPOFFER message (BOOTP header and options)
+pub(crate) fn parse_dhcp_offer(
+    payload: &[u8],
+    client_mac: MacAddr,
+    xids: &[u32],
+) -> Option<DhcpServerInfo> {
+    if payload.len() < DHCP_FIXED_LEN + DHCP_MAGIC_COOKIE.len() {
+        return None;
+    }
+    // op: BOOTREPLY
+    if payload[0] != 2 {
+        return None;
+    }
+    let xid: u32 = u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]);
+    if !xids.contains(&xid) || payload[28..34] != client_mac.octets() {
+        return None;
+    }
+    if payload[DHCP_FIXED_LEN..DHCP_FIXED_LEN + 4] != DHCP_MAGIC_COOKIE {
+        return None;
+    }
+    let yiaddr: Ipv4Addr = Ipv4Addr::new(payload[16], payload[17], payload[18], payload[19]);
+    let giaddr: Ipv4Addr = Ipv4Addr::new(payload[24], payload[25], payload[26], payload[27]);
+    let mut server = DhcpServerInfo {
+        mac_addr: MacAddr::zero(),
+        ip_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        server_id: String::new(),
+        relay_addr: if giaddr.is_unspecified() {
+            None
+        } else {
+            Some(IpAddr::V4(giaddr))
+        },
+        offered_addr: if yiaddr.is_unspecified() {
+            None
+        } else {
+            Some(IpAddr::V4(yiaddr))
+        },
+        subnet_mask: None,
+        gateways: Vec::new(),
+        dns_servers: Vec::new(),
+        domain_names: Vec::new(),
+        lease_time: None,
+        offer_count: 1,
+        rtt: Duration::from_millis(0),
+        rogue: false,
+    };
+    let mut message_type: Option<u8> = None;
+    let mut options: &[u8] = &payload[DHCP_FIXED_LEN + 4..];
+    while let Some(&option_type) = options.first() {
+        if option_type == DHCP_OPT_END {
+            break;
+        }
+        if option_type == DHCP_OPT_PAD {
+            options = &options[1..];
+            continue;
+        }
+        if options.len() < 2 || options[1] as usize + 2 > options.len() {
+            break;
+        }
+        let data: &[u8] = &options[2..options[1] as usize + 2];
+        match option_type {
+            DHCP_OPT_MESSAGE_TYPE if !data.is_empty() => {
+                message_type = Some(data[0]);
+            }
+            DHCP_OPT_SUBNET_MASK if data.len() == 4 => {
+                server.subnet_mask = Some(IpAddr::V4(Ipv4Addr::new(
+                    data[0], data[1], data[2], data[3],
+                )));
+            }
+            DHCP_OPT_ROUTER => {
+                server.gateways = parse_ipv4_list(data);
+            }
+            DHCP_OPT_DNS_SERVER => {
+                server.dns_servers = parse_ipv4_list(data);
+            }
+            DHCP_OPT_DOMAIN_NAME => {
+                let domain_name: String = String::from_utf8_lossy(data)
+                    .trim_end_matches('\0')
+                    .to_string();
+                if !domain_name.is_empty() {
+                    server.domain_names.push(domain_name);
+                }
+            }
+            DHCP_OPT_LEASE_TIME if data.len() == 4 => {
+                server.lease_time = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+            }
+            DHCP_OPT_SERVER_ID if data.len() == 4 => {
+                server.server_id = Ipv4Addr::new(data[0], data[1], data[2], data[3]).to_string();
+            }
+            _ => {}
+        }
+        options = &options[data.len() + 2..];
+    }
+    if message_type != Some(DHCP_OFFER) {
+        return None;
+    }
+    Some(server)
+}
+
+fn parse_ipv4_list(data: &[u8]) -> Vec<IpAddr> {
+    data.chunks_exact(4)
+        .map(|c| IpAddr::V4(Ipv4Addr::new(c[0], c[1], c[2], c[3])))
+        .collect()
+}
@@ -0,0 +1,180 @@
+use crate::packet::dhcp::{
+    DHCPV6_ADVERTISE, DHCPV6_CLIENT_PORT, DHCPV6_OPT_CLIENT_ID, DHCPV6_OPT_DNS_SERVERS,
+    DHCPV6_OPT_DOMAIN_LIST, DHCPV6_OPT_IAADDR, DHCPV6_OPT_IA_NA, DHCPV6_OPT_SERVER_ID,
+    DHCPV6_SERVER_PORT,
+};
+use crate::result::{DhcpDiscoveryResult, DhcpServerInfo, ProbeStatus};
+use crate::setting::{ProbeSetting, Protocol};
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use xenet::datalink::{DataLinkReceiver, DataLinkSender};
+use xenet::net::mac::MacAddr;
+use xenet::packet::frame::{Frame, ParseOption};
+
+/// IA_NA option header (IAID, T1, T2)
+const IA_NA_HEADER_LEN: usize = 12;
+/// IA Address option header (address, preferred lifetime, valid lifetime)
+const IAADDR_HEADER_LEN: usize = 24;
+
+pub(crate) fn run_dhcpv6_discovery(
+    tx: &mut Box<dyn DataLinkSender>,
+    rx: &mut Box<dyn DataLinkReceiver>,
+    setting: &ProbeSetting,
+    trusted_servers: &[IpAddr],
+    msg_tx: &Arc<Mutex<Sender<DhcpServerInfo>>>,
+) -> DhcpDiscoveryResult {
+    let mut result = DhcpDiscoveryResult::new();
+    result.protocol = Protocol::UDP;
+    let parse_option: ParseOption = ParseOption::default();
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let mut servers: Vec<DhcpServerInfo> = Vec::new();
+    let mut xids: Vec<u32> = Vec::new();
+    for seq in 1..setting.count + 1 {
+        // Transaction ID is 24 bits
+        let xid: u32 = rand::random::<u32>() & 0x00ff_ffff;
+        xids.push(xid);
+        let solicit_packet: Vec<u8> = crate::packet::dhcp::build_dhcp_packet(setting.clone(), xid);
+        let send_time = Instant::now();
+        let _ = tx.send(&solicit_packet);
+        // Every server on the link may answer. Keep listening for the whole receive window.
+        loop {
+            if let Ok(packet) = rx.next() {
+                let recv_time: Duration = Instant::now().duration_since(send_time);
+                let frame: Frame = Frame::from_bytes(packet, parse_option.clone());
+                if let Some(mut server) = parse_dhcpv6_advertise_frame(&frame, setting, &xids) {
+                    server.rtt = recv_time;
+                    if let Some(server) = super::merge_server(&mut servers, server, trusted_servers)
+                    {
+                        if let Ok(lr) = msg_tx.lock() {
+                            let _ = lr.send(server);
+                        }
+                    }
+                }
+            }
+            let wait_time: Duration = Instant::now().duration_since(send_time);
+            if wait_time > setting.receive_timeout {
+                break;
+            }
+        }
+        if seq < setting.count {
+            std::thread::sleep(setting.send_rate);
+        }
+    }
+    let probe_time = Instant::now().duration_since(start_time);
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = probe_time;
+    result.multiple_servers = servers.len() > 1;
+    result.servers = servers;
+    result.probe_status = ProbeStatus::new();
+    result
+}
+
+fn parse_dhcpv6_advertise_frame(
+    frame: &Frame,
+    setting: &ProbeSetting,
+    xids: &[u32],
+) -> Option<DhcpServerInfo> {
+    let ethernet_header = frame.datalink.as_ref()?.ethernet.as_ref()?;
+    let ipv6_header = frame.ip.as_ref()?.ipv6.as_ref()?;
+    let udp_header = frame.transport.as_ref()?.udp.as_ref()?;
+    if udp_header.source != DHCPV6_SERVER_PORT || udp_header.destination != DHCPV6_CLIENT_PORT {
+        return None;
+    }
+    let mut server = parse_dhcpv6_advertise(&frame.payload, setting.src_mac, xids)?;
+    server.mac_addr = ethernet_header.source;
+    server.ip_addr = IpAddr::V6(ipv6_header.source);
+    Some(server)
+}
+
+/// Parse DHCPv6 Advertise message
+pub(crate) fn parse_dhcpv6_advertise(
+    payload: &[u8],
+    client_mac: MacAddr,
+    xids: &[u32],
+) -> Option<DhcpServerInfo> {
+    if payload.len() < 4 || payload[0] != DHCPV6_ADVERTISE {
+        return None;
+    }
+    let xid: u32 = u32::from_be_bytes([0, payload[1], payload[2], payload[3]]);
+    if !xids.contains(&xid) {
+        return None;
+    }
+    let mut server = DhcpServerInfo {
+        mac_addr: MacAddr::zero(),
+        ip_addr: IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        server_id: String::new(),
+        relay_addr: None,
+        offered_addr: None,
+        subnet_mask: None,
+        gateways: Vec::new(),
+        dns_servers: Vec::new(),
+        domain_names: Vec::new(),
+        lease_time: None,
+        offer_count: 1,
+        rtt: Duration::from_millis(0),
+        rogue: false,
+    };
+    let mut client_matched: bool = false;
+    for (option_code, data) in parse_options(&payload[4..]) {
+        match option_code {
+            DHCPV6_OPT_CLIENT_ID => {
+                // DUID-LL or DUID-LLT ends with our link-layer address
+                client_matched = data.ends_with(&client_mac.octets());
+            }
+            DHCPV6_OPT_SERVER_ID => {
+                server.server_id = data.iter().map(|b| format!("{:02x}", b)).collect();
+            }
+            DHCPV6_OPT_IA_NA if data.len() >= IA_NA_HEADER_LEN => {
+                for (sub_code, sub_data) in parse_options(&data[IA_NA_HEADER_LEN..]) {
+                    if sub_code == DHCPV6_OPT_IAADDR && sub_data.len() >= IAADDR_HEADER_LEN {
+                        let mut octets: [u8; 16] = [0; 16];
+                        octets.copy_from_slice(&sub_data[0..16]);
+                        server.offered_addr = Some(IpAddr::V6(Ipv6Addr::from(octets)));
+                        server.lease_time = Some(u32::from_be_bytes([
+                            sub_data[20],
+                            sub_data[21],
+                            sub_data[22],
+                            sub_data[23],
+                        ]));
+                    }
+                }
+            }
+            DHCPV6_OPT_DNS_SERVERS => {
+                server.dns_servers = data
+                    .chunks_exact(16)
+                    .map(|c| {
+                        let mut octets: [u8; 16] = [0; 16];
+                        octets.copy_from_slice(c);
+                        IpAddr::V6(Ipv6Addr::from(octets))
+                    })
+                    .collect();
+            }
+            DHCPV6_OPT_DOMAIN_LIST => {
+                server.domain_names = crate::neighbor::router::parse_domain_names(data);
+            }
+            _ => {}
+        }
+    }
+    if !client_matched || server.server_id.is_empty() {
+        return None;
+    }
+    Some(server)
+}
+
+/// Split DHCPv6 options into (option-code, option-data)
+fn parse_options(mut options: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut parsed: Vec<(u16, &[u8])> = Vec::new();
+    while options.len() >= 4 {
+        let option_code: u16 = u16::from_be_bytes([options[0], options[1]]);
+        let option_len: usize = u16::from_be_bytes([options[2], options[3]]) as usize;
+        if 4 + option_len > options.len() {
+            break;
+        }
+        parsed.push((option_code, &options[4..4 + option_len]));
+        options = &options[4 + option_len..];
+    }
+    parsed
+}
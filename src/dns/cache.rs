@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Entries kept by default
+pub const DNS_CACHE_DEFAULT_CAPACITY: usize = 4096;
+/// Lifetime of a negative answer without SOA record
+pub const DNS_CACHE_DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(60);
+/// Percentage of the capacity left when a full cache makes room, so that entries
+/// are sorted once per batch of inserts rather than on every insert
+const EVICTION_TARGET_PERCENT: usize = 90;
+
+/// Query cached by `DnsCache`. `scope` tells resolver configurations apart.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum CacheKey {
+    Host { scope: u64, host_name: String },
+    Addr { scope: u64, ip_addr: IpAddr },
+}
+
+/// Cached answer. Empty for a negative answer.
+#[derive(Clone, Debug)]
+pub(crate) enum CacheValue {
+    Addrs(Vec<IpAddr>),
+    Names(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+struct CacheEntry {
+    value: CacheValue,
+    valid_until: Instant,
+}
+
+/// Answers of the `dns` functions, shared by all resolvers.
+///
+/// Answers are kept for their TTL. Names without records are cached too, for the
+/// negative TTL of the SOA record or `negative_ttl`. Failed lookups are not cached.
+#[derive(Debug)]
+pub struct DnsCache {
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+    capacity: AtomicUsize,
+    negative_ttl_secs: AtomicU64,
+    hit_count: AtomicU64,
+    miss_count: AtomicU64,
+}
+
+impl DnsCache {
+    pub fn new() -> DnsCache {
+        DnsCache {
+            entries: Mutex::new(HashMap::new()),
+            capacity: AtomicUsize::new(DNS_CACHE_DEFAULT_CAPACITY),
+            negative_ttl_secs: AtomicU64::new(DNS_CACHE_DEFAULT_NEGATIVE_TTL.as_secs()),
+            hit_count: AtomicU64::new(0),
+            miss_count: AtomicU64::new(0),
+        }
+    }
+    /// Set the maximum number of entries. Zero disables caching.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        if let Ok(mut entries) = self.entries.lock() {
+            evict(&mut entries, capacity);
+        }
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+    /// Set the lifetime of negative answers without SOA record
+    pub fn set_negative_ttl(&self, negative_ttl: Duration) {
+        self.negative_ttl_secs
+            .store(negative_ttl.as_secs(), Ordering::Relaxed);
+    }
+    pub fn negative_ttl(&self) -> Duration {
+        Duration::from_secs(self.negative_ttl_secs.load(Ordering::Relaxed))
+    }
+    /// Number of entries, including expired ones not evicted yet
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Lookups answered from the cache
+    pub fn hit_count(&self) -> u64 {
+        self.hit_count.load(Ordering::Relaxed)
+    }
+    /// Lookups sent to a name server
+    pub fn miss_count(&self) -> u64 {
+        self.miss_count.load(Ordering::Relaxed)
+    }
+    /// Remove all entries
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<CacheValue> {
+        let value: Option<CacheValue> = match self.entries.lock() {
+            Ok(mut entries) => match entries.get(key) {
+                Some(entry) if entry.valid_until > Instant::now() => Some(entry.value.clone()),
+                Some(_) => {
+                    entries.remove(key);
+                    None
+                }
+                None => None,
+            },
+            Err(_) => None,
+        };
+        let counter = if value.is_some() {
+            &self.hit_count
+        } else {
+            &self.miss_count
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+    pub(crate) fn insert(&self, key: CacheKey, value: CacheValue, valid_until: Instant) {
+        let capacity: usize = self.capacity();
+        if capacity == 0 || valid_until <= Instant::now() {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            if !entries.contains_key(&key) && entries.len() >= capacity {
+                let target: usize = (capacity * EVICTION_TARGET_PERCENT / 100).min(capacity - 1);
+                evict(&mut entries, target);
+            }
+            entries.insert(key, CacheEntry { value, valid_until });
+        }
+    }
+    /// Expiry of a negative answer
+    pub(crate) fn negative_valid_until(&self, negative_ttl: Option<u32>) -> Instant {
+        let ttl: Duration = match negative_ttl {
+            Some(ttl) => Duration::from_secs(ttl as u64),
+            None => self.negative_ttl(),
+        };
+        Instant::now() + ttl
+    }
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shrink `entries` to `capacity`, expired entries first, then the ones expiring soonest
+fn evict(entries: &mut HashMap<CacheKey, CacheEntry>, capacity: usize) {
+    if entries.len() <= capacity {
+        return;
+    }
+    let now = Instant::now();
+    entries.retain(|_, entry| entry.valid_until > now);
+    if entries.len() <= capacity {
+        return;
+    }
+    let mut expiries: Vec<(Instant, CacheKey)> = entries
+        .iter()
+        .map(|(key, entry)| (entry.valid_until, key.clone()))
+        .collect();
+    expiries.sort_by_key(|(valid_until, _)| *valid_until);
+    let excess: usize = entries.len() - capacity;
+    for (_, key) in expiries.into_iter().take(excess) {
+        entries.remove(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host_key(i: usize) -> CacheKey {
+        CacheKey::Host {
+            scope: 0,
+            host_name: format!("host{}.example", i),
+        }
+    }
+
+    fn addrs(i: u8) -> CacheValue {
+        CacheValue::Addrs(vec![IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, i))])
+    }
+
+    #[test]
+    fn get_until_expiry() {
+        let cache = DnsCache::new();
+        cache.insert(
+            host_key(1),
+            addrs(1),
+            Instant::now() + Duration::from_secs(60),
+        );
+        cache.insert(
+            host_key(2),
+            addrs(2),
+            Instant::now() + Duration::from_millis(20),
+        );
+        // Already expired, not stored
+        cache.insert(host_key(3), addrs(3), Instant::now());
+        assert_eq!(cache.len(), 2);
+        assert!(matches!(cache.get(&host_key(1)), Some(CacheValue::Addrs(ips)) if ips.len() == 1));
+        assert!(cache.get(&host_key(2)).is_some());
+        assert!(cache.get(&host_key(3)).is_none());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get(&host_key(2)).is_none());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.hit_count(), 2);
+        assert_eq!(cache.miss_count(), 2);
+        // Scopes are kept apart
+        let other_scope = CacheKey::Host {
+            scope: 1,
+            host_name: String::from("host1.example"),
+        };
+        assert!(cache.get(&other_scope).is_none());
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn negative_ttl() {
+        let cache = DnsCache::new();
+        let now = Instant::now();
+        // From the SOA record
+        let valid_until: Instant = cache.negative_valid_until(Some(30));
+        assert!(valid_until >= now + Duration::from_secs(30));
+        assert!(valid_until < now + Duration::from_secs(31));
+        // Default without SOA record
+        let valid_until: Instant = cache.negative_valid_until(None);
+        assert!(valid_until >= now + DNS_CACHE_DEFAULT_NEGATIVE_TTL);
+        cache.set_negative_ttl(Duration::from_secs(5));
+        assert_eq!(cache.negative_ttl(), Duration::from_secs(5));
+        let valid_until: Instant = cache.negative_valid_until(None);
+        assert!(valid_until < now + Duration::from_secs(6));
+        // Zero TTL is not cached
+        cache.insert(
+            host_key(1),
+            CacheValue::Addrs(Vec::new()),
+            cache.negative_valid_until(Some(0)),
+        );
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let cache = DnsCache::new();
+        let valid_until: Instant = Instant::now() + Duration::from_secs(60);
+        cache.insert(host_key(1), addrs(1), valid_until);
+        cache.set_capacity(0);
+        assert!(cache.is_empty());
+        cache.insert(host_key(2), addrs(2), valid_until);
+        assert!(cache.is_empty());
+        assert!(cache.get(&host_key(2)).is_none());
+    }
+
+    #[test]
+    fn evict_soonest_expiry_first() {
+        let cache = DnsCache::new();
+        cache.set_capacity(20);
+        let now = Instant::now();
+        // Inserted out of expiry order
+        for i in (0..20).rev() {
+            cache.insert(
+                host_key(i),
+                addrs(i as u8),
+                now + Duration::from_secs(60 + i as u64),
+            );
+        }
+        assert_eq!(cache.len(), 20);
+        // Updating a key makes no room
+        cache.insert(host_key(0), addrs(0), now + Duration::from_secs(60));
+        assert_eq!(cache.len(), 20);
+        // A full cache shrinks to 90% before a new key
+        cache.insert(host_key(20), addrs(20), now + Duration::from_secs(600));
+        assert_eq!(cache.len(), 19);
+        for i in 0..2 {
+            assert!(cache.get(&host_key(i)).is_none(), "host{} kept", i);
+        }
+        for i in 2..21 {
+            assert!(cache.get(&host_key(i)).is_some(), "host{} evicted", i);
+        }
+        cache.insert(host_key(21), addrs(21), now + Duration::from_secs(600));
+        assert_eq!(cache.len(), 20);
+        // Expired entries go first
+        cache.set_capacity(3);
+        cache.clear();
+        cache.insert(
+            host_key(0),
+            addrs(0),
+            Instant::now() + Duration::from_millis(10),
+        );
+        cache.insert(host_key(1), addrs(1), now + Duration::from_secs(60));
+        cache.insert(host_key(2), addrs(2), now + Duration::from_secs(30));
+        std::thread::sleep(Duration::from_millis(20));
+        cache.insert(host_key(3), addrs(3), now + Duration::from_secs(90));
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get(&host_key(0)).is_none());
+        assert!(cache.get(&host_key(2)).is_some());
+        // Shrinking keeps the entries expiring last
+        cache.set_capacity(1);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&host_key(3)).is_some());
+    }
+}
@@ -0,0 +1,207 @@
+use hickory_resolver::config::{
+    LookupIpStrategy, NameServerConfig, NameServerConfigGroup, ResolverConfig, ResolverOpts,
+};
+use hickory_resolver::proto::rr::Name;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Transport of resolver queries
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DnsProtocol {
+    Udp,
+    Tcp,
+}
+
+/// Address records looked up for a host name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IpStrategy {
+    /// A records only
+    Ipv4Only,
+    /// AAAA records only
+    Ipv6Only,
+    /// A and AAAA records in parallel
+    Ipv4AndIpv6,
+    /// A records, AAAA records if there are none
+    Ipv4ThenIpv6,
+    /// AAAA records, A records if there are none
+    Ipv6ThenIpv4,
+}
+
+/// Resolver configuration of the `dns` functions.
+///
+/// Without name servers, the system configuration (resolv.conf or the Windows registry)
+/// is read and the other settings override it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DnsConfig {
+    /// Name servers with their port. Empty to use the system name servers
+    pub nameservers: Vec<SocketAddr>,
+    pub protocol: DnsProtocol,
+    /// Timeout of one query
+    pub timeout: Duration,
+    /// Queries sent before giving up
+    pub attempts: usize,
+    /// Domains appended to names with fewer dots than `ndots`, in addition to the system ones
+    pub search_domains: Vec<String>,
+    pub ndots: usize,
+    pub ip_strategy: IpStrategy,
+    /// Timeout of reverse lookups of global addresses
+    pub reverse_timeout: Duration,
+    /// Timeout of reverse lookups of private and link-local addresses, which seldom have PTR records
+    pub local_reverse_timeout: Duration,
+    /// Answer from and store in the shared `DnsCache`
+    pub use_cache: bool,
+}
+
+impl DnsConfig {
+    pub fn new() -> DnsConfig {
+        DnsConfig {
+            nameservers: Vec::new(),
+            protocol: DnsProtocol::Udp,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            search_domains: Vec::new(),
+            ndots: 1,
+            ip_strategy: IpStrategy::Ipv4ThenIpv6,
+            reverse_timeout: Duration::from_millis(1000),
+            local_reverse_timeout: Duration::from_millis(200),
+            use_cache: true,
+        }
+    }
+    /// Query `ip_addr` on port 53
+    pub fn with_nameserver(mut self, ip_addr: IpAddr) -> DnsConfig {
+        self.nameservers
+            .push(SocketAddr::new(ip_addr, super::probe::DNS_DEFAULT_PORT));
+        self
+    }
+    pub fn with_nameservers(mut self, nameservers: Vec<SocketAddr>) -> DnsConfig {
+        self.nameservers = nameservers;
+        self
+    }
+    pub fn with_protocol(mut self, protocol: DnsProtocol) -> DnsConfig {
+        self.protocol = protocol;
+        self
+    }
+    pub fn with_timeout(mut self, timeout: Duration) -> DnsConfig {
+        self.timeout = timeout;
+        self
+    }
+    pub fn with_attempts(mut self, attempts: usize) -> DnsConfig {
+        self.attempts = attempts.max(1);
+        self
+    }
+    pub fn with_search_domains(mut self, search_domains: Vec<String>) -> DnsConfig {
+        self.search_domains = search_domains;
+        self
+    }
+    pub fn with_ndots(mut self, ndots: usize) -> DnsConfig {
+        self.ndots = ndots;
+        self
+    }
+    pub fn with_ip_strategy(mut self, ip_strategy: IpStrategy) -> DnsConfig {
+        self.ip_strategy = ip_strategy;
+        self
+    }
+    pub fn with_reverse_timeout(
+        mut self,
+        reverse_timeout: Duration,
+        local_reverse_timeout: Duration,
+    ) -> DnsConfig {
+        self.reverse_timeout = reverse_timeout;
+        self.local_reverse_timeout = local_reverse_timeout;
+        self
+    }
+    pub fn with_cache(mut self, use_cache: bool) -> DnsConfig {
+        self.use_cache = use_cache;
+        self
+    }
+    /// Cache key part of the settings that change answers
+    pub(crate) fn cache_scope(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.nameservers.hash(&mut hasher);
+        self.protocol.hash(&mut hasher);
+        self.search_domains.hash(&mut hasher);
+        self.ndots.hash(&mut hasher);
+        self.ip_strategy.hash(&mut hasher);
+        hasher.finish()
+    }
+    /// hickory resolver configuration and options. `timeout` replaces the query timeout.
+    pub(crate) fn resolver_parts(
+        &self,
+        timeout: Duration,
+    ) -> Result<(ResolverConfig, ResolverOpts), String> {
+        let (system_config, mut opts) = if self.nameservers.is_empty() {
+            system_conf()?
+        } else {
+            (ResolverConfig::new(), ResolverOpts::default())
+        };
+        let mut nameservers: Vec<SocketAddr> = self.nameservers.clone();
+        if nameservers.is_empty() {
+            for nameserver in system_config.name_servers() {
+                if !nameservers.contains(&nameserver.socket_addr) {
+                    nameservers.push(nameserver.socket_addr);
+                }
+            }
+        }
+        if nameservers.is_empty() {
+            return Err("DnsConfig: no name servers".to_string());
+        }
+        let protocol = match self.protocol {
+            DnsProtocol::Udp => hickory_resolver::config::Protocol::Udp,
+            DnsProtocol::Tcp => hickory_resolver::config::Protocol::Tcp,
+        };
+        let mut group: NameServerConfigGroup = NameServerConfigGroup::new();
+        for nameserver in nameservers {
+            group.push(NameServerConfig::new(nameserver, protocol));
+        }
+        let mut search: Vec<Name> = system_config.search().to_vec();
+        for domain in &self.search_domains {
+            let name: Name = Name::from_utf8(domain)
+                .map_err(|e| format!("DnsConfig: invalid search domain {}: {}", domain, e))?;
+            if !search.contains(&name) {
+                search.push(name);
+            }
+        }
+        let config: ResolverConfig =
+            ResolverConfig::from_parts(system_config.domain().cloned(), search, group);
+        opts.timeout = timeout;
+        opts.attempts = self.attempts;
+        opts.ndots = self.ndots;
+        if !self.use_cache {
+            opts.cache_size = 0;
+        }
+        opts.ip_strategy = match self.ip_strategy {
+            IpStrategy::Ipv4Only => LookupIpStrategy::Ipv4Only,
+            IpStrategy::Ipv6Only => LookupIpStrategy::Ipv6Only,
+            IpStrategy::Ipv4AndIpv6 => LookupIpStrategy::Ipv4AndIpv6,
+            IpStrategy::Ipv4ThenIpv6 => LookupIpStrategy::Ipv4thenIpv6,
+            IpStrategy::Ipv6ThenIpv4 => LookupIpStrategy::Ipv6thenIpv4,
+        };
+        Ok((config, opts))
+    }
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(unix, target_os = "windows"))]
+fn system_conf() -> Result<(ResolverConfig, ResolverOpts), String> {
+    hickory_resolver::system_conf::read_system_conf()
+        .map_err(|e| format!("DnsConfig: unable to read system configuration: {}", e))
+}
+
+/// No system configuration to read, use the public resolvers of hickory
+#[cfg(not(any(unix, target_os = "windows")))]
+fn system_conf() -> Result<(ResolverConfig, ResolverOpts), String> {
+    Ok((ResolverConfig::default(), ResolverOpts::default()))
+}
@@ -0,0 +1,432 @@
+pub mod cache;
+pub mod config;
+pub mod probe;
+pub mod target;
+
+pub use cache::DnsCache;
+pub use config::{DnsConfig, DnsProtocol, IpStrategy};
+pub use target::{AddressPolicy, TargetResolver};
+
+use cache::{CacheKey, CacheValue};
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::TokioAsyncResolver;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Instant;
+use tokio::runtime::Runtime;
+use tokio::task::JoinSet;
+
+#[cfg(feature = "async")]
+use std::str::FromStr;
+
+/// Configuration of the `dns` functions. None until set, meaning `DnsConfig::default()`.
+static DEFAULT_CONFIG: RwLock<Option<DnsConfig>> = RwLock::new(None);
+/// Resolver built from the default configuration, reused across calls
+static DEFAULT_RESOLVER: Mutex<Option<Arc<DnsResolver>>> = Mutex::new(None);
+/// Resolvers of `shared_resolver`, most recently used last
+static SHARED_RESOLVERS: Mutex<Vec<Arc<DnsResolver>>> = Mutex::new(Vec::new());
+/// Configurations kept by `shared_resolver`
+const SHARED_RESOLVER_CAPACITY: usize = 8;
+/// Runtime running the lookups of every resolver
+static RUNTIME: OnceLock<Result<Runtime, String>> = OnceLock::new();
+static DNS_CACHE: OnceLock<DnsCache> = OnceLock::new();
+
+/// Set the resolver configuration used by the `dns` functions and by
+/// `ProbeSetting::with_dst_target` and `ProbeSetting::with_dst_ip`
+pub fn set_default_config(config: DnsConfig) {
+    if let Ok(mut default_config) = DEFAULT_CONFIG.write() {
+        *default_config = Some(config);
+    }
+    if let Ok(mut default_resolver) = DEFAULT_RESOLVER.lock() {
+        *default_resolver = None;
+    }
+}
+
+/// Resolver configuration used by the `dns` functions
+pub fn default_config() -> DnsConfig {
+    match DEFAULT_CONFIG.read() {
+        Ok(config) => config.clone().unwrap_or_default(),
+        Err(_) => DnsConfig::default(),
+    }
+}
+
+/// Resolver of the default configuration
+pub fn default_resolver() -> Result<Arc<DnsResolver>, String> {
+    let mut default_resolver = DEFAULT_RESOLVER
+        .lock()
+        .map_err(|_| "default_resolver: lock poisoned".to_string())?;
+    if let Some(resolver) = default_resolver.as_ref() {
+        return Ok(resolver.clone());
+    }
+    let resolver: Arc<DnsResolver> = Arc::new(DnsResolver::new(default_config())?);
+    *default_resolver = Some(resolver.clone());
+    Ok(resolver)
+}
+
+/// Resolver of `config`, built once and reused by later calls with the same configuration
+pub(crate) fn shared_resolver(config: &DnsConfig) -> Result<Arc<DnsResolver>, String> {
+    let mut resolvers = SHARED_RESOLVERS
+        .lock()
+        .map_err(|_| "shared_resolver: lock poisoned".to_string())?;
+    if let Some(i) = resolvers.iter().position(|r| r.config() == config) {
+        let resolver: Arc<DnsResolver> = resolvers.remove(i);
+        resolvers.push(resolver.clone());
+        return Ok(resolver);
+    }
+    let resolver: Arc<DnsResolver> = Arc::new(DnsResolver::new(config.clone())?);
+    if resolvers.len() >= SHARED_RESOLVER_CAPACITY {
+        resolvers.remove(0);
+    }
+    resolvers.push(resolver.clone());
+    Ok(resolver)
+}
+
+/// Cache shared by all resolvers
+pub fn dns_cache() -> &'static DnsCache {
+    DNS_CACHE.get_or_init(DnsCache::new)
+}
+
+fn runtime() -> Result<&'static Runtime, String> {
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .thread_name("netprobe-dns")
+                .enable_all()
+                .build()
+                .map_err(|e| format!("dns: unable to start runtime: {}", e))
+        })
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+/// Run `future` on the shared runtime and wait for it.
+/// Works from any thread, including the ones of another runtime.
+fn block_on<F>(future: F) -> Result<F::Output, String>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::channel();
+    runtime()?.spawn(async move {
+        let _ = tx.send(future.await);
+    });
+    rx.recv().map_err(|_| "dns: lookup aborted".to_string())
+}
+
+/// Run `future` on the shared runtime, whichever runtime awaits it
+#[cfg(feature = "async")]
+async fn run<F>(future: F) -> Result<F::Output, String>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    runtime()?
+        .spawn(future)
+        .await
+        .map_err(|e| format!("dns: lookup aborted: {}", e))
+}
+
+/// Resolver with a fixed configuration.
+///
+/// Lookups run on a runtime shared by all resolvers and go through `dns_cache()`
+/// unless `DnsConfig::use_cache` is false. Cloning is cheap.
+#[derive(Clone)]
+pub struct DnsResolver {
+    config: DnsConfig,
+    /// Cache key part of `config`
+    scope: u64,
+    resolver: TokioAsyncResolver,
+    /// Resolvers with the reverse lookup timeouts
+    reverse_resolver: TokioAsyncResolver,
+    local_reverse_resolver: TokioAsyncResolver,
+}
+
+impl DnsResolver {
+    pub fn new(config: DnsConfig) -> Result<DnsResolver, String> {
+        let (resolver_config, opts) = config.resolver_parts(config.timeout)?;
+        let _guard = runtime()?.enter();
+        let mut reverse_opts = opts.clone();
+        reverse_opts.timeout = config.reverse_timeout;
+        let mut local_reverse_opts = opts.clone();
+        local_reverse_opts.timeout = config.local_reverse_timeout;
+        Ok(DnsResolver {
+            scope: config.cache_scope(),
+            resolver: TokioAsyncResolver::tokio(resolver_config.clone(), opts),
+            reverse_resolver: TokioAsyncResolver::tokio(resolver_config.clone(), reverse_opts),
+            local_reverse_resolver: TokioAsyncResolver::tokio(resolver_config, local_reverse_opts),
+            config,
+        })
+    }
+    pub fn config(&self) -> &DnsConfig {
+        &self.config
+    }
+    /// Addresses of `host_name`. Empty if the name has no address records.
+    pub fn lookup_host(&self, host_name: &str) -> Result<Vec<IpAddr>, String> {
+        let resolver: DnsResolver = self.clone();
+        let host_name: String = host_name.to_string();
+        block_on(async move { resolver.resolve_host(&host_name).await })?
+    }
+    /// Host names of `ip_addr`, without the trailing dot. Empty if there is no PTR record.
+    pub fn lookup_addr(&self, ip_addr: IpAddr) -> Result<Vec<String>, String> {
+        let resolver: DnsResolver = self.clone();
+        block_on(async move { resolver.resolve_addr(ip_addr).await })?
+    }
+    /// Host names of every address of `ip_addrs`, with at most `concurrency` lookups at a time
+    pub fn lookup_addrs(
+        &self,
+        ip_addrs: &[IpAddr],
+        concurrency: usize,
+    ) -> HashMap<IpAddr, Result<Vec<String>, String>> {
+        let resolver: DnsResolver = self.clone();
+        let queue: Vec<IpAddr> = ip_addrs.to_vec();
+        match block_on(async move { resolver.resolve_addrs(queue, concurrency).await }) {
+            Ok(results) => results,
+            Err(e) => ip_addrs.iter().map(|ip| (*ip, Err(e.clone()))).collect(),
+        }
+    }
+    #[cfg(feature = "async")]
+    pub async fn lookup_host_async(&self, host_name: &str) -> Result<Vec<IpAddr>, String> {
+        let resolver: DnsResolver = self.clone();
+        let host_name: String = host_name.to_string();
+        run(async move { resolver.resolve_host(&host_name).await }).await?
+    }
+    #[cfg(feature = "async")]
+    pub async fn lookup_addr_async(&self, ip_addr: IpAddr) -> Result<Vec<String>, String> {
+        let resolver: DnsResolver = self.clone();
+        run(async move { resolver.resolve_addr(ip_addr).await }).await?
+    }
+    #[cfg(feature = "async")]
+    pub async fn lookup_addrs_async(
+        &self,
+        ip_addrs: &[IpAddr],
+        concurrency: usize,
+    ) -> HashMap<IpAddr, Result<Vec<String>, String>> {
+        let resolver: DnsResolver = self.clone();
+        let queue: Vec<IpAddr> = ip_addrs.to_vec();
+        match run(async move { resolver.resolve_addrs(queue, concurrency).await }).await {
+            Ok(results) => results,
+            Err(e) => ip_addrs.iter().map(|ip| (*ip, Err(e.clone()))).collect(),
+        }
+    }
+    async fn resolve_host(&self, host_name: &str) -> Result<Vec<IpAddr>, String> {
+        let key = CacheKey::Host {
+            scope: self.scope,
+            host_name: host_name.to_ascii_lowercase(),
+        };
+        if let Some(CacheValue::Addrs(ip_addrs)) = self.cached(&key) {
+            return Ok(ip_addrs);
+        }
+        match self.resolver.lookup_ip(host_name).await {
+            Ok(lookup) => {
+                let ip_addrs: Vec<IpAddr> = lookup.iter().collect();
+                self.store(
+                    key,
+                    CacheValue::Addrs(ip_addrs.clone()),
+                    lookup.valid_until(),
+                );
+                Ok(ip_addrs)
+            }
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound { negative_ttl, .. } => {
+                    let valid_until: Instant = dns_cache().negative_valid_until(*negative_ttl);
+                    self.store(key, CacheValue::Addrs(Vec::new()), valid_until);
+                    Ok(Vec::new())
+                }
+                _ => Err(format!("Unable to resolve {}: {}", host_name, e)),
+            },
+        }
+    }
+    async fn resolve_addr(&self, ip_addr: IpAddr) -> Result<Vec<String>, String> {
+        let key = CacheKey::Addr {
+            scope: self.scope,
+            ip_addr,
+        };
+        if let Some(CacheValue::Names(names)) = self.cached(&key) {
+            return Ok(names);
+        }
+        let resolver: &TokioAsyncResolver = if crate::ip::is_global_addr(ip_addr) {
+            &self.reverse_resolver
+        } else {
+            &self.local_reverse_resolver
+        };
+        match resolver.reverse_lookup(ip_addr).await {
+            Ok(lookup) => {
+                let names: Vec<String> = lookup
+                    .iter()
+                    .map(|name| name.to_string().trim_end_matches('.').to_string())
+                    .collect();
+                self.store(key, CacheValue::Names(names.clone()), lookup.valid_until());
+                Ok(names)
+            }
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound { negative_ttl, .. } => {
+                    let valid_until: Instant = dns_cache().negative_valid_until(*negative_ttl);
+                    self.store(key, CacheValue::Names(Vec::new()), valid_until);
+                    Ok(Vec::new())
+                }
+                _ => Err(format!("Unable to resolve {}: {}", ip_addr, e)),
+            },
+        }
+    }
+    /// Reverse lookups by `concurrency` tasks sharing a queue
+    async fn resolve_addrs(
+        &self,
+        ip_addrs: Vec<IpAddr>,
+        concurrency: usize,
+    ) -> HashMap<IpAddr, Result<Vec<String>, String>> {
+        let mut unique: HashSet<IpAddr> = HashSet::new();
+        let queue: VecDeque<IpAddr> = ip_addrs
+            .into_iter()
+            .filter(|ip| unique.insert(*ip))
+            .collect();
+        let workers: usize = concurrency.clamp(1, queue.len().max(1));
+        let queue: Arc<Mutex<VecDeque<IpAddr>>> = Arc::new(Mutex::new(queue));
+        let mut tasks = JoinSet::new();
+        for _ in 0..workers {
+            let resolver: DnsResolver = self.clone();
+            let queue = queue.clone();
+            tasks.spawn(async move {
+                let mut results: Vec<(IpAddr, Result<Vec<String>, String>)> = Vec::new();
+                loop {
+                    let next: Option<IpAddr> = match queue.lock() {
+                        Ok(mut queue) => queue.pop_front(),
+                        Err(_) => None,
+                    };
+                    let Some(ip_addr) = next else {
+                        break;
+                    };
+                    results.push((ip_addr, resolver.resolve_addr(ip_addr).await));
+                }
+                results
+            });
+        }
+        let mut results: HashMap<IpAddr, Result<Vec<String>, String>> = HashMap::new();
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok(worker_results) = joined {
+                results.extend(worker_results);
+            }
+        }
+        results
+    }
+    fn cached(&self, key: &CacheKey) -> Option<CacheValue> {
+        if self.config.use_cache {
+            dns_cache().get(key)
+        } else {
+            None
+        }
+    }
+    fn store(&self, key: CacheKey, value: CacheValue, valid_until: Instant) {
+        if self.config.use_cache {
+            dns_cache().insert(key, value, valid_until);
+        }
+    }
+}
+
+impl std::fmt::Debug for DnsResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DnsResolver")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// First IPv4 address of `host_name`, else its first IPv6 address.
+/// `TargetResolver` chooses with another `AddressPolicy` and reports failures.
+pub fn lookup_host_name(host_name: String) -> Option<IpAddr> {
+    first_address(lookup_host(host_name))
+}
+
+#[cfg(feature = "async")]
+pub async fn lookup_host_name_async(host_name: String) -> Option<IpAddr> {
+    let resolver: Arc<DnsResolver> = default_resolver().ok()?;
+    first_address(resolver.lookup_host_async(&host_name).await.ok()?)
+}
+
+fn first_address(ip_vec: Vec<IpAddr>) -> Option<IpAddr> {
+    ip_vec
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or(ip_vec.first())
+        .copied()
+}
+
+pub fn lookup_ip_addr(ip_addr: IpAddr) -> Option<String> {
+    lookup_addr(ip_addr).into_iter().next()
+}
+
+#[cfg(feature = "async")]
+pub async fn lookup_ip_addr_async(ip_addr: String) -> String {
+    let Ok(ip_addr) = IpAddr::from_str(ip_addr.as_str()) else {
+        return String::new();
+    };
+    let Ok(resolver) = default_resolver() else {
+        return String::new();
+    };
+    match resolver.lookup_addr_async(ip_addr).await {
+        Ok(names) => names.into_iter().next().unwrap_or_default(),
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(feature = "async")]
+pub async fn lookup_ips_async(ips: Vec<IpAddr>) -> HashMap<IpAddr, String> {
+    let Ok(resolver) = default_resolver() else {
+        return HashMap::new();
+    };
+    first_names(resolver.lookup_addrs_async(&ips, 10).await)
+}
+
+#[cfg(feature = "async")]
+pub fn lookup_ips(ips: Vec<IpAddr>) -> HashMap<IpAddr, String> {
+    first_names(lookup_addrs_with_results(&ips, 10))
+}
+
+#[cfg(feature = "async")]
+fn first_names(results: HashMap<IpAddr, Result<Vec<String>, String>>) -> HashMap<IpAddr, String> {
+    results
+        .into_iter()
+        .map(|(ip, names)| {
+            let name: String = names
+                .ok()
+                .and_then(|names| names.into_iter().next())
+                .unwrap_or_default();
+            (ip, name)
+        })
+        .collect()
+}
+
+/// Addresses of `host` with the default configuration. Empty on failure.
+pub fn lookup_host(host: String) -> Vec<IpAddr> {
+    default_resolver()
+        .and_then(|resolver| resolver.lookup_host(&host))
+        .unwrap_or_default()
+}
+
+/// Host names of `addr` with the default configuration. Empty on failure.
+pub fn lookup_addr(addr: IpAddr) -> Vec<String> {
+    default_resolver()
+        .and_then(|resolver| resolver.lookup_addr(addr))
+        .unwrap_or_default()
+}
+
+/// Host names of every address of `addrs` with the default configuration, with at most
+/// `concurrency` lookups at a time. Empty for the addresses whose lookup failed.
+pub fn lookup_addrs(addrs: &[IpAddr], concurrency: usize) -> HashMap<IpAddr, Vec<String>> {
+    lookup_addrs_with_results(addrs, concurrency)
+        .into_iter()
+        .map(|(ip, names)| (ip, names.unwrap_or_default()))
+        .collect()
+}
+
+fn lookup_addrs_with_results(
+    addrs: &[IpAddr],
+    concurrency: usize,
+) -> HashMap<IpAddr, Result<Vec<String>, String>> {
+    match default_resolver() {
+        Ok(resolver) => resolver.lookup_addrs(addrs, concurrency),
+        Err(e) => addrs.iter().map(|ip| (*ip, Err(e.clone()))).collect(),
+    }
+}
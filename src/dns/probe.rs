@@ -0,0 +1,505 @@
+use crate::result::{
+    DnsProbeResult, DnsRecord, DnsResponse, DnsStat, ProbeStatus, ProbeStatusKind,
+};
+use crate::setting::Protocol;
+use crate::sys::io_status;
+use hickory_resolver::proto::op::{Edns, Message, MessageType, OpCode, Query};
+use hickory_resolver::proto::rr::{DNSClass, Name, Record, RecordType};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DNS_DEFAULT_PORT: u16 = 53;
+/// UDP payload size advertised with EDNS(0), as recommended by DNS Flag Day 2020
+pub const DNS_DEFAULT_EDNS_UDP_SIZE: u16 = 1232;
+
+/// DNS prober.
+///
+/// Sends `count` queries to a server, each over UDP with a TCP retry when the response is
+/// truncated, and reports every response with RTT and loss statistics.
+#[derive(Clone, Debug)]
+pub struct DnsProber {
+    /// Server to query
+    pub server: SocketAddr,
+    /// Queried name
+    pub name: Name,
+    pub record_type: RecordType,
+    pub query_class: DNSClass,
+    /// Set the RD flag to ask for recursion
+    pub recursion_desired: bool,
+    /// UDP payload size advertised with EDNS(0). None sends no OPT record
+    pub edns_udp_size: Option<u16>,
+    /// Query over TCP only
+    pub tcp_only: bool,
+    /// Number of queries
+    pub count: u8,
+    /// Interval between queries
+    pub send_rate: Duration,
+    /// Timeout of every query
+    pub receive_timeout: Duration,
+    /// Stop sending queries after this time
+    pub probe_timeout: Duration,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<DnsResponse>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<DnsResponse>>>,
+}
+
+impl DnsProber {
+    /// Create new DnsProber instance querying the A records of `name` at `server`
+    pub fn new(server: IpAddr, name: &str) -> Result<DnsProber, String> {
+        let name: Name = Name::from_utf8(name)
+            .map_err(|e| format!("DnsProber::new: invalid name {}: {}", name, e))?;
+        let (tx, rx) = channel();
+        let prober = DnsProber {
+            server: SocketAddr::new(server, DNS_DEFAULT_PORT),
+            name,
+            record_type: RecordType::A,
+            query_class: DNSClass::IN,
+            recursion_desired: true,
+            edns_udp_size: Some(DNS_DEFAULT_EDNS_UDP_SIZE),
+            tcp_only: false,
+            count: 4,
+            send_rate: Duration::from_secs(1),
+            receive_timeout: Duration::from_secs(2),
+            probe_timeout: Duration::from_secs(30),
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(prober)
+    }
+    pub fn with_port(mut self, port: u16) -> DnsProber {
+        self.server.set_port(port);
+        self
+    }
+    pub fn with_record_type(mut self, record_type: RecordType) -> DnsProber {
+        self.record_type = record_type;
+        self
+    }
+    pub fn with_query_class(mut self, query_class: DNSClass) -> DnsProber {
+        self.query_class = query_class;
+        self
+    }
+    pub fn with_recursion_desired(mut self, recursion_desired: bool) -> DnsProber {
+        self.recursion_desired = recursion_desired;
+        self
+    }
+    pub fn with_edns_udp_size(mut self, edns_udp_size: Option<u16>) -> DnsProber {
+        self.edns_udp_size = edns_udp_size;
+        self
+    }
+    pub fn with_tcp_only(mut self, tcp_only: bool) -> DnsProber {
+        self.tcp_only = tcp_only;
+        self
+    }
+    pub fn with_count(mut self, count: u8) -> DnsProber {
+        self.count = count;
+        self
+    }
+    pub fn with_send_rate(mut self, send_rate: Duration) -> DnsProber {
+        self.send_rate = send_rate;
+        self
+    }
+    pub fn with_receive_timeout(mut self, receive_timeout: Duration) -> DnsProber {
+        self.receive_timeout = receive_timeout;
+        self
+    }
+    pub fn with_probe_timeout(mut self, probe_timeout: Duration) -> DnsProber {
+        self.probe_timeout = probe_timeout;
+        self
+    }
+    /// Run probe
+    pub fn probe(&self) -> Result<DnsProbeResult, String> {
+        if self.count == 0 {
+            return Err("DnsProber::probe: count is zero".to_string());
+        }
+        let mut result = DnsProbeResult::new();
+        result.start_time = crate::sys::get_sysdate();
+        let start_time = Instant::now();
+        let mut responses: Vec<DnsResponse> = Vec::new();
+        for seq in 1..self.count + 1 {
+            if start_time.elapsed() > self.probe_timeout {
+                result.probe_status =
+                    ProbeStatus::with_timeout_message("dns_probe: probe timeout".to_string());
+                break;
+            }
+            let send_time = Instant::now();
+            let response: DnsResponse = self.query(seq)?;
+            responses.push(response.clone());
+            if let Ok(lr) = self.tx.lock() {
+                let _ = lr.send(response);
+            }
+            if seq < self.count {
+                std::thread::sleep(self.send_rate.saturating_sub(send_time.elapsed()));
+            }
+        }
+        let probe_time = start_time.elapsed();
+        result.end_time = crate::sys::get_sysdate();
+        result.elapsed_time = probe_time;
+        let answered: Vec<Duration> = responses
+            .iter()
+            .filter(|r| r.probe_status.kind == ProbeStatusKind::Done)
+            .map(|r| r.rtt)
+            .collect();
+        let (min, avg, max) = crate::sys::rtt_stat(&answered);
+        result.stat = DnsStat {
+            transmitted_count: responses.len(),
+            received_count: answered.len(),
+            probe_time,
+            min,
+            avg,
+            max,
+            responses,
+        };
+        Ok(result)
+    }
+    /// Get progress receiver
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<DnsResponse>>> {
+        self.rx.clone()
+    }
+    /// Send one query. Err only if the query cannot be encoded.
+    fn query(&self, seq: u8) -> Result<DnsResponse, String> {
+        let id: u16 = rand::random();
+        let query: Vec<u8> = self.build_query(id)?;
+        let mut response = DnsResponse {
+            seq,
+            ip_addr: self.server.ip(),
+            port: self.server.port(),
+            id,
+            sent_packet_size: query.len(),
+            ..DnsResponse::new()
+        };
+        let send_time = Instant::now();
+        let reply: Result<(Message, usize), ProbeStatus> = if self.tcp_only {
+            response.protocol = Protocol::TCP;
+            self.exchange_tcp(&query, id)
+        } else {
+            match self.exchange_udp(&query, id) {
+                // Retry over TCP for the full response
+                Ok((message, _)) if message.truncated() => {
+                    response.truncated = true;
+                    response.protocol = Protocol::TCP;
+                    self.exchange_tcp(&query, id)
+                }
+                reply => reply,
+            }
+        };
+        response.rtt = send_time.elapsed();
+        match reply {
+            Ok((message, size)) => {
+                fill_response(&mut response, &message);
+                response.received_packet_size = size;
+            }
+            Err(probe_status) => response.probe_status = probe_status,
+        }
+        Ok(response)
+    }
+    fn build_query(&self, id: u16) -> Result<Vec<u8>, String> {
+        let mut query: Query = Query::query(self.name.clone(), self.record_type);
+        query.set_query_class(self.query_class);
+        let mut message: Message = Message::new();
+        message
+            .set_id(id)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(self.recursion_desired)
+            .add_query(query);
+        if let Some(udp_size) = self.edns_udp_size {
+            let mut edns: Edns = Edns::new();
+            edns.set_max_payload(udp_size).set_version(0);
+            message.set_edns(edns);
+        }
+        message
+            .to_vec()
+            .map_err(|e| format!("DnsProber: unable to encode query: {}", e))
+    }
+    fn exchange_udp(&self, query: &[u8], id: u16) -> Result<(Message, usize), ProbeStatus> {
+        let local_addr: IpAddr = match self.server {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        let socket: UdpSocket = UdpSocket::bind(SocketAddr::new(local_addr, 0))
+            .and_then(|socket| socket.connect(self.server).map(|_| socket))
+            .map_err(|e| io_status("UDP socket", e))?;
+        socket.send(query).map_err(|e| io_status("Send query", e))?;
+        let deadline: Instant = Instant::now() + self.receive_timeout;
+        let mut buf = [0u8; 65535];
+        loop {
+            let remaining: Duration = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(self.timeout_status());
+            }
+            let _ = socket.set_read_timeout(Some(remaining));
+            let size: usize = socket.recv(&mut buf).map_err(|e| match e.kind() {
+                ErrorKind::TimedOut | ErrorKind::WouldBlock => self.timeout_status(),
+                _ => io_status("Receive response", e),
+            })?;
+            // Late answers to previous queries and spoofed replies are skipped
+            match Message::from_vec(&buf[..size]) {
+                Ok(message)
+                    if message.id() == id && message.message_type() == MessageType::Response =>
+                {
+                    return Ok((message, size))
+                }
+                _ => continue,
+            }
+        }
+    }
+    fn exchange_tcp(&self, query: &[u8], id: u16) -> Result<(Message, usize), ProbeStatus> {
+        let mut stream: TcpStream = TcpStream::connect_timeout(&self.server, self.receive_timeout)
+            .map_err(|e| io_status(&format!("Connect to {}", self.server), e))?;
+        let _ = stream.set_read_timeout(Some(self.receive_timeout));
+        let _ = stream.set_write_timeout(Some(self.receive_timeout));
+        // Messages over TCP are prefixed with their length
+        let mut request: Vec<u8> = (query.len() as u16).to_be_bytes().to_vec();
+        request.extend_from_slice(query);
+        stream
+            .write_all(&request)
+            .map_err(|e| io_status("Send query", e))?;
+        let mut length = [0u8; 2];
+        stream
+            .read_exact(&mut length)
+            .map_err(|e| io_status("Receive response", e))?;
+        let mut buf: Vec<u8> = vec![0u8; u16::from_be_bytes(length) as usize];
+        stream
+            .read_exact(&mut buf)
+            .map_err(|e| io_status("Receive response", e))?;
+        match Message::from_vec(&buf) {
+            Ok(message) if message.id() == id => Ok((message, buf.len())),
+            Ok(_) => Err(ProbeStatus::with_error_message(String::from(
+                "Response ID does not match query",
+            ))),
+            Err(e) => Err(ProbeStatus::with_error_message(format!(
+                "Malformed response: {}",
+                e
+            ))),
+        }
+    }
+    fn timeout_status(&self) -> ProbeStatus {
+        ProbeStatus::with_timeout_message(format!("Request timeout for {}", self.server))
+    }
+}
+
+fn fill_response(response: &mut DnsResponse, message: &Message) {
+    let rcode: u16 = message.response_code().into();
+    response.rcode = rcode;
+    response.rcode_name = rcode_name(rcode);
+    let flags: [(&str, bool); 7] = [
+        ("qr", message.message_type() == MessageType::Response),
+        ("aa", message.authoritative()),
+        ("tc", message.truncated()),
+        ("rd", message.recursion_desired()),
+        ("ra", message.recursion_available()),
+        ("ad", message.authentic_data()),
+        ("cd", message.checking_disabled()),
+    ];
+    response.flags = flags
+        .iter()
+        .filter(|(_, set)| *set)
+        .map(|(flag, _)| flag.to_string())
+        .collect();
+    response.answers = message.answers().iter().map(dns_record).collect();
+    response.authorities = message.name_servers().iter().map(dns_record).collect();
+    response.additionals = message.additionals().iter().map(dns_record).collect();
+}
+
+fn dns_record(record: &Record) -> DnsRecord {
+    DnsRecord {
+        name: record.name().to_string(),
+        record_type: record.record_type().to_string(),
+        class: record.dns_class().to_string(),
+        ttl: record.ttl(),
+        data: record.data().map(|d| d.to_string()).unwrap_or_default(),
+    }
+}
+
+/// Mnemonic of a response code (RFC 6895)
+pub fn rcode_name(rcode: u16) -> String {
+    match rcode {
+        0 => String::from("NOERROR"),
+        1 => String::from("FORMERR"),
+        2 => String::from("SERVFAIL"),
+        3 => String::from("NXDOMAIN"),
+        4 => String::from("NOTIMP"),
+        5 => String::from("REFUSED"),
+        6 => String::from("YXDOMAIN"),
+        7 => String::from("YXRRSET"),
+        8 => String::from("NXRRSET"),
+        9 => String::from("NOTAUTH"),
+        10 => String::from("NOTZONE"),
+        16 => String::from("BADVERS"),
+        23 => String::from("BADCOOKIE"),
+        _ => format!("RCODE{}", rcode),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::op::ResponseCode;
+    use hickory_resolver::proto::rr::rdata::A;
+    use hickory_resolver::proto::rr::RData;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// UDP socket and TCP listener on the same port of 127.0.0.1
+    fn bind() -> (UdpSocket, TcpListener, u16) {
+        for _ in 0..16 {
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            let port: u16 = listener.local_addr().unwrap().port();
+            if let Ok(socket) = UdpSocket::bind((Ipv4Addr::LOCALHOST, port)) {
+                return (socket, listener, port);
+            }
+        }
+        panic!("no free port for UDP and TCP");
+    }
+
+    /// Response to `query`, with one A record unless truncated
+    fn answer(query: &[u8], truncated: bool, response_code: ResponseCode) -> Vec<u8> {
+        let query: Message = Message::from_vec(query).unwrap();
+        let mut message: Message = Message::new();
+        message
+            .set_id(query.id())
+            .set_message_type(MessageType::Response)
+            .set_op_code(OpCode::Query)
+            .set_authoritative(true)
+            .set_recursion_desired(query.recursion_desired())
+            .set_recursion_available(true)
+            .set_truncated(truncated)
+            .set_response_code(response_code)
+            .add_queries(query.queries().to_vec());
+        if !truncated && response_code == ResponseCode::NoError {
+            let name: Name = query.queries()[0].name().clone();
+            message.add_answer(Record::from_rdata(
+                name,
+                300,
+                RData::A(A(Ipv4Addr::new(192, 0, 2, 1))),
+            ));
+        }
+        message.to_vec().unwrap()
+    }
+
+    /// Read one length-prefixed query and write the answer
+    fn answer_tcp(listener: &TcpListener, response_code: ResponseCode) -> Vec<u8> {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut length = [0u8; 2];
+        stream.read_exact(&mut length).unwrap();
+        let mut query: Vec<u8> = vec![0u8; u16::from_be_bytes(length) as usize];
+        stream.read_exact(&mut query).unwrap();
+        let response: Vec<u8> = answer(&query, false, response_code);
+        stream
+            .write_all(&(response.len() as u16).to_be_bytes())
+            .unwrap();
+        stream.write_all(&response).unwrap();
+        query
+    }
+
+    fn prober(port: u16) -> DnsProber {
+        DnsProber::new(IpAddr::V4(Ipv4Addr::LOCALHOST), "example.com")
+            .unwrap()
+            .with_port(port)
+            .with_count(1)
+            .with_receive_timeout(Duration::from_secs(2))
+    }
+
+    #[test]
+    fn build_query_message() {
+        let prober: DnsProber = prober(DNS_DEFAULT_PORT)
+            .with_record_type(RecordType::AAAA)
+            .with_recursion_desired(false);
+        let query: Message = Message::from_vec(&prober.build_query(0x1234).unwrap()).unwrap();
+        assert_eq!(query.id(), 0x1234);
+        assert_eq!(query.message_type(), MessageType::Query);
+        assert!(!query.recursion_desired());
+        assert_eq!(query.queries().len(), 1);
+        assert_eq!(query.queries()[0].name().to_string(), "example.com.");
+        assert_eq!(query.queries()[0].query_type(), RecordType::AAAA);
+        assert_eq!(query.queries()[0].query_class(), DNSClass::IN);
+        let edns: &Edns = query.extensions().as_ref().unwrap();
+        assert_eq!(edns.max_payload(), DNS_DEFAULT_EDNS_UDP_SIZE);
+
+        let prober: DnsProber = prober.with_edns_udp_size(None);
+        let query: Message = Message::from_vec(&prober.build_query(1).unwrap()).unwrap();
+        assert!(query.extensions().is_none());
+    }
+
+    #[test]
+    fn truncated_udp_falls_back_to_tcp() {
+        let (socket, listener, port) = bind();
+        let server = thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (size, peer) = socket.recv_from(&mut buf).unwrap();
+            let udp_query: Vec<u8> = buf[..size].to_vec();
+            // Late answer to another query, skipped
+            let mut stale: Vec<u8> = answer(&udp_query, false, ResponseCode::NoError);
+            stale[1] ^= 0xff;
+            socket.send_to(&stale, peer).unwrap();
+            socket
+                .send_to(&answer(&udp_query, true, ResponseCode::NoError), peer)
+                .unwrap();
+            let tcp_query: Vec<u8> = answer_tcp(&listener, ResponseCode::NoError);
+            (udp_query, tcp_query)
+        });
+        let result: DnsProbeResult = prober(port).probe().unwrap();
+        let (udp_query, tcp_query) = server.join().unwrap();
+        assert_eq!(udp_query, tcp_query);
+        assert_eq!(result.stat.transmitted_count, 1);
+        assert_eq!(result.stat.received_count, 1);
+        let response: &DnsResponse = &result.stat.responses[0];
+        assert_eq!(response.probe_status.kind, ProbeStatusKind::Done);
+        assert!(response.truncated);
+        assert!(matches!(response.protocol, Protocol::TCP));
+        assert_eq!(response.sent_packet_size, udp_query.len());
+        assert_eq!(response.rcode_name, "NOERROR");
+        assert_eq!(response.flags, vec!["qr", "aa", "rd", "ra"]);
+        assert_eq!(
+            response.answers,
+            vec![DnsRecord {
+                name: String::from("example.com."),
+                record_type: String::from("A"),
+                class: String::from("IN"),
+                ttl: 300,
+                data: String::from("192.0.2.1"),
+            }]
+        );
+    }
+
+    #[test]
+    fn tcp_only_query() {
+        let (_socket, listener, port) = bind();
+        let server = thread::spawn(move || answer_tcp(&listener, ResponseCode::NXDomain));
+        let result: DnsProbeResult = prober(port).with_tcp_only(true).probe().unwrap();
+        server.join().unwrap();
+        let response: &DnsResponse = &result.stat.responses[0];
+        assert_eq!(response.probe_status.kind, ProbeStatusKind::Done);
+        assert!(!response.truncated);
+        assert!(matches!(response.protocol, Protocol::TCP));
+        assert_eq!(response.rcode, 3);
+        assert_eq!(response.rcode_name, "NXDOMAIN");
+        assert!(response.answers.is_empty());
+    }
+
+    #[test]
+    fn udp_timeout() {
+        let (_socket, _listener, port) = bind();
+        let result: DnsProbeResult = prober(port)
+            .with_receive_timeout(Duration::from_millis(100))
+            .probe()
+            .unwrap();
+        assert_eq!(result.stat.received_count, 0);
+        let response: &DnsResponse = &result.stat.responses[0];
+        assert_eq!(response.probe_status.kind, ProbeStatusKind::Timeout);
+        assert!(matches!(response.protocol, Protocol::UDP));
+    }
+
+    #[test]
+    fn rcode_names() {
+        assert_eq!(rcode_name(0), "NOERROR");
+        assert_eq!(rcode_name(3), "NXDOMAIN");
+        assert_eq!(rcode_name(16), "BADVERS");
+        assert_eq!(rcode_name(23), "BADCOOKIE");
+        assert_eq!(rcode_name(11), "RCODE11");
+    }
+}
@@ -0,0 +1,580 @@
+use super::{block_on, default_config, shared_resolver, DnsConfig, DnsResolver, IpStrategy};
+use crate::result::ResolvedTarget;
+use std::cmp::Ordering;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Choice of the destination address among the addresses of a host name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AddressPolicy {
+    /// IPv4 addresses only
+    Ipv4Only,
+    /// IPv6 addresses only
+    Ipv6Only,
+    /// First IPv4 address, else first IPv6 address
+    PreferIpv4,
+    /// First IPv6 address, else first IPv4 address
+    PreferIpv6,
+    /// Destination address selection of RFC 6724
+    Rfc6724,
+    /// Happy Eyeballs v2 (RFC 8305). Lookups are raced and, with a port, so are
+    /// the connections to the RFC 6724 ordered addresses
+    HappyEyeballs,
+}
+
+impl AddressPolicy {
+    pub fn name(&self) -> String {
+        match *self {
+            AddressPolicy::Ipv4Only => String::from("IPv4 only"),
+            AddressPolicy::Ipv6Only => String::from("IPv6 only"),
+            AddressPolicy::PreferIpv4 => String::from("Prefer IPv4"),
+            AddressPolicy::PreferIpv6 => String::from("Prefer IPv6"),
+            AddressPolicy::Rfc6724 => String::from("RFC 6724"),
+            AddressPolicy::HappyEyeballs => String::from("Happy Eyeballs"),
+        }
+    }
+}
+
+/// Resolves a host name to the destination address chosen by an `AddressPolicy`
+#[derive(Clone, Debug)]
+pub struct TargetResolver {
+    /// Host name or IP address
+    pub host_name: String,
+    pub policy: AddressPolicy,
+    /// TCP port raced by `AddressPolicy::HappyEyeballs`. Without it only the lookups are raced
+    pub port: Option<u16>,
+    /// Timeout of one connection attempt
+    pub connect_timeout: Duration,
+    /// Delay between the starts of two connection attempts (RFC 8305 section 5)
+    pub connection_attempt_delay: Duration,
+    /// Wait for the other answer once the A or AAAA answer is in (RFC 8305 section 3)
+    pub resolution_delay: Duration,
+    pub dns_config: DnsConfig,
+}
+
+impl TargetResolver {
+    /// Create new TargetResolver instance with the default DNS configuration
+    pub fn new(host_name: &str, policy: AddressPolicy) -> TargetResolver {
+        TargetResolver {
+            host_name: host_name.trim_matches(['[', ']']).to_string(),
+            policy,
+            port: None,
+            connect_timeout: Duration::from_secs(5),
+            connection_attempt_delay: Duration::from_millis(250),
+            resolution_delay: Duration::from_millis(50),
+            dns_config: default_config(),
+        }
+    }
+    pub fn with_port(mut self, port: u16) -> TargetResolver {
+        self.port = Some(port);
+        self
+    }
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> TargetResolver {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+    pub fn with_connection_attempt_delay(mut self, delay: Duration) -> TargetResolver {
+        self.connection_attempt_delay = delay;
+        self
+    }
+    pub fn with_resolution_delay(mut self, resolution_delay: Duration) -> TargetResolver {
+        self.resolution_delay = resolution_delay;
+        self
+    }
+    pub fn with_dns_config(mut self, dns_config: DnsConfig) -> TargetResolver {
+        self.dns_config = dns_config;
+        self
+    }
+    /// Resolve the host name. Err if no address matches the policy
+    /// or, when racing connections, if none of them succeeds.
+    pub fn resolve(&self) -> Result<ResolvedTarget, String> {
+        let start_time = Instant::now();
+        let (want_ipv4, want_ipv6) = match self.policy {
+            AddressPolicy::Ipv4Only => (true, false),
+            AddressPolicy::Ipv6Only => (false, true),
+            _ => (true, true),
+        };
+        let family_addresses: FamilyAddresses = match self.host_name.parse::<IpAddr>() {
+            Ok(ip_addr) => FamilyAddresses::from_addresses(vec![ip_addr]),
+            Err(_) => {
+                let resolution_delay = if self.policy == AddressPolicy::HappyEyeballs {
+                    Some(self.resolution_delay)
+                } else {
+                    None
+                };
+                lookup_families(
+                    &self.dns_config,
+                    &self.host_name,
+                    want_ipv4,
+                    want_ipv6,
+                    resolution_delay,
+                )
+            }
+        };
+        let ipv4: Vec<IpAddr> = if want_ipv4 {
+            family_addresses.ipv4.clone()
+        } else {
+            Vec::new()
+        };
+        let ipv6: Vec<IpAddr> = if want_ipv6 {
+            family_addresses.ipv6.clone()
+        } else {
+            Vec::new()
+        };
+        let addresses: Vec<IpAddr> = match self.policy {
+            AddressPolicy::Ipv4Only => ipv4,
+            AddressPolicy::Ipv6Only => ipv6,
+            AddressPolicy::PreferIpv4 => ipv4.into_iter().chain(ipv6).collect(),
+            AddressPolicy::PreferIpv6 => ipv6.into_iter().chain(ipv4).collect(),
+            AddressPolicy::Rfc6724 => sort_addresses(&[ipv4, ipv6].concat()),
+            AddressPolicy::HappyEyeballs => interleave(&sort_addresses(&[ipv4, ipv6].concat())),
+        };
+        if addresses.is_empty() {
+            return Err(format!(
+                "TargetResolver::resolve: no {} address for {}{}",
+                match self.policy {
+                    AddressPolicy::Ipv4Only => "IPv4",
+                    AddressPolicy::Ipv6Only => "IPv6",
+                    _ => "IP",
+                },
+                self.host_name,
+                family_addresses.error_suffix()
+            ));
+        }
+        let lookup_time: Duration = start_time.elapsed();
+        let (ip_addr, connect_time) = match (self.policy, self.port) {
+            (AddressPolicy::HappyEyeballs, Some(port)) => {
+                let (ip_addr, connect_time) = self.race(&addresses, port)?;
+                (ip_addr, Some(connect_time))
+            }
+            _ => (addresses[0], None),
+        };
+        Ok(ResolvedTarget {
+            host_name: self.host_name.clone(),
+            ip_addr,
+            addresses,
+            policy: self.policy,
+            lookup_time,
+            connect_time,
+        })
+    }
+    /// Connect to `addresses` in turn, starting an attempt every `connection_attempt_delay`
+    /// or as soon as the previous one fails. The first established connection wins.
+    fn race(&self, addresses: &[IpAddr], port: u16) -> Result<(IpAddr, Duration), String> {
+        let (tx, rx): (Sender<ConnectAttempt>, Receiver<ConnectAttempt>) = channel();
+        let start_time = Instant::now();
+        let mut next: usize = 0;
+        let mut pending: usize = 0;
+        let mut next_attempt: Instant = start_time;
+        let mut errors: Vec<String> = Vec::new();
+        loop {
+            if next < addresses.len() && Instant::now() >= next_attempt {
+                let socket_addr = SocketAddr::new(addresses[next], port);
+                let connect_timeout = self.connect_timeout;
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    let result = TcpStream::connect_timeout(&socket_addr, connect_timeout)
+                        .map(|_| ())
+                        .map_err(|e| e.to_string());
+                    let _ = tx.send(ConnectAttempt {
+                        ip_addr: socket_addr.ip(),
+                        result,
+                    });
+                });
+                next += 1;
+                pending += 1;
+                next_attempt = Instant::now() + self.connection_attempt_delay;
+            }
+            if pending == 0 {
+                return Err(format!(
+                    "TargetResolver::resolve: unable to connect to {} port {}: {}",
+                    self.host_name,
+                    port,
+                    errors.join(", ")
+                ));
+            }
+            let wait: Duration = if next < addresses.len() {
+                next_attempt.saturating_duration_since(Instant::now())
+            } else {
+                self.connect_timeout
+            };
+            match rx.recv_timeout(wait) {
+                Ok(attempt) => match attempt.result {
+                    Ok(()) => return Ok((attempt.ip_addr, start_time.elapsed())),
+                    Err(e) => {
+                        pending -= 1;
+                        errors.push(format!("{}: {}", attempt.ip_addr, e));
+                        next_attempt = Instant::now();
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => pending = 0,
+            }
+        }
+    }
+}
+
+struct ConnectAttempt {
+    ip_addr: IpAddr,
+    result: Result<(), String>,
+}
+
+/// Addresses of a host name by family, with the errors of the failed lookups
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FamilyAddresses {
+    pub ipv4: Vec<IpAddr>,
+    pub ipv6: Vec<IpAddr>,
+    pub errors: Vec<String>,
+}
+
+impl FamilyAddresses {
+    pub(crate) fn from_addresses(addresses: Vec<IpAddr>) -> FamilyAddresses {
+        let (ipv4, ipv6) = addresses.into_iter().partition(|ip| ip.is_ipv4());
+        FamilyAddresses {
+            ipv4,
+            ipv6,
+            errors: Vec::new(),
+        }
+    }
+    fn error_suffix(&self) -> String {
+        if self.errors.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", self.errors.join(", "))
+        }
+    }
+}
+
+/// Look up the A and AAAA records of `host_name` in parallel, as tasks on the shared runtime.
+/// With `resolution_delay`, the first answer with addresses only waits that long for the other
+/// one. RFC 8305 does not wait for the A answer, but then IPv4 could not be raced.
+pub(crate) fn lookup_families(
+    config: &DnsConfig,
+    host_name: &str,
+    ipv4: bool,
+    ipv6: bool,
+    resolution_delay: Option<Duration>,
+) -> FamilyAddresses {
+    let mut addresses = FamilyAddresses::default();
+    let mut resolvers: Vec<(IpStrategy, Arc<DnsResolver>)> = Vec::new();
+    for (wanted, ip_strategy) in [(ipv4, IpStrategy::Ipv4Only), (ipv6, IpStrategy::Ipv6Only)] {
+        if !wanted {
+            continue;
+        }
+        match shared_resolver(&config.clone().with_ip_strategy(ip_strategy)) {
+            Ok(resolver) => resolvers.push((ip_strategy, resolver)),
+            Err(e) => addresses.errors.push(e),
+        }
+    }
+    let host_name: String = host_name.to_string();
+    let lookups = async move {
+        let mut tasks = JoinSet::new();
+        for (ip_strategy, resolver) in resolvers {
+            let host_name: String = host_name.clone();
+            tasks.spawn(async move { (ip_strategy, resolver.resolve_host(&host_name).await) });
+        }
+        let mut results: Vec<(IpStrategy, Result<Vec<IpAddr>, String>)> = Vec::new();
+        let mut deadline: Option<tokio::time::Instant> = None;
+        loop {
+            let joined = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, tasks.join_next()).await {
+                        Ok(joined) => joined,
+                        Err(_) => break,
+                    }
+                }
+                None => tasks.join_next().await,
+            };
+            let Some(joined) = joined else {
+                break;
+            };
+            let Ok((ip_strategy, result)) = joined else {
+                continue;
+            };
+            if deadline.is_none() && result.as_ref().is_ok_and(|ips| !ips.is_empty()) {
+                deadline = resolution_delay.map(|delay| tokio::time::Instant::now() + delay);
+            }
+            results.push((ip_strategy, result));
+        }
+        // A late answer still fills the cache
+        tasks.detach_all();
+        results
+    };
+    let results = match block_on(lookups) {
+        Ok(results) => results,
+        Err(e) => {
+            addresses.errors.push(e);
+            return addresses;
+        }
+    };
+    for (ip_strategy, result) in results {
+        match result {
+            Ok(ips) if ip_strategy == IpStrategy::Ipv4Only => addresses.ipv4 = ips,
+            Ok(ips) => addresses.ipv6 = ips,
+            Err(e) => addresses.errors.push(e),
+        }
+    }
+    addresses
+}
+
+/// Sort destination addresses by the rules of RFC 6724 section 6.
+///
+/// Rules 3, 4 and 7 need information the system does not expose and are skipped.
+/// Equal addresses keep their order (rule 10).
+pub fn sort_addresses(addresses: &[IpAddr]) -> Vec<IpAddr> {
+    let mut candidates: Vec<(IpAddr, Option<IpAddr>)> = addresses
+        .iter()
+        .map(|&ip_addr| (ip_addr, source_address(ip_addr)))
+        .collect();
+    candidates.sort_by(compare_destinations);
+    candidates.into_iter().map(|(ip_addr, _)| ip_addr).collect()
+}
+
+/// Alternate the address families, starting with the family of the first address
+/// (RFC 8305 section 4)
+fn interleave(addresses: &[IpAddr]) -> Vec<IpAddr> {
+    let Some(first) = addresses.first() else {
+        return Vec::new();
+    };
+    let (mut primary, mut secondary): (Vec<IpAddr>, Vec<IpAddr>) = addresses
+        .iter()
+        .partition(|ip| ip.is_ipv4() == first.is_ipv4());
+    primary.reverse();
+    secondary.reverse();
+    let mut interleaved: Vec<IpAddr> = Vec::with_capacity(addresses.len());
+    while !primary.is_empty() || !secondary.is_empty() {
+        interleaved.extend(primary.pop());
+        interleaved.extend(secondary.pop());
+    }
+    interleaved
+}
+
+/// Source address the system would use for `dst_ip`. None if it is unreachable.
+fn source_address(dst_ip: IpAddr) -> Option<IpAddr> {
+    let bind_addr: IpAddr = match dst_ip {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(bind_addr, 0)).ok()?;
+    // Connecting a UDP socket sends nothing, it only selects the route
+    socket.connect(SocketAddr::new(dst_ip, 9)).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+/// Less if destination `a` is preferred over `b`
+fn compare_destinations(a: &(IpAddr, Option<IpAddr>), b: &(IpAddr, Option<IpAddr>)) -> Ordering {
+    let (a_dst, b_dst) = (a.0, b.0);
+    let (a_src, b_src) = match (a.1, b.1) {
+        (Some(a_src), Some(b_src)) => (a_src, b_src),
+        // Rule 1: avoid unusable destinations
+        (Some(_), None) => return Ordering::Less,
+        (None, Some(_)) => return Ordering::Greater,
+        (None, None) => return Ordering::Equal,
+    };
+    // Rule 2: prefer matching scope
+    let a_scope_match = scope(a_dst) == scope(a_src);
+    let b_scope_match = scope(b_dst) == scope(b_src);
+    if a_scope_match != b_scope_match {
+        return b_scope_match.cmp(&a_scope_match);
+    }
+    let (a_precedence, a_label) = policy(a_dst);
+    let (b_precedence, b_label) = policy(b_dst);
+    // Rule 5: prefer matching label
+    let a_label_match = a_label == policy(a_src).1;
+    let b_label_match = b_label == policy(b_src).1;
+    if a_label_match != b_label_match {
+        return b_label_match.cmp(&a_label_match);
+    }
+    // Rule 6: prefer higher precedence
+    if a_precedence != b_precedence {
+        return b_precedence.cmp(&a_precedence);
+    }
+    // Rule 8: prefer smaller scope
+    if scope(a_dst) != scope(b_dst) {
+        return scope(a_dst).cmp(&scope(b_dst));
+    }
+    // Rule 9: use longest matching prefix
+    if let (IpAddr::V6(a_dst), IpAddr::V6(a_src), IpAddr::V6(b_dst), IpAddr::V6(b_src)) =
+        (a_dst, a_src, b_dst, b_src)
+    {
+        return common_prefix_len(b_dst, b_src).cmp(&common_prefix_len(a_dst, a_src));
+    }
+    Ordering::Equal
+}
+
+/// Multicast scope values of RFC 4291, used for unicast addresses as in RFC 6724 section 3.1
+fn scope(ip_addr: IpAddr) -> u8 {
+    match ip_addr {
+        IpAddr::V4(ipv4_addr) => {
+            if ipv4_addr.is_loopback() || ipv4_addr.is_link_local() {
+                0x02
+            } else {
+                0x0e
+            }
+        }
+        IpAddr::V6(ipv6_addr) => {
+            let segment: u16 = ipv6_addr.segments()[0];
+            if ipv6_addr.is_multicast() {
+                (segment & 0x000f) as u8
+            } else if ipv6_addr.is_loopback() || segment & 0xffc0 == 0xfe80 {
+                0x02
+            } else if segment & 0xffc0 == 0xfec0 {
+                0x05
+            } else {
+                0x0e
+            }
+        }
+    }
+}
+
+/// Default policy table of RFC 6724 section 2.1: prefix, prefix length, precedence, label
+const POLICY_TABLE: [(u128, u32, u8, u8); 9] = [
+    (0x0000_0000_0000_0000_0000_0000_0000_0001, 128, 50, 0),
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 0, 40, 1),
+    (0x0000_0000_0000_0000_0000_ffff_0000_0000, 96, 35, 4),
+    (0x2002_0000_0000_0000_0000_0000_0000_0000, 16, 30, 2),
+    (0x2001_0000_0000_0000_0000_0000_0000_0000, 32, 5, 5),
+    (0xfc00_0000_0000_0000_0000_0000_0000_0000, 7, 3, 13),
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 96, 1, 3),
+    (0xfec0_0000_0000_0000_0000_0000_0000_0000, 10, 1, 11),
+    (0x3ffe_0000_0000_0000_0000_0000_0000_0000, 16, 1, 12),
+];
+
+/// Precedence and label of the longest matching prefix. IPv4 addresses are looked up as
+/// IPv4-mapped addresses.
+fn policy(ip_addr: IpAddr) -> (u8, u8) {
+    let ipv6_addr: Ipv6Addr = match ip_addr {
+        IpAddr::V4(ipv4_addr) => ipv4_addr.to_ipv6_mapped(),
+        IpAddr::V6(ipv6_addr) => ipv6_addr,
+    };
+    let bits: u128 = u128::from(ipv6_addr);
+    POLICY_TABLE
+        .iter()
+        .filter(|(prefix, len, _, _)| {
+            let mask: u128 = u128::MAX.checked_shl(128 - len).unwrap_or(0);
+            bits & mask == *prefix
+        })
+        .max_by_key(|(_, len, _, _)| *len)
+        .map(|(_, _, precedence, label)| (*precedence, *label))
+        .unwrap_or((40, 1))
+}
+
+/// Common prefix length, up to the 64-bit prefix of the source
+fn common_prefix_len(dst: Ipv6Addr, src: Ipv6Addr) -> u32 {
+    (u128::from(dst) ^ u128::from(src)).leading_zeros().min(64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::op::{Message, MessageType};
+    use hickory_resolver::proto::rr::rdata::{A, AAAA};
+    use hickory_resolver::proto::rr::{RData, Record, RecordType};
+
+    /// Name server on 127.0.0.1 answering A and AAAA queries, the AAAA ones after `aaaa_delay`
+    fn serve(aaaa_delay: Duration) -> SocketAddr {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let server_addr: SocketAddr = socket.local_addr().unwrap();
+        let _ = socket.set_read_timeout(Some(Duration::from_secs(5)));
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while let Ok((size, peer)) = socket.recv_from(&mut buf) {
+                let query: Message = Message::from_vec(&buf[..size]).unwrap();
+                let name = query.queries()[0].name().clone();
+                let (rdata, delay) = match query.queries()[0].query_type() {
+                    RecordType::A => (RData::A(A(Ipv4Addr::new(192, 0, 2, 1))), Duration::ZERO),
+                    _ => (
+                        RData::AAAA(AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))),
+                        aaaa_delay,
+                    ),
+                };
+                let mut response: Message = Message::new();
+                response
+                    .set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_available(true)
+                    .add_queries(query.queries().to_vec())
+                    .add_answer(Record::from_rdata(name, 300, rdata));
+                let response: Vec<u8> = response.to_vec().unwrap();
+                let socket = socket.try_clone().unwrap();
+                std::thread::spawn(move || {
+                    std::thread::sleep(delay);
+                    let _ = socket.send_to(&response, peer);
+                });
+            }
+        });
+        server_addr
+    }
+
+    fn config(server_addr: SocketAddr) -> DnsConfig {
+        DnsConfig::new()
+            .with_nameservers(vec![server_addr])
+            .with_timeout(Duration::from_secs(2))
+            .with_cache(false)
+    }
+
+    #[test]
+    fn lookup_both_families() {
+        let config: DnsConfig = config(serve(Duration::ZERO));
+        let addresses: FamilyAddresses = lookup_families(&config, "host.example", true, true, None);
+        assert!(addresses.errors.is_empty(), "{:?}", addresses.errors);
+        assert_eq!(
+            addresses.ipv4,
+            vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]
+        );
+        assert_eq!(
+            addresses.ipv6,
+            vec![IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))]
+        );
+        let addresses: FamilyAddresses =
+            lookup_families(&config, "host.example", false, true, None);
+        assert!(addresses.ipv4.is_empty());
+        assert_eq!(addresses.ipv6.len(), 1);
+    }
+
+    #[test]
+    fn resolution_delay() {
+        let config: DnsConfig = config(serve(Duration::from_millis(1000)));
+        let start_time = Instant::now();
+        let addresses: FamilyAddresses = lookup_families(
+            &config,
+            "host.example",
+            true,
+            true,
+            Some(Duration::from_millis(50)),
+        );
+        assert!(start_time.elapsed() < Duration::from_millis(800));
+        assert_eq!(addresses.ipv4.len(), 1);
+        assert!(addresses.ipv6.is_empty());
+    }
+
+    #[test]
+    fn shared_resolver_is_reused() {
+        let config: DnsConfig = config(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 53));
+        let resolver: Arc<DnsResolver> = shared_resolver(&config).unwrap();
+        assert!(Arc::ptr_eq(&resolver, &shared_resolver(&config).unwrap()));
+        let ipv6_config: DnsConfig = config.with_ip_strategy(IpStrategy::Ipv6Only);
+        assert!(!Arc::ptr_eq(
+            &resolver,
+            &shared_resolver(&ipv6_config).unwrap()
+        ));
+    }
+
+    #[test]
+    fn interleave_families() {
+        let v4 = |i: u8| IpAddr::V4(Ipv4Addr::new(192, 0, 2, i));
+        let v6 = |i: u16| IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, i));
+        assert_eq!(
+            interleave(&[v6(1), v6(2), v6(3), v4(1)]),
+            vec![v6(1), v4(1), v6(2), v6(3)]
+        );
+        assert!(interleave(&[]).is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::dns::target::{lookup_families, sort_addresses, FamilyAddresses};
+use crate::dns::{default_config, DnsConfig};
+use crate::result::{
+    DualStackResult, PingStat, PortStatus, ProbeResult, ProbeStatus, ProbeStatusKind,
+};
+use crate::setting::Protocol;
+use crate::sys::io_status;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Dual-stack prober.
+///
+/// Measures the TCP connect RTT to the IPv4 and the IPv6 address of the same host name,
+/// alternating between them so that both families see the same network conditions.
+/// The address of each family is the first one in RFC 6724 order. A refused connection
+/// still counts as a reply, with a closed port.
+#[derive(Clone, Debug)]
+pub struct DualStackProber {
+    pub host_name: String,
+    pub port: u16,
+    /// Connections per address family
+    pub count: u8,
+    pub connect_timeout: Duration,
+    /// Interval between two rounds of connections
+    pub send_rate: Duration,
+    pub dns_config: DnsConfig,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<ProbeResult>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<ProbeResult>>>,
+}
+
+impl DualStackProber {
+    /// Create new DualStackProber instance for `host_name:port`
+    pub fn new(host_name: &str, port: u16) -> Result<DualStackProber, String> {
+        let host_name: String = host_name.trim_matches(['[', ']']).to_string();
+        if host_name.is_empty() {
+            return Err("DualStackProber::new: missing host name".to_string());
+        }
+        let (tx, rx) = channel();
+        let prober = DualStackProber {
+            host_name,
+            port,
+            count: 4,
+            connect_timeout: Duration::from_secs(2),
+            send_rate: Duration::from_secs(1),
+            dns_config: default_config(),
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(prober)
+    }
+    pub fn with_count(mut self, count: u8) -> DualStackProber {
+        self.count = count;
+        self
+    }
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> DualStackProber {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+    pub fn with_send_rate(mut self, send_rate: Duration) -> DualStackProber {
+        self.send_rate = send_rate;
+        self
+    }
+    pub fn with_dns_config(mut self, dns_config: DnsConfig) -> DualStackProber {
+        self.dns_config = dns_config;
+        self
+    }
+    /// Run probe
+    pub fn probe(&self) -> Result<DualStackResult, String> {
+        if self.count == 0 {
+            return Err("DualStackProber::probe: count is zero".to_string());
+        }
+        let mut result = DualStackResult::new();
+        result.host_name = self.host_name.clone();
+        result.port = self.port;
+        result.start_time = crate::sys::get_sysdate();
+        let start_time = Instant::now();
+        let family_addresses: FamilyAddresses = match self.host_name.parse::<IpAddr>() {
+            Ok(ip_addr) => FamilyAddresses::from_addresses(vec![ip_addr]),
+            Err(_) => lookup_families(&self.dns_config, &self.host_name, true, true, None),
+        };
+        let lookup_error: String = if family_addresses.errors.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", family_addresses.errors.join(", "))
+        };
+        result.ipv4_addr = sort_addresses(&family_addresses.ipv4).first().copied();
+        result.ipv6_addr = sort_addresses(&family_addresses.ipv6).first().copied();
+        if result.ipv4_addr.is_none() && result.ipv6_addr.is_none() {
+            return Err(format!(
+                "DualStackProber::probe: no address for {}{}",
+                self.host_name, lookup_error
+            ));
+        }
+        let missing = |family: &str| -> ProbeStatus {
+            ProbeStatus::with_error_message(format!("No {} address{}", family, lookup_error))
+        };
+        if result.ipv4_addr.is_none() {
+            result.ipv4_status = missing("IPv4");
+        }
+        if result.ipv6_addr.is_none() {
+            result.ipv6_status = missing("IPv6");
+        }
+        let targets: Vec<IpAddr> = [result.ipv4_addr, result.ipv6_addr]
+            .into_iter()
+            .flatten()
+            .collect();
+        let mut ipv4_responses: Vec<ProbeResult> = Vec::new();
+        let mut ipv6_responses: Vec<ProbeResult> = Vec::new();
+        for seq in 1..self.count + 1 {
+            let send_time = Instant::now();
+            for ip_addr in &targets {
+                let probe_result: ProbeResult = self.connect(seq, *ip_addr);
+                if let Ok(lr) = self.tx.lock() {
+                    let _ = lr.send(probe_result.clone());
+                }
+                if ip_addr.is_ipv4() {
+                    ipv4_responses.push(probe_result);
+                } else {
+                    ipv6_responses.push(probe_result);
+                }
+            }
+            if seq < self.count {
+                std::thread::sleep(self.send_rate.saturating_sub(send_time.elapsed()));
+            }
+        }
+        let probe_time = start_time.elapsed();
+        result.ipv4_stat = ping_stat(ipv4_responses, probe_time);
+        result.ipv6_stat = ping_stat(ipv6_responses, probe_time);
+        result.end_time = crate::sys::get_sysdate();
+        result.elapsed_time = probe_time;
+        Ok(result)
+    }
+    /// Get progress receiver
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<ProbeResult>>> {
+        self.rx.clone()
+    }
+    fn connect(&self, seq: u8, ip_addr: IpAddr) -> ProbeResult {
+        let socket_addr = SocketAddr::new(ip_addr, self.port);
+        let mut probe_result = ProbeResult {
+            seq,
+            ip_addr,
+            host_name: self.host_name.clone(),
+            port_number: Some(self.port),
+            protocol: Protocol::TCP,
+            ..ProbeResult::new()
+        };
+        let connect_start = Instant::now();
+        let connected = TcpStream::connect_timeout(&socket_addr, self.connect_timeout);
+        probe_result.rtt = connect_start.elapsed();
+        match connected {
+            Ok(_) => probe_result.port_status = Some(PortStatus::Open),
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                probe_result.port_status = Some(PortStatus::Closed)
+            }
+            Err(e) => {
+                probe_result.probe_status = io_status(&format!("Connect to {}", socket_addr), e)
+            }
+        }
+        probe_result
+    }
+}
+
+fn ping_stat(responses: Vec<ProbeResult>, probe_time: Duration) -> PingStat {
+    let answered: Vec<Duration> = responses
+        .iter()
+        .filter(|r| r.probe_status.kind == ProbeStatusKind::Done)
+        .map(|r| r.rtt)
+        .collect();
+    let (min, avg, max) = crate::sys::rtt_stat(&answered);
+    PingStat {
+        transmitted_count: responses.len(),
+        received_count: answered.len(),
+        probe_time,
+        min,
+        avg,
+        max,
+        responses,
+    }
+}
@@ -0,0 +1,249 @@
+use super::signature::OsSignature;
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Built-in signature database
+const BUILTIN_OS_DB: &str = include_str!("os.db");
+
+/// Weight of a signature field when scoring a match
+fn field_weight(key: &str) -> u32 {
+    match key {
+        "ttl" | "options" => 3,
+        "window" | "icmp_ttl" | "ip_id" => 2,
+        _ => 1,
+    }
+}
+
+/// OS signature database entry
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OsDbEntry {
+    /// OS name
+    pub name: String,
+    /// OS family
+    pub class: String,
+    /// Signature fields and their accepted values
+    pub fields: Vec<(String, Vec<String>)>,
+}
+
+/// OS guess with confidence
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OsMatch {
+    /// OS name
+    pub name: String,
+    /// OS family
+    pub class: String,
+    /// Confidence (0.0 - 1.0)
+    pub confidence: f64,
+    /// Fields that matched
+    pub matched_fields: Vec<String>,
+    /// Fields that were compared but did not match
+    pub mismatched_fields: Vec<String>,
+}
+
+/// OS signature database
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OsDatabase {
+    pub entries: Vec<OsDbEntry>,
+}
+
+impl OsDatabase {
+    /// Create empty database
+    pub fn new() -> OsDatabase {
+        OsDatabase {
+            entries: Vec::new(),
+        }
+    }
+    /// Database bundled with netprobe
+    pub fn builtin() -> OsDatabase {
+        // The bundled file is known to be valid
+        OsDatabase::parse(BUILTIN_OS_DB).unwrap_or_default()
+    }
+    /// Load database from file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<OsDatabase, String> {
+        match std::fs::read_to_string(path.as_ref()) {
+            Ok(content) => OsDatabase::parse(&content),
+            Err(e) => Err(format!(
+                "OsDatabase::load: unable to read {}: {}",
+                path.as_ref().display(),
+                e
+            )),
+        }
+    }
+    /// Parse database text.
+    ///
+    /// Sections start with `[OS name]` followed by `key = value|value` lines.
+    /// Lines starting with `#` are comments.
+    pub fn parse(content: &str) -> Result<OsDatabase, String> {
+        let mut db: OsDatabase = OsDatabase::new();
+        for (i, line) in content.lines().enumerate() {
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                db.entries.push(OsDbEntry {
+                    name: line[1..line.len() - 1].trim().to_string(),
+                    class: String::new(),
+                    fields: Vec::new(),
+                });
+                continue;
+            }
+            let entry: &mut OsDbEntry = match db.entries.last_mut() {
+                Some(entry) => entry,
+                None => {
+                    return Err(format!(
+                        "OsDatabase::parse: line {}: field outside of section",
+                        i + 1
+                    ))
+                }
+            };
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    return Err(format!(
+                        "OsDatabase::parse: line {}: expected key = value",
+                        i + 1
+                    ))
+                }
+            };
+            if key == "class" {
+                entry.class = value.to_string();
+                continue;
+            }
+            if !is_known_field(key) {
+                return Err(format!(
+                    "OsDatabase::parse: line {}: unknown field {}",
+                    i + 1,
+                    key
+                ));
+            }
+            let values: Vec<String> = value
+                .split('|')
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect();
+            entry.fields.push((key.to_string(), values));
+        }
+        Ok(db)
+    }
+    /// Match signature against all entries. Returns guesses sorted by confidence.
+    ///
+    /// Only fields present in both the entry and the signature are compared.
+    pub fn match_signature(&self, signature: &OsSignature) -> Vec<OsMatch> {
+        // Pairs of guess and matched weight
+        let mut ranked: Vec<(OsMatch, u32)> = Vec::new();
+        for entry in &self.entries {
+            let mut matched_weight: u32 = 0;
+            let mut compared_weight: u32 = 0;
+            let mut os_match = OsMatch {
+                name: entry.name.clone(),
+                class: entry.class.clone(),
+                confidence: 0.0,
+                matched_fields: Vec::new(),
+                mismatched_fields: Vec::new(),
+            };
+            for (key, values) in &entry.fields {
+                let observed: String = match signature.field_value(key) {
+                    Some(observed) => observed,
+                    None => continue,
+                };
+                let weight: u32 = field_weight(key);
+                compared_weight += weight;
+                if values.contains(&observed) {
+                    matched_weight += weight;
+                    os_match.matched_fields.push(key.clone());
+                } else {
+                    os_match.mismatched_fields.push(key.clone());
+                }
+            }
+            if compared_weight == 0 || matched_weight == 0 {
+                continue;
+            }
+            os_match.confidence = matched_weight as f64 / compared_weight as f64;
+            ranked.push((os_match, matched_weight));
+        }
+        // Sort by confidence, then by amount of evidence
+        ranked.sort_by(|a, b| {
+            b.0.confidence
+                .partial_cmp(&a.0.confidence)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.1.cmp(&a.1))
+        });
+        ranked.into_iter().map(|(os_match, _)| os_match).collect()
+    }
+}
+
+impl Default for OsDatabase {
+    fn default() -> Self {
+        OsDatabase::new()
+    }
+}
+
+fn is_known_field(key: &str) -> bool {
+    matches!(
+        key,
+        "ttl"
+            | "icmp_ttl"
+            | "df"
+            | "window"
+            | "options"
+            | "mss"
+            | "wscale"
+            | "ip_id"
+            | "ecn"
+            | "icmp_echo"
+            | "icmp_timestamp"
+            | "icmp_address_mask"
+            | "icmp_information"
+            | "icmp_unreachable"
+            | "flow_label"
+            | "ext_header"
+            | "node_info"
+            | "syn_windows"
+            | "tcp_null"
+            | "tcp_fin"
+            | "tcp_xmas"
+            | "tcp_ack_open"
+            | "tcp_ack_closed"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_database_parses() {
+        let db: OsDatabase = OsDatabase::parse(BUILTIN_OS_DB).unwrap();
+        assert!(!db.entries.is_empty());
+        assert!(db.entries.iter().all(|entry| !entry.fields.is_empty()));
+        assert_eq!(OsDatabase::builtin().entries.len(), db.entries.len());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_lines() {
+        assert!(OsDatabase::parse("ttl = 64").is_err());
+        assert!(OsDatabase::parse("[Test]\nttl 64").is_err());
+        assert!(OsDatabase::parse("[Test]\nunknown = 1").is_err());
+    }
+
+    #[test]
+    fn match_signature_ranks_linux_first() {
+        let db: OsDatabase = OsDatabase::builtin();
+        let signature = OsSignature {
+            initial_ttl: Some(64),
+            df: Some(true),
+            window: Some(65160),
+            tcp_options: Some(String::from("mss,sok,ts,nop,ws")),
+            ..OsSignature::new()
+        };
+        let matches: Vec<OsMatch> = db.match_signature(&signature);
+        assert_eq!(matches[0].name, "Linux 3.x-6.x");
+        assert_eq!(matches[0].confidence, 1.0);
+    }
+}
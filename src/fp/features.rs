@@ -0,0 +1,210 @@
+use crate::packet::decode::IcmpError;
+use xenet::packet::frame::Frame;
+use xenet::packet::ipv4::Ipv4Flags;
+use xenet::packet::tcp::{TcpFlags, TcpHeader, TcpOptionKind};
+use xenet::packet::PrimitiveValues;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Build TCP option order string. e.g. "mss,nop,ws,sok,ts"
+pub fn tcp_option_order(tcp_header: &TcpHeader) -> String {
+    let names: Vec<&str> = tcp_header
+        .options
+        .iter()
+        .map(|option| match option.kind {
+            TcpOptionKind::EOL => "eol",
+            TcpOptionKind::NOP => "nop",
+            TcpOptionKind::MSS => "mss",
+            TcpOptionKind::WSCALE => "ws",
+            TcpOptionKind::SACK_PERMITTED => "sok",
+            TcpOptionKind::SACK => "sack",
+            TcpOptionKind::TIMESTAMPS => "ts",
+        })
+        .collect();
+    names.join(",")
+}
+
+/// Build TCP flag string in Nmap order (ECE, URG, ACK, PSH, RST, SYN, FIN). e.g. "AR"
+pub fn tcp_flag_string(flags: u8) -> String {
+    [
+        (TcpFlags::ECE, 'E'),
+        (TcpFlags::URG, 'U'),
+        (TcpFlags::ACK, 'A'),
+        (TcpFlags::PSH, 'P'),
+        (TcpFlags::RST, 'R'),
+        (TcpFlags::SYN, 'S'),
+        (TcpFlags::FIN, 'F'),
+    ]
+    .iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, c)| *c)
+    .collect()
+}
+
+/// Typed features of a fingerprint reply packet
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FingerprintFeatures {
+    /// IPv4 TTL or IPv6 hop limit
+    pub ttl: u8,
+    /// Guessed initial TTL (64, 128 or 255)
+    pub initial_ttl: u8,
+    /// Don't Fragment bit (IPv4 only)
+    pub df: Option<bool>,
+    /// IP ID (IPv4 only)
+    pub ip_id: Option<u16>,
+    /// IPv4 TOS or IPv6 traffic class
+    pub tos: u8,
+    /// Flow label (IPv6 only)
+    pub flow_label: Option<u32>,
+    /// TCP flags
+    pub tcp_flags: Option<u8>,
+    /// TCP sequence number
+    pub tcp_seq: Option<u32>,
+    /// TCP acknowledgement number
+    pub tcp_ack: Option<u32>,
+    /// TCP window size
+    pub window: Option<u16>,
+    /// TCP MSS option
+    pub mss: Option<u16>,
+    /// TCP window scale option
+    pub wscale: Option<u8>,
+    /// TCP SACK permitted option present
+    pub sack_permitted: bool,
+    /// TCP timestamp value (TSval), if timestamp option present
+    pub timestamp: Option<u32>,
+    /// TCP option order string
+    pub tcp_options: Option<String>,
+    /// ICMP/ICMPv6 type
+    pub icmp_type: Option<u8>,
+    /// ICMP/ICMPv6 code
+    pub icmp_code: Option<u8>,
+    /// Length of ICMP data following the type, code and checksum
+    pub icmp_payload_len: Option<usize>,
+    /// Length of the datagram quoted in an ICMP error
+    pub icmp_quoted_len: Option<usize>,
+    /// IP total length of the quoted datagram as returned by the host
+    pub icmp_quoted_total_length: Option<u16>,
+}
+
+impl FingerprintFeatures {
+    /// Extract features from reply frame. Returns `None` if frame has no IP layer.
+    pub fn from_frame(frame: &Frame) -> Option<FingerprintFeatures> {
+        let ip_layer = frame.ip.as_ref()?;
+        let mut features = if let Some(ipv4_header) = &ip_layer.ipv4 {
+            FingerprintFeatures::new(
+                ipv4_header.ttl,
+                (ipv4_header.dscp << 2) | ipv4_header.ecn,
+                Some(ipv4_header.flags & Ipv4Flags::DontFragment != 0),
+                Some(ipv4_header.identification),
+            )
+        } else if let Some(ipv6_header) = &ip_layer.ipv6 {
+            let mut features = FingerprintFeatures::new(
+                ipv6_header.hop_limit,
+                ipv6_header.traffic_class,
+                None,
+                None,
+            );
+            features.flow_label = Some(ipv6_header.flow_label);
+            features
+        } else {
+            return None;
+        };
+        if let Some(tcp_header) = frame.transport.as_ref().and_then(|t| t.tcp.as_ref()) {
+            features.tcp_flags = Some(tcp_header.flags);
+            features.tcp_seq = Some(tcp_header.sequence);
+            features.tcp_ack = Some(tcp_header.acknowledgement);
+            features.window = Some(tcp_header.window);
+            features.tcp_options = Some(tcp_option_order(tcp_header));
+            for option in &tcp_header.options {
+                match option.kind {
+                    TcpOptionKind::MSS => features.mss = Some(option.get_mss()),
+                    TcpOptionKind::WSCALE => features.wscale = Some(option.get_wscale()),
+                    TcpOptionKind::SACK_PERMITTED => features.sack_permitted = true,
+                    TcpOptionKind::TIMESTAMPS => {
+                        features.timestamp = Some(option.get_timestamp().0)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(icmp_header) = &ip_layer.icmp {
+            let icmp_type: u8 = icmp_header.icmp_type.to_primitive_values().0;
+            features.icmp_type = Some(icmp_type);
+            features.icmp_code = Some(icmp_header.icmp_code.0);
+        }
+        if let Some(icmpv6_header) = &ip_layer.icmpv6 {
+            let icmpv6_type: u8 = icmpv6_header.icmpv6_type.to_primitive_values().0;
+            features.icmp_type = Some(icmpv6_type);
+            features.icmp_code = Some(icmpv6_header.icmpv6_code.0);
+        }
+        if features.icmp_type.is_some() {
+            features.icmp_payload_len = Some(frame.payload.len());
+        }
+        if let Some(quoted) = IcmpError::from_frame(frame).and_then(|e| e.quoted) {
+            features.icmp_quoted_len = Some(quoted.data.len());
+            features.icmp_quoted_total_length = Some(quoted.total_length);
+        }
+        Some(features)
+    }
+    fn new(ttl: u8, tos: u8, df: Option<bool>, ip_id: Option<u16>) -> FingerprintFeatures {
+        FingerprintFeatures {
+            ttl,
+            initial_ttl: crate::ip::guess_initial_ttl(ttl),
+            df,
+            ip_id,
+            tos,
+            flow_label: None,
+            tcp_flags: None,
+            tcp_seq: None,
+            tcp_ack: None,
+            window: None,
+            mss: None,
+            wscale: None,
+            sack_permitted: false,
+            timestamp: None,
+            tcp_options: None,
+            icmp_type: None,
+            icmp_code: None,
+            icmp_payload_len: None,
+            icmp_quoted_len: None,
+            icmp_quoted_total_length: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::decode::parse_ip_packet;
+
+    /// ICMPv4 error from 192.0.2.1 quoting a UDP datagram of 60 bytes
+    fn icmpv4_error(icmp_type: u8, icmp_code: u8) -> Vec<u8> {
+        let mut packet: Vec<u8> = vec![0x45, 0, 0, 56, 0, 0, 0, 0, 64, 1, 0, 0];
+        packet.extend_from_slice(&[192, 0, 2, 1, 192, 0, 2, 2]);
+        packet.extend_from_slice(&[icmp_type, icmp_code, 0, 0, 0, 0, 0, 0]);
+        packet.extend_from_slice(&[0x45, 0, 0, 60, 0x12, 0x34, 0, 0, 1, 17, 0, 0]);
+        packet.extend_from_slice(&[192, 0, 2, 2, 198, 51, 100, 1]);
+        packet.extend_from_slice(&[0x9c, 0x40, 0x82, 0x9b, 0, 40, 0, 0]);
+        packet
+    }
+
+    #[test]
+    fn icmp_error_quoted_datagram() {
+        // Destination Unreachable, Source Quench, Redirect, Time Exceeded, Parameter Problem
+        for icmp_type in [3, 4, 5, 11, 12] {
+            let frame: Frame = parse_ip_packet(&icmpv4_error(icmp_type, 0));
+            let features = FingerprintFeatures::from_frame(&frame).unwrap();
+            assert_eq!(features.icmp_type, Some(icmp_type));
+            assert_eq!(features.icmp_payload_len, Some(32));
+            assert_eq!(features.icmp_quoted_len, Some(28), "type {}", icmp_type);
+            assert_eq!(features.icmp_quoted_total_length, Some(60));
+        }
+        // Echo Reply quotes nothing
+        let frame: Frame = parse_ip_packet(&icmpv4_error(0, 0));
+        let features = FingerprintFeatures::from_frame(&frame).unwrap();
+        assert_eq!(features.icmp_quoted_len, None);
+        assert_eq!(features.icmp_quoted_total_length, None);
+    }
+}
@@ -1,7 +1,18 @@
+pub mod db;
+pub mod features;
+pub mod os;
+pub mod p0f;
+pub mod passive;
+pub mod sequence;
+pub mod session;
+pub mod signature;
+
 use std::{time::{Instant, Duration}, net::IpAddr};
 
 use crate::setting::{ProbeSetting, Protocol};
 use crate::result::ProbeStatus;
+use crate::template::ProbeTemplate;
+use features::FingerprintFeatures;
 use default_net::Interface;
 use xenet::packet::{frame::{Frame, ParseOption}, icmp, icmpv6, tcp::TcpFlags};
 
@@ -20,6 +31,22 @@ pub enum  FingerprintType {
     TcpSynAck,
     TcpRstAck,
     TcpEcn,
+    /// ICMPv6 Node Information Query for node name
+    Icmpv6NodeInfo,
+    /// ICMPv6 Echo with unknown Destination Options
+    Icmpv6DestOpts,
+    /// SYN to open port with one of the Nmap SEQ window and option sets (1-6)
+    TcpSeq(u8),
+    /// No flags to open port
+    TcpNull,
+    /// FIN to open port
+    TcpFin,
+    /// FIN, PSH and URG to closed port
+    TcpXmas,
+    /// ACK to open port
+    TcpAckOpen,
+    /// ACK to closed port
+    TcpAckClosed,
 }
 
 impl FingerprintType {
@@ -33,8 +60,26 @@ impl FingerprintType {
             FingerprintType::TcpSynAck => Protocol::TCP,
             FingerprintType::TcpRstAck => Protocol::TCP,
             FingerprintType::TcpEcn => Protocol::TCP,
+            FingerprintType::Icmpv6NodeInfo => Protocol::ICMP,
+            FingerprintType::Icmpv6DestOpts => Protocol::ICMP,
+            FingerprintType::TcpSeq(_) => Protocol::TCP,
+            FingerprintType::TcpNull => Protocol::TCP,
+            FingerprintType::TcpFin => Protocol::TCP,
+            FingerprintType::TcpXmas => Protocol::TCP,
+            FingerprintType::TcpAckOpen => Protocol::TCP,
+            FingerprintType::TcpAckClosed => Protocol::TCP,
         }
     }
+    /// Probe is sent to `probe_setting.dst_port`, which must be open
+    pub fn requires_open_port(&self) -> bool {
+        matches!(self, FingerprintType::TcpSynAck | FingerprintType::TcpEcn | FingerprintType::TcpSeq(_)
+            | FingerprintType::TcpNull | FingerprintType::TcpFin | FingerprintType::TcpAckOpen)
+    }
+    /// Probe is sent to a port expected to be closed
+    pub fn requires_closed_port(&self) -> bool {
+        matches!(self, FingerprintType::TcpRstAck | FingerprintType::IcmpUnreachable
+            | FingerprintType::TcpXmas | FingerprintType::TcpAckClosed)
+    }
 }
 
 /// Result of fingerprinting
@@ -44,6 +89,8 @@ pub struct Fingerprint {
     pub probe_status: ProbeStatus,
     pub rtt: Duration,
     pub packet_frame: Option<Frame>,
+    /// Typed features of the reply packet
+    pub features: Option<FingerprintFeatures>,
 }
 
 /// Struct for fingerprint probe
@@ -54,6 +101,8 @@ pub struct Fingerprinter {
     pub probe_setting: ProbeSetting,
     /// ProbeType
     pub probe_type: FingerprintType,
+    /// Probe template sent instead of the built-in packet of `probe_type`
+    pub template: Option<ProbeTemplate>,
     // Result of fingerprinting
     pub fingerprint: Fingerprint,
 }
@@ -64,13 +113,20 @@ impl Fingerprinter {
         Fingerprinter {
             probe_setting: setting,
             probe_type: probe_type,
+            template: None,
             fingerprint: Fingerprint {
                 probe_status: ProbeStatus::new(),
                 rtt: Duration::new(0, 0),
                 packet_frame: None,
+                features: None,
             },
         }
     }
+    /// Send `template` and keep the reply selected by its `ReplyMatch`
+    pub fn with_template(mut self, template: ProbeTemplate) -> Fingerprinter {
+        self.template = Some(template);
+        self
+    }
     pub fn run_probe(&mut self) {
         self.fingerprint = run_probe_impl(self);
     }
@@ -84,6 +140,7 @@ fn run_probe_impl(fp: &Fingerprinter) -> Fingerprint {
         probe_status: ProbeStatus::new(),
         rtt: Duration::new(0, 0),
         packet_frame: None,
+        features: None,
     };
     let interface: Interface = match crate::interface::get_interface_by_index(fp.probe_setting.if_index) {
         Some(interface) => interface,
@@ -123,15 +180,15 @@ fn run_probe_impl(fp: &Fingerprinter) -> Fingerprint {
         parse_option.from_ip_packet = true;
         parse_option.offset = payload_offset;
     }
-    let probe_packet = match fp.probe_type {
-        FingerprintType::IcmpEcho => crate::packet::icmp::build_icmp_probe_packet(fp.probe_setting.clone(), fp.probe_type),
-        FingerprintType::IcmpTimestamp => crate::packet::icmp::build_icmp_probe_packet(fp.probe_setting.clone(), fp.probe_type),
-        FingerprintType::IcmpAddressMask => crate::packet::icmp::build_icmp_probe_packet(fp.probe_setting.clone(), fp.probe_type),
-        FingerprintType::IcmpInformation => crate::packet::icmp::build_icmp_probe_packet(fp.probe_setting.clone(), fp.probe_type),
-        FingerprintType::IcmpUnreachable => crate::packet::udp::build_udp_probe_packet(fp.probe_setting.clone()),
-        FingerprintType::TcpSynAck => crate::packet::tcp::build_tcp_probe_packet(fp.probe_setting.clone(), fp.probe_type),
-        FingerprintType::TcpRstAck => crate::packet::tcp::build_tcp_probe_packet(fp.probe_setting.clone(), fp.probe_type),
-        FingerprintType::TcpEcn => crate::packet::tcp::build_tcp_probe_packet(fp.probe_setting.clone(), fp.probe_type),
+    let probe_packet = match &fp.template {
+        Some(template) => match template.build(&fp.probe_setting) {
+            Ok(packet) => packet,
+            Err(e) => {
+                result.probe_status = ProbeStatus::with_error_message(e);
+                return result;
+            }
+        },
+        None => build_probe_packet(&fp.probe_setting, fp.probe_type),
     };
     let send_time = Instant::now();
     match tx.send(&probe_packet) {
@@ -143,24 +200,20 @@ fn run_probe_impl(fp: &Fingerprinter) -> Fingerprint {
             Ok(packet) => {
                 let recv_time: Duration = Instant::now().duration_since(send_time);
                 let frame: Frame = Frame::from_bytes(&packet, parse_option.clone());
-                match fp.probe_type.protocol() {
-                    Protocol::ICMP | Protocol::UDP => {
-                        if filter_icmp_packet(&frame, &fp.probe_setting, &fp.probe_type) {
-                            result.probe_status = ProbeStatus::new();
-                            result.rtt = recv_time;
-                            result.packet_frame = Some(frame);
-                            break;
-                        }  
+                let matched: bool = match &fp.template {
+                    Some(template) => template.matches_reply(&frame, &fp.probe_setting),
+                    None => match fp.probe_type.protocol() {
+                        Protocol::ICMP | Protocol::UDP => filter_icmp_packet(&frame, &fp.probe_setting, &fp.probe_type),
+                        Protocol::TCP => filter_tcp_packet(&frame, &fp.probe_setting, &fp.probe_type),
+                        _ => false,
                     },
-                    Protocol::TCP => {
-                        if filter_tcp_packet(&frame, &fp.probe_setting, &fp.probe_type) {
-                            result.probe_status = ProbeStatus::new();
-                            result.rtt = recv_time;
-                            result.packet_frame = Some(frame);
-                            break;
-                        }
-                    },
-                    _ => {},
+                };
+                if matched {
+                    result.probe_status = ProbeStatus::new();
+                    result.rtt = recv_time;
+                    result.features = FingerprintFeatures::from_frame(&frame);
+                    result.packet_frame = Some(frame);
+                    break;
                 }
             },
             Err(_e) => {}
@@ -175,6 +228,29 @@ fn run_probe_impl(fp: &Fingerprinter) -> Fingerprint {
     result
 }
 
+fn build_probe_packet(setting: &ProbeSetting, probe_type: FingerprintType) -> Vec<u8> {
+    let mut packet = match probe_type {
+        FingerprintType::IcmpEcho => crate::packet::icmp::build_icmp_probe_packet(setting.clone(), probe_type),
+        FingerprintType::IcmpTimestamp => crate::packet::icmp::build_icmp_probe_packet(setting.clone(), probe_type),
+        FingerprintType::IcmpAddressMask => crate::packet::icmp::build_icmp_probe_packet(setting.clone(), probe_type),
+        FingerprintType::IcmpInformation => crate::packet::icmp::build_icmp_probe_packet(setting.clone(), probe_type),
+        FingerprintType::IcmpUnreachable => crate::packet::udp::build_udp_probe_packet(setting.clone()),
+        FingerprintType::TcpSynAck => crate::packet::tcp::build_tcp_probe_packet(setting.clone(), probe_type),
+        FingerprintType::TcpRstAck => crate::packet::tcp::build_tcp_probe_packet(setting.clone(), probe_type),
+        FingerprintType::TcpEcn => crate::packet::tcp::build_tcp_probe_packet(setting.clone(), probe_type),
+        FingerprintType::Icmpv6NodeInfo => crate::packet::icmpv6::build_node_info_query_packet(setting.clone()),
+        FingerprintType::Icmpv6DestOpts => crate::packet::icmpv6::build_echo_dest_opts_packet(setting.clone()),
+        FingerprintType::TcpSeq(_) | FingerprintType::TcpNull | FingerprintType::TcpFin | FingerprintType::TcpXmas
+            | FingerprintType::TcpAckOpen | FingerprintType::TcpAckClosed => crate::packet::tcp::build_tcp_probe_packet(setting.clone(), probe_type),
+    };
+    // Known flow label on TCP probes, to tell whether the reply echoes it
+    if setting.dst_ip.is_ipv6() && matches!(probe_type.protocol(), Protocol::TCP) {
+        let ip_offset = crate::packet::icmpv6::ip_offset(setting);
+        crate::packet::icmpv6::set_ipv6_flow_label(&mut packet, ip_offset, crate::packet::icmpv6::PROBE_FLOW_LABEL);
+    }
+    packet
+}
+
 fn filter_icmp_packet(frame: &Frame, setting: &ProbeSetting, probe_type: &FingerprintType) -> bool {
     if let Some(ip_layer) = &frame.ip {
         if let Some(ipv4_header) = &ip_layer.ipv4 {
@@ -220,6 +296,17 @@ fn filter_icmp_packet(frame: &Frame, setting: &ProbeSetting, probe_type: &Finger
                                 return true;
                             }
                         },
+                        FingerprintType::IcmpUnreachable if icmpv6_header.icmpv6_type == icmpv6::Icmpv6Type::DestinationUnreachable => {
+                            return true;
+                        },
+                        FingerprintType::Icmpv6NodeInfo if icmpv6_header.icmpv6_type == icmpv6::Icmpv6Type::NodeInformationResponse => {
+                            return true;
+                        },
+                        // Compliant hosts answer Parameter Problem, others may ignore the option
+                        FingerprintType::Icmpv6DestOpts if icmpv6_header.icmpv6_type == icmpv6::Icmpv6Type::ParameterProblem
+                            || icmpv6_header.icmpv6_type == icmpv6::Icmpv6Type::EchoReply => {
+                            return true;
+                        },
                         _ => {},
                     }
                 }
@@ -260,6 +347,11 @@ fn filter_tcp_packet(frame: &Frame, setting: &ProbeSetting, probe_type: &Fingerp
                         return true;
                     }
                 },
+                // Any reply (SYN-ACK, RST or none at all) is part of the fingerprint
+                FingerprintType::TcpSeq(_) | FingerprintType::TcpNull | FingerprintType::TcpFin | FingerprintType::TcpXmas
+                    | FingerprintType::TcpAckOpen | FingerprintType::TcpAckClosed if Some(tcp_header.source) == setting.dst_port => {
+                    return true;
+                },
                 _ => {},
             }
         }
@@ -0,0 +1,161 @@
+use super::db::{OsDatabase, OsMatch};
+use super::sequence::{SequenceProber, SequenceResult};
+use super::session::{FingerprintReport, FingerprintSession};
+use super::signature::{IpIdBehavior, OsSignature};
+use super::{Fingerprint, FingerprintType};
+use crate::result::ProbeStatus;
+use crate::setting::ProbeSetting;
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Result of OS fingerprinting
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OsFingerprintResult {
+    /// Signature extracted from the replies
+    pub signature: OsSignature,
+    /// OS guesses, best first
+    pub matches: Vec<OsMatch>,
+    /// Raw result of each probe
+    pub fingerprints: Vec<(FingerprintType, Fingerprint)>,
+    /// IP ID and TCP timestamp series, if enabled
+    pub sequence: Option<SequenceResult>,
+    /// Status
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl OsFingerprintResult {
+    pub fn new() -> OsFingerprintResult {
+        OsFingerprintResult {
+            signature: OsSignature::new(),
+            matches: Vec::new(),
+            fingerprints: Vec::new(),
+            sequence: None,
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Best OS guess
+    pub fn best_match(&self) -> Option<&OsMatch> {
+        self.matches.first()
+    }
+}
+
+impl Default for OsFingerprintResult {
+    fn default() -> Self {
+        OsFingerprintResult::new()
+    }
+}
+
+/// OS fingerprinting engine.
+///
+/// Runs a set of fingerprint probes against `probe_setting.dst_ip` and matches
+/// the extracted signature against an OS signature database.
+/// TCP probes that require an open port are sent to `probe_setting.dst_port`,
+/// probes that require a closed port to `closed_port`.
+#[derive(Clone, Debug)]
+pub struct OsFingerprinter {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Probes to run
+    pub probe_types: Vec<FingerprintType>,
+    /// Port expected to be closed on target (TCP and UDP)
+    pub closed_port: u16,
+    /// Signature database
+    pub database: OsDatabase,
+    /// Run a probe series to classify IP ID generation
+    pub sequence: bool,
+}
+
+impl OsFingerprinter {
+    /// Create new OsFingerprinter instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<OsFingerprinter, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "OsFingerprinter::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let fingerprinter = OsFingerprinter {
+            probe_types: super::session::default_probe_types(&setting.dst_ip),
+            probe_setting: setting,
+            closed_port: crate::packet::udp::UDP_BASE_DST_PORT,
+            database: OsDatabase::builtin(),
+            sequence: false,
+        };
+        Ok(fingerprinter)
+    }
+    /// Set probes to run
+    pub fn with_probe_types(mut self, probe_types: Vec<FingerprintType>) -> OsFingerprinter {
+        self.probe_types = probe_types;
+        self
+    }
+    /// Set port expected to be closed on target
+    pub fn with_closed_port(mut self, closed_port: u16) -> OsFingerprinter {
+        self.closed_port = closed_port;
+        self
+    }
+    /// Set signature database
+    pub fn with_database(mut self, database: OsDatabase) -> OsFingerprinter {
+        self.database = database;
+        self
+    }
+    /// Run a probe series to classify IP ID generation and TCP timestamp clock
+    pub fn with_sequence(mut self, sequence: bool) -> OsFingerprinter {
+        self.sequence = sequence;
+        self
+    }
+    /// Run probes and match against database
+    pub fn detect(&self) -> Result<OsFingerprintResult, String> {
+        run_os_fingerprinting(self)
+    }
+}
+
+fn run_os_fingerprinting(
+    os_fingerprinter: &OsFingerprinter,
+) -> Result<OsFingerprintResult, String> {
+    let needs_open_port: bool = os_fingerprinter
+        .probe_types
+        .iter()
+        .any(|probe_type| probe_type.requires_open_port());
+    if needs_open_port && os_fingerprinter.probe_setting.dst_port.is_none() {
+        return Err("run_os_fingerprinting: TCP probes require an open dst_port".to_string());
+    }
+    let session: FingerprintSession =
+        FingerprintSession::new(os_fingerprinter.probe_setting.clone())?
+            .with_probe_types(os_fingerprinter.probe_types.clone())
+            .with_closed_port(os_fingerprinter.closed_port);
+    let report: FingerprintReport = session.run()?;
+    let mut result = OsFingerprintResult::new();
+    result.signature = OsSignature::from_fingerprints(&report.fingerprints);
+    if os_fingerprinter.sequence {
+        let sequence: SequenceResult =
+            SequenceProber::new(os_fingerprinter.probe_setting.clone())?.probe()?;
+        // A dedicated series is more reliable than IDs of mixed probes
+        match sequence.ip_id {
+            IpIdBehavior::Unknown => {}
+            ip_id => result.signature.ip_id = Some(ip_id),
+        }
+        result.sequence = Some(sequence);
+    }
+    result.matches = os_fingerprinter.database.match_signature(&result.signature);
+    result.fingerprints = report.fingerprints;
+    result.probe_status = report.probe_status;
+    result.start_time = report.start_time;
+    result.end_time = report.end_time;
+    result.elapsed_time = report.elapsed_time;
+    Ok(result)
+}
@@ -0,0 +1,633 @@
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Built-in p0f signatures
+const BUILTIN_P0F_DB: &str = include_str!("p0f.fp");
+
+/// Largest difference between initial and observed TTL for an exact match
+const MAX_DISTANCE: u8 = 35;
+
+/// Quirks that may be missing from an observed packet in a fuzzy match
+const FUZZY_DELETED_QUIRKS: [&str; 2] = ["df", "id+"];
+/// Quirks that may be added to an observed packet in a fuzzy match
+const FUZZY_ADDED_QUIRKS: [&str; 2] = ["id-", "ecn"];
+/// Quirks ignored when matching IPv6 against `*` signatures: IPv4 header
+/// fields, and the flow label which those signatures cannot describe
+const IPV6_IGNORED_QUIRKS: [&str; 5] = ["df", "id+", "id-", "0+", "flow"];
+
+const KNOWN_QUIRKS: [&str; 17] = [
+    "df", "id+", "id-", "ecn", "0+", "flow", "seq-", "ack+", "ack-", "uptr+", "urgf+", "pushf+",
+    "ts1-", "ts2+", "opt+", "exws", "bad",
+];
+
+/// Direction of the observed packet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TcpDirection {
+    /// SYN from a client
+    Request,
+    /// SYN/ACK from a server
+    Response,
+}
+
+impl TcpDirection {
+    pub fn name(&self) -> String {
+        match *self {
+            TcpDirection::Request => String::from("SYN"),
+            TcpDirection::Response => String::from("SYN/ACK"),
+        }
+    }
+}
+
+/// Window size rule of a p0f signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WindowRule {
+    Any,
+    Value(u16),
+    /// Multiple of MSS
+    Mss(u16),
+    /// Multiple of MTU (MSS plus IP and TCP headers)
+    Mtu(u16),
+    /// Any multiple of the value
+    Modulo(u16),
+}
+
+/// Label of a p0f signature. e.g. `s:unix:Linux:3.11 and newer`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct P0fLabel {
+    /// Generic signature (`g`), matched after specific ones
+    pub generic: bool,
+    /// OS class (unix, win, ...). `!` for applications such as scanners
+    pub class: String,
+    /// OS or application name
+    pub name: String,
+    /// Version or other detail
+    pub flavor: String,
+}
+
+impl P0fLabel {
+    /// Name and flavor. e.g. "Linux 3.11 and newer"
+    pub fn full_name(&self) -> String {
+        if self.flavor.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} {}", self.name, self.flavor)
+        }
+    }
+}
+
+/// p0f TCP signature
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct P0fSignature {
+    pub label: P0fLabel,
+    /// IP version, `None` for both
+    pub version: Option<u8>,
+    /// Initial TTL
+    pub initial_ttl: u8,
+    /// TTL may have been altered, skip distance check
+    pub bad_ttl: bool,
+    /// Length of IPv4 options
+    pub olen: u8,
+    pub mss: Option<u16>,
+    pub window: WindowRule,
+    pub wscale: Option<u8>,
+    /// TCP option layout. e.g. ["mss", "sok", "ts", "nop", "ws"]
+    pub olayout: Vec<String>,
+    pub quirks: Vec<String>,
+    /// Payload present, `None` for either
+    pub payload: Option<bool>,
+    /// Signature as written in the database
+    pub raw: String,
+}
+
+/// Signature of an observed SYN or SYN/ACK packet
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TcpSynSignature {
+    /// IP version (4 or 6)
+    pub version: u8,
+    /// IPv4 TTL or IPv6 hop limit
+    pub ttl: u8,
+    /// Length of IPv4 options
+    pub olen: u8,
+    pub mss: Option<u16>,
+    pub window: u16,
+    pub wscale: Option<u8>,
+    /// TCP option layout
+    pub olayout: Vec<String>,
+    pub quirks: Vec<String>,
+    /// Payload present
+    pub payload: bool,
+}
+
+impl TcpSynSignature {
+    /// Format as p0f raw signature. e.g. "4:64+0:0:1460:64240,7:mss,sok,ts,nop,ws:df,id+:0"
+    pub fn to_p0f_string(&self) -> String {
+        let initial_ttl: u8 = crate::ip::guess_initial_ttl(self.ttl);
+        format!(
+            "{}:{}+{}:{}:{}:{},{}:{}:{}:{}",
+            self.version,
+            self.ttl,
+            initial_ttl - self.ttl,
+            self.olen,
+            self.mss.map_or(String::from("*"), |mss| mss.to_string()),
+            self.window,
+            self.wscale
+                .map_or(String::from("*"), |wscale| wscale.to_string()),
+            self.olayout.join(","),
+            self.quirks.join(","),
+            if self.payload { "+" } else { "0" }
+        )
+    }
+}
+
+/// Signature that matched an observed packet
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct P0fMatch {
+    pub label: P0fLabel,
+    /// Matched only after relaxing TTL distance or DF/IP ID quirks
+    pub fuzzy: bool,
+    /// Estimated hop count from initial TTL
+    pub distance: u8,
+    /// Matched signature as written in the database
+    pub signature: String,
+}
+
+/// p0f v3 style TCP signature database
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct P0fDatabase {
+    /// Signatures for SYN packets
+    pub request: Vec<P0fSignature>,
+    /// Signatures for SYN/ACK packets
+    pub response: Vec<P0fSignature>,
+}
+
+impl P0fDatabase {
+    /// Create empty database
+    pub fn new() -> P0fDatabase {
+        P0fDatabase {
+            request: Vec::new(),
+            response: Vec::new(),
+        }
+    }
+    /// Database bundled with netprobe
+    pub fn builtin() -> P0fDatabase {
+        // The bundled file is known to be valid
+        P0fDatabase::parse(BUILTIN_P0F_DB).unwrap_or_default()
+    }
+    /// Load database from file. A p0f v3 `p0f.fp` can be loaded as is.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<P0fDatabase, String> {
+        match std::fs::read_to_string(path.as_ref()) {
+            Ok(content) => P0fDatabase::parse(&content),
+            Err(e) => Err(format!(
+                "P0fDatabase::load: unable to read {}: {}",
+                path.as_ref().display(),
+                e
+            )),
+        }
+    }
+    /// Parse database text.
+    ///
+    /// Only `[tcp:request]` and `[tcp:response]` sections are read, other sections
+    /// (MTU, HTTP) are skipped. Lines starting with `;` are comments.
+    pub fn parse(content: &str) -> Result<P0fDatabase, String> {
+        let mut db: P0fDatabase = P0fDatabase::new();
+        let mut direction: Option<TcpDirection> = None;
+        let mut label: Option<P0fLabel> = None;
+        for (i, line) in content.lines().enumerate() {
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                direction = match &line[1..line.len() - 1] {
+                    "tcp:request" => Some(TcpDirection::Request),
+                    "tcp:response" => Some(TcpDirection::Response),
+                    _ => None,
+                };
+                label = None;
+                continue;
+            }
+            let direction: TcpDirection = match direction {
+                Some(direction) => direction,
+                None => continue,
+            };
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    return Err(format!(
+                        "P0fDatabase::parse: line {}: expected key = value",
+                        i + 1
+                    ))
+                }
+            };
+            match key {
+                "label" => match parse_label(value) {
+                    Some(l) => label = Some(l),
+                    None => {
+                        return Err(format!(
+                            "P0fDatabase::parse: line {}: invalid label {}",
+                            i + 1,
+                            value
+                        ))
+                    }
+                },
+                "sig" => {
+                    let label: P0fLabel = match &label {
+                        Some(label) => label.clone(),
+                        None => {
+                            return Err(format!(
+                                "P0fDatabase::parse: line {}: sig before label",
+                                i + 1
+                            ))
+                        }
+                    };
+                    let signature: P0fSignature = match parse_signature(label, value) {
+                        Some(signature) => signature,
+                        None => {
+                            return Err(format!(
+                                "P0fDatabase::parse: line {}: invalid sig {}",
+                                i + 1,
+                                value
+                            ))
+                        }
+                    };
+                    match direction {
+                        TcpDirection::Request => db.request.push(signature),
+                        TcpDirection::Response => db.response.push(signature),
+                    }
+                }
+                // OS hints for application labels
+                "sys" => {}
+                _ => {
+                    return Err(format!(
+                        "P0fDatabase::parse: line {}: unknown field {}",
+                        i + 1,
+                        key
+                    ))
+                }
+            }
+        }
+        Ok(db)
+    }
+    /// Match observed packet against signatures for its direction.
+    ///
+    /// Exact matches come before fuzzy ones and specific signatures before generic
+    /// ones, otherwise in database order.
+    pub fn match_signature(
+        &self,
+        direction: TcpDirection,
+        observed: &TcpSynSignature,
+    ) -> Vec<P0fMatch> {
+        let signatures: &Vec<P0fSignature> = match direction {
+            TcpDirection::Request => &self.request,
+            TcpDirection::Response => &self.response,
+        };
+        let mut matches: Vec<P0fMatch> = signatures
+            .iter()
+            .filter_map(|signature| {
+                compare_signature(signature, observed).map(|fuzzy| P0fMatch {
+                    label: signature.label.clone(),
+                    fuzzy,
+                    distance: signature.initial_ttl - observed.ttl,
+                    signature: signature.raw.clone(),
+                })
+            })
+            .collect();
+        // Stable sort keeps database order within each group
+        matches.sort_by_key(|m| (m.fuzzy, m.label.generic));
+        matches
+    }
+}
+
+impl Default for P0fDatabase {
+    fn default() -> Self {
+        P0fDatabase::new()
+    }
+}
+
+/// Compare signature with observed packet. Returns `Some(fuzzy)` on match.
+fn compare_signature(signature: &P0fSignature, observed: &TcpSynSignature) -> Option<bool> {
+    if signature
+        .version
+        .is_some_and(|version| version != observed.version)
+        || signature.olen != observed.olen
+        || signature.olayout != observed.olayout
+        || observed.ttl > signature.initial_ttl
+    {
+        return None;
+    }
+    if signature.mss.is_some_and(|mss| Some(mss) != observed.mss)
+        || signature
+            .wscale
+            .is_some_and(|wscale| wscale != observed.wscale.unwrap_or(0))
+        || signature
+            .payload
+            .is_some_and(|payload| payload != observed.payload)
+    {
+        return None;
+    }
+    let window: u32 = observed.window as u32;
+    let window_matched: bool = match signature.window {
+        WindowRule::Any => true,
+        WindowRule::Value(value) => window == value as u32,
+        WindowRule::Mss(multiplier) => observed
+            .mss
+            .is_some_and(|mss| window == mss as u32 * multiplier as u32),
+        WindowRule::Mtu(multiplier) => observed.mss.is_some_and(|mss| {
+            let header_len: u32 = if observed.version == 4 { 40 } else { 60 };
+            window == (mss as u32 + header_len) * multiplier as u32
+        }),
+        WindowRule::Modulo(divisor) => window.checked_rem(divisor as u32) == Some(0),
+    };
+    if !window_matched {
+        return None;
+    }
+    let mut fuzzy: bool = !signature.bad_ttl && signature.initial_ttl - observed.ttl > MAX_DISTANCE;
+    // Signatures for both versions are written for IPv4
+    let ignored: &[&str] = match (signature.version, observed.version) {
+        (None, 6) => &IPV6_IGNORED_QUIRKS,
+        _ => &[],
+    };
+    let relevant = |quirk: &&String| !ignored.contains(&quirk.as_str());
+    let deleted: Vec<&String> = signature
+        .quirks
+        .iter()
+        .filter(relevant)
+        .filter(|quirk| !observed.quirks.contains(quirk))
+        .collect();
+    let added: Vec<&String> = observed
+        .quirks
+        .iter()
+        .filter(relevant)
+        .filter(|quirk| !signature.quirks.contains(quirk))
+        .collect();
+    if !deleted.is_empty() || !added.is_empty() {
+        if deleted
+            .iter()
+            .any(|quirk| !FUZZY_DELETED_QUIRKS.contains(&quirk.as_str()))
+            || added
+                .iter()
+                .any(|quirk| !FUZZY_ADDED_QUIRKS.contains(&quirk.as_str()))
+        {
+            return None;
+        }
+        fuzzy = true;
+    }
+    Some(fuzzy)
+}
+
+fn parse_label(value: &str) -> Option<P0fLabel> {
+    let fields: Vec<&str> = value.splitn(4, ':').collect();
+    if fields.len() != 4 {
+        return None;
+    }
+    let generic: bool = match fields[0] {
+        "s" => false,
+        "g" => true,
+        _ => return None,
+    };
+    Some(P0fLabel {
+        generic,
+        class: fields[1].to_string(),
+        name: fields[2].to_string(),
+        flavor: fields[3].to_string(),
+    })
+}
+
+/// Parse `ver:ittl:olen:mss:wsize,scale:olayout:quirks:pclass`
+fn parse_signature(label: P0fLabel, value: &str) -> Option<P0fSignature> {
+    let fields: Vec<&str> = value.split(':').collect();
+    if fields.len() != 8 {
+        return None;
+    }
+    let version: Option<u8> = match fields[0] {
+        "*" => None,
+        "4" => Some(4),
+        "6" => Some(6),
+        _ => return None,
+    };
+    let (initial_ttl, bad_ttl) = match fields[1].strip_suffix('-') {
+        Some(ttl) => (ttl.parse::<u8>().ok()?, true),
+        None => (fields[1].parse::<u8>().ok()?, false),
+    };
+    let olen: u8 = fields[2].parse::<u8>().ok()?;
+    let mss: Option<u16> = parse_wildcard(fields[3])?;
+    let (window, wscale) = fields[4].split_once(',')?;
+    let window: WindowRule = if window == "*" {
+        WindowRule::Any
+    } else if let Some(multiplier) = window.strip_prefix("mss*") {
+        WindowRule::Mss(multiplier.parse::<u16>().ok()?)
+    } else if let Some(multiplier) = window.strip_prefix("mtu*") {
+        WindowRule::Mtu(multiplier.parse::<u16>().ok()?)
+    } else if let Some(divisor) = window.strip_prefix('%') {
+        WindowRule::Modulo(divisor.parse::<u16>().ok()?)
+    } else {
+        WindowRule::Value(window.parse::<u16>().ok()?)
+    };
+    let wscale: Option<u8> = parse_wildcard(wscale)?;
+    let olayout: Vec<String> = split_list(fields[5]);
+    if !olayout.iter().all(|option| is_known_option(option)) {
+        return None;
+    }
+    let quirks: Vec<String> = split_list(fields[6]);
+    if !quirks
+        .iter()
+        .all(|quirk| KNOWN_QUIRKS.contains(&quirk.as_str()))
+    {
+        return None;
+    }
+    let payload: Option<bool> = match fields[7] {
+        "*" => None,
+        "0" => Some(false),
+        "+" => Some(true),
+        _ => return None,
+    };
+    Some(P0fSignature {
+        label,
+        version,
+        initial_ttl,
+        bad_ttl,
+        olen,
+        mss,
+        window,
+        wscale,
+        olayout,
+        quirks,
+        payload,
+        raw: value.to_string(),
+    })
+}
+
+/// Parse number or `*`. Returns `None` if invalid.
+fn parse_wildcard<T: std::str::FromStr>(value: &str) -> Option<Option<T>> {
+    if value == "*" {
+        Some(None)
+    } else {
+        value.parse::<T>().ok().map(Some)
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn is_known_option(option: &str) -> bool {
+    if let Some(padding) = option.strip_prefix("eol+") {
+        return padding.parse::<u8>().is_ok();
+    }
+    if let Some(kind) = option.strip_prefix('?') {
+        return kind.parse::<u8>().is_ok();
+    }
+    matches!(option, "nop" | "mss" | "ws" | "sok" | "sack" | "ts")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(raw: &str) -> P0fSignature {
+        let label: P0fLabel = parse_label("s:unix:Test:1").unwrap();
+        parse_signature(label, raw).unwrap()
+    }
+
+    fn observed(ttl: u8, window: u16, wscale: u8, olayout: &str, quirks: &str) -> TcpSynSignature {
+        TcpSynSignature {
+            version: 4,
+            ttl,
+            olen: 0,
+            mss: Some(1460),
+            window,
+            wscale: Some(wscale),
+            olayout: split_list(olayout),
+            quirks: split_list(quirks),
+            payload: false,
+        }
+    }
+
+    /// SYN sent by Linux 4.x and later
+    fn linux_syn() -> TcpSynSignature {
+        observed(60, 64240, 7, "mss,sok,ts,nop,ws", "df,id+")
+    }
+
+    #[test]
+    fn builtin_database_parses() {
+        let db: P0fDatabase = P0fDatabase::parse(BUILTIN_P0F_DB).unwrap();
+        assert!(!db.request.is_empty());
+        assert!(!db.response.is_empty());
+        assert_eq!(P0fDatabase::builtin().request.len(), db.request.len());
+        assert_eq!(P0fDatabase::builtin().response.len(), db.response.len());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_lines() {
+        assert!(P0fDatabase::parse("[tcp:request]\nsig = *:64:0:*:*,*:mss:df:0").is_err());
+        assert!(P0fDatabase::parse("[tcp:request]\nlabel = x:unix:Test:1").is_err());
+        assert!(P0fDatabase::parse(
+            "[tcp:request]\nlabel = s:unix:Test:1\nsig = *:64:0:*:*,*:mss:zz:0"
+        )
+        .is_err());
+        // Other sections are skipped
+        let db: P0fDatabase = P0fDatabase::parse("[http:request]\nua = anything").unwrap();
+        assert!(db.request.is_empty());
+    }
+
+    #[test]
+    fn parse_signature_fields() {
+        let sig: P0fSignature = signature("4:64-:0:1460:mss*44,7:mss,sok,eol+1:df,id+:+");
+        assert_eq!(sig.version, Some(4));
+        assert_eq!(sig.initial_ttl, 64);
+        assert!(sig.bad_ttl);
+        assert_eq!(sig.mss, Some(1460));
+        assert_eq!(sig.window, WindowRule::Mss(44));
+        assert_eq!(sig.wscale, Some(7));
+        assert_eq!(sig.olayout, ["mss", "sok", "eol+1"]);
+        assert_eq!(sig.quirks, ["df", "id+"]);
+        assert_eq!(sig.payload, Some(true));
+        assert_eq!(
+            signature("*:64:0:*:%8192,*:mss::*").window,
+            WindowRule::Modulo(8192)
+        );
+    }
+
+    #[test]
+    fn compare_syn_signature() {
+        let sig: P0fSignature = signature("*:64:0:*:mss*44,7:mss,sok,ts,nop,ws:df,id+:0");
+        assert_eq!(compare_signature(&sig, &linux_syn()), Some(false));
+        // Window scale, option layout and initial TTL must match
+        let mut syn: TcpSynSignature = linux_syn();
+        syn.wscale = Some(8);
+        assert_eq!(compare_signature(&sig, &syn), None);
+        syn = linux_syn();
+        syn.olayout.pop();
+        assert_eq!(compare_signature(&sig, &syn), None);
+        syn = linux_syn();
+        syn.ttl = 100;
+        assert_eq!(compare_signature(&sig, &syn), None);
+        // Too many hops away
+        syn = linux_syn();
+        syn.ttl = 20;
+        assert_eq!(compare_signature(&sig, &syn), Some(true));
+        // DF and IP ID quirks are relaxed, others are not
+        syn = observed(60, 64240, 7, "mss,sok,ts,nop,ws", "id-,ecn");
+        assert_eq!(compare_signature(&sig, &syn), Some(true));
+        syn = observed(60, 64240, 7, "mss,sok,ts,nop,ws", "df,id+,ts1-");
+        assert_eq!(compare_signature(&sig, &syn), None);
+        // IPv4 header quirks are ignored for IPv6
+        syn = observed(60, 64240, 7, "mss,sok,ts,nop,ws", "flow");
+        syn.version = 6;
+        assert_eq!(compare_signature(&sig, &syn), Some(false));
+    }
+
+    #[test]
+    fn compare_mtu_window() {
+        let sig: P0fSignature = signature("*:64:0:*:mtu*2,0:mss::0");
+        let mut syn: TcpSynSignature = observed(64, 3000, 0, "mss", "");
+        assert_eq!(compare_signature(&sig, &syn), Some(false));
+        syn.version = 6;
+        assert_eq!(compare_signature(&sig, &syn), None);
+        syn.window = 3040;
+        assert_eq!(compare_signature(&sig, &syn), Some(false));
+    }
+
+    #[test]
+    fn match_syn() {
+        let db: P0fDatabase = P0fDatabase::builtin();
+        let matches: Vec<P0fMatch> = db.match_signature(TcpDirection::Request, &linux_syn());
+        assert_eq!(matches[0].label.full_name(), "Linux 4.x-6.x");
+        assert!(!matches[0].fuzzy);
+        assert_eq!(matches[0].distance, 4);
+        // Generic signatures come after specific ones
+        let generic: usize = matches.iter().position(|m| m.label.generic).unwrap();
+        assert!(matches[..generic].iter().all(|m| !m.label.generic));
+        assert!(matches[generic..]
+            .iter()
+            .all(|m| m.label.generic || m.fuzzy));
+    }
+
+    #[test]
+    fn match_syn_ack() {
+        let db: P0fDatabase = P0fDatabase::builtin();
+        let syn_ack: TcpSynSignature = observed(64, 65160, 7, "mss,sok,ts,nop,ws", "df");
+        let matches: Vec<P0fMatch> = db.match_signature(TcpDirection::Response, &syn_ack);
+        assert_eq!(matches[0].label.full_name(), "Linux 4.x-6.x");
+        assert!(!matches[0].fuzzy);
+        assert_eq!(matches[0].distance, 0);
+        // SYN signatures are not used for a SYN/ACK
+        assert!(db
+            .match_signature(TcpDirection::Request, &syn_ack)
+            .iter()
+            .all(|m| m.label.full_name() != "Linux 4.x-6.x"));
+    }
+}
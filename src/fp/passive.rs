@@ -0,0 +1,601 @@
+use super::p0f::{P0fDatabase, P0fMatch, TcpDirection, TcpSynSignature};
+use crate::packet::decode::NEXT_HEADER_TCP;
+use crate::result::ProbeStatus;
+use crate::setting::ProbeSetting;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use xenet::datalink::DataLinkReceiver;
+use xenet::net::interface::Interface;
+use xenet::packet::ethernet::{EtherType, ETHERNET_HEADER_LEN};
+use xenet::packet::ipv4::{Ipv4Flags, IPV4_HEADER_LEN};
+use xenet::packet::ipv6::IPV6_HEADER_LEN;
+use xenet::packet::tcp::{TcpFlags, TcpOptionKind, TCP_HEADER_LEN};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Reserved bit of the IPv4 flags
+const IPV4_FLAG_RESERVED: u8 = 0b100;
+/// ECT(1), ECT(0) or CE
+const IP_ECN_MASK: u8 = 0x03;
+
+const TCP_OPTION_EOL: u8 = TcpOptionKind::EOL as u8;
+const TCP_OPTION_NOP: u8 = TcpOptionKind::NOP as u8;
+const TCP_OPTION_MSS: u8 = TcpOptionKind::MSS as u8;
+const TCP_OPTION_WSCALE: u8 = TcpOptionKind::WSCALE as u8;
+const TCP_OPTION_SACK_PERMITTED: u8 = TcpOptionKind::SACK_PERMITTED as u8;
+const TCP_OPTION_SACK: u8 = TcpOptionKind::SACK as u8;
+const TCP_OPTION_TIMESTAMPS: u8 = TcpOptionKind::TIMESTAMPS as u8;
+/// Largest window scale allowed by RFC 7323
+const MAX_WSCALE: u8 = 14;
+
+/// SYN or SYN/ACK seen on the wire, with its OS guesses
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PassiveObservation {
+    /// Host that sent the packet
+    pub src_ip: IpAddr,
+    pub src_port: u16,
+    pub dst_ip: IpAddr,
+    pub dst_port: u16,
+    /// SYN from a client or SYN/ACK from a server
+    pub direction: TcpDirection,
+    /// Packet signature
+    pub signature: TcpSynSignature,
+    /// Estimated hop count to the sender
+    pub distance: u8,
+    /// OS or application guesses, best first
+    pub matches: Vec<P0fMatch>,
+    /// Number of packets seen with this signature from this host
+    pub packet_count: usize,
+    /// Time first seen, in RFC 3339 and ISO 8601 date and time string
+    pub first_seen: String,
+}
+
+impl PassiveObservation {
+    /// Best guess
+    pub fn best_match(&self) -> Option<&P0fMatch> {
+        self.matches.first()
+    }
+}
+
+/// Result of passive fingerprinting
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PassiveFingerprintResult {
+    /// One entry per host, direction and signature
+    pub observations: Vec<PassiveObservation>,
+    /// Status
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl PassiveFingerprintResult {
+    pub fn new() -> PassiveFingerprintResult {
+        PassiveFingerprintResult {
+            observations: Vec::new(),
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Observations of packets sent by `ip_addr`
+    pub fn get(&self, ip_addr: IpAddr) -> Vec<&PassiveObservation> {
+        self.observations
+            .iter()
+            .filter(|observation| observation.src_ip == ip_addr)
+            .collect()
+    }
+}
+
+impl Default for PassiveFingerprintResult {
+    fn default() -> Self {
+        PassiveFingerprintResult::new()
+    }
+}
+
+/// Passive OS fingerprinting from observed TCP SYN and SYN/ACK packets.
+///
+/// Listens on the interface for `probe_setting.probe_timeout` and matches each
+/// handshake packet against a p0f-style signature database. Nothing is transmitted.
+/// If `probe_setting.dst_ip` is specified, only packets sent by that host are used.
+/// If `probe_setting.dst_port` is specified, only SYNs to and SYN/ACKs from that port are used.
+#[derive(Clone, Debug)]
+pub struct PassiveFingerprinter {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Signature database
+    pub database: P0fDatabase,
+    /// Also observe traffic between other hosts
+    pub promiscuous: bool,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<PassiveObservation>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<PassiveObservation>>>,
+}
+
+impl PassiveFingerprinter {
+    /// Create new PassiveFingerprinter instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<PassiveFingerprinter, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "PassiveFingerprinter::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let (tx, rx) = channel();
+        let fingerprinter = PassiveFingerprinter {
+            probe_setting: setting,
+            database: P0fDatabase::builtin(),
+            promiscuous: false,
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(fingerprinter)
+    }
+    /// Set signature database
+    pub fn with_database(mut self, database: P0fDatabase) -> PassiveFingerprinter {
+        self.database = database;
+        self
+    }
+    /// Put the interface in promiscuous mode to observe traffic between other hosts
+    pub fn with_promiscuous(mut self, promiscuous: bool) -> PassiveFingerprinter {
+        self.promiscuous = promiscuous;
+        self
+    }
+    /// Listen for SYN and SYN/ACK packets
+    pub fn listen(&self) -> Result<PassiveFingerprintResult, String> {
+        run_passive_fingerprinting(self)
+    }
+    /// Get progress receiver. A message is sent for each new host and signature.
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<PassiveObservation>>> {
+        self.rx.clone()
+    }
+}
+
+fn run_passive_fingerprinting(
+    fingerprinter: &PassiveFingerprinter,
+) -> Result<PassiveFingerprintResult, String> {
+    let setting: &ProbeSetting = &fingerprinter.probe_setting;
+    let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
+        Some(interface) => interface,
+        None => {
+            return Err(format!(
+                "run_passive_fingerprinting: unable to get interface by index {}",
+                setting.if_index
+            ))
+        }
+    };
+    let config = xenet::datalink::Config {
+        write_buffer_size: 4096,
+        read_buffer_size: 4096,
+        read_timeout: Some(setting.receive_timeout),
+        write_timeout: None,
+        channel_type: xenet::datalink::ChannelType::Layer2,
+        bpf_fd_attempts: 1000,
+        linux_fanout: None,
+        promiscuous: fingerprinter.promiscuous,
+    };
+    // Create a channel to receive packet. The sender is never used.
+    let mut rx: Box<dyn DataLinkReceiver> = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(_tx, rx)) => rx,
+        Ok(_) => return Err("run_passive_fingerprinting: unable to create channel".to_string()),
+        Err(e) => {
+            return Err(format!(
+                "run_passive_fingerprinting: unable to create channel: {}",
+                e
+            ))
+        }
+    };
+    let mut result = PassiveFingerprintResult::new();
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    loop {
+        if let Ok(packet) = rx.next() {
+            if let Some(observation) = parse_packet(packet, setting, &fingerprinter.database) {
+                match result.observations.iter_mut().find(|o| {
+                    o.src_ip == observation.src_ip
+                        && o.direction == observation.direction
+                        && o.signature == observation.signature
+                }) {
+                    Some(known) => known.packet_count += 1,
+                    None => {
+                        if let Ok(lr) = fingerprinter.tx.lock() {
+                            let _ = lr.send(observation.clone());
+                        }
+                        result.observations.push(observation);
+                    }
+                }
+            }
+        }
+        if Instant::now().duration_since(start_time) > setting.probe_timeout {
+            break;
+        }
+    }
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = Instant::now().duration_since(start_time);
+    if result.observations.is_empty() {
+        result.probe_status =
+            ProbeStatus::with_timeout_message(String::from("No SYN or SYN/ACK observed"));
+    }
+    Ok(result)
+}
+
+/// Parse captured frame and match it if it is a SYN or SYN/ACK.
+///
+/// Headers are read from the raw bytes rather than through `Frame`, which panics on
+/// TCP option kinds it does not know (TFO, MPTCP and others seen in the wild).
+fn parse_packet(
+    packet: &[u8],
+    setting: &ProbeSetting,
+    database: &P0fDatabase,
+) -> Option<PassiveObservation> {
+    let ip_packet: &[u8] = if setting.tunnel {
+        let offset: usize = if setting.loopback {
+            ETHERNET_HEADER_LEN
+        } else {
+            0
+        };
+        packet.get(offset..)?
+    } else {
+        let (ether_type, payload) = crate::packet::decode::ethernet_payload(packet)?;
+        match EtherType::new(ether_type) {
+            EtherType::Ipv4 | EtherType::Ipv6 => payload,
+            _ => return None,
+        }
+    };
+    let mut quirks: Vec<String> = Vec::new();
+    let (src_ip, dst_ip, ttl, olen, tcp_segment) = match ip_packet.first()? >> 4 {
+        4 => parse_ipv4(ip_packet, &mut quirks)?,
+        6 => parse_ipv6(ip_packet, &mut quirks)?,
+        _ => return None,
+    };
+    if !setting.dst_ip.is_unspecified() && setting.dst_ip != src_ip {
+        return None;
+    }
+    let (src_port, dst_port, direction, signature) =
+        parse_tcp_syn(tcp_segment, src_ip, ttl, olen, quirks)?;
+    if let Some(port) = setting.dst_port {
+        let service_port: u16 = match direction {
+            TcpDirection::Request => dst_port,
+            TcpDirection::Response => src_port,
+        };
+        if service_port != port {
+            return None;
+        }
+    }
+    let matches: Vec<P0fMatch> = database.match_signature(direction, &signature);
+    let distance: u8 = match matches.first() {
+        Some(best) => best.distance,
+        None => crate::ip::guess_initial_ttl(ttl) - ttl,
+    };
+    Some(PassiveObservation {
+        src_ip,
+        src_port,
+        dst_ip,
+        dst_port,
+        direction,
+        signature,
+        distance,
+        matches,
+        packet_count: 1,
+        first_seen: crate::sys::get_sysdate(),
+    })
+}
+
+/// Parse IPv4 header. Returns addresses, TTL, options length and TCP segment.
+fn parse_ipv4<'a>(
+    packet: &'a [u8],
+    quirks: &mut Vec<String>,
+) -> Option<(IpAddr, IpAddr, u8, u8, &'a [u8])> {
+    if packet.len() < IPV4_HEADER_LEN {
+        return None;
+    }
+    let header_len: usize = ((packet[0] & 0x0f) as usize) * 4;
+    let total_len: usize = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+    let flags: u8 = packet[6] >> 5;
+    let fragment_offset: u16 = u16::from_be_bytes([packet[6] & 0x1f, packet[7]]);
+    if header_len < IPV4_HEADER_LEN
+        || total_len < header_len
+        || total_len > packet.len()
+        || packet[9] != NEXT_HEADER_TCP
+        || fragment_offset != 0
+        || flags & Ipv4Flags::MoreFragments != 0
+    {
+        return None;
+    }
+    let ip_id: u16 = u16::from_be_bytes([packet[4], packet[5]]);
+    if flags & Ipv4Flags::DontFragment != 0 {
+        quirks.push(String::from("df"));
+        if ip_id != 0 {
+            quirks.push(String::from("id+"));
+        }
+    } else if ip_id == 0 {
+        quirks.push(String::from("id-"));
+    }
+    if packet[1] & IP_ECN_MASK != 0 {
+        quirks.push(String::from("ecn"));
+    }
+    if flags & IPV4_FLAG_RESERVED != 0 {
+        quirks.push(String::from("0+"));
+    }
+    let src_ip = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    let dst_ip = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+    Some((
+        IpAddr::V4(src_ip),
+        IpAddr::V4(dst_ip),
+        packet[8],
+        (header_len - IPV4_HEADER_LEN) as u8,
+        &packet[header_len..total_len],
+    ))
+}
+
+/// Parse IPv6 header. Only TCP directly after the fixed header is supported.
+fn parse_ipv6<'a>(
+    packet: &'a [u8],
+    quirks: &mut Vec<String>,
+) -> Option<(IpAddr, IpAddr, u8, u8, &'a [u8])> {
+    if packet.len() < IPV6_HEADER_LEN || packet[6] != NEXT_HEADER_TCP {
+        return None;
+    }
+    let payload_len: usize = u16::from_be_bytes([packet[4], packet[5]]) as usize;
+    if IPV6_HEADER_LEN + payload_len > packet.len() {
+        return None;
+    }
+    let traffic_class: u8 = (packet[0] << 4) | (packet[1] >> 4);
+    let flow_label: u32 = u32::from_be_bytes([0, packet[1] & 0x0f, packet[2], packet[3]]);
+    if traffic_class & IP_ECN_MASK != 0 {
+        quirks.push(String::from("ecn"));
+    }
+    if flow_label != 0 {
+        quirks.push(String::from("flow"));
+    }
+    let mut src_octets: [u8; 16] = [0u8; 16];
+    src_octets.copy_from_slice(&packet[8..24]);
+    let mut dst_octets: [u8; 16] = [0u8; 16];
+    dst_octets.copy_from_slice(&packet[24..40]);
+    Some((
+        IpAddr::V6(Ipv6Addr::from(src_octets)),
+        IpAddr::V6(Ipv6Addr::from(dst_octets)),
+        packet[7],
+        0,
+        &packet[IPV6_HEADER_LEN..IPV6_HEADER_LEN + payload_len],
+    ))
+}
+
+/// Parse TCP segment. Returns `None` unless it is a SYN or SYN/ACK.
+fn parse_tcp_syn(
+    segment: &[u8],
+    src_ip: IpAddr,
+    ttl: u8,
+    olen: u8,
+    mut quirks: Vec<String>,
+) -> Option<(u16, u16, TcpDirection, TcpSynSignature)> {
+    if segment.len() < TCP_HEADER_LEN {
+        return None;
+    }
+    let flags: u8 = segment[13];
+    if flags & TcpFlags::SYN == 0 || flags & (TcpFlags::FIN | TcpFlags::RST) != 0 {
+        return None;
+    }
+    let header_len: usize = ((segment[12] >> 4) as usize) * 4;
+    if header_len < TCP_HEADER_LEN || header_len > segment.len() {
+        return None;
+    }
+    let src_port: u16 = u16::from_be_bytes([segment[0], segment[1]]);
+    let dst_port: u16 = u16::from_be_bytes([segment[2], segment[3]]);
+    let seq: u32 = u32::from_be_bytes([segment[4], segment[5], segment[6], segment[7]]);
+    let ack: u32 = u32::from_be_bytes([segment[8], segment[9], segment[10], segment[11]]);
+    let window: u16 = u16::from_be_bytes([segment[14], segment[15]]);
+    let urgent_ptr: u16 = u16::from_be_bytes([segment[18], segment[19]]);
+    let direction: TcpDirection = if flags & TcpFlags::ACK != 0 {
+        TcpDirection::Response
+    } else {
+        TcpDirection::Request
+    };
+    // Own ECN quirk may already be set from the IP header
+    if flags & (TcpFlags::ECE | TcpFlags::CWR) != 0 && !quirks.iter().any(|q| q == "ecn") {
+        quirks.push(String::from("ecn"));
+    }
+    if seq == 0 {
+        quirks.push(String::from("seq-"));
+    }
+    if flags & TcpFlags::ACK == 0 && ack != 0 {
+        quirks.push(String::from("ack+"));
+    }
+    if flags & TcpFlags::ACK != 0 && ack == 0 {
+        quirks.push(String::from("ack-"));
+    }
+    if flags & TcpFlags::URG == 0 && urgent_ptr != 0 {
+        quirks.push(String::from("uptr+"));
+    }
+    if flags & TcpFlags::URG != 0 {
+        quirks.push(String::from("urgf+"));
+    }
+    if flags & TcpFlags::PSH != 0 {
+        quirks.push(String::from("pushf+"));
+    }
+    let mut signature = TcpSynSignature {
+        version: if src_ip.is_ipv4() { 4 } else { 6 },
+        ttl,
+        olen,
+        mss: None,
+        window,
+        wscale: None,
+        olayout: Vec::new(),
+        quirks: Vec::new(),
+        payload: segment.len() > header_len,
+    };
+    parse_tcp_options(
+        &segment[TCP_HEADER_LEN..header_len],
+        direction,
+        &mut signature,
+        &mut quirks,
+    );
+    signature.quirks = quirks;
+    Some((src_port, dst_port, direction, signature))
+}
+
+/// Walk raw TCP options. Unknown kinds are recorded as `?N`.
+fn parse_tcp_options(
+    options: &[u8],
+    direction: TcpDirection,
+    signature: &mut TcpSynSignature,
+    quirks: &mut Vec<String>,
+) {
+    let mut i: usize = 0;
+    while i < options.len() {
+        let kind: u8 = options[i];
+        if kind == TCP_OPTION_EOL {
+            let padding: &[u8] = &options[i + 1..];
+            signature.olayout.push(format!("eol+{}", padding.len()));
+            if padding.iter().any(|b| *b != 0) {
+                quirks.push(String::from("opt+"));
+            }
+            return;
+        }
+        if kind == TCP_OPTION_NOP {
+            signature.olayout.push(String::from("nop"));
+            i += 1;
+            continue;
+        }
+        let len: usize = match options.get(i + 1) {
+            Some(len) => *len as usize,
+            None => {
+                quirks.push(String::from("bad"));
+                return;
+            }
+        };
+        if len < 2 || i + len > options.len() {
+            quirks.push(String::from("bad"));
+            return;
+        }
+        let data: &[u8] = &options[i + 2..i + len];
+        match kind {
+            TCP_OPTION_MSS if data.len() == 2 => {
+                signature.olayout.push(String::from("mss"));
+                signature.mss = Some(u16::from_be_bytes([data[0], data[1]]));
+            }
+            TCP_OPTION_WSCALE if data.len() == 1 => {
+                signature.olayout.push(String::from("ws"));
+                signature.wscale = Some(data[0]);
+                if data[0] > MAX_WSCALE {
+                    quirks.push(String::from("exws"));
+                }
+            }
+            TCP_OPTION_SACK_PERMITTED if data.is_empty() => {
+                signature.olayout.push(String::from("sok"));
+            }
+            TCP_OPTION_SACK => signature.olayout.push(String::from("sack")),
+            TCP_OPTION_TIMESTAMPS if data.len() == 8 => {
+                signature.olayout.push(String::from("ts"));
+                if data[0..4] == [0, 0, 0, 0] {
+                    quirks.push(String::from("ts1-"));
+                }
+                // Peer timestamp is not known yet in the initial SYN
+                if direction == TcpDirection::Request && data[4..8] != [0, 0, 0, 0] {
+                    quirks.push(String::from("ts2+"));
+                }
+            }
+            TCP_OPTION_MSS
+            | TCP_OPTION_WSCALE
+            | TCP_OPTION_SACK_PERMITTED
+            | TCP_OPTION_TIMESTAMPS => {
+                quirks.push(String::from("bad"));
+                return;
+            }
+            _ => signature.olayout.push(format!("?{}", kind)),
+        }
+        i += len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn any_host() -> ProbeSetting {
+        ProbeSetting {
+            dst_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            ..ProbeSetting::new()
+        }
+    }
+
+    /// SYN from a Linux client, 802.1Q tagged, with `extra_options` appended
+    fn linux_syn(extra_options: &[u8]) -> Vec<u8> {
+        let mut options: Vec<u8> = vec![
+            2, 4, 0x05, 0xb4, // mss 1460
+            4, 2, // sok
+            8, 10, 0, 0, 0, 1, 0, 0, 0, 0, // ts
+            1, // nop
+            3, 3, 7, // ws 7
+        ];
+        options.extend_from_slice(extra_options);
+        let tcp_len: usize = TCP_HEADER_LEN + options.len();
+        let mut packet: Vec<u8> = vec![0u8; 12];
+        packet.extend_from_slice(&[0x81, 0x00, 0x00, 0x01, 0x08, 0x00]);
+        packet.extend_from_slice(&[0x45, 0x00]);
+        packet.extend_from_slice(&((IPV4_HEADER_LEN + tcp_len) as u16).to_be_bytes());
+        packet.extend_from_slice(&[0x12, 0x34, 0x40, 0x00, 60, NEXT_HEADER_TCP, 0, 0]);
+        packet.extend_from_slice(&[192, 0, 2, 1, 192, 0, 2, 2]);
+        packet.extend_from_slice(&40000u16.to_be_bytes());
+        packet.extend_from_slice(&80u16.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0]);
+        packet.push(((tcp_len / 4) as u8) << 4);
+        packet.push(TcpFlags::SYN);
+        packet.extend_from_slice(&64240u16.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0]);
+        packet.extend_from_slice(&options);
+        packet
+    }
+
+    #[test]
+    fn parse_tagged_syn() {
+        let packet: Vec<u8> = linux_syn(&[]);
+        let observation: PassiveObservation =
+            parse_packet(&packet, &any_host(), &P0fDatabase::builtin()).unwrap();
+        assert_eq!(observation.src_ip, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(observation.dst_port, 80);
+        assert_eq!(observation.direction, TcpDirection::Request);
+        assert_eq!(
+            observation.signature.to_p0f_string(),
+            "4:60+4:0:1460:64240,7:mss,sok,ts,nop,ws:df,id+:0"
+        );
+        assert_eq!(observation.distance, 4);
+        let best: &P0fMatch = observation.best_match().unwrap();
+        assert_eq!(best.label.full_name(), "Linux 4.x-6.x");
+        assert!(!best.fuzzy);
+    }
+
+    #[test]
+    fn parse_syn_with_unknown_option() {
+        // TCP Fast Open cookie request and padding
+        let packet: Vec<u8> = linux_syn(&[34, 2, 1, 1]);
+        let observation: PassiveObservation =
+            parse_packet(&packet, &any_host(), &P0fDatabase::new()).unwrap();
+        assert_eq!(
+            observation.signature.olayout,
+            ["mss", "sok", "ts", "nop", "ws", "?34", "nop", "nop"]
+        );
+        assert!(observation.matches.is_empty());
+    }
+
+    #[test]
+    fn parse_ignores_non_syn() {
+        let mut packet: Vec<u8> = linux_syn(&[]);
+        let flags_offset: usize = 18 + IPV4_HEADER_LEN + 13;
+        packet[flags_offset] = TcpFlags::ACK;
+        assert!(parse_packet(&packet, &any_host(), &P0fDatabase::new()).is_none());
+    }
+}
@@ -0,0 +1,368 @@
+use super::signature::{classify_ip_id, IpIdBehavior};
+use super::{Fingerprint, FingerprintType};
+use crate::result::ProbeStatus;
+use crate::setting::ProbeSetting;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use xenet::net::interface::Interface;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Common TCP timestamp clock frequencies (Hz)
+const KNOWN_TIMESTAMP_HZ: [u32; 7] = [1, 2, 10, 100, 200, 250, 1000];
+/// Relative error accepted when snapping to a known frequency
+const TIMESTAMP_HZ_TOLERANCE: f64 = 0.25;
+/// Boot times closer than this are considered the same clock
+const BOOT_TIME_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// One reply of a probe series
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SequenceSample {
+    /// IP ID (IPv4 only)
+    pub ip_id: Option<u16>,
+    /// TCP timestamp value (TSval)
+    pub timestamp: Option<u32>,
+    /// Time the reply was received, relative to the start of the series
+    pub time: Duration,
+}
+
+/// Series analysis for one target
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TargetSequence {
+    /// Target IP address
+    pub ip_addr: IpAddr,
+    /// Replies in the order they were received
+    pub samples: Vec<SequenceSample>,
+    /// Number of probes sent
+    pub sent_count: usize,
+    /// IP ID generation of this target
+    pub ip_id: IpIdBehavior,
+    /// Estimated TCP timestamp clock frequency (Hz)
+    pub timestamp_hz: Option<u32>,
+    /// Estimated uptime, derived from TSval and clock frequency
+    pub uptime: Option<Duration>,
+}
+
+/// Result of probe series
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SequenceResult {
+    /// Analysis per target
+    pub targets: Vec<TargetSequence>,
+    /// IP ID generation over all targets.
+    /// `PerHost` when each target counts independently.
+    pub ip_id: IpIdBehavior,
+    /// Whether all targets appear to be the same host.
+    /// `None` if there is a single target or no evidence either way.
+    pub same_host: Option<bool>,
+    /// Status
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl SequenceResult {
+    pub fn new() -> SequenceResult {
+        SequenceResult {
+            targets: Vec::new(),
+            ip_id: IpIdBehavior::Unknown,
+            same_host: None,
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+}
+
+impl Default for SequenceResult {
+    fn default() -> Self {
+        SequenceResult::new()
+    }
+}
+
+/// Sends a series of probes to analyze IP ID generation and TCP timestamp clock.
+///
+/// Probes to `probe_setting.dst_ip` and any additional targets are interleaved,
+/// so that a counter shared between targets shows up as one sequence.
+/// TCP probes go to `probe_setting.dst_port`, which must be open for timestamps.
+#[derive(Clone, Debug)]
+pub struct SequenceProber {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Probe to repeat. TcpSynAck or IcmpEcho
+    pub probe_type: FingerprintType,
+    /// Other IP addresses suspected to belong to the same host
+    pub additional_targets: Vec<IpAddr>,
+    /// Number of probes per target
+    pub count: usize,
+    /// Interval between rounds
+    pub interval: Duration,
+}
+
+impl SequenceProber {
+    /// Create new SequenceProber instance with setting.
+    ///
+    /// Uses TCP SYN if `dst_port` is set, otherwise ICMP echo.
+    pub fn new(setting: ProbeSetting) -> Result<SequenceProber, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "SequenceProber::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let probe_type: FingerprintType = if setting.dst_port.is_some() {
+            FingerprintType::TcpSynAck
+        } else {
+            FingerprintType::IcmpEcho
+        };
+        let prober = SequenceProber {
+            probe_setting: setting,
+            probe_type,
+            additional_targets: Vec::new(),
+            count: 6,
+            interval: Duration::from_millis(100),
+        };
+        Ok(prober)
+    }
+    /// Set probe to repeat
+    pub fn with_probe_type(mut self, probe_type: FingerprintType) -> SequenceProber {
+        self.probe_type = probe_type;
+        self
+    }
+    /// Add IP addresses to compare with the target
+    pub fn with_additional_targets(mut self, targets: Vec<IpAddr>) -> SequenceProber {
+        self.additional_targets = targets;
+        self
+    }
+    /// Set number of probes per target
+    pub fn with_count(mut self, count: usize) -> SequenceProber {
+        self.count = count;
+        self
+    }
+    /// Set interval between rounds
+    pub fn with_interval(mut self, interval: Duration) -> SequenceProber {
+        self.interval = interval;
+        self
+    }
+    /// Run probe series
+    pub fn probe(&self) -> Result<SequenceResult, String> {
+        run_sequence_probe(self)
+    }
+}
+
+fn run_sequence_probe(prober: &SequenceProber) -> Result<SequenceResult, String> {
+    let setting: &ProbeSetting = &prober.probe_setting;
+    match prober.probe_type {
+        FingerprintType::TcpSynAck | FingerprintType::IcmpEcho => {}
+        _ => {
+            return Err(format!(
+                "run_sequence_probe: unsupported probe type {:?}",
+                prober.probe_type
+            ))
+        }
+    }
+    if prober.probe_type == FingerprintType::TcpSynAck && setting.dst_port.is_none() {
+        return Err("run_sequence_probe: TCP probe requires an open dst_port".to_string());
+    }
+    if prober
+        .additional_targets
+        .iter()
+        .any(|ip_addr| ip_addr.is_ipv4() != setting.dst_ip.is_ipv4())
+    {
+        return Err(
+            "run_sequence_probe: all targets must be of the same address family".to_string(),
+        );
+    }
+    if prober.count < 2 {
+        return Err("run_sequence_probe: count must be at least 2".to_string());
+    }
+    let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
+        Some(interface) => interface,
+        None => {
+            return Err(format!(
+                "run_sequence_probe: unable to get interface by index {}",
+                setting.if_index
+            ))
+        }
+    };
+    let config = xenet::datalink::Config {
+        write_buffer_size: 4096,
+        read_buffer_size: 4096,
+        read_timeout: Some(setting.receive_timeout),
+        write_timeout: None,
+        channel_type: xenet::datalink::ChannelType::Layer2,
+        bpf_fd_attempts: 1000,
+        linux_fanout: None,
+        promiscuous: false,
+    };
+    // Create a channel to send/receive packet
+    let (mut tx, mut rx) = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+        Ok(_) => return Err("run_sequence_probe: unable to create channel".to_string()),
+        Err(e) => {
+            return Err(format!(
+                "run_sequence_probe: unable to create channel: {}",
+                e
+            ))
+        }
+    };
+    let mut targets: Vec<IpAddr> = vec![setting.dst_ip];
+    for ip_addr in &prober.additional_targets {
+        if !targets.contains(ip_addr) {
+            targets.push(*ip_addr);
+        }
+    }
+    let mut result = SequenceResult::new();
+    for ip_addr in &targets {
+        result.targets.push(TargetSequence {
+            ip_addr: *ip_addr,
+            samples: Vec::new(),
+            sent_count: 0,
+            ip_id: IpIdBehavior::Unknown,
+            timestamp_hz: None,
+            uptime: None,
+        });
+    }
+    // IP IDs of all targets in the order they were received
+    let mut merged_ip_ids: Vec<u16> = Vec::new();
+    let base_src_port: u16 = setting
+        .src_port
+        .unwrap_or(crate::packet::tcp::TCP_DEFAULT_SRC_PORT);
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let mut probe_index: u16 = 0;
+    'rounds: for round in 0..prober.count {
+        for target in result.targets.iter_mut() {
+            let mut probe_setting: ProbeSetting = setting.clone();
+            probe_setting.dst_ip = target.ip_addr;
+            probe_setting.dst_hostname = target.ip_addr.to_string();
+            probe_setting.protocol = prober.probe_type.protocol();
+            // Fresh source port per probe so that every SYN starts a new connection
+            probe_setting.src_port = Some(base_src_port.wrapping_add(probe_index));
+            probe_index = probe_index.wrapping_add(1);
+            let send_offset: Duration = Instant::now().duration_since(start_time);
+            let fingerprint: Fingerprint = super::session::send_fingerprint_probe(
+                &mut tx,
+                &mut rx,
+                &probe_setting,
+                prober.probe_type,
+            );
+            target.sent_count += 1;
+            if let Some(features) = fingerprint.features {
+                if let Some(ip_id) = features.ip_id {
+                    merged_ip_ids.push(ip_id);
+                }
+                target.samples.push(SequenceSample {
+                    ip_id: features.ip_id,
+                    timestamp: features.timestamp,
+                    time: send_offset + fingerprint.rtt,
+                });
+            }
+            if Instant::now().duration_since(start_time) > setting.probe_timeout {
+                result.probe_status =
+                    ProbeStatus::with_timeout_message("Probe series timeout".to_string());
+                break 'rounds;
+            }
+        }
+        if round + 1 < prober.count {
+            std::thread::sleep(prober.interval);
+        }
+    }
+    for target in result.targets.iter_mut() {
+        let ip_ids: Vec<u16> = target.samples.iter().filter_map(|s| s.ip_id).collect();
+        target.ip_id = classify_ip_id(&ip_ids);
+        target.timestamp_hz = estimate_timestamp_hz(&target.samples);
+        if let (Some(hz), Some(last)) = (target.timestamp_hz, target.samples.last()) {
+            if let Some(timestamp) = last.timestamp {
+                target.uptime = Some(Duration::from_secs(timestamp as u64 / hz as u64));
+            }
+        }
+    }
+    result.ip_id = classify_targets(&result.targets, &merged_ip_ids);
+    result.same_host = same_host(&result);
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = Instant::now().duration_since(start_time);
+    Ok(result)
+}
+
+/// Estimate TCP timestamp clock frequency from first and last sample
+pub(crate) fn estimate_timestamp_hz(samples: &[SequenceSample]) -> Option<u32> {
+    let stamped: Vec<&SequenceSample> = samples.iter().filter(|s| s.timestamp.is_some()).collect();
+    let (first, last) = (stamped.first()?, stamped.last()?);
+    let elapsed: f64 = last.time.checked_sub(first.time)?.as_secs_f64();
+    // Zero timestamps are sent by hosts that randomize or disable the clock
+    if elapsed <= 0.0 || first.timestamp == Some(0) {
+        return None;
+    }
+    let ticks: u32 = last.timestamp?.wrapping_sub(first.timestamp?);
+    let hz: f64 = ticks as f64 / elapsed;
+    if hz < 0.5 {
+        return None;
+    }
+    for known in KNOWN_TIMESTAMP_HZ {
+        if (hz - known as f64).abs() <= known as f64 * TIMESTAMP_HZ_TOLERANCE {
+            return Some(known);
+        }
+    }
+    Some(hz.round() as u32)
+}
+
+/// Overall IP ID behavior. A shared counter makes the merged sequence incremental.
+fn classify_targets(targets: &[TargetSequence], merged_ip_ids: &[u16]) -> IpIdBehavior {
+    if targets.len() < 2 {
+        return targets
+            .first()
+            .map(|t| t.ip_id)
+            .unwrap_or(IpIdBehavior::Unknown);
+    }
+    let merged: IpIdBehavior = classify_ip_id(merged_ip_ids);
+    let all_incremental: bool = targets.iter().all(|t| t.ip_id == IpIdBehavior::Incremental);
+    if all_incremental && merged != IpIdBehavior::Incremental {
+        IpIdBehavior::PerHost
+    } else {
+        merged
+    }
+}
+
+fn same_host(result: &SequenceResult) -> Option<bool> {
+    if result.targets.len() < 2 {
+        return None;
+    }
+    // Shared IP ID counter
+    if result.ip_id == IpIdBehavior::Incremental {
+        return Some(true);
+    }
+    // Same timestamp clock: same frequency and boot time
+    let mut boot_times: Vec<(u32, Duration)> = Vec::new();
+    for target in &result.targets {
+        let hz: u32 = target.timestamp_hz?;
+        let uptime: Duration = target.uptime?;
+        let last: &SequenceSample = target.samples.last()?;
+        // Boot time relative to start of the series
+        boot_times.push((hz, uptime.saturating_sub(last.time)));
+    }
+    let (hz, boot_time) = boot_times[0];
+    let same_clock: bool = boot_times.iter().all(|(other_hz, other_boot_time)| {
+        *other_hz == hz
+            && (other_boot_time
+                .max(&boot_time)
+                .saturating_sub(*other_boot_time.min(&boot_time)))
+                <= BOOT_TIME_TOLERANCE
+    });
+    if same_clock {
+        Some(true)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,309 @@
+use super::features::FingerprintFeatures;
+use super::{Fingerprint, FingerprintType};
+use crate::result::ProbeStatus;
+use crate::setting::{ProbeSetting, Protocol};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use xenet::datalink::{DataLinkReceiver, DataLinkSender};
+use xenet::net::interface::Interface;
+use xenet::packet::frame::{Frame, ParseOption};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// IPv4 probe types, in the order they are sent
+pub const IPV4_PROBE_TYPES: [FingerprintType; 8] = [
+    FingerprintType::TcpSynAck,
+    FingerprintType::TcpEcn,
+    FingerprintType::TcpRstAck,
+    FingerprintType::IcmpEcho,
+    FingerprintType::IcmpTimestamp,
+    FingerprintType::IcmpAddressMask,
+    FingerprintType::IcmpInformation,
+    FingerprintType::IcmpUnreachable,
+];
+
+/// IPv6 probe types, in the order they are sent
+pub const IPV6_PROBE_TYPES: [FingerprintType; 7] = [
+    FingerprintType::TcpSynAck,
+    FingerprintType::TcpEcn,
+    FingerprintType::TcpRstAck,
+    FingerprintType::IcmpEcho,
+    FingerprintType::Icmpv6DestOpts,
+    FingerprintType::Icmpv6NodeInfo,
+    FingerprintType::IcmpUnreachable,
+];
+
+/// Nmap-style TCP probes, sent after the address family specific probes
+pub const TCP_PROBE_TYPES: [FingerprintType; 11] = [
+    FingerprintType::TcpSeq(1),
+    FingerprintType::TcpSeq(2),
+    FingerprintType::TcpSeq(3),
+    FingerprintType::TcpSeq(4),
+    FingerprintType::TcpSeq(5),
+    FingerprintType::TcpSeq(6),
+    FingerprintType::TcpNull,
+    FingerprintType::TcpFin,
+    FingerprintType::TcpXmas,
+    FingerprintType::TcpAckOpen,
+    FingerprintType::TcpAckClosed,
+];
+
+/// Probe types applicable to the address family of `ip_addr`
+pub fn default_probe_types(ip_addr: &IpAddr) -> Vec<FingerprintType> {
+    let mut probe_types: Vec<FingerprintType> = match ip_addr {
+        IpAddr::V4(_) => IPV4_PROBE_TYPES.to_vec(),
+        IpAddr::V6(_) => IPV6_PROBE_TYPES.to_vec(),
+    };
+    probe_types.extend_from_slice(&TCP_PROBE_TYPES);
+    probe_types
+}
+
+/// Combined result of a fingerprint session
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FingerprintReport {
+    /// Target IP address
+    pub ip_addr: IpAddr,
+    /// Port used for TCP probes that require an open port
+    pub open_port: Option<u16>,
+    /// Port used for probes that require a closed port
+    pub closed_port: u16,
+    /// Result of each probe, in the order they were sent
+    pub fingerprints: Vec<(FingerprintType, Fingerprint)>,
+    /// Status
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl FingerprintReport {
+    pub fn new() -> FingerprintReport {
+        FingerprintReport {
+            ip_addr: IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+            open_port: None,
+            closed_port: 0,
+            fingerprints: Vec::new(),
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Get result of a probe
+    pub fn get(&self, probe_type: FingerprintType) -> Option<&Fingerprint> {
+        self.fingerprints
+            .iter()
+            .find(|(t, _)| *t == probe_type)
+            .map(|(_, fingerprint)| fingerprint)
+    }
+    /// Probes that received a reply
+    pub fn replied(&self) -> Vec<FingerprintType> {
+        self.fingerprints
+            .iter()
+            .filter(|(_, fingerprint)| fingerprint.packet_frame.is_some())
+            .map(|(t, _)| *t)
+            .collect()
+    }
+}
+
+impl Default for FingerprintReport {
+    fn default() -> Self {
+        FingerprintReport::new()
+    }
+}
+
+/// Runs a set of fingerprint probes against one target over a single channel.
+///
+/// TCP probes that require an open port are sent to `probe_setting.dst_port`,
+/// TCP RST-ACK, Xmas, ACK to closed port and ICMP unreachable probes to `closed_port`.
+#[derive(Clone, Debug)]
+pub struct FingerprintSession {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Probes to run
+    pub probe_types: Vec<FingerprintType>,
+    /// Port expected to be closed on target (TCP and UDP)
+    pub closed_port: u16,
+}
+
+impl FingerprintSession {
+    /// Create new FingerprintSession instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<FingerprintSession, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "FingerprintSession::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let session = FingerprintSession {
+            probe_types: default_probe_types(&setting.dst_ip),
+            probe_setting: setting,
+            closed_port: crate::packet::udp::UDP_BASE_DST_PORT,
+        };
+        Ok(session)
+    }
+    /// Set probes to run
+    pub fn with_probe_types(mut self, probe_types: Vec<FingerprintType>) -> FingerprintSession {
+        self.probe_types = probe_types;
+        self
+    }
+    /// Set port expected to be closed on target
+    pub fn with_closed_port(mut self, closed_port: u16) -> FingerprintSession {
+        self.closed_port = closed_port;
+        self
+    }
+    /// Run probes
+    pub fn run(&self) -> Result<FingerprintReport, String> {
+        run_fingerprint_session(self)
+    }
+}
+
+fn run_fingerprint_session(session: &FingerprintSession) -> Result<FingerprintReport, String> {
+    let setting: &ProbeSetting = &session.probe_setting;
+    if session.probe_types.is_empty() {
+        return Err("run_fingerprint_session: no probe types".to_string());
+    }
+    let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
+        Some(interface) => interface,
+        None => {
+            return Err(format!(
+                "run_fingerprint_session: unable to get interface by index {}",
+                setting.if_index
+            ))
+        }
+    };
+    let config = xenet::datalink::Config {
+        write_buffer_size: 4096,
+        read_buffer_size: 4096,
+        read_timeout: Some(setting.receive_timeout),
+        write_timeout: None,
+        channel_type: xenet::datalink::ChannelType::Layer2,
+        bpf_fd_attempts: 1000,
+        linux_fanout: None,
+        promiscuous: false,
+    };
+    // Create a channel to send/receive packet
+    let (mut tx, mut rx) = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+        Ok(_) => return Err("run_fingerprint_session: unable to create channel".to_string()),
+        Err(e) => {
+            return Err(format!(
+                "run_fingerprint_session: unable to create channel: {}",
+                e
+            ))
+        }
+    };
+    let mut report = FingerprintReport::new();
+    report.ip_addr = setting.dst_ip;
+    report.open_port = setting.dst_port;
+    report.closed_port = session.closed_port;
+    report.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let base_src_port: u16 = setting
+        .src_port
+        .unwrap_or(crate::packet::tcp::TCP_DEFAULT_SRC_PORT);
+    for (i, probe_type) in session.probe_types.iter().enumerate() {
+        let mut probe_setting: ProbeSetting = setting.clone();
+        probe_setting.protocol = probe_type.protocol();
+        // Distinct source port per probe so that replies cannot be confused
+        probe_setting.src_port = Some(base_src_port.wrapping_add(i as u16));
+        let fingerprint: Fingerprint = match probe_type {
+            _ if probe_type.requires_open_port() && setting.dst_port.is_none() => Fingerprint {
+                probe_status: ProbeStatus::with_error_message(
+                    "TCP probe requires an open port".to_string(),
+                ),
+                rtt: Duration::from_millis(0),
+                packet_frame: None,
+                features: None,
+            },
+            _ if probe_type.requires_closed_port() => {
+                probe_setting.dst_port = Some(session.closed_port);
+                send_fingerprint_probe(&mut tx, &mut rx, &probe_setting, *probe_type)
+            }
+            _ => send_fingerprint_probe(&mut tx, &mut rx, &probe_setting, *probe_type),
+        };
+        report.fingerprints.push((*probe_type, fingerprint));
+        if Instant::now().duration_since(start_time) > setting.probe_timeout {
+            report.probe_status =
+                ProbeStatus::with_timeout_message("Fingerprint session timeout".to_string());
+            break;
+        }
+    }
+    report.end_time = crate::sys::get_sysdate();
+    report.elapsed_time = Instant::now().duration_since(start_time);
+    Ok(report)
+}
+
+/// Send one probe and wait for its reply on an open channel
+pub(crate) fn send_fingerprint_probe(
+    tx: &mut Box<dyn DataLinkSender>,
+    rx: &mut Box<dyn DataLinkReceiver>,
+    setting: &ProbeSetting,
+    probe_type: FingerprintType,
+) -> Fingerprint {
+    let mut fingerprint = Fingerprint {
+        probe_status: ProbeStatus::new(),
+        rtt: Duration::from_millis(0),
+        packet_frame: None,
+        features: None,
+    };
+    let mut parse_option: ParseOption = ParseOption::default();
+    if setting.tunnel {
+        let payload_offset = if setting.loopback { 14 } else { 0 };
+        parse_option.from_ip_packet = true;
+        parse_option.offset = payload_offset;
+    }
+    let probe_packet: Vec<u8> = super::build_probe_packet(setting, probe_type);
+    let send_time = Instant::now();
+    if let Some(Err(e)) = tx.send(&probe_packet) {
+        fingerprint.probe_status =
+            ProbeStatus::with_error_message(format!("unable to send packet: {}", e));
+        return fingerprint;
+    }
+    loop {
+        if let Ok(packet) = rx.next() {
+            let recv_time: Duration = Instant::now().duration_since(send_time);
+            let frame: Frame = Frame::from_bytes(packet, parse_option.clone());
+            if is_probe_reply(&frame, setting, &probe_type) {
+                fingerprint.rtt = recv_time;
+                fingerprint.features = FingerprintFeatures::from_frame(&frame);
+                fingerprint.packet_frame = Some(frame);
+                break;
+            }
+        }
+        if Instant::now().duration_since(send_time) > setting.receive_timeout {
+            fingerprint.probe_status =
+                ProbeStatus::with_timeout_message("Probe timeout".to_string());
+            break;
+        }
+    }
+    fingerprint
+}
+
+fn is_probe_reply(frame: &Frame, setting: &ProbeSetting, probe_type: &FingerprintType) -> bool {
+    match probe_type.protocol() {
+        Protocol::ICMP | Protocol::UDP => super::filter_icmp_packet(frame, setting, probe_type),
+        Protocol::TCP => {
+            if !super::filter_tcp_packet(frame, setting, probe_type) {
+                return false;
+            }
+            // Reply must be addressed to the source port of this probe
+            match &frame.transport {
+                Some(transport_layer) => match &transport_layer.tcp {
+                    Some(tcp_header) => Some(tcp_header.destination) == setting.src_port,
+                    None => false,
+                },
+                None => false,
+            }
+        }
+        _ => false,
+    }
+}
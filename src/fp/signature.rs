@@ -0,0 +1,347 @@
+use super::features::{tcp_flag_string, FingerprintFeatures};
+use super::{Fingerprint, FingerprintType};
+use crate::packet::decode::ICMPV6_PARAMETER_PROBLEM;
+use crate::result::ProbeStatusKind;
+use xenet::packet::tcp::TcpFlags;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// IP ID generation behavior
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IpIdBehavior {
+    /// Always zero
+    Zero,
+    /// Small positive increments between replies
+    Incremental,
+    /// No recognizable sequence
+    Random,
+    /// Incremental per destination, but not shared between destinations
+    PerHost,
+    /// Not enough samples
+    Unknown,
+}
+
+impl IpIdBehavior {
+    pub fn name(&self) -> String {
+        match *self {
+            IpIdBehavior::Zero => String::from("zero"),
+            IpIdBehavior::Incremental => String::from("incremental"),
+            IpIdBehavior::Random => String::from("random"),
+            IpIdBehavior::PerHost => String::from("per-host"),
+            IpIdBehavior::Unknown => String::from("unknown"),
+        }
+    }
+}
+
+/// IPv6 flow label on TCP replies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FlowLabelBehavior {
+    /// Always zero
+    Zero,
+    /// Copied from the probe
+    Echoed,
+    /// Same non-zero value for different connections
+    Constant,
+    /// Differs between connections
+    PerFlow,
+}
+
+impl FlowLabelBehavior {
+    pub fn name(&self) -> String {
+        match *self {
+            FlowLabelBehavior::Zero => String::from("zero"),
+            FlowLabelBehavior::Echoed => String::from("echoed"),
+            FlowLabelBehavior::Constant => String::from("constant"),
+            FlowLabelBehavior::PerFlow => String::from("per-flow"),
+        }
+    }
+}
+
+/// Response to an IPv6 packet with an unknown Destination Option
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ExtHeaderHandling {
+    /// ICMPv6 Parameter Problem, as required by RFC 8200
+    ParameterProblem,
+    /// Option ignored and echo answered
+    Ignored,
+    /// No response
+    Dropped,
+}
+
+impl ExtHeaderHandling {
+    pub fn name(&self) -> String {
+        match *self {
+            ExtHeaderHandling::ParameterProblem => String::from("param-problem"),
+            ExtHeaderHandling::Ignored => String::from("ignored"),
+            ExtHeaderHandling::Dropped => String::from("dropped"),
+        }
+    }
+}
+
+/// Classify flow labels of TCP replies to probes on different connections
+pub fn classify_flow_label(flow_labels: &[u32]) -> Option<FlowLabelBehavior> {
+    if flow_labels.is_empty() {
+        return None;
+    }
+    if flow_labels.iter().all(|label| *label == 0) {
+        Some(FlowLabelBehavior::Zero)
+    } else if flow_labels
+        .iter()
+        .all(|label| *label == crate::packet::icmpv6::PROBE_FLOW_LABEL)
+    {
+        Some(FlowLabelBehavior::Echoed)
+    } else if flow_labels.len() < 2 {
+        None
+    } else if flow_labels.windows(2).all(|pair| pair[0] == pair[1]) {
+        Some(FlowLabelBehavior::Constant)
+    } else {
+        Some(FlowLabelBehavior::PerFlow)
+    }
+}
+
+/// Largest step between consecutive IP IDs still considered incremental
+const IP_ID_INCREMENTAL_MAX_STEP: u16 = 1000;
+
+/// Classify IP ID generation from IDs in the order the replies were received
+pub fn classify_ip_id(ip_ids: &[u16]) -> IpIdBehavior {
+    if ip_ids.len() < 2 {
+        return IpIdBehavior::Unknown;
+    }
+    if ip_ids.iter().all(|id| *id == 0) {
+        return IpIdBehavior::Zero;
+    }
+    let incremental: bool = ip_ids.windows(2).all(|pair| {
+        let step: u16 = pair[1].wrapping_sub(pair[0]);
+        step > 0 && step <= IP_ID_INCREMENTAL_MAX_STEP
+    });
+    if incremental {
+        IpIdBehavior::Incremental
+    } else if ip_ids.windows(2).all(|pair| pair[0] == pair[1]) {
+        IpIdBehavior::Unknown
+    } else {
+        IpIdBehavior::Random
+    }
+}
+
+/// OS signature extracted from fingerprint probe replies.
+///
+/// Fields are `None` when the probe that reveals them was not run or failed.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OsSignature {
+    /// Guessed initial TTL of TCP replies (or ICMP if no TCP reply)
+    pub initial_ttl: Option<u8>,
+    /// Guessed initial TTL of ICMP echo reply
+    pub icmp_initial_ttl: Option<u8>,
+    /// Don't Fragment bit on SYN-ACK
+    pub df: Option<bool>,
+    /// SYN-ACK window size
+    pub window: Option<u16>,
+    /// SYN-ACK TCP option order
+    pub tcp_options: Option<String>,
+    pub mss: Option<u16>,
+    pub wscale: Option<u8>,
+    /// IP ID generation across all IPv4 replies
+    pub ip_id: Option<IpIdBehavior>,
+    /// ECE set in reply to ECN SYN
+    pub ecn: Option<bool>,
+    /// Replies to ICMP echo
+    pub icmp_echo: Option<bool>,
+    /// Replies to ICMP timestamp
+    pub icmp_timestamp: Option<bool>,
+    /// Replies to ICMP address mask
+    pub icmp_address_mask: Option<bool>,
+    /// Replies to ICMP information
+    pub icmp_information: Option<bool>,
+    /// Sends port unreachable for closed UDP port
+    pub icmp_unreachable: Option<bool>,
+    /// IPv6 flow label on TCP replies
+    pub flow_label: Option<FlowLabelBehavior>,
+    /// Handling of unknown IPv6 Destination Option
+    pub ext_header: Option<ExtHeaderHandling>,
+    /// Replies to ICMPv6 Node Information Query
+    pub node_info: Option<bool>,
+    /// SYN-ACK windows for the six SEQ probes, comma separated
+    pub syn_windows: Option<String>,
+    /// Reply flags to TCP probes, "none" if no reply. e.g. "AR"
+    pub tcp_null: Option<String>,
+    pub tcp_fin: Option<String>,
+    pub tcp_xmas: Option<String>,
+    pub tcp_ack_open: Option<String>,
+    pub tcp_ack_closed: Option<String>,
+}
+
+impl OsSignature {
+    pub fn new() -> OsSignature {
+        OsSignature::default()
+    }
+    /// Extract signature from probe results
+    pub fn from_fingerprints(fingerprints: &[(FingerprintType, Fingerprint)]) -> OsSignature {
+        let mut signature: OsSignature = OsSignature::new();
+        let mut ip_ids: Vec<u16> = Vec::new();
+        let mut icmp_ttl: Option<u8> = None;
+        let mut flow_labels: Vec<u32> = Vec::new();
+        let mut seq_probe_count: usize = 0;
+        let mut syn_windows: Vec<String> = Vec::new();
+        for (probe_type, fingerprint) in fingerprints {
+            let replied: Option<bool> = match fingerprint.probe_status.kind {
+                ProbeStatusKind::Done => Some(fingerprint.features.is_some()),
+                ProbeStatusKind::Timeout => Some(false),
+                ProbeStatusKind::Error => None,
+            };
+            match probe_type {
+                FingerprintType::IcmpEcho => signature.icmp_echo = replied,
+                FingerprintType::IcmpTimestamp => signature.icmp_timestamp = replied,
+                FingerprintType::IcmpAddressMask => signature.icmp_address_mask = replied,
+                FingerprintType::IcmpInformation => signature.icmp_information = replied,
+                FingerprintType::IcmpUnreachable => signature.icmp_unreachable = replied,
+                FingerprintType::TcpEcn => signature.ecn = replied,
+                FingerprintType::Icmpv6NodeInfo => signature.node_info = replied,
+                FingerprintType::Icmpv6DestOpts if replied == Some(false) => {
+                    signature.ext_header = Some(ExtHeaderHandling::Dropped);
+                }
+                FingerprintType::TcpSeq(_) => seq_probe_count += 1,
+                _ if replied == Some(false) => {
+                    if let Some(field) = signature.tcp_reply_field(probe_type) {
+                        *field = Some(String::from("none"));
+                    }
+                }
+                _ => {}
+            }
+            let features: &FingerprintFeatures = match &fingerprint.features {
+                Some(features) => features,
+                None => continue,
+            };
+            if let Some(ip_id) = features.ip_id {
+                ip_ids.push(ip_id);
+            }
+            if let (Some(flow_label), Some(_)) = (features.flow_label, features.tcp_flags) {
+                flow_labels.push(flow_label);
+            }
+            match probe_type {
+                FingerprintType::TcpSynAck => {
+                    signature.initial_ttl = Some(features.initial_ttl);
+                    signature.df = features.df;
+                    signature.window = features.window;
+                    signature.tcp_options = features.tcp_options.clone();
+                    signature.mss = features.mss;
+                    signature.wscale = features.wscale;
+                }
+                FingerprintType::TcpEcn => {
+                    if let Some(tcp_flags) = features.tcp_flags {
+                        signature.ecn = Some(tcp_flags & TcpFlags::ECE != 0);
+                    }
+                }
+                FingerprintType::IcmpEcho => {
+                    icmp_ttl = Some(features.initial_ttl);
+                }
+                FingerprintType::TcpSeq(_) => {
+                    if let (Some(window), Some(tcp_flags)) = (features.window, features.tcp_flags) {
+                        if tcp_flags & TcpFlags::SYN != 0 {
+                            syn_windows.push(window.to_string());
+                        }
+                    }
+                }
+                FingerprintType::TcpNull
+                | FingerprintType::TcpFin
+                | FingerprintType::TcpXmas
+                | FingerprintType::TcpAckOpen
+                | FingerprintType::TcpAckClosed => {
+                    if let (Some(field), Some(tcp_flags)) =
+                        (signature.tcp_reply_field(probe_type), features.tcp_flags)
+                    {
+                        *field = Some(tcp_flag_string(tcp_flags));
+                    }
+                }
+                FingerprintType::Icmpv6DestOpts => {
+                    signature.ext_header = match features.icmp_type {
+                        Some(ICMPV6_PARAMETER_PROBLEM) => Some(ExtHeaderHandling::ParameterProblem),
+                        Some(_) => Some(ExtHeaderHandling::Ignored),
+                        None => None,
+                    };
+                }
+                _ => {
+                    if signature.initial_ttl.is_none() {
+                        signature.initial_ttl = Some(features.initial_ttl);
+                    }
+                }
+            }
+        }
+        // The ECN probe only matches SYN-ACK with ECE. Without a plain SYN-ACK
+        // the port may be closed, so a missing reply says nothing about ECN.
+        if signature.window.is_none() && signature.ecn == Some(false) {
+            signature.ecn = None;
+        }
+        // Replies of the other open port probes are only comparable if the port answered SYN-ACK
+        if signature.window.is_none() && syn_windows.is_empty() {
+            signature.tcp_null = None;
+            signature.tcp_fin = None;
+            signature.tcp_ack_open = None;
+        }
+        signature.icmp_initial_ttl = icmp_ttl;
+        if signature.initial_ttl.is_none() {
+            signature.initial_ttl = icmp_ttl;
+        }
+        if !ip_ids.is_empty() {
+            signature.ip_id = Some(classify_ip_id(&ip_ids));
+        }
+        signature.flow_label = classify_flow_label(&flow_labels);
+        // Windows only mean something as a complete series
+        if seq_probe_count > 0 && syn_windows.len() == seq_probe_count {
+            signature.syn_windows = Some(syn_windows.join(","));
+        }
+        signature
+    }
+    /// Field holding the reply flags of a TCP probe
+    fn tcp_reply_field(&mut self, probe_type: &FingerprintType) -> Option<&mut Option<String>> {
+        match probe_type {
+            FingerprintType::TcpNull => Some(&mut self.tcp_null),
+            FingerprintType::TcpFin => Some(&mut self.tcp_fin),
+            FingerprintType::TcpXmas => Some(&mut self.tcp_xmas),
+            FingerprintType::TcpAckOpen => Some(&mut self.tcp_ack_open),
+            FingerprintType::TcpAckClosed => Some(&mut self.tcp_ack_closed),
+            _ => None,
+        }
+    }
+    /// Get value of a signature field as used in the signature database
+    pub fn field_value(&self, key: &str) -> Option<String> {
+        match key {
+            "ttl" => self.initial_ttl.map(|v| v.to_string()),
+            "icmp_ttl" => self.icmp_initial_ttl.map(|v| v.to_string()),
+            "df" => self.df.map(bool_value),
+            "window" => self.window.map(|v| v.to_string()),
+            "options" => self.tcp_options.clone(),
+            "mss" => self.mss.map(|v| v.to_string()),
+            "wscale" => self.wscale.map(|v| v.to_string()),
+            "ip_id" => self.ip_id.map(|v| v.name()),
+            "ecn" => self.ecn.map(bool_value),
+            "icmp_echo" => self.icmp_echo.map(bool_value),
+            "icmp_timestamp" => self.icmp_timestamp.map(bool_value),
+            "icmp_address_mask" => self.icmp_address_mask.map(bool_value),
+            "icmp_information" => self.icmp_information.map(bool_value),
+            "icmp_unreachable" => self.icmp_unreachable.map(bool_value),
+            "flow_label" => self.flow_label.map(|v| v.name()),
+            "ext_header" => self.ext_header.map(|v| v.name()),
+            "node_info" => self.node_info.map(bool_value),
+            "syn_windows" => self.syn_windows.clone(),
+            "tcp_null" => self.tcp_null.clone(),
+            "tcp_fin" => self.tcp_fin.clone(),
+            "tcp_xmas" => self.tcp_xmas.clone(),
+            "tcp_ack_open" => self.tcp_ack_open.clone(),
+            "tcp_ack_closed" => self.tcp_ack_closed.clone(),
+            _ => None,
+        }
+    }
+}
+
+fn bool_value(value: bool) -> String {
+    if value {
+        String::from("1")
+    } else {
+        String::from("0")
+    }
+}
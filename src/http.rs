@@ -0,0 +1,736 @@
+use crate::result::{HttpProbeResult, HttpResponse, HttpTiming, ProbeStatus};
+use crate::sys::io_status;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+pub const HTTP_DEFAULT_PORT: u16 = 80;
+const USER_AGENT: &str = concat!("netprobe/", env!("CARGO_PKG_VERSION"));
+/// Longest status line or header field accepted
+const MAX_LINE_SIZE: u64 = 16 * 1024;
+
+/// URL of an HTTP probe
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpUrl {
+    pub scheme: String,
+    /// Host name or IP address, without brackets
+    pub host: String,
+    pub port: u16,
+    /// Path and query
+    pub path: String,
+}
+
+impl HttpUrl {
+    /// Parse `http://host[:port][/path][?query]`
+    pub fn parse(url: &str) -> Result<HttpUrl, String> {
+        let (scheme, rest) = url
+            .trim()
+            .split_once("://")
+            .ok_or(format!("HttpUrl::parse: missing scheme in {}", url))?;
+        let scheme: String = scheme.to_ascii_lowercase();
+        let default_port: u16 = match scheme.as_str() {
+            "http" => HTTP_DEFAULT_PORT,
+            _ => return Err(format!("HttpUrl::parse: unsupported scheme {}", scheme)),
+        };
+        let (authority, path) = match rest.find(['/', '?', '#']) {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let path: &str = path.split('#').next().unwrap_or("/");
+        let path: String = match path {
+            "" => String::from("/"),
+            p if p.starts_with('?') => format!("/{}", p),
+            p => p.to_string(),
+        };
+        // User information is not sent
+        let authority: &str = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let (host, after) = bracketed
+                .split_once(']')
+                .ok_or(format!("HttpUrl::parse: invalid host in {}", url))?;
+            (host, after.strip_prefix(':'))
+        } else {
+            match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            }
+        };
+        if host.is_empty() {
+            return Err(format!("HttpUrl::parse: missing host in {}", url));
+        }
+        let port: u16 = match port {
+            Some("") | None => default_port,
+            Some(port) => port
+                .parse::<u16>()
+                .map_err(|_| format!("HttpUrl::parse: invalid port {}", port))?,
+        };
+        Ok(HttpUrl {
+            scheme,
+            host: host.to_ascii_lowercase(),
+            port,
+            path,
+        })
+    }
+    /// Resolve the Location of a redirect against this URL
+    pub fn join(&self, location: &str) -> Result<HttpUrl, String> {
+        if location.contains("://") {
+            HttpUrl::parse(location)
+        } else if location.starts_with("//") {
+            HttpUrl::parse(&format!("{}:{}", self.scheme, location))
+        } else if location.starts_with('/') {
+            Ok(HttpUrl {
+                path: location.to_string(),
+                ..self.clone()
+            })
+        } else {
+            let path: &str = self.path.split('?').next().unwrap_or("/");
+            let dir: &str = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+            Ok(HttpUrl {
+                path: format!("{}/{}", dir, location),
+                ..self.clone()
+            })
+        }
+    }
+    /// Value of the Host header field
+    pub fn authority(&self) -> String {
+        let host: String = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        if self.port == HTTP_DEFAULT_PORT {
+            host
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+}
+
+impl fmt::Display for HttpUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}://{}{}", self.scheme, self.authority(), self.path)
+    }
+}
+
+/// HTTP/1.1 prober.
+///
+/// Sends one request per followed redirect, each over a new connection, and reports
+/// the time spent in every phase of the exchange.
+#[derive(Clone, Debug)]
+pub struct HttpProber {
+    pub url: HttpUrl,
+    pub method: String,
+    /// Extra header fields. Host, Connection and Content-Length are set by the prober
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub follow_redirects: bool,
+    pub max_redirects: u8,
+    /// Address to connect to instead of resolving the host of `url`
+    pub dst_ip: Option<IpAddr>,
+    pub connect_timeout: Duration,
+    /// Timeout of every read and write
+    pub receive_timeout: Duration,
+    /// Stop following redirects after this time
+    pub probe_timeout: Duration,
+}
+
+impl HttpProber {
+    /// Create new HttpProber instance for `url`
+    pub fn new(url: &str) -> Result<HttpProber, String> {
+        let prober = HttpProber {
+            url: HttpUrl::parse(url)?,
+            method: String::from("GET"),
+            headers: Vec::new(),
+            body: Vec::new(),
+            follow_redirects: false,
+            max_redirects: 5,
+            dst_ip: None,
+            connect_timeout: Duration::from_secs(5),
+            receive_timeout: Duration::from_secs(5),
+            probe_timeout: Duration::from_secs(30),
+        };
+        Ok(prober)
+    }
+    pub fn with_method(mut self, method: &str) -> HttpProber {
+        self.method = method.to_ascii_uppercase();
+        self
+    }
+    pub fn with_header(mut self, name: &str, value: &str) -> HttpProber {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+    pub fn with_body(mut self, body: Vec<u8>) -> HttpProber {
+        self.body = body;
+        self
+    }
+    pub fn with_follow_redirects(mut self, follow_redirects: bool) -> HttpProber {
+        self.follow_redirects = follow_redirects;
+        self
+    }
+    pub fn with_max_redirects(mut self, max_redirects: u8) -> HttpProber {
+        self.max_redirects = max_redirects;
+        self
+    }
+    /// Connect to `dst_ip` instead of resolving the host. Redirects to other hosts are resolved.
+    pub fn with_dst_ip(mut self, dst_ip: IpAddr) -> HttpProber {
+        self.dst_ip = Some(dst_ip);
+        self
+    }
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> HttpProber {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+    pub fn with_receive_timeout(mut self, receive_timeout: Duration) -> HttpProber {
+        self.receive_timeout = receive_timeout;
+        self
+    }
+    pub fn with_probe_timeout(mut self, probe_timeout: Duration) -> HttpProber {
+        self.probe_timeout = probe_timeout;
+        self
+    }
+    /// Run probe
+    pub fn probe(&self) -> Result<HttpProbeResult, String> {
+        let mut result = HttpProbeResult::new();
+        result.start_time = crate::sys::get_sysdate();
+        let start_time = Instant::now();
+        let mut url: HttpUrl = self.url.clone();
+        let mut method: String = self.method.clone();
+        let mut body: &[u8] = &self.body;
+        loop {
+            let response: HttpResponse = match self.request(&url, &method, body) {
+                Ok(response) => response,
+                Err(probe_status) => {
+                    result.probe_status = probe_status;
+                    break;
+                }
+            };
+            let location: Option<String> = match response.header("location") {
+                Some(location) if self.follow_redirects && response.is_redirect() => {
+                    Some(location.to_string())
+                }
+                _ => None,
+            };
+            let status_code: u16 = response.status_code;
+            result.responses.push(response);
+            let location: String = match location {
+                Some(location) => location,
+                None => break,
+            };
+            if result.responses.len() > self.max_redirects as usize {
+                result.probe_status = ProbeStatus::with_error_message(format!(
+                    "Too many redirects ({})",
+                    self.max_redirects
+                ));
+                break;
+            }
+            if start_time.elapsed() > self.probe_timeout {
+                result.probe_status =
+                    ProbeStatus::with_timeout_message(String::from("http_probe: probe timeout"));
+                break;
+            }
+            url = match url.join(&location) {
+                Ok(url) => url,
+                Err(e) => {
+                    result.probe_status = ProbeStatus::with_error_message(e);
+                    break;
+                }
+            };
+            // 303, and 301/302 after a POST as browsers do, switch to a GET without body
+            if status_code == 303 || (matches!(status_code, 301 | 302) && method == "POST") {
+                method = String::from("GET");
+                body = &[];
+            }
+        }
+        result.end_time = crate::sys::get_sysdate();
+        result.elapsed_time = start_time.elapsed();
+        Ok(result)
+    }
+    /// Send one request over a new connection and read the response
+    fn request(
+        &self,
+        url: &HttpUrl,
+        method: &str,
+        body: &[u8],
+    ) -> Result<HttpResponse, ProbeStatus> {
+        let lookup_start = Instant::now();
+        let ip_addr: IpAddr = match (self.dst_ip, url.host.parse::<IpAddr>()) {
+            (_, Ok(ip_addr)) => ip_addr,
+            (Some(dst_ip), _) if url.host == self.url.host => dst_ip,
+            _ => crate::dns::lookup_host_name(url.host.clone()).ok_or(
+                ProbeStatus::with_error_message(format!("Unable to resolve {}", url.host)),
+            )?,
+        };
+        let dns_lookup: Duration = lookup_start.elapsed();
+        let socket_addr = SocketAddr::new(ip_addr, url.port);
+        let connect_start = Instant::now();
+        let stream: TcpStream = TcpStream::connect_timeout(&socket_addr, self.connect_timeout)
+            .map_err(|e| io_status(&format!("Connect to {}", socket_addr), e))?;
+        let tcp_connect: Duration = connect_start.elapsed();
+        let _ = stream.set_read_timeout(Some(self.receive_timeout));
+        let _ = stream.set_write_timeout(Some(self.receive_timeout));
+        let mut response: HttpResponse = self.exchange(stream, url, method, body)?;
+        response.url = url.to_string();
+        response.ip_addr = ip_addr;
+        response.port = url.port;
+        response.timing.dns_lookup = dns_lookup;
+        response.timing.tcp_connect = tcp_connect;
+        response.timing.total = lookup_start.elapsed();
+        Ok(response)
+    }
+    /// Write the request and read the response over a connected stream
+    fn exchange<S: Read + Write>(
+        &self,
+        mut stream: S,
+        url: &HttpUrl,
+        method: &str,
+        body: &[u8],
+    ) -> Result<HttpResponse, ProbeStatus> {
+        let send_start = Instant::now();
+        stream
+            .write_all(&self.build_request(url, method, body))
+            .and_then(|_| stream.flush())
+            .map_err(|e| io_status("Send request", e))?;
+        let request_send: Duration = send_start.elapsed();
+        let wait_start = Instant::now();
+        let mut reader = BufReader::new(stream);
+        let received: usize = reader
+            .fill_buf()
+            .map_err(|e| io_status("Receive response", e))?
+            .len();
+        if received == 0 {
+            return Err(ProbeStatus::with_error_message(String::from(
+                "Connection closed before response",
+            )));
+        }
+        let first_byte: Duration = wait_start.elapsed();
+        let transfer_start = Instant::now();
+        // Interim 1xx responses precede the final one
+        let head: ResponseHead = loop {
+            let head: ResponseHead = read_head(&mut reader)?;
+            if !(100..200).contains(&head.status_code) || head.status_code == 101 {
+                break head;
+            }
+        };
+        let status_code: u16 = head.status_code;
+        let header = |name: &str| -> Option<&str> {
+            head.headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.trim())
+        };
+        let body_size: usize = if method == "HEAD" || matches!(status_code, 100..=199 | 204 | 304) {
+            0
+        } else if header("transfer-encoding")
+            .is_some_and(|te| te.to_ascii_lowercase().ends_with("chunked"))
+        {
+            read_chunked_body(&mut reader)?
+        } else if let Some(length) = header("content-length") {
+            let length: u64 = length.parse::<u64>().map_err(|_| {
+                ProbeStatus::with_error_message(format!("Invalid Content-Length {}", length))
+            })?;
+            let size: u64 = std::io::copy(&mut reader.by_ref().take(length), &mut std::io::sink())
+                .map_err(|e| io_status("Receive body", e))?;
+            if size < length {
+                return Err(ProbeStatus::with_error_message(format!(
+                    "Body truncated at {} of {} bytes",
+                    size, length
+                )));
+            }
+            size as usize
+        } else {
+            // Body delimited by the end of the connection
+            std::io::copy(&mut reader, &mut std::io::sink())
+                .map_err(|e| io_status("Receive body", e))? as usize
+        };
+        Ok(HttpResponse {
+            url: String::new(),
+            ip_addr: IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+            port: 0,
+            version: head.version,
+            status_code,
+            reason_phrase: head.reason_phrase,
+            headers: head.headers,
+            body_size,
+            timing: HttpTiming {
+                request_send,
+                first_byte,
+                content_transfer: transfer_start.elapsed(),
+                ..HttpTiming::default()
+            },
+        })
+    }
+    fn build_request(&self, url: &HttpUrl, method: &str, body: &[u8]) -> Vec<u8> {
+        let mut request: String = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\n",
+            method,
+            url.path,
+            url.authority()
+        );
+        let has_header = |name: &str| {
+            self.headers
+                .iter()
+                .any(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+        if !has_header("user-agent") {
+            request.push_str(&format!("User-Agent: {}\r\n", USER_AGENT));
+        }
+        if !has_header("accept") {
+            request.push_str("Accept: */*\r\n");
+        }
+        for (name, value) in &self.headers {
+            if ["host", "connection", "content-length"]
+                .iter()
+                .any(|n| name.eq_ignore_ascii_case(n))
+            {
+                continue;
+            }
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !body.is_empty() || matches!(method, "POST" | "PUT" | "PATCH") {
+            request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        // One request per connection keeps the timing of every request complete
+        request.push_str("Connection: close\r\n\r\n");
+        let mut buf: Vec<u8> = request.into_bytes();
+        buf.extend_from_slice(body);
+        buf
+    }
+}
+
+/// Status line and header fields
+struct ResponseHead {
+    version: String,
+    status_code: u16,
+    reason_phrase: String,
+    headers: Vec<(String, String)>,
+}
+
+fn read_head<R: BufRead>(reader: &mut R) -> Result<ResponseHead, ProbeStatus> {
+    let status_line: String = read_line(reader)?;
+    let mut parts = status_line.splitn(3, ' ');
+    let version: String = parts.next().unwrap_or("").to_string();
+    let status_code: Option<u16> = parts.next().and_then(|code| code.parse::<u16>().ok());
+    let status_code: u16 = match status_code {
+        Some(status_code) if version.starts_with("HTTP/") => status_code,
+        _ => {
+            return Err(ProbeStatus::with_error_message(format!(
+                "Invalid status line {}",
+                status_line
+            )))
+        }
+    };
+    let reason_phrase: String = parts.next().unwrap_or("").to_string();
+    let mut headers: Vec<(String, String)> = Vec::new();
+    loop {
+        let line: String = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        match line.split_once(':') {
+            Some((name, value)) => {
+                headers.push((name.trim().to_string(), value.trim().to_string()))
+            }
+            None => {
+                return Err(ProbeStatus::with_error_message(format!(
+                    "Invalid header field {}",
+                    line
+                )))
+            }
+        }
+    }
+    Ok(ResponseHead {
+        version,
+        status_code,
+        reason_phrase,
+        headers,
+    })
+}
+
+/// Size of a body with chunked transfer coding
+fn read_chunked_body<R: BufRead>(reader: &mut R) -> Result<usize, ProbeStatus> {
+    let mut body_size: usize = 0;
+    loop {
+        let line: String = read_line(reader)?;
+        let size: &str = line.split(';').next().unwrap_or("").trim();
+        let size: u64 = u64::from_str_radix(size, 16)
+            .map_err(|_| ProbeStatus::with_error_message(format!("Invalid chunk size {}", line)))?;
+        if size == 0 {
+            // Trailer fields
+            while !read_line(reader)?.is_empty() {}
+            return Ok(body_size);
+        }
+        let received: u64 = std::io::copy(&mut reader.by_ref().take(size), &mut std::io::sink())
+            .map_err(|e| io_status("Receive body", e))?;
+        if received < size {
+            return Err(ProbeStatus::with_error_message(String::from(
+                "Chunked body truncated",
+            )));
+        }
+        body_size += size as usize;
+        read_line(reader)?;
+    }
+}
+
+/// Line without its CRLF
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, ProbeStatus> {
+    let mut line: Vec<u8> = Vec::new();
+    reader
+        .take(MAX_LINE_SIZE)
+        .read_until(b'\n', &mut line)
+        .map_err(|e| io_status("Receive response", e))?;
+    if !line.ends_with(b"\n") {
+        return Err(ProbeStatus::with_error_message(String::from(
+            "Connection closed unexpectedly",
+        )));
+    }
+    Ok(String::from_utf8_lossy(&line)
+        .trim_end_matches(['\r', '\n'])
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::ProbeStatusKind;
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::thread::{self, JoinHandle};
+
+    /// Serve one canned response per connection on 127.0.0.1, returning the requests received
+    fn serve(responses: Vec<&'static [u8]>) -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port: u16 = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let mut requests: Vec<String> = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                let mut content_length: u64 = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                }
+                let mut body = String::new();
+                reader
+                    .by_ref()
+                    .take(content_length)
+                    .read_to_string(&mut body)
+                    .unwrap();
+                request.push_str(&body);
+                requests.push(request);
+                reader.get_mut().write_all(response).unwrap();
+            }
+            requests
+        });
+        (port, handle)
+    }
+
+    fn prober(port: u16, path: &str) -> HttpProber {
+        HttpProber::new(&format!("http://127.0.0.1:{}{}", port, path))
+            .unwrap()
+            .with_receive_timeout(Duration::from_secs(2))
+    }
+
+    #[test]
+    fn content_length_body() {
+        let (port, server) = serve(vec![b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello"]);
+        let result = prober(port, "/index.html?q=1").probe().unwrap();
+        let requests = server.join().unwrap();
+        assert_eq!(result.probe_status.kind, ProbeStatusKind::Done);
+        assert!(requests[0].starts_with("GET /index.html?q=1 HTTP/1.1\r\n"));
+        assert!(requests[0].contains(&format!("\r\nHost: 127.0.0.1:{}\r\n", port)));
+        assert!(requests[0].contains("\r\nConnection: close\r\n"));
+        let response = result.final_response().unwrap();
+        assert_eq!(response.version, "HTTP/1.1");
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.reason_phrase, "OK");
+        assert_eq!(response.header("content-length"), Some("5"));
+        assert_eq!(response.body_size, 5);
+        assert_eq!(response.port, port);
+        assert_eq!(response.ip_addr, IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+
+    #[test]
+    fn chunked_body() {
+        let (port, server) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              4\r\nWiki\r\n6;ext=1\r\npedia \r\nE\r\nin \r\n\r\nchunks.\r\n0\r\nExpires: 0\r\n\r\n",
+        ]);
+        let result = prober(port, "/").probe().unwrap();
+        server.join().unwrap();
+        assert_eq!(result.probe_status.kind, ProbeStatusKind::Done);
+        assert_eq!(result.final_response().unwrap().body_size, 24);
+    }
+
+    #[test]
+    fn close_delimited_body() {
+        let (port, server) = serve(vec![b"HTTP/1.0 200 OK\r\nServer: test\r\n\r\nuntil close"]);
+        let result = prober(port, "/").probe().unwrap();
+        server.join().unwrap();
+        assert_eq!(result.probe_status.kind, ProbeStatusKind::Done);
+        let response = result.final_response().unwrap();
+        assert_eq!(response.version, "HTTP/1.0");
+        assert_eq!(response.body_size, 11);
+    }
+
+    #[test]
+    fn interim_responses() {
+        let (port, server) = serve(vec![
+            b"HTTP/1.1 100 Continue\r\n\r\n\
+              HTTP/1.1 103 Early Hints\r\nLink: </style.css>\r\n\r\n\
+              HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok",
+        ]);
+        let result = prober(port, "/items")
+            .with_method("post")
+            .with_body(b"{}".to_vec())
+            .probe()
+            .unwrap();
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /items HTTP/1.1\r\n"));
+        assert!(requests[0].ends_with("\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}"));
+        assert_eq!(result.responses.len(), 1);
+        let response = result.final_response().unwrap();
+        assert_eq!(response.status_code, 201);
+        assert_eq!(response.header("link"), None);
+        assert_eq!(response.body_size, 2);
+    }
+
+    #[test]
+    fn redirect_post_to_get() {
+        for redirect in [
+            &b"HTTP/1.1 301 Moved Permanently\r\nLocation: next?a=b\r\nContent-Length: 0\r\n\r\n"[..],
+            &b"HTTP/1.1 303 See Other\r\nLocation: next?a=b\r\nContent-Length: 0\r\n\r\n"[..],
+        ] {
+            let (port, server) = serve(vec![
+                redirect,
+                b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+            ]);
+            let result = prober(port, "/form/submit")
+                .with_method("POST")
+                .with_body(b"a=b".to_vec())
+                .with_follow_redirects(true)
+                .probe()
+                .unwrap();
+            let requests = server.join().unwrap();
+            assert_eq!(result.probe_status.kind, ProbeStatusKind::Done);
+            assert!(requests[0].starts_with("POST /form/submit HTTP/1.1\r\n"));
+            assert!(requests[0].ends_with("\r\n\r\na=b"));
+            assert!(requests[1].starts_with("GET /form/next?a=b HTTP/1.1\r\n"));
+            assert!(!requests[1].contains("Content-Length"));
+            assert_eq!(result.responses.len(), 2);
+            assert_eq!(
+                result.responses[1].url,
+                format!("http://127.0.0.1:{}/form/next?a=b", port)
+            );
+        }
+    }
+
+    #[test]
+    fn redirect_not_followed() {
+        let (port, server) = serve(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: /next\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let result = prober(port, "/").probe().unwrap();
+        server.join().unwrap();
+        assert_eq!(result.probe_status.kind, ProbeStatusKind::Done);
+        assert_eq!(result.responses.len(), 1);
+        assert_eq!(result.final_response().unwrap().status_code, 302);
+    }
+
+    #[test]
+    fn too_many_redirects() {
+        let redirect: &[u8] = b"HTTP/1.1 307 Temporary Redirect\r\nLocation: /loop\r\n\r\n";
+        let (port, server) = serve(vec![redirect, redirect, redirect]);
+        let result = prober(port, "/")
+            .with_follow_redirects(true)
+            .with_max_redirects(2)
+            .probe()
+            .unwrap();
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(result.responses.len(), 3);
+        assert_eq!(result.probe_status.kind, ProbeStatusKind::Error);
+        assert_eq!(result.probe_status.message, "Too many redirects (2)");
+    }
+
+    #[test]
+    fn truncated_body() {
+        let (port, server) = serve(vec![b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort"]);
+        let result = prober(port, "/").probe().unwrap();
+        server.join().unwrap();
+        assert!(result.responses.is_empty());
+        assert_eq!(result.probe_status.kind, ProbeStatusKind::Error);
+        assert_eq!(
+            result.probe_status.message,
+            "Body truncated at 5 of 10 bytes"
+        );
+
+        let (port, server) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nshort",
+        ]);
+        let result = prober(port, "/").probe().unwrap();
+        server.join().unwrap();
+        assert_eq!(result.probe_status.kind, ProbeStatusKind::Error);
+        assert_eq!(result.probe_status.message, "Chunked body truncated");
+    }
+
+    #[test]
+    fn parse_url() {
+        let url = HttpUrl::parse("HTTP://Example.COM").unwrap();
+        assert_eq!(url.scheme, "http");
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, HTTP_DEFAULT_PORT);
+        assert_eq!(url.path, "/");
+        assert_eq!(url.to_string(), "http://example.com/");
+
+        let url = HttpUrl::parse("http://[2001:db8::1]:8080/a/b?c=d#frag").unwrap();
+        assert_eq!(url.host, "2001:db8::1");
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.path, "/a/b?c=d");
+        assert_eq!(url.authority(), "[2001:db8::1]:8080");
+
+        let url = HttpUrl::parse("http://[::1]/").unwrap();
+        assert_eq!(url.host, "::1");
+        assert_eq!(url.authority(), "[::1]");
+
+        let url = HttpUrl::parse("http://example.com?q=1").unwrap();
+        assert_eq!(url.path, "/?q=1");
+
+        let url = HttpUrl::parse("http://user:p@ss@example.com:81/").unwrap();
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, 81);
+        assert_eq!(url.to_string(), "http://example.com:81/");
+
+        assert!(HttpUrl::parse("example.com").is_err());
+        assert!(HttpUrl::parse("https://example.com").is_err());
+        assert!(HttpUrl::parse("http://:80/").is_err());
+        assert!(HttpUrl::parse("http://[::1/").is_err());
+        assert!(HttpUrl::parse("http://example.com:99999/").is_err());
+    }
+
+    #[test]
+    fn join_location() {
+        let url = HttpUrl::parse("http://example.com:8080/a/b/c?q=/x/y").unwrap();
+        let join = |location: &str| url.join(location).unwrap().to_string();
+        assert_eq!(join("d"), "http://example.com:8080/a/b/d");
+        assert_eq!(join("d?e=f"), "http://example.com:8080/a/b/d?e=f");
+        assert_eq!(join("../d"), "http://example.com:8080/a/b/../d");
+        assert_eq!(join("/d"), "http://example.com:8080/d");
+        assert_eq!(join("//other.example/d"), "http://other.example/d");
+        assert_eq!(join("http://[::1]:81/d"), "http://[::1]:81/d");
+        assert!(url.join("https://example.com/").is_err());
+
+        let url = HttpUrl::parse("http://example.com?q=1").unwrap();
+        assert_eq!(url.join("d").unwrap().path, "/d");
+    }
+}
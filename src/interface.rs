@@ -46,6 +46,15 @@ pub(crate) fn get_interface_local_ipv6(iface: &Interface) -> Option<IpAddr> {
     return None;
 }
 
+pub(crate) fn get_interface_link_local_ipv6(iface: &Interface) -> Option<IpAddr> {
+    for ip in iface.ipv6.clone() {
+        if ip.addr.segments()[0] & 0xffc0 == 0xfe80 {
+            return Some(IpAddr::V6(ip.addr));
+        }
+    }
+    None
+}
+
 pub(crate) fn get_interface_macaddr(iface: &Interface) -> MacAddr {
     match &iface.mac_addr {
         Some(mac_addr) => mac_addr.clone(),
@@ -1,11 +1,20 @@
+pub mod dhcp;
 pub mod dns;
 mod interface;
 mod ip;
 pub mod neighbor;
-mod packet;
+pub mod packet;
 pub mod ping;
 pub mod result;
+pub mod scan;
 pub mod setting;
 mod sys;
+pub mod template;
 pub mod trace;
+pub mod wol;
 pub mod fp;
+pub mod service;
+pub mod http;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod dualstack;
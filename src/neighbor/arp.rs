@@ -66,6 +66,9 @@ pub(crate) fn run_arp(
                                         node_type: NodeType::Destination,
                                         sent_packet_size: arp_packet.len(),
                                         received_packet_size: packet.len(),
+                                        reply_frame: None,
+                                        unreachable_reason: None,
+                                        service: None,
                                     };
                                     responses.push(probe_result.clone());
                                     match msg_tx.lock() {
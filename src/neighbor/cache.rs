@@ -0,0 +1,257 @@
+use crate::result::{NeighborEntry, NeighborState};
+use std::net::IpAddr;
+
+#[cfg(target_os = "linux")]
+use std::net::{Ipv4Addr, Ipv6Addr};
+#[cfg(target_os = "linux")]
+use xenet::net::mac::MacAddr;
+
+#[cfg(target_os = "linux")]
+const NETLINK_ROUTE: libc::c_int = 0;
+#[cfg(target_os = "linux")]
+const NLMSG_HDR_LEN: usize = 16;
+#[cfg(target_os = "linux")]
+const NLMSG_ERROR: u16 = 2;
+#[cfg(target_os = "linux")]
+const NLMSG_DONE: u16 = 3;
+#[cfg(target_os = "linux")]
+const NLM_F_REQUEST: u16 = 0x01;
+#[cfg(target_os = "linux")]
+const NLM_F_DUMP: u16 = 0x300;
+#[cfg(target_os = "linux")]
+const RTM_NEWNEIGH: u16 = 28;
+#[cfg(target_os = "linux")]
+const RTM_GETNEIGH: u16 = 30;
+#[cfg(target_os = "linux")]
+const NDMSG_LEN: usize = 12;
+#[cfg(target_os = "linux")]
+const NDA_DST: u16 = 1;
+#[cfg(target_os = "linux")]
+const NDA_LLADDR: u16 = 2;
+
+/// Get all entries of the system neighbor cache
+#[cfg(target_os = "linux")]
+pub(crate) fn get_neighbor_entries() -> Result<Vec<NeighborEntry>, String> {
+    match read_netlink_neighbors() {
+        Ok(entries) => Ok(entries),
+        // Netlink may be unavailable (e.g. restricted sandbox). IPv4 entries are still readable.
+        Err(_) => read_proc_net_arp(),
+    }
+}
+
+/// Get all entries of the system neighbor cache
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn get_neighbor_entries() -> Result<Vec<NeighborEntry>, String> {
+    Err(String::from(
+        "get_neighbor_entries: neighbor cache lookup is only supported on Linux",
+    ))
+}
+
+/// Find usable neighbor cache entry for the IP address on the interface
+pub(crate) fn lookup_neighbor(ip_addr: IpAddr, if_index: u32) -> Option<NeighborEntry> {
+    let entries: Vec<NeighborEntry> = get_neighbor_entries().ok()?;
+    entries.into_iter().find(|entry| {
+        entry.ip_addr == ip_addr
+            && (entry.if_index == if_index || entry.if_index == 0)
+            && entry.state.is_usable()
+            && entry.mac_addr != xenet::net::mac::MacAddr::zero()
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn nud_state(state: u16) -> NeighborState {
+    match state {
+        0x01 => NeighborState::Incomplete,
+        0x02 => NeighborState::Reachable,
+        0x04 => NeighborState::Stale,
+        0x08 => NeighborState::Delay,
+        0x10 => NeighborState::Probe,
+        0x20 => NeighborState::Failed,
+        0x40 => NeighborState::NoArp,
+        0x80 => NeighborState::Permanent,
+        _ => NeighborState::Unknown,
+    }
+}
+
+/// Dump the neighbor table with RTM_GETNEIGH
+#[cfg(target_os = "linux")]
+fn read_netlink_neighbors() -> Result<Vec<NeighborEntry>, String> {
+    let fd: libc::c_int = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            NETLINK_ROUTE,
+        )
+    };
+    if fd < 0 {
+        return Err(format!(
+            "read_netlink_neighbors: unable to open netlink socket: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    // nlmsghdr + ndmsg (family AF_UNSPEC: both IPv4 and IPv6)
+    let mut request: Vec<u8> = Vec::with_capacity(NLMSG_HDR_LEN + NDMSG_LEN);
+    request.extend_from_slice(&((NLMSG_HDR_LEN + NDMSG_LEN) as u32).to_ne_bytes());
+    request.extend_from_slice(&RTM_GETNEIGH.to_ne_bytes());
+    request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    request.extend_from_slice(&1u32.to_ne_bytes());
+    request.extend_from_slice(&0u32.to_ne_bytes());
+    request.extend_from_slice(&[0u8; NDMSG_LEN]);
+    let sent: isize = unsafe {
+        libc::send(
+            fd,
+            request.as_ptr() as *const libc::c_void,
+            request.len(),
+            0,
+        )
+    };
+    if sent < 0 {
+        let e = std::io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(format!(
+            "read_netlink_neighbors: unable to send request: {}",
+            e
+        ));
+    }
+    let mut entries: Vec<NeighborEntry> = Vec::new();
+    let mut buf: Vec<u8> = vec![0u8; 32768];
+    let result: Result<(), String> = 'recv: loop {
+        let n: isize =
+            unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+        if n < 0 {
+            break Err(format!(
+                "read_netlink_neighbors: unable to receive response: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        if n == 0 {
+            break Ok(());
+        }
+        let data: &[u8] = &buf[..n as usize];
+        let mut offset: usize = 0;
+        while offset + NLMSG_HDR_LEN <= data.len() {
+            let msg_len: usize = u32::from_ne_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ]) as usize;
+            let msg_type: u16 = u16::from_ne_bytes([data[offset + 4], data[offset + 5]]);
+            if msg_len < NLMSG_HDR_LEN || offset + msg_len > data.len() {
+                break 'recv Ok(());
+            }
+            match msg_type {
+                NLMSG_DONE => break 'recv Ok(()),
+                NLMSG_ERROR => {
+                    break 'recv Err(String::from(
+                        "read_netlink_neighbors: netlink returned an error",
+                    ))
+                }
+                RTM_NEWNEIGH => {
+                    if let Some(entry) =
+                        parse_neighbor_message(&data[offset + NLMSG_HDR_LEN..offset + msg_len])
+                    {
+                        entries.push(entry);
+                    }
+                }
+                _ => {}
+            }
+            offset += (msg_len + 3) & !3;
+        }
+    };
+    unsafe { libc::close(fd) };
+    result.map(|_| entries)
+}
+
+/// Parse ndmsg and its attributes
+#[cfg(target_os = "linux")]
+fn parse_neighbor_message(msg: &[u8]) -> Option<NeighborEntry> {
+    if msg.len() < NDMSG_LEN {
+        return None;
+    }
+    let family: i32 = msg[0] as i32;
+    let if_index: i32 = i32::from_ne_bytes([msg[4], msg[5], msg[6], msg[7]]);
+    let state: u16 = u16::from_ne_bytes([msg[8], msg[9]]);
+    let mut ip_addr: Option<IpAddr> = None;
+    let mut mac_addr: MacAddr = MacAddr::zero();
+    let mut offset: usize = NDMSG_LEN;
+    while offset + 4 <= msg.len() {
+        let rta_len: usize = u16::from_ne_bytes([msg[offset], msg[offset + 1]]) as usize;
+        let rta_type: u16 = u16::from_ne_bytes([msg[offset + 2], msg[offset + 3]]);
+        if rta_len < 4 || offset + rta_len > msg.len() {
+            break;
+        }
+        let value: &[u8] = &msg[offset + 4..offset + rta_len];
+        match rta_type {
+            NDA_DST if family == libc::AF_INET && value.len() == 4 => {
+                ip_addr = Some(IpAddr::V4(Ipv4Addr::new(
+                    value[0], value[1], value[2], value[3],
+                )));
+            }
+            NDA_DST if family == libc::AF_INET6 && value.len() == 16 => {
+                let mut octets: [u8; 16] = [0; 16];
+                octets.copy_from_slice(value);
+                ip_addr = Some(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            NDA_LLADDR if value.len() == 6 => {
+                let mut octets: [u8; 6] = [0; 6];
+                octets.copy_from_slice(value);
+                mac_addr = MacAddr::from_octets(octets);
+            }
+            _ => {}
+        }
+        offset += (rta_len + 3) & !3;
+    }
+    Some(NeighborEntry {
+        ip_addr: ip_addr?,
+        mac_addr,
+        if_index: if_index as u32,
+        state: nud_state(state),
+    })
+}
+
+/// Read IPv4 ARP table from /proc/net/arp
+#[cfg(target_os = "linux")]
+fn read_proc_net_arp() -> Result<Vec<NeighborEntry>, String> {
+    let content: String = match std::fs::read_to_string("/proc/net/arp") {
+        Ok(content) => content,
+        Err(e) => {
+            return Err(format!(
+                "read_proc_net_arp: unable to read /proc/net/arp: {}",
+                e
+            ))
+        }
+    };
+    let mut entries: Vec<NeighborEntry> = Vec::new();
+    // IP address, HW type, Flags, HW address, Mask, Device
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 {
+            continue;
+        }
+        let ip_addr: Ipv4Addr = match fields[0].parse::<Ipv4Addr>() {
+            Ok(ip_addr) => ip_addr,
+            Err(_) => continue,
+        };
+        let flags: u32 = u32::from_str_radix(fields[2].trim_start_matches("0x"), 16).unwrap_or(0);
+        // ATF_PERM: 0x04, ATF_COM: 0x02
+        let state: NeighborState = if flags & 0x04 != 0 {
+            NeighborState::Permanent
+        } else if flags & 0x02 != 0 {
+            NeighborState::Unknown
+        } else {
+            NeighborState::Incomplete
+        };
+        let if_index: u32 = match crate::interface::get_interface_by_name(fields[5].to_string()) {
+            Some(iface) => iface.index,
+            None => 0,
+        };
+        entries.push(NeighborEntry {
+            ip_addr: IpAddr::V4(ip_addr),
+            mac_addr: MacAddr::from_hex_format(fields[3]),
+            if_index,
+            state,
+        });
+    }
+    Ok(entries)
+}
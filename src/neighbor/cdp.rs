@@ -0,0 +1,314 @@
+use crate::result::LinkNeighbor;
+use crate::setting::Protocol;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use xenet::net::mac::MacAddr;
+
+/// CDP multicast address
+const CDP_MULTICAST_MAC: MacAddr = MacAddr(0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc);
+/// LLC/SNAP header: DSAP, SSAP, control, Cisco OUI and protocol ID 0x2000
+const CDP_LLC_SNAP: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x0c, 0x20, 0x00];
+/// Version, TTL, checksum
+const CDP_HEADER_LEN: usize = 4;
+
+const CDP_TLV_DEVICE_ID: u16 = 0x0001;
+const CDP_TLV_ADDRESSES: u16 = 0x0002;
+const CDP_TLV_PORT_ID: u16 = 0x0003;
+const CDP_TLV_CAPABILITIES: u16 = 0x0004;
+const CDP_TLV_SOFTWARE_VERSION: u16 = 0x0005;
+const CDP_TLV_PLATFORM: u16 = 0x0006;
+const CDP_TLV_NATIVE_VLAN: u16 = 0x000a;
+const CDP_TLV_MANAGEMENT_ADDRESSES: u16 = 0x0016;
+
+/// NLPID protocol type
+const CDP_PROTOCOL_TYPE_NLPID: u8 = 1;
+/// IEEE 802.2 protocol type
+const CDP_PROTOCOL_TYPE_8022: u8 = 2;
+const NLPID_IP: u8 = 0xcc;
+/// LLC/SNAP for IPv6 (ethertype 0x86dd)
+const SNAP_IPV6: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x86, 0xdd];
+
+const CDP_CAPABILITIES: [&str; 11] = [
+    "Router",
+    "Transparent Bridge",
+    "Source Route Bridge",
+    "Switch",
+    "Host",
+    "IGMP",
+    "Repeater",
+    "VoIP Phone",
+    "Remotely Managed",
+    "CVTA",
+    "Two-port MAC Relay",
+];
+
+/// CDP is carried in 802.3 frames (length field instead of ethertype) with LLC/SNAP
+pub(crate) fn is_cdp_frame(dst_mac: MacAddr, length: u16, payload: &[u8]) -> bool {
+    dst_mac == CDP_MULTICAST_MAC
+        && length < 0x0600
+        && payload.len() >= CDP_LLC_SNAP.len() + CDP_HEADER_LEN
+        && payload[..CDP_LLC_SNAP.len()] == CDP_LLC_SNAP
+}
+
+/// Parse CDP message following the LLC/SNAP header
+pub(crate) fn parse_cdp(payload: &[u8]) -> Option<LinkNeighbor> {
+    let cdp: &[u8] = payload.get(CDP_LLC_SNAP.len()..)?;
+    if cdp.len() < CDP_HEADER_LEN {
+        return None;
+    }
+    let mut neighbor = LinkNeighbor {
+        protocol: Protocol::CDP,
+        mac_addr: MacAddr::zero(),
+        chassis_id: String::new(),
+        port_id: String::new(),
+        port_description: None,
+        system_name: None,
+        system_description: None,
+        platform: None,
+        management_addrs: Vec::new(),
+        vlan_id: None,
+        capabilities: Vec::new(),
+        enabled_capabilities: Vec::new(),
+        ttl: cdp[1] as u16,
+        frame_count: 1,
+    };
+    let mut tlvs: &[u8] = &cdp[CDP_HEADER_LEN..];
+    while tlvs.len() >= 4 {
+        let tlv_type: u16 = u16::from_be_bytes([tlvs[0], tlvs[1]]);
+        // Length includes type and length fields
+        let tlv_len: usize = u16::from_be_bytes([tlvs[2], tlvs[3]]) as usize;
+        if tlv_len < 4 || tlv_len > tlvs.len() {
+            break;
+        }
+        let data: &[u8] = &tlvs[4..tlv_len];
+        match tlv_type {
+            CDP_TLV_DEVICE_ID => {
+                let device_id: String = super::lldp::format_id_string(data);
+                neighbor.system_name = Some(device_id.clone());
+                neighbor.chassis_id = device_id;
+            }
+            CDP_TLV_ADDRESSES | CDP_TLV_MANAGEMENT_ADDRESSES => {
+                for ip_addr in parse_addresses(data) {
+                    if !neighbor.management_addrs.contains(&ip_addr) {
+                        neighbor.management_addrs.push(ip_addr);
+                    }
+                }
+            }
+            CDP_TLV_PORT_ID => {
+                neighbor.port_id = super::lldp::format_id_string(data);
+            }
+            CDP_TLV_CAPABILITIES if data.len() >= 4 => {
+                let bits: u32 = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+                neighbor.capabilities = super::lldp::capability_names(bits, &CDP_CAPABILITIES);
+                neighbor.enabled_capabilities = neighbor.capabilities.clone();
+            }
+            CDP_TLV_SOFTWARE_VERSION => {
+                neighbor.system_description = Some(super::lldp::format_id_string(data));
+            }
+            CDP_TLV_PLATFORM => {
+                neighbor.platform = Some(super::lldp::format_id_string(data));
+            }
+            CDP_TLV_NATIVE_VLAN if data.len() >= 2 => {
+                neighbor.vlan_id = Some(u16::from_be_bytes([data[0], data[1]]));
+            }
+            _ => {}
+        }
+        tlvs = &tlvs[tlv_len..];
+    }
+    if neighbor.chassis_id.is_empty() {
+        return None;
+    }
+    Some(neighbor)
+}
+
+/// Parse CDP address list (count, then protocol type/length/protocol/address length/address)
+fn parse_addresses(data: &[u8]) -> Vec<IpAddr> {
+    let mut addrs: Vec<IpAddr> = Vec::new();
+    if data.len() < 4 {
+        return addrs;
+    }
+    let count: u32 = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+    let mut rest: &[u8] = &data[4..];
+    for _ in 0..count {
+        if rest.len() < 2 {
+            break;
+        }
+        let protocol_type: u8 = rest[0];
+        let protocol_len: usize = rest[1] as usize;
+        if rest.len() < 2 + protocol_len + 2 {
+            break;
+        }
+        let protocol: &[u8] = &rest[2..2 + protocol_len];
+        let addr_offset: usize = 2 + protocol_len;
+        let addr_len: usize =
+            u16::from_be_bytes([rest[addr_offset], rest[addr_offset + 1]]) as usize;
+        if rest.len() < addr_offset + 2 + addr_len {
+            break;
+        }
+        let addr: &[u8] = &rest[addr_offset + 2..addr_offset + 2 + addr_len];
+        match (protocol_type, addr.len()) {
+            (CDP_PROTOCOL_TYPE_NLPID, 4) if protocol == [NLPID_IP] => {
+                addrs.push(IpAddr::V4(Ipv4Addr::new(
+                    addr[0], addr[1], addr[2], addr[3],
+                )));
+            }
+            (CDP_PROTOCOL_TYPE_8022, 16) if protocol == SNAP_IPV6 => {
+                let mut octets: [u8; 16] = [0; 16];
+                octets.copy_from_slice(addr);
+                addrs.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+        rest = &rest[addr_offset + 2 + addr_len..];
+    }
+    addrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighbor::lldp::parse_link_discovery_frame;
+
+    fn tlv(tlv_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut tlv: Vec<u8> = tlv_type.to_be_bytes().to_vec();
+        tlv.extend_from_slice(&((data.len() + 4) as u16).to_be_bytes());
+        tlv.extend_from_slice(data);
+        tlv
+    }
+
+    fn ipv4_address(octets: [u8; 4]) -> Vec<u8> {
+        let mut addr: Vec<u8> = vec![CDP_PROTOCOL_TYPE_NLPID, 1, NLPID_IP, 0, 4];
+        addr.extend_from_slice(&octets);
+        addr
+    }
+
+    fn ipv6_address(ip_addr: &str) -> Vec<u8> {
+        let mut addr: Vec<u8> = vec![CDP_PROTOCOL_TYPE_8022, 8];
+        addr.extend_from_slice(&SNAP_IPV6);
+        addr.extend_from_slice(&[0, 16]);
+        addr.extend_from_slice(&ip_addr.parse::<Ipv6Addr>().unwrap().octets());
+        addr
+    }
+
+    fn address_list(count: u32, addrs: &[Vec<u8>]) -> Vec<u8> {
+        let mut data: Vec<u8> = count.to_be_bytes().to_vec();
+        for addr in addrs {
+            data.extend_from_slice(addr);
+        }
+        data
+    }
+
+    /// 802.3 frame to the CDP multicast address
+    fn cdp_frame(tlvs: &[Vec<u8>]) -> Vec<u8> {
+        let mut cdp: Vec<u8> = CDP_LLC_SNAP.to_vec();
+        // Version 2, TTL 180, checksum
+        cdp.extend_from_slice(&[2, 180, 0, 0]);
+        for tlv in tlvs {
+            cdp.extend_from_slice(tlv);
+        }
+        let mut frame: Vec<u8> = vec![0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc];
+        frame.extend_from_slice(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        frame.extend_from_slice(&(cdp.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&cdp);
+        frame
+    }
+
+    #[test]
+    fn parse_cdp_frame() {
+        let addrs: Vec<u8> = address_list(
+            2,
+            &[ipv4_address([192, 0, 2, 10]), ipv6_address("2001:db8::10")],
+        );
+        let frame: Vec<u8> = cdp_frame(&[
+            tlv(CDP_TLV_DEVICE_ID, b"sw1.example.com"),
+            tlv(CDP_TLV_ADDRESSES, &addrs),
+            tlv(CDP_TLV_PORT_ID, b"GigabitEthernet1/0/1"),
+            // Router and Switch
+            tlv(CDP_TLV_CAPABILITIES, &[0, 0, 0, 0x09]),
+            tlv(CDP_TLV_SOFTWARE_VERSION, b"IOS 15.2"),
+            tlv(CDP_TLV_PLATFORM, b"cisco WS-C2960"),
+            tlv(CDP_TLV_NATIVE_VLAN, &[0, 10]),
+            // Same addresses again, not duplicated
+            tlv(CDP_TLV_MANAGEMENT_ADDRESSES, &addrs),
+        ]);
+        let neighbor: LinkNeighbor = parse_link_discovery_frame(&frame).unwrap();
+        assert_eq!(
+            neighbor.mac_addr,
+            MacAddr::new(0x00, 0x11, 0x22, 0x33, 0x44, 0x55)
+        );
+        assert_eq!(neighbor.chassis_id, "sw1.example.com");
+        assert_eq!(neighbor.system_name.as_deref(), Some("sw1.example.com"));
+        assert_eq!(neighbor.port_id, "GigabitEthernet1/0/1");
+        assert_eq!(neighbor.ttl, 180);
+        assert_eq!(neighbor.capabilities, vec!["Router", "Switch"]);
+        assert_eq!(neighbor.system_description.as_deref(), Some("IOS 15.2"));
+        assert_eq!(neighbor.platform.as_deref(), Some("cisco WS-C2960"));
+        assert_eq!(neighbor.vlan_id, Some(10));
+        assert_eq!(
+            neighbor.management_addrs,
+            vec![
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10)),
+                "2001:db8::10".parse::<IpAddr>().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn not_cdp_frame() {
+        let mut frame: Vec<u8> = cdp_frame(&[tlv(CDP_TLV_DEVICE_ID, b"sw1")]);
+        frame[5] = 0xcd;
+        assert!(parse_link_discovery_frame(&frame).is_none());
+        // Device ID is required
+        let frame: Vec<u8> = cdp_frame(&[tlv(CDP_TLV_PORT_ID, b"Gi1/0/1")]);
+        assert!(parse_link_discovery_frame(&frame).is_none());
+    }
+
+    #[test]
+    fn truncated_tlv() {
+        let mut frame: Vec<u8> = cdp_frame(&[
+            tlv(CDP_TLV_DEVICE_ID, b"sw1"),
+            tlv(CDP_TLV_PLATFORM, b"cisco WS-C2960"),
+        ]);
+        frame.truncate(frame.len() - 1);
+        let neighbor: LinkNeighbor = parse_link_discovery_frame(&frame).unwrap();
+        assert_eq!(neighbor.chassis_id, "sw1");
+        assert_eq!(neighbor.platform, None);
+        // Length shorter than the TLV header
+        let mut bad_len: Vec<u8> = tlv(CDP_TLV_PLATFORM, b"x");
+        bad_len[3] = 3;
+        let frame: Vec<u8> = cdp_frame(&[tlv(CDP_TLV_DEVICE_ID, b"sw1"), bad_len]);
+        let neighbor: LinkNeighbor = parse_link_discovery_frame(&frame).unwrap();
+        assert_eq!(neighbor.platform, None);
+    }
+
+    #[test]
+    fn parse_address_list() {
+        let ipv4: Vec<u8> = ipv4_address([192, 0, 2, 10]);
+        let ipv6: Vec<u8> = ipv6_address("2001:db8::10");
+        // Count larger than the addresses present
+        assert_eq!(
+            parse_addresses(&address_list(5, &[ipv4.clone()])),
+            vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10))]
+        );
+        // Count smaller than the addresses present
+        assert_eq!(
+            parse_addresses(&address_list(1, &[ipv6.clone(), ipv4.clone()])).len(),
+            1
+        );
+        // Address length past the end
+        let mut truncated: Vec<u8> = ipv6.clone();
+        truncated.truncate(ipv6.len() - 1);
+        assert!(parse_addresses(&address_list(1, &[truncated])).is_empty());
+        // Protocol length past the end
+        assert!(
+            parse_addresses(&address_list(1, &[vec![CDP_PROTOCOL_TYPE_8022, 8, 0xaa]])).is_empty()
+        );
+        // IPv4 protocol with an IPv6 sized address is skipped, the next one is parsed
+        let mut mismatched: Vec<u8> = vec![CDP_PROTOCOL_TYPE_NLPID, 1, NLPID_IP, 0, 16];
+        mismatched.extend_from_slice(&[0; 16]);
+        assert_eq!(
+            parse_addresses(&address_list(2, &[mismatched, ipv4])),
+            vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10))]
+        );
+        assert!(parse_addresses(&[0, 0, 0]).is_empty());
+    }
+}
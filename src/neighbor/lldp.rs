@@ -0,0 +1,404 @@
+use crate::result::{LinkDiscoveryResult, LinkNeighbor, ProbeStatus};
+use crate::setting::{ProbeSetting, Protocol};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use xenet::datalink::{DataLinkReceiver, DataLinkSender};
+use xenet::net::mac::MacAddr;
+
+const ETHER_TYPE_LLDP: u16 = 0x88cc;
+
+const LLDP_TLV_END: u8 = 0;
+const LLDP_TLV_CHASSIS_ID: u8 = 1;
+const LLDP_TLV_PORT_ID: u8 = 2;
+const LLDP_TLV_TTL: u8 = 3;
+const LLDP_TLV_PORT_DESCRIPTION: u8 = 4;
+const LLDP_TLV_SYSTEM_NAME: u8 = 5;
+const LLDP_TLV_SYSTEM_DESCRIPTION: u8 = 6;
+const LLDP_TLV_SYSTEM_CAPABILITIES: u8 = 7;
+const LLDP_TLV_MANAGEMENT_ADDRESS: u8 = 8;
+const LLDP_TLV_ORG_SPECIFIC: u8 = 127;
+
+/// Chassis ID subtype: MAC address
+const CHASSIS_ID_MAC_ADDRESS: u8 = 4;
+/// Chassis ID subtype: network address
+const CHASSIS_ID_NETWORK_ADDRESS: u8 = 5;
+/// Port ID subtype: MAC address
+const PORT_ID_MAC_ADDRESS: u8 = 3;
+/// Port ID subtype: network address
+const PORT_ID_NETWORK_ADDRESS: u8 = 4;
+
+/// IANA address family numbers
+const ADDRESS_FAMILY_IPV4: u8 = 1;
+const ADDRESS_FAMILY_IPV6: u8 = 2;
+
+/// IEEE 802.1 organizationally specific TLV
+const OUI_IEEE_8021: [u8; 3] = [0x00, 0x80, 0xc2];
+const IEEE_8021_PORT_VLAN_ID: u8 = 1;
+
+const LLDP_CAPABILITIES: [&str; 11] = [
+    "Other",
+    "Repeater",
+    "Bridge",
+    "WLAN Access Point",
+    "Router",
+    "Telephone",
+    "DOCSIS Cable Device",
+    "Station Only",
+    "C-VLAN Component",
+    "S-VLAN Component",
+    "Two-port MAC Relay",
+];
+
+pub(crate) fn run_link_discovery(
+    _tx: &mut Box<dyn DataLinkSender>,
+    rx: &mut Box<dyn DataLinkReceiver>,
+    setting: &ProbeSetting,
+    stop_on_first: bool,
+    msg_tx: &Arc<Mutex<Sender<LinkNeighbor>>>,
+) -> LinkDiscoveryResult {
+    let mut result = LinkDiscoveryResult::new();
+    result.protocol = setting.protocol.clone();
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let mut neighbors: Vec<LinkNeighbor> = Vec::new();
+    // Passive: nothing is transmitted. Listen for the whole probe window.
+    loop {
+        if let Ok(packet) = rx.next() {
+            if let Some(neighbor) = parse_link_discovery_frame(packet) {
+                match neighbors.iter_mut().find(|n| {
+                    n.mac_addr == neighbor.mac_addr
+                        && n.chassis_id == neighbor.chassis_id
+                        && n.port_id == neighbor.port_id
+                }) {
+                    Some(known) => {
+                        let frame_count: usize = known.frame_count + 1;
+                        *known = neighbor;
+                        known.frame_count = frame_count;
+                    }
+                    None => {
+                        neighbors.push(neighbor.clone());
+                        if let Ok(lr) = msg_tx.lock() {
+                            let _ = lr.send(neighbor);
+                        }
+                        if stop_on_first {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        if Instant::now().duration_since(start_time) > setting.probe_timeout {
+            break;
+        }
+    }
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = Instant::now().duration_since(start_time);
+    result.probe_status = if neighbors.is_empty() {
+        ProbeStatus::with_timeout_message(String::from("No LLDP or CDP advertisement received"))
+    } else {
+        ProbeStatus::new()
+    };
+    result.neighbors = neighbors;
+    result
+}
+
+/// Parse raw Ethernet frame carrying LLDP or CDP
+pub(crate) fn parse_link_discovery_frame(packet: &[u8]) -> Option<LinkNeighbor> {
+    let (ether_type, payload) = crate::packet::decode::ethernet_payload(packet)?;
+    let dst_mac: MacAddr = MacAddr::new(
+        packet[0], packet[1], packet[2], packet[3], packet[4], packet[5],
+    );
+    let src_mac: MacAddr = MacAddr::new(
+        packet[6], packet[7], packet[8], packet[9], packet[10], packet[11],
+    );
+    let mut neighbor = if ether_type == ETHER_TYPE_LLDP {
+        parse_lldp(payload)?
+    } else if super::cdp::is_cdp_frame(dst_mac, ether_type, payload) {
+        super::cdp::parse_cdp(payload)?
+    } else {
+        return None;
+    };
+    neighbor.mac_addr = src_mac;
+    Some(neighbor)
+}
+
+/// Parse LLDPDU (IEEE 802.1AB)
+pub(crate) fn parse_lldp(payload: &[u8]) -> Option<LinkNeighbor> {
+    let mut neighbor = LinkNeighbor {
+        protocol: Protocol::LLDP,
+        mac_addr: MacAddr::zero(),
+        chassis_id: String::new(),
+        port_id: String::new(),
+        port_description: None,
+        system_name: None,
+        system_description: None,
+        platform: None,
+        management_addrs: Vec::new(),
+        vlan_id: None,
+        capabilities: Vec::new(),
+        enabled_capabilities: Vec::new(),
+        ttl: 0,
+        frame_count: 1,
+    };
+    let mut tlvs: &[u8] = payload;
+    while tlvs.len() >= 2 {
+        let header: u16 = u16::from_be_bytes([tlvs[0], tlvs[1]]);
+        let tlv_type: u8 = (header >> 9) as u8;
+        let tlv_len: usize = (header & 0x01ff) as usize;
+        if tlv_type == LLDP_TLV_END || 2 + tlv_len > tlvs.len() {
+            break;
+        }
+        let data: &[u8] = &tlvs[2..2 + tlv_len];
+        match tlv_type {
+            LLDP_TLV_CHASSIS_ID if !data.is_empty() => {
+                neighbor.chassis_id = match data[0] {
+                    CHASSIS_ID_MAC_ADDRESS => format_id_mac(&data[1..]),
+                    CHASSIS_ID_NETWORK_ADDRESS => format_id_address(&data[1..]),
+                    _ => format_id_string(&data[1..]),
+                };
+            }
+            LLDP_TLV_PORT_ID if !data.is_empty() => {
+                neighbor.port_id = match data[0] {
+                    PORT_ID_MAC_ADDRESS => format_id_mac(&data[1..]),
+                    PORT_ID_NETWORK_ADDRESS => format_id_address(&data[1..]),
+                    _ => format_id_string(&data[1..]),
+                };
+            }
+            LLDP_TLV_TTL if data.len() >= 2 => {
+                neighbor.ttl = u16::from_be_bytes([data[0], data[1]]);
+            }
+            LLDP_TLV_PORT_DESCRIPTION => {
+                neighbor.port_description = Some(format_id_string(data));
+            }
+            LLDP_TLV_SYSTEM_NAME => {
+                neighbor.system_name = Some(format_id_string(data));
+            }
+            LLDP_TLV_SYSTEM_DESCRIPTION => {
+                neighbor.system_description = Some(format_id_string(data));
+            }
+            LLDP_TLV_SYSTEM_CAPABILITIES if data.len() >= 4 => {
+                neighbor.capabilities = capability_names(
+                    u16::from_be_bytes([data[0], data[1]]) as u32,
+                    &LLDP_CAPABILITIES,
+                );
+                neighbor.enabled_capabilities = capability_names(
+                    u16::from_be_bytes([data[2], data[3]]) as u32,
+                    &LLDP_CAPABILITIES,
+                );
+            }
+            LLDP_TLV_MANAGEMENT_ADDRESS if data.len() >= 2 => {
+                // Address string length includes the subtype octet
+                let addr_len: usize = data[0] as usize;
+                if addr_len >= 1 && addr_len < data.len() {
+                    if let Some(ip_addr) = parse_address(data[1], &data[2..1 + addr_len]) {
+                        neighbor.management_addrs.push(ip_addr);
+                    }
+                }
+            }
+            LLDP_TLV_ORG_SPECIFIC
+                if data.len() >= 6
+                    && data[0..3] == OUI_IEEE_8021
+                    && data[3] == IEEE_8021_PORT_VLAN_ID =>
+            {
+                neighbor.vlan_id = Some(u16::from_be_bytes([data[4], data[5]]));
+            }
+            _ => {}
+        }
+        tlvs = &tlvs[2 + tlv_len..];
+    }
+    // Chassis ID and Port ID are mandatory
+    if neighbor.chassis_id.is_empty() || neighbor.port_id.is_empty() {
+        return None;
+    }
+    Some(neighbor)
+}
+
+/// Names of the capability bits set
+pub(crate) fn capability_names(bits: u32, names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| bits & (1 << i) != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Address with IANA address family number
+fn parse_address(family: u8, data: &[u8]) -> Option<IpAddr> {
+    match family {
+        ADDRESS_FAMILY_IPV4 if data.len() == 4 => Some(IpAddr::V4(Ipv4Addr::new(
+            data[0], data[1], data[2], data[3],
+        ))),
+        ADDRESS_FAMILY_IPV6 if data.len() == 16 => {
+            let mut octets: [u8; 16] = [0; 16];
+            octets.copy_from_slice(data);
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+fn format_id_mac(data: &[u8]) -> String {
+    if data.len() == 6 {
+        MacAddr::new(data[0], data[1], data[2], data[3], data[4], data[5]).to_string()
+    } else {
+        format_id_string(data)
+    }
+}
+
+fn format_id_address(data: &[u8]) -> String {
+    match data.split_first() {
+        Some((family, addr)) => match parse_address(*family, addr) {
+            Some(ip_addr) => ip_addr.to_string(),
+            None => format_id_string(data),
+        },
+        None => String::new(),
+    }
+}
+
+pub(crate) fn format_id_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tlv(tlv_type: u8, data: &[u8]) -> Vec<u8> {
+        let header: u16 = (tlv_type as u16) << 9 | data.len() as u16;
+        let mut tlv: Vec<u8> = header.to_be_bytes().to_vec();
+        tlv.extend_from_slice(data);
+        tlv
+    }
+
+    /// Chassis ID (MAC), Port ID (interface name) and TTL
+    fn mandatory_tlvs() -> Vec<u8> {
+        [
+            tlv(
+                LLDP_TLV_CHASSIS_ID,
+                &[4, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55],
+            ),
+            tlv(LLDP_TLV_PORT_ID, b"\x05Gi1/0/1"),
+            tlv(LLDP_TLV_TTL, &[0, 120]),
+        ]
+        .concat()
+    }
+
+    fn management_address(data: &[u8]) -> Vec<u8> {
+        let mut lldpdu: Vec<u8> = mandatory_tlvs();
+        lldpdu.extend(tlv(LLDP_TLV_MANAGEMENT_ADDRESS, data));
+        lldpdu.extend(tlv(LLDP_TLV_END, &[]));
+        lldpdu
+    }
+
+    #[test]
+    fn parse_lldpdu() {
+        let mut lldpdu: Vec<u8> = mandatory_tlvs();
+        lldpdu.extend(tlv(LLDP_TLV_PORT_DESCRIPTION, b"uplink"));
+        lldpdu.extend(tlv(LLDP_TLV_SYSTEM_NAME, b"sw1.example.com\0"));
+        lldpdu.extend(tlv(LLDP_TLV_SYSTEM_DESCRIPTION, b"Switch OS 1.0"));
+        // Bridge and Router, Bridge enabled
+        lldpdu.extend(tlv(LLDP_TLV_SYSTEM_CAPABILITIES, &[0, 0x14, 0, 0x04]));
+        // Interface number subtype 2, ifIndex 3, no OID
+        lldpdu.extend(tlv(
+            LLDP_TLV_MANAGEMENT_ADDRESS,
+            &[5, 1, 192, 0, 2, 10, 2, 0, 0, 0, 3, 0],
+        ));
+        let mut ipv6_addr: Vec<u8> = vec![17, 2];
+        ipv6_addr.extend_from_slice(&"2001:db8::10".parse::<Ipv6Addr>().unwrap().octets());
+        ipv6_addr.extend_from_slice(&[2, 0, 0, 0, 3, 0]);
+        lldpdu.extend(tlv(LLDP_TLV_MANAGEMENT_ADDRESS, &ipv6_addr));
+        lldpdu.extend(tlv(LLDP_TLV_ORG_SPECIFIC, &[0x00, 0x80, 0xc2, 1, 0, 100]));
+        lldpdu.extend(tlv(LLDP_TLV_END, &[]));
+        // Ignored after End of LLDPDU
+        lldpdu.extend(tlv(LLDP_TLV_SYSTEM_NAME, b"ignored"));
+
+        let mut frame: Vec<u8> = vec![0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e];
+        frame.extend_from_slice(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x66]);
+        frame.extend_from_slice(&ETHER_TYPE_LLDP.to_be_bytes());
+        frame.extend_from_slice(&lldpdu);
+        let neighbor: LinkNeighbor = parse_link_discovery_frame(&frame).unwrap();
+        assert_eq!(
+            neighbor.mac_addr,
+            MacAddr::new(0x00, 0x11, 0x22, 0x33, 0x44, 0x66)
+        );
+        assert_eq!(neighbor.chassis_id, "00:11:22:33:44:55");
+        assert_eq!(neighbor.port_id, "Gi1/0/1");
+        assert_eq!(neighbor.ttl, 120);
+        assert_eq!(neighbor.port_description.as_deref(), Some("uplink"));
+        assert_eq!(neighbor.system_name.as_deref(), Some("sw1.example.com"));
+        assert_eq!(
+            neighbor.system_description.as_deref(),
+            Some("Switch OS 1.0")
+        );
+        assert_eq!(neighbor.capabilities, vec!["Bridge", "Router"]);
+        assert_eq!(neighbor.enabled_capabilities, vec!["Bridge"]);
+        assert_eq!(
+            neighbor.management_addrs,
+            vec![
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10)),
+                "2001:db8::10".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert_eq!(neighbor.vlan_id, Some(100));
+    }
+
+    #[test]
+    fn parse_network_address_ids() {
+        let lldpdu: Vec<u8> = [
+            tlv(LLDP_TLV_CHASSIS_ID, &[5, 1, 192, 0, 2, 1]),
+            tlv(LLDP_TLV_PORT_ID, &[3, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+        ]
+        .concat();
+        let neighbor: LinkNeighbor = parse_lldp(&lldpdu).unwrap();
+        assert_eq!(neighbor.chassis_id, "192.0.2.1");
+        assert_eq!(neighbor.port_id, "00:11:22:33:44:55");
+    }
+
+    #[test]
+    fn truncated_tlv() {
+        // TLV length past the end stops parsing, earlier TLVs are kept
+        let mut lldpdu: Vec<u8> = mandatory_tlvs();
+        lldpdu.extend(tlv(LLDP_TLV_SYSTEM_NAME, b"sw1"));
+        lldpdu.extend_from_slice(&tlv(LLDP_TLV_SYSTEM_DESCRIPTION, b"Switch OS")[..6]);
+        let neighbor: LinkNeighbor = parse_lldp(&lldpdu).unwrap();
+        assert_eq!(neighbor.system_name.as_deref(), Some("sw1"));
+        assert_eq!(neighbor.system_description, None);
+        // A lone header byte
+        let mut lldpdu: Vec<u8> = mandatory_tlvs();
+        lldpdu.push(LLDP_TLV_SYSTEM_NAME << 1);
+        assert_eq!(parse_lldp(&lldpdu).unwrap().system_name, None);
+        // Mandatory TLVs truncated
+        let lldpdu: Vec<u8> = mandatory_tlvs();
+        assert!(parse_lldp(&lldpdu[..10]).is_none());
+        assert!(parse_lldp(&[]).is_none());
+    }
+
+    #[test]
+    fn management_address_length() {
+        let valid: Vec<u8> = management_address(&[5, 1, 192, 0, 2, 10, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(parse_lldp(&valid).unwrap().management_addrs.len(), 1);
+        // Zero, shorter than the IPv4 address, past the end of the TLV
+        for addr_len in [0, 4, 12, 255] {
+            let lldpdu: Vec<u8> =
+                management_address(&[addr_len, 1, 192, 0, 2, 10, 1, 0, 0, 0, 0, 0]);
+            let neighbor: LinkNeighbor = parse_lldp(&lldpdu).unwrap();
+            assert!(
+                neighbor.management_addrs.is_empty(),
+                "addr_len {}",
+                addr_len
+            );
+        }
+        // IPv6 family with an IPv4 sized address
+        let lldpdu: Vec<u8> = management_address(&[5, 2, 192, 0, 2, 10, 1, 0, 0, 0, 0, 0]);
+        assert!(parse_lldp(&lldpdu).unwrap().management_addrs.is_empty());
+        // Address string length only
+        let lldpdu: Vec<u8> = management_address(&[1, 1]);
+        assert!(parse_lldp(&lldpdu).unwrap().management_addrs.is_empty());
+    }
+}
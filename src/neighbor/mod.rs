@@ -1,19 +1,36 @@
 pub(crate) mod arp;
+pub(crate) mod cache;
+pub(crate) mod cdp;
+pub(crate) mod lldp;
 pub(crate) mod ndp;
+pub(crate) mod router;
 
+use std::net::IpAddr;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use xenet::net::interface::Interface;
 
-use crate::result::{DeviceResolveResult, ProbeResult};
+use crate::result::{
+    DeviceResolveResult, LinkDiscoveryResult, LinkNeighbor, NeighborEntry, NodeType, ProbeResult,
+    ProbeStatus, RouterDiscoveryResult, RouterInfo,
+};
 use crate::setting::ProbeSetting;
 
+/// Get entries of the system neighbor cache (ARP table and NDP neighbor table).
+///
+/// Currently supported on Linux.
+pub fn get_neighbor_cache() -> Result<Vec<NeighborEntry>, String> {
+    cache::get_neighbor_entries()
+}
+
 /// Device Resolver structure.
 ///
 /// Supports ARP and NDP.
 pub struct DeviceResolver {
     /// Probe Setting
     pub probe_setting: ProbeSetting,
+    /// Consult the system neighbor cache before sending ARP/NDP
+    pub use_neighbor_cache: bool,
     /// Sender for progress messaging
     tx: Arc<Mutex<Sender<ProbeResult>>>,
     /// Receiver for progress messaging
@@ -35,13 +52,26 @@ impl DeviceResolver {
         let (tx, rx) = channel();
         let pinger = DeviceResolver {
             probe_setting: setting,
+            use_neighbor_cache: false,
             tx: Arc::new(Mutex::new(tx)),
             rx: Arc::new(Mutex::new(rx)),
         };
         return Ok(pinger);
     }
+    /// Consult the system neighbor cache first and fall back to ARP/NDP only when absent
+    pub fn with_neighbor_cache(mut self, use_neighbor_cache: bool) -> DeviceResolver {
+        self.use_neighbor_cache = use_neighbor_cache;
+        self
+    }
     /// Run arp/ndp
     pub fn resolve(&self) -> Result<DeviceResolveResult, String> {
+        if self.use_neighbor_cache {
+            if let Some(entry) =
+                cache::lookup_neighbor(self.probe_setting.dst_ip, self.probe_setting.if_index)
+            {
+                return Ok(resolve_from_cache(&self.probe_setting, entry, &self.tx));
+            }
+        }
         run_resolver(&self.probe_setting, &self.tx)
     }
     /// Get progress receiver
@@ -50,6 +80,43 @@ impl DeviceResolver {
     }
 }
 
+fn resolve_from_cache(
+    setting: &ProbeSetting,
+    entry: NeighborEntry,
+    msg_tx: &Arc<Mutex<Sender<ProbeResult>>>,
+) -> DeviceResolveResult {
+    let mut result = DeviceResolveResult::new();
+    result.protocol = setting.protocol.clone();
+    result.start_time = crate::sys::get_sysdate();
+    let probe_result: ProbeResult = ProbeResult {
+        seq: 1,
+        mac_addr: entry.mac_addr,
+        ip_addr: setting.dst_ip,
+        host_name: setting.dst_hostname.clone(),
+        port_number: None,
+        port_status: None,
+        ttl: 0,
+        hop: 0,
+        rtt: std::time::Duration::from_millis(0),
+        probe_status: ProbeStatus::new(),
+        protocol: setting.protocol.clone(),
+        node_type: NodeType::Destination,
+        sent_packet_size: 0,
+        received_packet_size: 0,
+        reply_frame: None,
+        unreachable_reason: None,
+        service: None,
+    };
+    if let Ok(lr) = msg_tx.lock() {
+        let _ = lr.send(probe_result.clone());
+    }
+    result.end_time = crate::sys::get_sysdate();
+    result.results = vec![probe_result];
+    result.cache_entry = Some(entry);
+    result.probe_status = ProbeStatus::new();
+    result
+}
+
 fn run_resolver(
     setting: &ProbeSetting,
     msg_tx: &Arc<Mutex<Sender<ProbeResult>>>,
@@ -93,3 +160,195 @@ fn run_resolver(
         }
     }
 }
+
+/// IPv6 Router Discovery structure.
+///
+/// Sends Router Solicitation and collects every Router Advertisement on the link.
+#[derive(Clone, Debug)]
+pub struct RouterDiscoverer {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Known legitimate routers. If not empty, routers not listed are reported as rogue
+    pub trusted_routers: Vec<IpAddr>,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<RouterInfo>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<RouterInfo>>>,
+}
+
+impl RouterDiscoverer {
+    /// Create new RouterDiscoverer instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<RouterDiscoverer, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "RouterDiscoverer::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let (tx, rx) = channel();
+        let discoverer = RouterDiscoverer {
+            probe_setting: setting,
+            trusted_routers: Vec::new(),
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(discoverer)
+    }
+    /// Set known legitimate routers
+    pub fn with_trusted_routers(mut self, trusted_routers: Vec<IpAddr>) -> RouterDiscoverer {
+        self.trusted_routers = trusted_routers;
+        self
+    }
+    /// Run router discovery
+    pub fn discover(&self) -> Result<RouterDiscoveryResult, String> {
+        run_router_discovery(&self.probe_setting, &self.trusted_routers, &self.tx)
+    }
+    /// Get progress receiver
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<RouterInfo>>> {
+        self.rx.clone()
+    }
+}
+
+fn run_router_discovery(
+    setting: &ProbeSetting,
+    trusted_routers: &[IpAddr],
+    msg_tx: &Arc<Mutex<Sender<RouterInfo>>>,
+) -> Result<RouterDiscoveryResult, String> {
+    let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
+        Some(interface) => interface,
+        None => {
+            return Err(format!(
+                "run_router_discovery: unable to get interface by index {}",
+                setting.if_index
+            ))
+        }
+    };
+    let config = xenet::datalink::Config {
+        write_buffer_size: 4096,
+        read_buffer_size: 4096,
+        read_timeout: Some(setting.receive_timeout),
+        write_timeout: None,
+        channel_type: xenet::datalink::ChannelType::Layer2,
+        bpf_fd_attempts: 1000,
+        linux_fanout: None,
+        promiscuous: false,
+    };
+    // Create a channel to send/receive packet
+    let (mut tx, mut rx) = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+        Ok(_) => return Err("run_router_discovery: unable to create channel".to_string()),
+        Err(e) => {
+            return Err(format!(
+                "run_router_discovery: unable to create channel: {}",
+                e
+            ))
+        }
+    };
+    Ok(router::run_router_discovery(
+        &mut tx,
+        &mut rx,
+        setting,
+        trusted_routers,
+        msg_tx,
+    ))
+}
+
+/// LLDP and CDP listener.
+///
+/// Passively listens for link-layer discovery advertisements from the attached switch.
+/// Nothing is transmitted.
+#[derive(Clone, Debug)]
+pub struct LinkDiscoverer {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Return as soon as the first neighbor is found instead of waiting for the whole window
+    pub stop_on_first: bool,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<LinkNeighbor>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<LinkNeighbor>>>,
+}
+
+impl LinkDiscoverer {
+    /// Create new LinkDiscoverer instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<LinkDiscoverer, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "LinkDiscoverer::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let (tx, rx) = channel();
+        let discoverer = LinkDiscoverer {
+            probe_setting: setting,
+            stop_on_first: false,
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(discoverer)
+    }
+    /// Return as soon as the first neighbor is found
+    pub fn with_stop_on_first(mut self, stop_on_first: bool) -> LinkDiscoverer {
+        self.stop_on_first = stop_on_first;
+        self
+    }
+    /// Listen for LLDP and CDP advertisements
+    pub fn discover(&self) -> Result<LinkDiscoveryResult, String> {
+        run_link_discovery(&self.probe_setting, self.stop_on_first, &self.tx)
+    }
+    /// Get progress receiver
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<LinkNeighbor>>> {
+        self.rx.clone()
+    }
+}
+
+fn run_link_discovery(
+    setting: &ProbeSetting,
+    stop_on_first: bool,
+    msg_tx: &Arc<Mutex<Sender<LinkNeighbor>>>,
+) -> Result<LinkDiscoveryResult, String> {
+    let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
+        Some(interface) => interface,
+        None => {
+            return Err(format!(
+                "run_link_discovery: unable to get interface by index {}",
+                setting.if_index
+            ))
+        }
+    };
+    let config = xenet::datalink::Config {
+        write_buffer_size: 4096,
+        read_buffer_size: 4096,
+        read_timeout: Some(setting.receive_timeout),
+        write_timeout: None,
+        channel_type: xenet::datalink::ChannelType::Layer2,
+        bpf_fd_attempts: 1000,
+        linux_fanout: None,
+        // LLDP/CDP multicast groups are not joined by the host, so the NIC may filter them out
+        promiscuous: true,
+    };
+    // Create a channel to send/receive packet
+    let (mut tx, mut rx) = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+        Ok(_) => return Err("run_link_discovery: unable to create channel".to_string()),
+        Err(e) => {
+            return Err(format!(
+                "run_link_discovery: unable to create channel: {}",
+                e
+            ))
+        }
+    };
+    Ok(lldp::run_link_discovery(
+        &mut tx,
+        &mut rx,
+        setting,
+        stop_on_first,
+        msg_tx,
+    ))
+}
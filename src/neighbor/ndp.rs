@@ -69,6 +69,9 @@ pub(crate) fn run_ndp(
                                                 node_type: NodeType::Destination,
                                                 sent_packet_size: ndp_packet.len(),
                                                 received_packet_size: packet.len(),
+                                                reply_frame: None,
+                                                unreachable_reason: None,
+                                                service: None,
                                             };
                                             responses.push(probe_result.clone());
                                             match msg_tx.lock() {
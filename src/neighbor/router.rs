@@ -0,0 +1,320 @@
+use crate::result::{PrefixInfo, ProbeStatus, RouterDiscoveryResult, RouterInfo, RouterPreference};
+use crate::setting::{ProbeSetting, Protocol};
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use xenet::datalink::{DataLinkReceiver, DataLinkSender};
+use xenet::net::mac::MacAddr;
+use xenet::packet::frame::{Frame, ParseOption};
+use xenet::packet::icmpv6::Icmpv6Type;
+
+const NDP_OPT_SOURCE_LL_ADDR: u8 = 1;
+const NDP_OPT_PREFIX_INFORMATION: u8 = 3;
+const NDP_OPT_MTU: u8 = 5;
+const NDP_OPT_RDNSS: u8 = 25;
+const NDP_OPT_DNSSL: u8 = 31;
+
+const RA_FLAG_MANAGED: u8 = 0b1000_0000;
+const RA_FLAG_OTHER: u8 = 0b0100_0000;
+const PREFIX_FLAG_ON_LINK: u8 = 0b1000_0000;
+const PREFIX_FLAG_AUTONOMOUS: u8 = 0b0100_0000;
+
+pub(crate) fn run_router_discovery(
+    tx: &mut Box<dyn DataLinkSender>,
+    rx: &mut Box<dyn DataLinkReceiver>,
+    setting: &ProbeSetting,
+    trusted_routers: &[IpAddr],
+    msg_tx: &Arc<Mutex<Sender<RouterInfo>>>,
+) -> RouterDiscoveryResult {
+    let mut result = RouterDiscoveryResult::new();
+    result.protocol = Protocol::NDP;
+    let parse_option: ParseOption = ParseOption::default();
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let mut routers: Vec<RouterInfo> = Vec::new();
+    for seq in 1..setting.count + 1 {
+        let rs_packet: Vec<u8> = crate::packet::ndp::build_router_solicit_packet(setting.clone());
+        let send_time = Instant::now();
+        let _ = tx.send(&rs_packet);
+        // Unlike NS/NA, every router on the link may answer a single solicitation.
+        // Keep listening for the whole receive window.
+        loop {
+            if let Ok(packet) = rx.next() {
+                let recv_time: Duration = Instant::now().duration_since(send_time);
+                let frame: Frame = Frame::from_bytes(packet, parse_option.clone());
+                if let Some(mut router) = parse_router_advert_frame(&frame, setting) {
+                    router.rtt = recv_time;
+                    router.rogue = router.rogue
+                        || (!trusted_routers.is_empty()
+                            && !trusted_routers.contains(&router.ip_addr));
+                    match routers
+                        .iter_mut()
+                        .find(|r| r.ip_addr == router.ip_addr && r.mac_addr == router.mac_addr)
+                    {
+                        Some(known) => {
+                            router.advertisement_count = known.advertisement_count + 1;
+                            router.rtt = known.rtt;
+                            *known = router;
+                        }
+                        None => {
+                            routers.push(router.clone());
+                            if let Ok(lr) = msg_tx.lock() {
+                                let _ = lr.send(router);
+                            }
+                        }
+                    }
+                }
+            }
+            let wait_time: Duration = Instant::now().duration_since(send_time);
+            if wait_time > setting.receive_timeout {
+                break;
+            }
+        }
+        if seq < setting.count {
+            std::thread::sleep(setting.send_rate);
+        }
+    }
+    let probe_time = Instant::now().duration_since(start_time);
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = probe_time;
+    result.multiple_routers = routers.len() > 1;
+    result.routers = routers;
+    result.probe_status = ProbeStatus::new();
+    result
+}
+
+fn parse_router_advert_frame(frame: &Frame, setting: &ProbeSetting) -> Option<RouterInfo> {
+    let ethernet_header = frame.datalink.as_ref()?.ethernet.as_ref()?;
+    let ip_layer = frame.ip.as_ref()?;
+    let ipv6_header = ip_layer.ipv6.as_ref()?;
+    let icmpv6_header = ip_layer.icmpv6.as_ref()?;
+    if icmpv6_header.icmpv6_type != Icmpv6Type::RouterAdvertisement {
+        return None;
+    }
+    // Solicited RAs are sent to all-nodes multicast or unicast to the soliciting host.
+    let all_nodes: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+    if ipv6_header.destination != all_nodes && IpAddr::V6(ipv6_header.destination) != setting.src_ip
+    {
+        return None;
+    }
+    let mut router = parse_router_advert(&frame.payload)?;
+    router.mac_addr = ethernet_header.source;
+    router.ip_addr = IpAddr::V6(ipv6_header.source);
+    router.ttl = ipv6_header.hop_limit;
+    // RFC 4861 § 6.1.2: source must be link-local and hop limit must be 255
+    let link_local: bool = ipv6_header.source.segments()[0] & 0xffc0 == 0xfe80;
+    router.rogue = !link_local || ipv6_header.hop_limit != u8::MAX;
+    if let Some(ll_addr) = router.link_layer_addr {
+        if ll_addr != ethernet_header.source {
+            router.rogue = true;
+        }
+    }
+    Some(router)
+}
+
+/// Parse Router Advertisement body (after ICMPv6 type, code and checksum)
+pub(crate) fn parse_router_advert(payload: &[u8]) -> Option<RouterInfo> {
+    if payload.len() < 12 {
+        return None;
+    }
+    let mut router = RouterInfo {
+        mac_addr: MacAddr::zero(),
+        ip_addr: IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        link_layer_addr: None,
+        ttl: 0,
+        cur_hop_limit: payload[0],
+        managed: payload[1] & RA_FLAG_MANAGED != 0,
+        other_config: payload[1] & RA_FLAG_OTHER != 0,
+        preference: RouterPreference::from_flags(payload[1]),
+        router_lifetime: u16::from_be_bytes([payload[2], payload[3]]),
+        reachable_time: u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]),
+        retrans_timer: u32::from_be_bytes([payload[8], payload[9], payload[10], payload[11]]),
+        mtu: None,
+        prefixes: Vec::new(),
+        dns_servers: Vec::new(),
+        search_domains: Vec::new(),
+        advertisement_count: 1,
+        rtt: Duration::from_millis(0),
+        rogue: false,
+    };
+    let mut options: &[u8] = &payload[12..];
+    while options.len() >= 2 {
+        let option_type: u8 = options[0];
+        let option_len: usize = options[1] as usize * 8;
+        if option_len == 0 || option_len > options.len() {
+            break;
+        }
+        let data: &[u8] = &options[2..option_len];
+        match option_type {
+            NDP_OPT_SOURCE_LL_ADDR if data.len() >= 6 => {
+                let mut octets: [u8; 6] = [0; 6];
+                octets.copy_from_slice(&data[0..6]);
+                router.link_layer_addr = Some(MacAddr::from_octets(octets));
+            }
+            NDP_OPT_PREFIX_INFORMATION if data.len() >= 30 => {
+                let mut prefix: [u8; 16] = [0; 16];
+                prefix.copy_from_slice(&data[14..30]);
+                router.prefixes.push(PrefixInfo {
+                    prefix: Ipv6Addr::from(prefix),
+                    prefix_len: data[0],
+                    on_link: data[1] & PREFIX_FLAG_ON_LINK != 0,
+                    autonomous: data[1] & PREFIX_FLAG_AUTONOMOUS != 0,
+                    valid_lifetime: u32::from_be_bytes([data[2], data[3], data[4], data[5]]),
+                    preferred_lifetime: u32::from_be_bytes([data[6], data[7], data[8], data[9]]),
+                });
+            }
+            NDP_OPT_MTU if data.len() >= 6 => {
+                router.mtu = Some(u32::from_be_bytes([data[2], data[3], data[4], data[5]]));
+            }
+            // Reserved(2) + Lifetime(4) + Addresses
+            NDP_OPT_RDNSS if data.len() >= 6 => {
+                for addr in data[6..].chunks_exact(16) {
+                    let mut octets: [u8; 16] = [0; 16];
+                    octets.copy_from_slice(addr);
+                    router.dns_servers.push(Ipv6Addr::from(octets));
+                }
+            }
+            NDP_OPT_DNSSL if data.len() >= 6 => {
+                router.search_domains.extend(parse_domain_names(&data[6..]));
+            }
+            _ => {}
+        }
+        options = &options[option_len..];
+    }
+    Some(router)
+}
+
+/// Parse DNS-encoded domain names padded with zeros (RFC 8106 § 5.2)
+pub(crate) fn parse_domain_names(data: &[u8]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut labels: Vec<String> = Vec::new();
+    let mut i: usize = 0;
+    while i < data.len() {
+        let label_len: usize = data[i] as usize;
+        i += 1;
+        if label_len == 0 {
+            if labels.is_empty() {
+                // Padding
+                break;
+            }
+            names.push(labels.join("."));
+            labels.clear();
+            continue;
+        }
+        if i + label_len > data.len() {
+            break;
+        }
+        labels.push(String::from_utf8_lossy(&data[i..i + label_len]).to_string());
+        i += label_len;
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Router Advertisement body: hop limit 64, M and O flags, high preference,
+    /// lifetime 1800 s, reachable time 30000 ms, retrans timer 1000 ms
+    const RA_HEADER: [u8; 12] = [64, 0xc8, 0x07, 0x08, 0, 0, 0x75, 0x30, 0, 0, 0x03, 0xe8];
+
+    fn advert(options: &[&[u8]]) -> Vec<u8> {
+        let mut payload: Vec<u8> = RA_HEADER.to_vec();
+        for option in options {
+            payload.extend_from_slice(option);
+        }
+        payload
+    }
+
+    #[test]
+    fn parse_router_advert_header() {
+        let router = parse_router_advert(&advert(&[])).unwrap();
+        assert_eq!(router.cur_hop_limit, 64);
+        assert!(router.managed);
+        assert!(router.other_config);
+        assert_eq!(router.preference, RouterPreference::High);
+        assert_eq!(router.router_lifetime, 1800);
+        assert_eq!(router.reachable_time, 30000);
+        assert_eq!(router.retrans_timer, 1000);
+        assert!(!router.rogue);
+        assert!(parse_router_advert(&RA_HEADER[..11]).is_none());
+    }
+
+    #[test]
+    fn parse_router_advert_options() {
+        let source_ll_addr: [u8; 8] = [1, 1, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+        let mtu: [u8; 8] = [5, 1, 0, 0, 0, 0, 0x05, 0xdc];
+        let mut prefix: Vec<u8> = vec![3, 4, 64, 0xc0];
+        prefix.extend_from_slice(&2592000u32.to_be_bytes());
+        prefix.extend_from_slice(&604800u32.to_be_bytes());
+        prefix.extend_from_slice(&[0; 4]);
+        prefix.extend_from_slice(&"2001:db8:1::".parse::<Ipv6Addr>().unwrap().octets());
+        let mut rdnss: Vec<u8> = vec![25, 5, 0, 0, 0, 0, 0x0e, 0x10];
+        rdnss.extend_from_slice(&"2001:db8::53".parse::<Ipv6Addr>().unwrap().octets());
+        rdnss.extend_from_slice(&"2001:db8::54".parse::<Ipv6Addr>().unwrap().octets());
+        let mut dnssl: Vec<u8> = vec![31, 4, 0, 0, 0, 0, 0x0e, 0x10];
+        dnssl.extend_from_slice(b"\x07example\x03com\x00\x03lan\x00\x00\x00\x00\x00\x00\x00");
+        let unknown: [u8; 8] = [200, 1, 0, 0, 0, 0, 0, 0];
+        let payload: Vec<u8> = advert(&[&source_ll_addr, &mtu, &prefix, &rdnss, &dnssl, &unknown]);
+
+        let router = parse_router_advert(&payload).unwrap();
+        assert_eq!(
+            router.link_layer_addr,
+            Some(MacAddr::new(0x00, 0x11, 0x22, 0x33, 0x44, 0x55))
+        );
+        assert_eq!(router.mtu, Some(1500));
+        assert_eq!(
+            router.prefixes,
+            vec![PrefixInfo {
+                prefix: "2001:db8:1::".parse().unwrap(),
+                prefix_len: 64,
+                on_link: true,
+                autonomous: true,
+                valid_lifetime: 2592000,
+                preferred_lifetime: 604800,
+            }]
+        );
+        assert_eq!(
+            router.dns_servers,
+            vec![
+                "2001:db8::53".parse::<Ipv6Addr>().unwrap(),
+                "2001:db8::54".parse::<Ipv6Addr>().unwrap()
+            ]
+        );
+        assert_eq!(router.search_domains, vec!["example.com", "lan"]);
+    }
+
+    #[test]
+    fn parse_router_advert_bad_option_length() {
+        let mtu: [u8; 8] = [5, 1, 0, 0, 0, 0, 0x05, 0xdc];
+        // Zero length and a length past the end stop option parsing
+        for bad in [
+            &[1u8, 0, 0, 0, 0, 0, 0, 0][..],
+            &[1, 3, 0, 0, 0, 0, 0, 0][..],
+        ] {
+            let router = parse_router_advert(&advert(&[bad, &mtu])).unwrap();
+            assert_eq!(router.link_layer_addr, None);
+            assert_eq!(router.mtu, None);
+        }
+        // Options too short for their type are skipped
+        let short_prefix: [u8; 8] = [3, 1, 64, 0xc0, 0, 0, 0, 0];
+        let router = parse_router_advert(&advert(&[&short_prefix, &mtu])).unwrap();
+        assert!(router.prefixes.is_empty());
+        assert_eq!(router.mtu, Some(1500));
+    }
+
+    #[test]
+    fn parse_domain_name_labels() {
+        assert_eq!(
+            parse_domain_names(b"\x03www\x07example\x03com\x00\x00\x00"),
+            vec!["www.example.com"]
+        );
+        assert_eq!(parse_domain_names(b"\x01a\x00\x01b\x00"), vec!["a", "b"]);
+        assert!(parse_domain_names(b"").is_empty());
+        assert!(parse_domain_names(b"\x00\x03lan\x00").is_empty());
+        // Unterminated name and a label past the end are dropped
+        assert!(parse_domain_names(b"\x03lan").is_empty());
+        assert_eq!(parse_domain_names(b"\x03lan\x00\x09short"), vec!["lan"]);
+    }
+}
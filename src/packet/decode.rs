@@ -0,0 +1,347 @@
+use crate::result::UnreachableReason;
+use crate::setting::ProbeSetting;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use xenet::packet::ethernet::{EtherType, ETHERNET_HEADER_LEN};
+use xenet::packet::frame::{Frame, ParseOption};
+use xenet::packet::PrimitiveValues;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// ICMPv4 Destination Unreachable, Source Quench, Redirect, Time Exceeded and Parameter Problem
+pub const ICMPV4_ERROR_TYPES: [u8; 5] = [3, 4, 5, 11, 12];
+/// ICMPv6 Destination Unreachable, Packet Too Big, Time Exceeded and Parameter Problem
+pub const ICMPV6_ERROR_TYPES: [u8; 4] = [1, 2, 3, 4];
+
+const ICMPV4_UNREACHABLE: u8 = 3;
+const ICMPV4_FRAGMENTATION_NEEDED: u8 = 4;
+const ICMPV4_REDIRECT: u8 = 5;
+const ICMPV4_PARAMETER_PROBLEM: u8 = 12;
+const ICMPV6_PACKET_TOO_BIG: u8 = 2;
+pub(crate) const ICMPV6_PARAMETER_PROBLEM: u8 = 4;
+pub(crate) const ICMPV6_ECHO_REQUEST: u8 = 128;
+
+/// Unused (or MTU, pointer, gateway) field preceding the quoted datagram
+const ICMP_ERROR_UNUSED_LEN: usize = 4;
+
+const IPV4_MIN_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+
+/// IEEE 802.1Q tag between the source MAC address and the EtherType
+const VLAN_TAG_LEN: usize = 4;
+
+const NEXT_HEADER_HOP_BY_HOP: u8 = 0;
+pub(crate) const NEXT_HEADER_ICMP: u8 = 1;
+pub(crate) const NEXT_HEADER_TCP: u8 = 6;
+pub(crate) const NEXT_HEADER_UDP: u8 = 17;
+const NEXT_HEADER_ROUTING: u8 = 43;
+const NEXT_HEADER_FRAGMENT: u8 = 44;
+pub(crate) const NEXT_HEADER_ICMPV6: u8 = 58;
+pub(crate) const NEXT_HEADER_DEST_OPTS: u8 = 60;
+
+/// Parse a received packet the same way probes in this crate do.
+/// Tunnel interfaces deliver IP packets, loopback on some platforms adds a 14-byte header.
+pub fn parse_frame(packet: &[u8], setting: &ProbeSetting) -> Frame {
+    let mut parse_option: ParseOption = ParseOption::default();
+    if setting.tunnel {
+        parse_option.from_ip_packet = true;
+        parse_option.offset = if setting.loopback { 14 } else { 0 };
+    }
+    Frame::from_bytes(packet, parse_option)
+}
+
+/// Parse a packet starting at the IP header
+pub fn parse_ip_packet(packet: &[u8]) -> Frame {
+    let parse_option: ParseOption = ParseOption {
+        from_ip_packet: true,
+        ..Default::default()
+    };
+    Frame::from_bytes(packet, parse_option)
+}
+
+/// Split a raw Ethernet frame into its EtherType (or 802.3 length) and payload.
+/// One 802.1Q tag is skipped.
+pub(crate) fn ethernet_payload(packet: &[u8]) -> Option<(u16, &[u8])> {
+    if packet.len() < ETHERNET_HEADER_LEN {
+        return None;
+    }
+    let mut offset: usize = 12;
+    let mut ether_type: u16 = u16::from_be_bytes([packet[offset], packet[offset + 1]]);
+    if EtherType::new(ether_type) == EtherType::Vlan
+        && packet.len() >= ETHERNET_HEADER_LEN + VLAN_TAG_LEN
+    {
+        offset += VLAN_TAG_LEN;
+        ether_type = u16::from_be_bytes([packet[offset], packet[offset + 1]]);
+    }
+    Some((ether_type, &packet[offset + 2..]))
+}
+
+/// Original datagram quoted inside an ICMP or ICMPv6 error.
+/// Routers usually quote only the IP header and the first 8 bytes of payload,
+/// so transport fields beyond the ports may be missing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct QuotedDatagram {
+    pub src_ip: IpAddr,
+    pub dst_ip: IpAddr,
+    /// Protocol of the quoted payload, after IPv6 extension headers
+    pub next_protocol: u8,
+    /// TTL or hop limit when the datagram reached the reporting node
+    pub ttl: u8,
+    /// IPv4 identification
+    pub ip_id: Option<u16>,
+    /// IPv4 total length, or payload length plus header for IPv6
+    pub total_length: u16,
+    /// IPv6 flow label
+    pub flow_label: Option<u32>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    pub tcp_seq: Option<u32>,
+    pub udp_length: Option<u16>,
+    pub icmp_type: Option<u8>,
+    pub icmp_id: Option<u16>,
+    pub icmp_seq: Option<u16>,
+    /// Quoted bytes, starting at the IP header
+    pub data: Vec<u8>,
+}
+
+impl QuotedDatagram {
+    /// Parse quoted bytes starting at the IP header
+    pub fn from_bytes(data: &[u8]) -> Option<QuotedDatagram> {
+        let (mut quoted, transport_offset) = match data.first().map(|b| b >> 4) {
+            Some(4) => parse_quoted_ipv4(data)?,
+            Some(6) => parse_quoted_ipv6(data)?,
+            _ => return None,
+        };
+        let transport: &[u8] = &data[transport_offset.min(data.len())..];
+        match quoted.next_protocol {
+            NEXT_HEADER_TCP | NEXT_HEADER_UDP if transport.len() >= 4 => {
+                quoted.src_port = Some(u16::from_be_bytes([transport[0], transport[1]]));
+                quoted.dst_port = Some(u16::from_be_bytes([transport[2], transport[3]]));
+                if quoted.next_protocol == NEXT_HEADER_TCP && transport.len() >= 8 {
+                    quoted.tcp_seq = Some(u32::from_be_bytes([
+                        transport[4],
+                        transport[5],
+                        transport[6],
+                        transport[7],
+                    ]));
+                }
+                if quoted.next_protocol == NEXT_HEADER_UDP && transport.len() >= 6 {
+                    quoted.udp_length = Some(u16::from_be_bytes([transport[4], transport[5]]));
+                }
+            }
+            NEXT_HEADER_ICMP | NEXT_HEADER_ICMPV6 if !transport.is_empty() => {
+                quoted.icmp_type = Some(transport[0]);
+                if transport.len() >= 8 {
+                    quoted.icmp_id = Some(u16::from_be_bytes([transport[4], transport[5]]));
+                    quoted.icmp_seq = Some(u16::from_be_bytes([transport[6], transport[7]]));
+                }
+            }
+            _ => {}
+        }
+        quoted.data = data.to_vec();
+        Some(quoted)
+    }
+}
+
+/// ICMP or ICMPv6 error message with the datagram it refers to
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IcmpError {
+    /// Node that sent the error
+    pub src_ip: IpAddr,
+    pub icmp_type: u8,
+    pub icmp_code: u8,
+    /// Next-hop MTU of Fragmentation Needed or Packet Too Big
+    pub mtu: Option<u32>,
+    /// Offset of the offending byte in Parameter Problem
+    pub pointer: Option<u32>,
+    /// Gateway of an ICMPv4 Redirect
+    pub gateway: Option<Ipv4Addr>,
+    /// Original datagram, if enough of it was quoted
+    pub quoted: Option<QuotedDatagram>,
+}
+
+impl IcmpError {
+    /// Decode the ICMP error carried by `frame`. Returns None for other packets.
+    pub fn from_frame(frame: &Frame) -> Option<IcmpError> {
+        let ip_layer = frame.ip.as_ref()?;
+        let (src_ip, icmp_type, icmp_code, is_ipv6) = if let Some(icmp_header) = &ip_layer.icmp {
+            let ipv4_header = ip_layer.ipv4.as_ref()?;
+            (
+                IpAddr::V4(ipv4_header.source),
+                icmp_header.icmp_type.to_primitive_values().0,
+                icmp_header.icmp_code.0,
+                false,
+            )
+        } else if let Some(icmpv6_header) = &ip_layer.icmpv6 {
+            let ipv6_header = ip_layer.ipv6.as_ref()?;
+            (
+                IpAddr::V6(ipv6_header.source),
+                icmpv6_header.icmpv6_type.to_primitive_values().0,
+                icmpv6_header.icmpv6_code.0,
+                true,
+            )
+        } else {
+            return None;
+        };
+        if is_ipv6 && !ICMPV6_ERROR_TYPES.contains(&icmp_type) {
+            return None;
+        }
+        if !is_ipv6 && !ICMPV4_ERROR_TYPES.contains(&icmp_type) {
+            return None;
+        }
+        // frame.payload starts right after type, code and checksum
+        if frame.payload.len() < ICMP_ERROR_UNUSED_LEN {
+            return None;
+        }
+        let rest: [u8; 4] = [
+            frame.payload[0],
+            frame.payload[1],
+            frame.payload[2],
+            frame.payload[3],
+        ];
+        let mut error = IcmpError {
+            src_ip,
+            icmp_type,
+            icmp_code,
+            mtu: None,
+            pointer: None,
+            gateway: None,
+            quoted: QuotedDatagram::from_bytes(&frame.payload[ICMP_ERROR_UNUSED_LEN..]),
+        };
+        match (is_ipv6, icmp_type) {
+            (false, ICMPV4_UNREACHABLE) if icmp_code == ICMPV4_FRAGMENTATION_NEEDED => {
+                error.mtu = Some(u16::from_be_bytes([rest[2], rest[3]]) as u32);
+            }
+            (false, ICMPV4_REDIRECT) => {
+                error.gateway = Some(Ipv4Addr::from(rest));
+            }
+            (false, ICMPV4_PARAMETER_PROBLEM) => {
+                error.pointer = Some(rest[0] as u32);
+            }
+            (true, ICMPV6_PACKET_TOO_BIG) => {
+                error.mtu = Some(u32::from_be_bytes(rest));
+            }
+            (true, ICMPV6_PARAMETER_PROBLEM) => {
+                error.pointer = Some(u32::from_be_bytes(rest));
+            }
+            _ => {}
+        }
+        Some(error)
+    }
+    /// Destination Unreachable (ICMPv4 type 3 or ICMPv6 type 1)
+    pub fn is_unreachable(&self) -> bool {
+        match self.src_ip {
+            IpAddr::V4(_) => self.icmp_type == 3,
+            IpAddr::V6(_) => self.icmp_type == 1,
+        }
+    }
+    /// Reason of a Destination Unreachable, None for other errors
+    pub fn unreachable_reason(&self) -> Option<UnreachableReason> {
+        if !self.is_unreachable() {
+            return None;
+        }
+        match self.src_ip {
+            IpAddr::V4(_) => Some(UnreachableReason::from_icmpv4_code(self.icmp_code)),
+            IpAddr::V6(_) => Some(UnreachableReason::from_icmpv6_code(self.icmp_code)),
+        }
+    }
+    /// Time Exceeded (ICMPv4 type 11 or ICMPv6 type 3)
+    pub fn is_time_exceeded(&self) -> bool {
+        match self.src_ip {
+            IpAddr::V4(_) => self.icmp_type == 11,
+            IpAddr::V6(_) => self.icmp_type == 3,
+        }
+    }
+    /// Port Unreachable (ICMPv4 code 3 or ICMPv6 code 4)
+    pub fn is_port_unreachable(&self) -> bool {
+        self.is_unreachable()
+            && match self.src_ip {
+                IpAddr::V4(_) => self.icmp_code == 3,
+                IpAddr::V6(_) => self.icmp_code == 4,
+            }
+    }
+    /// Check that the quoted datagram was sent by `setting`
+    pub fn quotes_probe(&self, setting: &ProbeSetting) -> bool {
+        match &self.quoted {
+            Some(quoted) => quoted.src_ip == setting.src_ip && quoted.dst_ip == setting.dst_ip,
+            None => false,
+        }
+    }
+}
+
+/// Quoted IPv4 header fields and offset of the transport header
+fn parse_quoted_ipv4(data: &[u8]) -> Option<(QuotedDatagram, usize)> {
+    if data.len() < IPV4_MIN_HEADER_LEN {
+        return None;
+    }
+    let header_len: usize = ((data[0] & 0x0f) as usize) * 4;
+    if header_len < IPV4_MIN_HEADER_LEN {
+        return None;
+    }
+    let quoted = QuotedDatagram {
+        src_ip: IpAddr::V4(Ipv4Addr::new(data[12], data[13], data[14], data[15])),
+        dst_ip: IpAddr::V4(Ipv4Addr::new(data[16], data[17], data[18], data[19])),
+        next_protocol: data[9],
+        ttl: data[8],
+        ip_id: Some(u16::from_be_bytes([data[4], data[5]])),
+        total_length: u16::from_be_bytes([data[2], data[3]]),
+        flow_label: None,
+        src_port: None,
+        dst_port: None,
+        tcp_seq: None,
+        udp_length: None,
+        icmp_type: None,
+        icmp_id: None,
+        icmp_seq: None,
+        data: Vec::new(),
+    };
+    Some((quoted, header_len))
+}
+
+/// Quoted IPv6 header fields and offset of the transport header, after extension headers
+fn parse_quoted_ipv6(data: &[u8]) -> Option<(QuotedDatagram, usize)> {
+    if data.len() < IPV6_HEADER_LEN {
+        return None;
+    }
+    let mut src: [u8; 16] = [0u8; 16];
+    src.copy_from_slice(&data[8..24]);
+    let mut dst: [u8; 16] = [0u8; 16];
+    dst.copy_from_slice(&data[24..40]);
+    let mut next_header: u8 = data[6];
+    let mut offset: usize = IPV6_HEADER_LEN;
+    loop {
+        match next_header {
+            NEXT_HEADER_HOP_BY_HOP | NEXT_HEADER_ROUTING | NEXT_HEADER_DEST_OPTS
+                if data.len() >= offset + 2 =>
+            {
+                next_header = data[offset];
+                offset += (data[offset + 1] as usize + 1) * 8;
+            }
+            NEXT_HEADER_FRAGMENT if data.len() >= offset + 8 => {
+                next_header = data[offset];
+                offset += 8;
+            }
+            _ => break,
+        }
+    }
+    let quoted = QuotedDatagram {
+        src_ip: IpAddr::V6(Ipv6Addr::from(src)),
+        dst_ip: IpAddr::V6(Ipv6Addr::from(dst)),
+        next_protocol: next_header,
+        ttl: data[7],
+        ip_id: None,
+        total_length: u16::from_be_bytes([data[4], data[5]]).saturating_add(IPV6_HEADER_LEN as u16),
+        flow_label: Some(u32::from_be_bytes([0, data[1] & 0x0f, data[2], data[3]])),
+        src_port: None,
+        dst_port: None,
+        tcp_seq: None,
+        udp_length: None,
+        icmp_type: None,
+        icmp_id: None,
+        icmp_seq: None,
+        data: Vec::new(),
+    };
+    Some((quoted, offset))
+}
@@ -0,0 +1,169 @@
+use crate::setting::ProbeSetting;
+use std::net::{IpAddr, SocketAddr};
+use xenet::net::mac::MacAddr;
+use xenet::packet::ethernet::EtherType;
+use xenet::packet::ip::IpNextLevelProtocol;
+use xenet::packet::ipv4::IPV4_HEADER_LEN;
+use xenet::packet::udp::UDP_HEADER_LEN;
+use xenet::util::packet_builder::builder::PacketBuilder;
+use xenet::util::packet_builder::ethernet::EthernetPacketBuilder;
+use xenet::util::packet_builder::ipv4::Ipv4PacketBuilder;
+use xenet::util::packet_builder::ipv6::Ipv6PacketBuilder;
+use xenet::util::packet_builder::udp::UdpPacketBuilder;
+
+pub(crate) const DHCP_CLIENT_PORT: u16 = 68;
+pub(crate) const DHCP_SERVER_PORT: u16 = 67;
+pub(crate) const DHCPV6_CLIENT_PORT: u16 = 546;
+pub(crate) const DHCPV6_SERVER_PORT: u16 = 547;
+
+pub(crate) const DHCP_MAGIC_COOKIE: [u8; 4] = [0x63, 0x82, 0x53, 0x63];
+/// BOOTP fixed fields (op .. file)
+pub(crate) const DHCP_FIXED_LEN: usize = 236;
+/// Minimum BOOTP message size (RFC 1542)
+const DHCP_MIN_LEN: usize = 300;
+
+pub(crate) const DHCP_OPT_PAD: u8 = 0;
+pub(crate) const DHCP_OPT_SUBNET_MASK: u8 = 1;
+pub(crate) const DHCP_OPT_ROUTER: u8 = 3;
+pub(crate) const DHCP_OPT_DNS_SERVER: u8 = 6;
+pub(crate) const DHCP_OPT_DOMAIN_NAME: u8 = 15;
+pub(crate) const DHCP_OPT_LEASE_TIME: u8 = 51;
+pub(crate) const DHCP_OPT_MESSAGE_TYPE: u8 = 53;
+pub(crate) const DHCP_OPT_SERVER_ID: u8 = 54;
+const DHCP_OPT_PARAMETER_REQUEST_LIST: u8 = 55;
+const DHCP_OPT_CLIENT_ID: u8 = 61;
+pub(crate) const DHCP_OPT_END: u8 = 255;
+
+pub(crate) const DHCP_DISCOVER: u8 = 1;
+pub(crate) const DHCP_OFFER: u8 = 2;
+
+pub(crate) const DHCPV6_SOLICIT: u8 = 1;
+pub(crate) const DHCPV6_ADVERTISE: u8 = 2;
+
+pub(crate) const DHCPV6_OPT_CLIENT_ID: u16 = 1;
+pub(crate) const DHCPV6_OPT_SERVER_ID: u16 = 2;
+pub(crate) const DHCPV6_OPT_IA_NA: u16 = 3;
+pub(crate) const DHCPV6_OPT_IAADDR: u16 = 5;
+const DHCPV6_OPT_ORO: u16 = 6;
+const DHCPV6_OPT_ELAPSED_TIME: u16 = 8;
+pub(crate) const DHCPV6_OPT_DNS_SERVERS: u16 = 23;
+pub(crate) const DHCPV6_OPT_DOMAIN_LIST: u16 = 24;
+
+/// Build DHCPDISCOVER message (BOOTP header and options)
+pub fn build_dhcp_discover(src_mac: MacAddr, xid: u32) -> Vec<u8> {
+    let mut message: Vec<u8> = vec![0u8; DHCP_FIXED_LEN];
+    // op: BOOTREQUEST, htype: Ethernet, hlen: 6
+    message[0] = 1;
+    message[1] = 1;
+    message[2] = 6;
+    message[4..8].copy_from_slice(&xid.to_be_bytes());
+    // Ask for broadcast reply, we have no address yet
+    message[10..12].copy_from_slice(&0x8000u16.to_be_bytes());
+    message[28..34].copy_from_slice(&src_mac.octets());
+    message.extend_from_slice(&DHCP_MAGIC_COOKIE);
+    message.extend_from_slice(&[DHCP_OPT_MESSAGE_TYPE, 1, DHCP_DISCOVER]);
+    message.extend_from_slice(&[DHCP_OPT_CLIENT_ID, 7, 1]);
+    message.extend_from_slice(&src_mac.octets());
+    message.extend_from_slice(&[
+        DHCP_OPT_PARAMETER_REQUEST_LIST,
+        5,
+        DHCP_OPT_SUBNET_MASK,
+        DHCP_OPT_ROUTER,
+        DHCP_OPT_DNS_SERVER,
+        DHCP_OPT_DOMAIN_NAME,
+        DHCP_OPT_LEASE_TIME,
+    ]);
+    message.push(DHCP_OPT_END);
+    if message.len() < DHCP_MIN_LEN {
+        message.resize(DHCP_MIN_LEN, DHCP_OPT_PAD);
+    }
+    message
+}
+
+/// Build DHCPv6 Solicit message. Transaction ID is the lower 24 bits of xid.
+pub fn build_dhcpv6_solicit(src_mac: MacAddr, xid: u32) -> Vec<u8> {
+    let mut message: Vec<u8> = Vec::new();
+    message.push(DHCPV6_SOLICIT);
+    message.extend_from_slice(&xid.to_be_bytes()[1..4]);
+    // Client Identifier: DUID-LL (type 3, hardware type 1)
+    message.extend_from_slice(&DHCPV6_OPT_CLIENT_ID.to_be_bytes());
+    message.extend_from_slice(&10u16.to_be_bytes());
+    message.extend_from_slice(&3u16.to_be_bytes());
+    message.extend_from_slice(&1u16.to_be_bytes());
+    message.extend_from_slice(&src_mac.octets());
+    // Elapsed Time
+    message.extend_from_slice(&DHCPV6_OPT_ELAPSED_TIME.to_be_bytes());
+    message.extend_from_slice(&2u16.to_be_bytes());
+    message.extend_from_slice(&0u16.to_be_bytes());
+    // IA_NA: IAID, T1, T2
+    message.extend_from_slice(&DHCPV6_OPT_IA_NA.to_be_bytes());
+    message.extend_from_slice(&12u16.to_be_bytes());
+    message.extend_from_slice(&src_mac.octets()[2..6]);
+    message.extend_from_slice(&[0u8; 8]);
+    // Option Request: DNS servers, domain list
+    message.extend_from_slice(&DHCPV6_OPT_ORO.to_be_bytes());
+    message.extend_from_slice(&4u16.to_be_bytes());
+    message.extend_from_slice(&DHCPV6_OPT_DNS_SERVERS.to_be_bytes());
+    message.extend_from_slice(&DHCPV6_OPT_DOMAIN_LIST.to_be_bytes());
+    message
+}
+
+/// Build DHCPDISCOVER or DHCPv6 Solicit packet depending on the destination address family
+pub fn build_dhcp_packet(setting: ProbeSetting, xid: u32) -> Vec<u8> {
+    let mut packet_builder = PacketBuilder::new();
+
+    // Ethernet Header
+    let ethernet_packet_builder = EthernetPacketBuilder {
+        src_mac: setting.src_mac,
+        dst_mac: setting.dst_mac,
+        ether_type: match setting.dst_ip {
+            IpAddr::V4(_) => EtherType::Ipv4,
+            IpAddr::V6(_) => EtherType::Ipv6,
+        },
+    };
+    packet_builder.set_ethernet(ethernet_packet_builder);
+
+    match (setting.src_ip, setting.dst_ip) {
+        (IpAddr::V4(src_ipv4), IpAddr::V4(dst_ipv4)) => {
+            let payload: Vec<u8> = build_dhcp_discover(setting.src_mac, xid);
+            // IP Header
+            let mut ipv4_packet_builder =
+                Ipv4PacketBuilder::new(src_ipv4, dst_ipv4, IpNextLevelProtocol::Udp);
+            ipv4_packet_builder.ttl = Some(setting.hop_limit);
+            ipv4_packet_builder.total_length =
+                Some((IPV4_HEADER_LEN + UDP_HEADER_LEN + payload.len()) as u16);
+            packet_builder.set_ipv4(ipv4_packet_builder);
+            // UDP Header
+            let mut udp_packet_builder = UdpPacketBuilder::new(
+                SocketAddr::new(setting.src_ip, setting.src_port.unwrap_or(DHCP_CLIENT_PORT)),
+                SocketAddr::new(setting.dst_ip, setting.dst_port.unwrap_or(DHCP_SERVER_PORT)),
+            );
+            udp_packet_builder.payload = payload;
+            packet_builder.set_udp(udp_packet_builder);
+        }
+        (IpAddr::V6(src_ipv6), IpAddr::V6(dst_ipv6)) => {
+            let payload: Vec<u8> = build_dhcpv6_solicit(setting.src_mac, xid);
+            // IP Header
+            let mut ipv6_packet_builder =
+                Ipv6PacketBuilder::new(src_ipv6, dst_ipv6, IpNextLevelProtocol::Udp);
+            ipv6_packet_builder.hop_limit = Some(setting.hop_limit);
+            ipv6_packet_builder.payload_length = Some((UDP_HEADER_LEN + payload.len()) as u16);
+            packet_builder.set_ipv6(ipv6_packet_builder);
+            // UDP Header
+            let mut udp_packet_builder = UdpPacketBuilder::new(
+                SocketAddr::new(
+                    setting.src_ip,
+                    setting.src_port.unwrap_or(DHCPV6_CLIENT_PORT),
+                ),
+                SocketAddr::new(
+                    setting.dst_ip,
+                    setting.dst_port.unwrap_or(DHCPV6_SERVER_PORT),
+                ),
+            );
+            udp_packet_builder.payload = payload;
+            packet_builder.set_udp(udp_packet_builder);
+        }
+        _ => {}
+    }
+    packet_builder.packet()
+}
@@ -0,0 +1,143 @@
+use crate::packet::decode::{ICMPV6_ECHO_REQUEST, NEXT_HEADER_DEST_OPTS, NEXT_HEADER_ICMPV6};
+use crate::setting::ProbeSetting;
+use std::net::{IpAddr, Ipv6Addr};
+use xenet::packet::ethernet::{EtherType, ETHERNET_HEADER_LEN};
+use xenet::packet::icmpv6::Icmpv6Packet;
+use xenet::packet::ipv6::IPV6_HEADER_LEN;
+use xenet::util::packet_builder::builder::PacketBuilder;
+use xenet::util::packet_builder::ethernet::EthernetPacketBuilder;
+
+/// Flow label set on IPv6 fingerprint probes (20 bits)
+pub(crate) const PROBE_FLOW_LABEL: u32 = 0x5eed5;
+
+const ICMPV6_NODE_INFO_QUERY: u8 = 139;
+/// Subject of the query is an IPv6 address
+const NODE_INFO_CODE_IPV6_SUBJECT: u8 = 0;
+const NODE_INFO_QTYPE_NODE_NAME: u16 = 2;
+/// Type, code, checksum, qtype, flags and nonce
+const NODE_INFO_HEADER_LEN: usize = 16;
+
+/// Unknown option type. The high-order bits (10) ask the receiver to discard
+/// the packet and send ICMPv6 Parameter Problem, code 2.
+const UNKNOWN_DEST_OPTION_TYPE: u8 = 0x9e;
+/// Destination Options header with one 4-byte unknown option
+const DEST_OPTS_HEADER_LEN: usize = 8;
+const ICMPV6_ECHO_LEN: usize = 8;
+
+/// Build IPv6 header with flow label
+pub fn build_ipv6_header(
+    src_ip: Ipv6Addr,
+    dst_ip: Ipv6Addr,
+    next_header: u8,
+    payload_length: u16,
+    hop_limit: u8,
+    flow_label: u32,
+) -> Vec<u8> {
+    let mut header: Vec<u8> = vec![0u8; IPV6_HEADER_LEN];
+    let first_word: u32 = (6 << 28) | (flow_label & 0xfffff);
+    header[0..4].copy_from_slice(&first_word.to_be_bytes());
+    header[4..6].copy_from_slice(&payload_length.to_be_bytes());
+    header[6] = next_header;
+    header[7] = hop_limit;
+    header[8..24].copy_from_slice(&src_ip.octets());
+    header[24..40].copy_from_slice(&dst_ip.octets());
+    header
+}
+
+/// Overwrite flow label of IPv6 header at `ip_offset`. Traffic class is kept.
+pub fn set_ipv6_flow_label(packet: &mut [u8], ip_offset: usize, flow_label: u32) {
+    if packet.len() < ip_offset + 4 || packet[ip_offset] >> 4 != 6 {
+        return;
+    }
+    packet[ip_offset + 1] = (packet[ip_offset + 1] & 0xf0) | ((flow_label >> 16) & 0x0f) as u8;
+    packet[ip_offset + 2] = (flow_label >> 8) as u8;
+    packet[ip_offset + 3] = flow_label as u8;
+}
+
+/// Build ICMPv6 Node Information Query (RFC 4620) for the node name of the target
+pub fn build_node_info_query_packet(setting: ProbeSetting) -> Vec<u8> {
+    let (src_ipv6, dst_ipv6) = match (setting.src_ip, setting.dst_ip) {
+        (IpAddr::V6(src_ipv6), IpAddr::V6(dst_ipv6)) => (src_ipv6, dst_ipv6),
+        _ => return Vec::new(),
+    };
+    let mut query: Vec<u8> = vec![0u8; NODE_INFO_HEADER_LEN];
+    query[0] = ICMPV6_NODE_INFO_QUERY;
+    query[1] = NODE_INFO_CODE_IPV6_SUBJECT;
+    query[4..6].copy_from_slice(&NODE_INFO_QTYPE_NODE_NAME.to_be_bytes());
+    query[8..16].copy_from_slice(&rand::random::<u64>().to_be_bytes());
+    query.extend_from_slice(&dst_ipv6.octets());
+    set_icmpv6_checksum(&mut query, &src_ipv6, &dst_ipv6);
+    let ipv6_header: Vec<u8> = build_ipv6_header(
+        src_ipv6,
+        dst_ipv6,
+        NEXT_HEADER_ICMPV6,
+        query.len() as u16,
+        setting.hop_limit,
+        PROBE_FLOW_LABEL,
+    );
+    build_ipv6_frame(&setting, ipv6_header, query)
+}
+
+/// Build ICMPv6 Echo Request behind a Destination Options header carrying an unknown option
+pub fn build_echo_dest_opts_packet(setting: ProbeSetting) -> Vec<u8> {
+    let (src_ipv6, dst_ipv6) = match (setting.src_ip, setting.dst_ip) {
+        (IpAddr::V6(src_ipv6), IpAddr::V6(dst_ipv6)) => (src_ipv6, dst_ipv6),
+        _ => return Vec::new(),
+    };
+    let mut payload: Vec<u8> = vec![0u8; DEST_OPTS_HEADER_LEN];
+    payload[0] = NEXT_HEADER_ICMPV6;
+    // Header length in 8-octet units, not including the first 8 octets
+    payload[1] = 0;
+    payload[2] = UNKNOWN_DEST_OPTION_TYPE;
+    payload[3] = 4;
+    let mut echo: Vec<u8> = vec![0u8; ICMPV6_ECHO_LEN];
+    echo[0] = ICMPV6_ECHO_REQUEST;
+    echo[4..6].copy_from_slice(&rand::random::<u16>().to_be_bytes());
+    echo[6..8].copy_from_slice(&1u16.to_be_bytes());
+    set_icmpv6_checksum(&mut echo, &src_ipv6, &dst_ipv6);
+    payload.extend_from_slice(&echo);
+    let ipv6_header: Vec<u8> = build_ipv6_header(
+        src_ipv6,
+        dst_ipv6,
+        NEXT_HEADER_DEST_OPTS,
+        payload.len() as u16,
+        setting.hop_limit,
+        PROBE_FLOW_LABEL,
+    );
+    build_ipv6_frame(&setting, ipv6_header, payload)
+}
+
+/// Offset of the IP header in packets built for this setting
+pub(crate) fn ip_offset(setting: &ProbeSetting) -> usize {
+    if setting.tunnel {
+        0
+    } else {
+        ETHERNET_HEADER_LEN
+    }
+}
+
+fn set_icmpv6_checksum(icmpv6: &mut [u8], src_ipv6: &Ipv6Addr, dst_ipv6: &Ipv6Addr) {
+    let checksum: u16 = match Icmpv6Packet::new(icmpv6) {
+        Some(icmpv6_packet) => xenet::packet::icmpv6::checksum(&icmpv6_packet, src_ipv6, dst_ipv6),
+        None => 0,
+    };
+    icmpv6[2..4].copy_from_slice(&checksum.to_be_bytes());
+}
+
+fn build_ipv6_frame(setting: &ProbeSetting, ipv6_header: Vec<u8>, payload: Vec<u8>) -> Vec<u8> {
+    let mut packet: Vec<u8> = if setting.tunnel {
+        Vec::new()
+    } else {
+        let mut packet_builder = PacketBuilder::new();
+        let ethernet_packet_builder = EthernetPacketBuilder {
+            src_mac: setting.src_mac,
+            dst_mac: setting.dst_mac,
+            ether_type: EtherType::Ipv6,
+        };
+        packet_builder.set_ethernet(ethernet_packet_builder);
+        packet_builder.packet()
+    };
+    packet.extend_from_slice(&ipv6_header);
+    packet.extend_from_slice(&payload);
+    packet
+}
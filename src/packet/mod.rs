@@ -1,6 +1,12 @@
 pub mod arp;
+pub mod decode;
+pub mod dhcp;
 pub mod icmp;
+pub mod icmpv6;
 pub mod ndp;
 pub mod tcp;
+pub mod template;
 pub mod udp;
+pub mod wol;
 pub use xenet::packet::frame;
+
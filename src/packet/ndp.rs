@@ -4,7 +4,11 @@ use crate::setting::ProbeSetting;
 use xenet::net::mac::MacAddr;
 use xenet::packet::ethernet::EtherType;
 use xenet::packet::ethernet::MAC_ADDR_LEN;
-use xenet::packet::icmpv6::ndp::{NDP_OPT_PACKET_LEN, NDP_SOL_PACKET_LEN};
+use xenet::packet::icmpv6::ndp::{
+    MutableNdpOptionPacket, MutableRouterSolicitPacket, NdpOptionTypes, NDP_OPT_PACKET_LEN,
+    NDP_SOL_PACKET_LEN,
+};
+use xenet::packet::icmpv6::{Icmpv6Packet, Icmpv6Type};
 use xenet::packet::ip::IpNextLevelProtocol;
 use xenet::util::packet_builder::builder::PacketBuilder;
 use xenet::util::packet_builder::ethernet::EthernetPacketBuilder;
@@ -44,3 +48,57 @@ pub fn build_ndp_packet(setting: ProbeSetting) -> Vec<u8> {
     }
     packet_builder.packet()
 }
+
+/// Router Solicitation message length (without options)
+pub(crate) const NDP_RS_PACKET_LEN: usize = 8;
+
+/// Build Router Solicitation packet with Source Link-Layer Address option
+pub fn build_router_solicit_packet(setting: ProbeSetting) -> Vec<u8> {
+    let mut packet_builder = PacketBuilder::new();
+    let ethernet_packet_builder = EthernetPacketBuilder {
+        src_mac: setting.src_mac,
+        dst_mac: setting.dst_mac,
+        ether_type: EtherType::Ipv6,
+    };
+    packet_builder.set_ethernet(ethernet_packet_builder);
+
+    let rs_packet_len: usize = NDP_RS_PACKET_LEN + NDP_OPT_PACKET_LEN + MAC_ADDR_LEN;
+    let mut rs_buffer: Vec<u8> = vec![0u8; rs_packet_len];
+    match setting.src_ip {
+        IpAddr::V4(_) => {}
+        IpAddr::V6(src_ipv6) => {
+            match setting.dst_ip {
+                IpAddr::V4(_) => {}
+                IpAddr::V6(dst_ipv6) => {
+                    // IPv6 Header
+                    let mut ipv6_packet_builder =
+                        Ipv6PacketBuilder::new(src_ipv6, dst_ipv6, IpNextLevelProtocol::Icmpv6);
+                    ipv6_packet_builder.payload_length = Some(rs_packet_len as u16);
+                    ipv6_packet_builder.hop_limit = Some(u8::MAX);
+                    packet_builder.set_ipv6(ipv6_packet_builder);
+                    // Router Solicitation
+                    if let Some(mut rs_packet) = MutableRouterSolicitPacket::new(&mut rs_buffer) {
+                        rs_packet.set_icmpv6_type(Icmpv6Type::RouterSolicitation);
+                        if let Some(mut opt_packet) =
+                            MutableNdpOptionPacket::new(rs_packet.get_options_raw_mut())
+                        {
+                            opt_packet.set_option_type(NdpOptionTypes::SourceLLAddr);
+                            opt_packet.set_length(((NDP_OPT_PACKET_LEN + MAC_ADDR_LEN) / 8) as u8);
+                            opt_packet.set_data(&setting.src_mac.octets());
+                        }
+                    }
+                    let checksum: u16 = match Icmpv6Packet::new(&rs_buffer) {
+                        Some(icmpv6_packet) => {
+                            xenet::packet::icmpv6::checksum(&icmpv6_packet, &src_ipv6, &dst_ipv6)
+                        }
+                        None => 0,
+                    };
+                    rs_buffer[2..4].copy_from_slice(&checksum.to_be_bytes());
+                }
+            }
+        }
+    }
+    let mut packet: Vec<u8> = packet_builder.packet();
+    packet.extend_from_slice(&rs_buffer);
+    packet
+}
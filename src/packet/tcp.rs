@@ -1,17 +1,19 @@
 use crate::setting::ProbeSetting;
 use crate::fp::FingerprintType;
-use std::net::{IpAddr, SocketAddr};
-use xenet::packet::ethernet::EtherType;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use xenet::packet::ethernet::{EtherType, ETHERNET_HEADER_LEN};
 use xenet::packet::ip::IpNextLevelProtocol;
-use xenet::packet::tcp::{TcpFlags, TcpOption};
+use xenet::packet::ipv4::{Ipv4Packet, IPV4_HEADER_LEN};
+use xenet::packet::ipv6::IPV6_HEADER_LEN;
+use xenet::packet::tcp::{TcpFlags, TcpOption, TcpPacket, TCP_HEADER_LEN};
 use xenet::util::packet_builder::builder::PacketBuilder;
 use xenet::util::packet_builder::ethernet::EthernetPacketBuilder;
 use xenet::util::packet_builder::ipv4::Ipv4PacketBuilder;
 use xenet::util::packet_builder::ipv6::Ipv6PacketBuilder;
 use xenet::util::packet_builder::tcp::TcpPacketBuilder;
 
-pub(crate) const TCP_DEFAULT_SRC_PORT: u16 = 44322;
-pub(crate) const TCP_DEFAULT_DST_PORT: u16 = 80;
+pub const TCP_DEFAULT_SRC_PORT: u16 = 44322;
+pub const TCP_DEFAULT_DST_PORT: u16 = 80;
 
 /// Build TCP packet
 pub fn build_tcp_packet(setting: ProbeSetting, hop_limit: Option<u8>) -> Vec<u8> {
@@ -85,8 +87,67 @@ pub fn build_tcp_packet(setting: ProbeSetting, hop_limit: Option<u8>) -> Vec<u8>
     }
 }
 
+/// Rewrite destination address, destination port and sequence number of a packet built by
+/// [`build_tcp_packet`] and update the checksums. High-rate senders build the packet only once.
+pub fn rewrite_tcp_packet(
+    packet: &mut [u8],
+    tunnel: bool,
+    dst_ip: IpAddr,
+    dst_port: u16,
+    seq: u32,
+) -> Result<(), String> {
+    let ip_offset: usize = if tunnel { 0 } else { ETHERNET_HEADER_LEN };
+    let version: u8 = match packet.get(ip_offset) {
+        Some(b) => b >> 4,
+        None => return Err("rewrite_tcp_packet: packet too short".to_string()),
+    };
+    let tcp_offset: usize = match (dst_ip, version) {
+        (IpAddr::V4(dst_ipv4), 4) => {
+            let header_len: usize = (packet[ip_offset] & 0x0f) as usize * 4;
+            if header_len < IPV4_HEADER_LEN || packet.len() < ip_offset + header_len + TCP_HEADER_LEN {
+                return Err("rewrite_tcp_packet: packet too short".to_string());
+            }
+            let header: &mut [u8] = &mut packet[ip_offset..ip_offset + header_len];
+            header[16..20].copy_from_slice(&dst_ipv4.octets());
+            let checksum: u16 = match Ipv4Packet::new(header) {
+                Some(ipv4_packet) => xenet::packet::ipv4::checksum(&ipv4_packet),
+                None => return Err("rewrite_tcp_packet: invalid IPv4 header".to_string()),
+            };
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+            ip_offset + header_len
+        }
+        (IpAddr::V6(dst_ipv6), 6) => {
+            if packet.len() < ip_offset + IPV6_HEADER_LEN + TCP_HEADER_LEN {
+                return Err("rewrite_tcp_packet: packet too short".to_string());
+            }
+            packet[ip_offset + 24..ip_offset + 40].copy_from_slice(&dst_ipv6.octets());
+            ip_offset + IPV6_HEADER_LEN
+        }
+        _ => return Err("rewrite_tcp_packet: address family does not match the packet".to_string()),
+    };
+    let (ip_header, segment) = packet[ip_offset..].split_at_mut(tcp_offset - ip_offset);
+    segment[2..4].copy_from_slice(&dst_port.to_be_bytes());
+    segment[4..8].copy_from_slice(&seq.to_be_bytes());
+    let checksum: u16 = match (TcpPacket::new(segment), dst_ip) {
+        (Some(tcp_packet), IpAddr::V4(dst_ipv4)) => {
+            let src_ipv4: Ipv4Addr = Ipv4Addr::new(ip_header[12], ip_header[13], ip_header[14], ip_header[15]);
+            xenet::packet::tcp::ipv4_checksum(&tcp_packet, &src_ipv4, &dst_ipv4)
+        }
+        (Some(tcp_packet), IpAddr::V6(dst_ipv6)) => {
+            let mut src_octets: [u8; 16] = [0u8; 16];
+            src_octets.copy_from_slice(&ip_header[8..24]);
+            xenet::packet::tcp::ipv6_checksum(&tcp_packet, &Ipv6Addr::from(src_octets), &dst_ipv6)
+        }
+        (None, _) => return Err("rewrite_tcp_packet: invalid TCP header".to_string()),
+    };
+    segment[16..18].copy_from_slice(&checksum.to_be_bytes());
+    Ok(())
+}
+
 /// Build TCP probe packet
 pub fn build_tcp_probe_packet(setting: ProbeSetting, probe_type: FingerprintType) -> Vec<u8> {
+    let (flags, window, options) = tcp_probe_shape(probe_type);
+    let tcp_len: usize = TCP_HEADER_LEN + tcp_options_len(&options);
     let mut packet_builder = PacketBuilder::new();
 
     // Ethernet Header
@@ -107,7 +168,7 @@ pub fn build_tcp_probe_packet(setting: ProbeSetting, probe_type: FingerprintType
                 let mut ipv4_packet_builder =
                     Ipv4PacketBuilder::new(src_ipv4, dst_ipv4, IpNextLevelProtocol::Tcp);
                 ipv4_packet_builder.ttl = Some(setting.hop_limit);
-                ipv4_packet_builder.total_length = Some(64);
+                ipv4_packet_builder.total_length = Some((IPV4_HEADER_LEN + tcp_len) as u16);
                 packet_builder.set_ipv4(ipv4_packet_builder);
             }
             IpAddr::V6(_) => {}
@@ -118,7 +179,7 @@ pub fn build_tcp_probe_packet(setting: ProbeSetting, probe_type: FingerprintType
                 let mut ipv6_packet_builder =
                     Ipv6PacketBuilder::new(src_ipv4, dst_ipv6, IpNextLevelProtocol::Tcp);
                 ipv6_packet_builder.hop_limit = Some(setting.hop_limit);
-                ipv6_packet_builder.payload_length = Some(44);
+                ipv6_packet_builder.payload_length = Some(tcp_len as u16);
                 packet_builder.set_ipv6(ipv6_packet_builder);
             }
         },
@@ -134,30 +195,9 @@ pub fn build_tcp_probe_packet(setting: ProbeSetting, probe_type: FingerprintType
             setting.dst_port.unwrap_or(TCP_DEFAULT_DST_PORT),
         ),
     );
-    match probe_type {
-        FingerprintType::TcpSynAck => {
-            tcp_packet_builder.flags = TcpFlags::SYN;
-        }
-        FingerprintType::TcpRstAck => {
-            tcp_packet_builder.flags = TcpFlags::SYN;
-        }
-        FingerprintType::TcpEcn => {
-            tcp_packet_builder.flags = TcpFlags::CWR | TcpFlags::ECE | TcpFlags::SYN;
-        }
-        _ => {
-            tcp_packet_builder.flags = TcpFlags::SYN;
-        }
-    }
-    tcp_packet_builder.window = 65535;
-    tcp_packet_builder.options = vec![
-        TcpOption::mss(1460),
-        TcpOption::nop(),
-        TcpOption::wscale(6),
-        TcpOption::nop(),
-        TcpOption::nop(),
-        TcpOption::timestamp(u32::MAX, u32::MIN),
-        TcpOption::sack_perm(),
-    ];
+    tcp_packet_builder.flags = flags;
+    tcp_packet_builder.window = window;
+    tcp_packet_builder.options = options;
     packet_builder.set_tcp(tcp_packet_builder);
 
     if setting.tunnel {
@@ -167,6 +207,116 @@ pub fn build_tcp_probe_packet(setting: ProbeSetting, probe_type: FingerprintType
     }
 }
 
+/// Flags, window and options of a TCP fingerprint probe
+fn tcp_probe_shape(probe_type: FingerprintType) -> (u8, u16, Vec<TcpOption>) {
+    // Options of the Nmap T2-T7 probes
+    let nmap_options = |wscale: u8| {
+        vec![
+            TcpOption::wscale(wscale),
+            TcpOption::nop(),
+            TcpOption::mss(265),
+            TcpOption::timestamp(u32::MAX, u32::MIN),
+            TcpOption::sack_perm(),
+        ]
+    };
+    match probe_type {
+        FingerprintType::TcpSeq(n) => tcp_seq_probe_shape(n),
+        FingerprintType::TcpNull => (0, 128, nmap_options(10)),
+        FingerprintType::TcpFin => (TcpFlags::FIN, 256, nmap_options(10)),
+        FingerprintType::TcpXmas => (
+            TcpFlags::FIN | TcpFlags::PSH | TcpFlags::URG,
+            65535,
+            nmap_options(15),
+        ),
+        FingerprintType::TcpAckOpen => (TcpFlags::ACK, 1024, nmap_options(10)),
+        FingerprintType::TcpAckClosed => (TcpFlags::ACK, 32768, nmap_options(10)),
+        _ => {
+            let flags: u8 = match probe_type {
+                FingerprintType::TcpEcn => TcpFlags::CWR | TcpFlags::ECE | TcpFlags::SYN,
+                _ => TcpFlags::SYN,
+            };
+            let options = vec![
+                TcpOption::mss(1460),
+                TcpOption::nop(),
+                TcpOption::wscale(6),
+                TcpOption::nop(),
+                TcpOption::nop(),
+                TcpOption::timestamp(u32::MAX, u32::MIN),
+                TcpOption::sack_perm(),
+            ];
+            (flags, 65535, options)
+        }
+    }
+}
+
+/// Window and options of the Nmap SEQ probes. `n` is 1-6.
+fn tcp_seq_probe_shape(n: u8) -> (u8, u16, Vec<TcpOption>) {
+    let (window, options) = match n {
+        2 => (
+            63,
+            vec![
+                TcpOption::mss(1400),
+                TcpOption::wscale(0),
+                TcpOption::sack_perm(),
+                TcpOption::timestamp(u32::MAX, u32::MIN),
+            ],
+        ),
+        3 => (
+            4,
+            vec![
+                TcpOption::timestamp(u32::MAX, u32::MIN),
+                TcpOption::nop(),
+                TcpOption::nop(),
+                TcpOption::wscale(5),
+                TcpOption::nop(),
+                TcpOption::mss(640),
+            ],
+        ),
+        4 => (
+            4,
+            vec![
+                TcpOption::sack_perm(),
+                TcpOption::timestamp(u32::MAX, u32::MIN),
+                TcpOption::wscale(10),
+            ],
+        ),
+        5 => (
+            16,
+            vec![
+                TcpOption::mss(536),
+                TcpOption::sack_perm(),
+                TcpOption::timestamp(u32::MAX, u32::MIN),
+                TcpOption::wscale(10),
+            ],
+        ),
+        6 => (
+            512,
+            vec![
+                TcpOption::mss(265),
+                TcpOption::sack_perm(),
+                TcpOption::timestamp(u32::MAX, u32::MIN),
+            ],
+        ),
+        _ => (
+            1,
+            vec![
+                TcpOption::wscale(10),
+                TcpOption::nop(),
+                TcpOption::mss(1460),
+                TcpOption::timestamp(u32::MAX, u32::MIN),
+                TcpOption::sack_perm(),
+            ],
+        ),
+    };
+    (TcpFlags::SYN, window, options)
+}
+
+/// Length of TCP options padded to 4 bytes
+fn tcp_options_len(options: &[TcpOption]) -> usize {
+    let len: usize = options.iter().map(|option| option.kind().size()).sum();
+    len.div_ceil(4) * 4
+}
+
 #[allow(dead_code)]
 pub fn build_tcp_control_packet(probe_setting: ProbeSetting, tcp_flags: u8) -> Vec<u8> {
     let mut packet_builder = PacketBuilder::new();
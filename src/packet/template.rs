@@ -0,0 +1,418 @@
+use crate::packet::decode::{
+    ICMPV6_ECHO_REQUEST, NEXT_HEADER_ICMP, NEXT_HEADER_ICMPV6, NEXT_HEADER_TCP, NEXT_HEADER_UDP,
+};
+use crate::setting::{ProbeSetting, Protocol};
+use crate::template::ProbeTemplate;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use xenet::packet::ethernet::EtherType;
+use xenet::packet::icmp::IcmpPacket;
+use xenet::packet::icmpv6::Icmpv6Packet;
+use xenet::packet::ipv4::{Ipv4Packet, IPV4_HEADER_LEN};
+use xenet::packet::tcp::{TcpPacket, TCP_HEADER_LEN};
+use xenet::packet::udp::{UdpPacket, UDP_HEADER_LEN};
+use xenet::util::packet_builder::builder::PacketBuilder;
+use xenet::util::packet_builder::ethernet::EthernetPacketBuilder;
+
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_HEADER_LEN: usize = 8;
+
+/// Don't Fragment bit of the IPv4 flags and fragment offset field
+const IPV4_FLAG_DF: u16 = 0x4000;
+
+/// Build packet described by a probe template
+pub fn build_template_packet(
+    setting: &ProbeSetting,
+    template: &ProbeTemplate,
+) -> Result<Vec<u8>, String> {
+    let hop_limit: u8 = template.ttl.unwrap_or(setting.hop_limit);
+    let ip_packet: Vec<u8> = match (setting.src_ip, setting.dst_ip) {
+        (IpAddr::V4(src_ipv4), IpAddr::V4(dst_ipv4)) => {
+            let (next_header, transport) = build_transport(
+                template,
+                setting,
+                &IpAddr::V4(src_ipv4),
+                &IpAddr::V4(dst_ipv4),
+            )?;
+            let mut packet: Vec<u8> = build_ipv4_header(
+                template,
+                src_ipv4,
+                dst_ipv4,
+                next_header,
+                transport.len(),
+                hop_limit,
+            )?;
+            packet.extend_from_slice(&transport);
+            packet
+        }
+        (IpAddr::V6(src_ipv6), IpAddr::V6(dst_ipv6)) => {
+            let (next_header, transport) = build_transport(
+                template,
+                setting,
+                &IpAddr::V6(src_ipv6),
+                &IpAddr::V6(dst_ipv6),
+            )?;
+            if transport.len() > u16::MAX as usize {
+                return Err("build_template_packet: payload too large".to_string());
+            }
+            let mut packet: Vec<u8> = crate::packet::icmpv6::build_ipv6_header(
+                src_ipv6,
+                dst_ipv6,
+                next_header,
+                transport.len() as u16,
+                hop_limit,
+                template.flow_label,
+            );
+            // Traffic class sits between version and flow label
+            packet[0] = 0x60 | (template.tos >> 4);
+            packet[1] = (template.tos << 4) | (packet[1] & 0x0f);
+            packet.extend_from_slice(&transport);
+            packet
+        }
+        _ => {
+            return Err(
+                "build_template_packet: source and destination address family mismatch".to_string(),
+            )
+        }
+    };
+    if setting.tunnel {
+        return Ok(ip_packet);
+    }
+    let mut packet_builder = PacketBuilder::new();
+    let ethernet_packet_builder = EthernetPacketBuilder {
+        src_mac: setting.src_mac,
+        dst_mac: setting.dst_mac,
+        ether_type: match setting.dst_ip {
+            IpAddr::V4(_) => EtherType::Ipv4,
+            IpAddr::V6(_) => EtherType::Ipv6,
+        },
+    };
+    packet_builder.set_ethernet(ethernet_packet_builder);
+    let mut packet: Vec<u8> = packet_builder.packet();
+    packet.extend_from_slice(&ip_packet);
+    Ok(packet)
+}
+
+fn build_ipv4_header(
+    template: &ProbeTemplate,
+    src_ip: Ipv4Addr,
+    dst_ip: Ipv4Addr,
+    next_header: u8,
+    payload_len: usize,
+    ttl: u8,
+) -> Result<Vec<u8>, String> {
+    let total_length: usize = IPV4_HEADER_LEN + payload_len;
+    if total_length > u16::MAX as usize {
+        return Err("build_template_packet: payload too large".to_string());
+    }
+    let mut header: Vec<u8> = vec![0u8; IPV4_HEADER_LEN];
+    header[0] = 0x45;
+    header[1] = template.tos;
+    header[2..4].copy_from_slice(&(total_length as u16).to_be_bytes());
+    header[4..6].copy_from_slice(
+        &template
+            .ip_id
+            .unwrap_or(rand::random::<u16>())
+            .to_be_bytes(),
+    );
+    if template.dont_fragment {
+        header[6..8].copy_from_slice(&IPV4_FLAG_DF.to_be_bytes());
+    }
+    header[8] = ttl;
+    header[9] = next_header;
+    header[12..16].copy_from_slice(&src_ip.octets());
+    header[16..20].copy_from_slice(&dst_ip.octets());
+    let checksum: u16 = match Ipv4Packet::new(&header) {
+        Some(ipv4_packet) => xenet::packet::ipv4::checksum(&ipv4_packet),
+        None => 0,
+    };
+    header[10..12].copy_from_slice(&checksum.to_be_bytes());
+    Ok(header)
+}
+
+/// Transport header and payload, with the IP next header value
+fn build_transport(
+    template: &ProbeTemplate,
+    setting: &ProbeSetting,
+    src_ip: &IpAddr,
+    dst_ip: &IpAddr,
+) -> Result<(u8, Vec<u8>), String> {
+    match template.protocol {
+        Protocol::TCP => Ok((
+            NEXT_HEADER_TCP,
+            build_tcp_segment(template, setting, src_ip, dst_ip)?,
+        )),
+        Protocol::UDP => Ok((
+            NEXT_HEADER_UDP,
+            build_udp_datagram(template, setting, src_ip, dst_ip)?,
+        )),
+        Protocol::ICMP => match (src_ip, dst_ip) {
+            (IpAddr::V6(src_ipv6), IpAddr::V6(dst_ipv6)) => Ok((
+                NEXT_HEADER_ICMPV6,
+                build_icmpv6_message(template, src_ipv6, dst_ipv6),
+            )),
+            _ => Ok((NEXT_HEADER_ICMP, build_icmp_message(template))),
+        },
+        _ => Err("build_template_packet: template protocol must be TCP, UDP or ICMP".to_string()),
+    }
+}
+
+fn build_tcp_segment(
+    template: &ProbeTemplate,
+    setting: &ProbeSetting,
+    src_ip: &IpAddr,
+    dst_ip: &IpAddr,
+) -> Result<Vec<u8>, String> {
+    let mut options: Vec<u8> = Vec::new();
+    for option in &template.tcp_options {
+        options.extend_from_slice(&option.to_bytes());
+    }
+    // Pad with EOL to a 32-bit boundary
+    options.resize(options.len().next_multiple_of(4), 0);
+    let header_len: usize = TCP_HEADER_LEN + options.len();
+    if header_len > 60 {
+        return Err("build_template_packet: TCP options exceed 40 bytes".to_string());
+    }
+    let mut segment: Vec<u8> = vec![0u8; TCP_HEADER_LEN];
+    segment[0..2].copy_from_slice(&template.get_src_port(setting).to_be_bytes());
+    segment[2..4].copy_from_slice(&template.get_dst_port(setting).to_be_bytes());
+    segment[4..8].copy_from_slice(&template.tcp_seq.to_be_bytes());
+    segment[8..12].copy_from_slice(&template.tcp_ack.to_be_bytes());
+    segment[12] = ((header_len / 4) as u8) << 4;
+    segment[13] = template.tcp_flags;
+    segment[14..16].copy_from_slice(&template.tcp_window.to_be_bytes());
+    segment[18..20].copy_from_slice(&template.tcp_urgent_ptr.to_be_bytes());
+    segment.extend_from_slice(&options);
+    segment.extend_from_slice(&template.payload);
+    let checksum: u16 = match TcpPacket::new(&segment) {
+        Some(tcp_packet) => match (src_ip, dst_ip) {
+            (IpAddr::V4(src_ipv4), IpAddr::V4(dst_ipv4)) => {
+                xenet::packet::tcp::ipv4_checksum(&tcp_packet, src_ipv4, dst_ipv4)
+            }
+            (IpAddr::V6(src_ipv6), IpAddr::V6(dst_ipv6)) => {
+                xenet::packet::tcp::ipv6_checksum(&tcp_packet, src_ipv6, dst_ipv6)
+            }
+            _ => 0,
+        },
+        None => 0,
+    };
+    segment[16..18].copy_from_slice(&checksum.to_be_bytes());
+    Ok(segment)
+}
+
+fn build_udp_datagram(
+    template: &ProbeTemplate,
+    setting: &ProbeSetting,
+    src_ip: &IpAddr,
+    dst_ip: &IpAddr,
+) -> Result<Vec<u8>, String> {
+    let length: usize = UDP_HEADER_LEN + template.payload.len();
+    if length > u16::MAX as usize {
+        return Err("build_template_packet: payload too large".to_string());
+    }
+    let mut datagram: Vec<u8> = vec![0u8; UDP_HEADER_LEN];
+    datagram[0..2].copy_from_slice(&template.get_src_port(setting).to_be_bytes());
+    datagram[2..4].copy_from_slice(&template.get_dst_port(setting).to_be_bytes());
+    datagram[4..6].copy_from_slice(&(length as u16).to_be_bytes());
+    datagram.extend_from_slice(&template.payload);
+    let checksum: u16 = match UdpPacket::new(&datagram) {
+        Some(udp_packet) => match (src_ip, dst_ip) {
+            (IpAddr::V4(src_ipv4), IpAddr::V4(dst_ipv4)) => {
+                xenet::packet::udp::ipv4_checksum(&udp_packet, src_ipv4, dst_ipv4)
+            }
+            (IpAddr::V6(src_ipv6), IpAddr::V6(dst_ipv6)) => {
+                xenet::packet::udp::ipv6_checksum(&udp_packet, src_ipv6, dst_ipv6)
+            }
+            _ => 0,
+        },
+        None => 0,
+    };
+    datagram[6..8].copy_from_slice(&checksum.to_be_bytes());
+    Ok(datagram)
+}
+
+/// Type, code, checksum, identifier, sequence number and payload
+fn build_icmp_header(template: &ProbeTemplate, default_type: u8) -> Vec<u8> {
+    let mut message: Vec<u8> = vec![0u8; ICMP_HEADER_LEN];
+    message[0] = template.icmp_type.unwrap_or(default_type);
+    message[1] = template.icmp_code;
+    message[4..6].copy_from_slice(&template.icmp_id.to_be_bytes());
+    message[6..8].copy_from_slice(&template.icmp_seq.to_be_bytes());
+    message.extend_from_slice(&template.payload);
+    message
+}
+
+fn build_icmp_message(template: &ProbeTemplate) -> Vec<u8> {
+    let mut message: Vec<u8> = build_icmp_header(template, ICMP_ECHO_REQUEST);
+    let checksum: u16 = match IcmpPacket::new(&message) {
+        Some(icmp_packet) => xenet::packet::icmp::checksum(&icmp_packet),
+        None => 0,
+    };
+    message[2..4].copy_from_slice(&checksum.to_be_bytes());
+    message
+}
+
+fn build_icmpv6_message(template: &ProbeTemplate, src_ip: &Ipv6Addr, dst_ip: &Ipv6Addr) -> Vec<u8> {
+    let mut message: Vec<u8> = build_icmp_header(template, ICMPV6_ECHO_REQUEST);
+    let checksum: u16 = match Icmpv6Packet::new(&message) {
+        Some(icmpv6_packet) => xenet::packet::icmpv6::checksum(&icmpv6_packet, src_ip, dst_ip),
+        None => 0,
+    };
+    message[2..4].copy_from_slice(&checksum.to_be_bytes());
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::TemplateTcpOption;
+
+    fn setting(src_ip: IpAddr, dst_ip: IpAddr) -> ProbeSetting {
+        ProbeSetting {
+            src_ip,
+            dst_ip,
+            src_port: Some(40000),
+            dst_port: Some(80),
+            tunnel: true,
+            ..ProbeSetting::new()
+        }
+    }
+
+    fn ipv4_setting() -> ProbeSetting {
+        setting(
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+        )
+    }
+
+    fn ipv6_setting() -> ProbeSetting {
+        setting(
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2)),
+        )
+    }
+
+    /// Internet checksum over all parts. Zero when the checksums in them are correct.
+    fn checksum(parts: &[&[u8]]) -> u16 {
+        let bytes: Vec<u8> = parts.concat();
+        let mut sum: u32 = bytes
+            .chunks(2)
+            .map(|word| u16::from_be_bytes([word[0], *word.get(1).unwrap_or(&0)]) as u32)
+            .sum();
+        while sum > 0xffff {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+        !(sum as u16)
+    }
+
+    fn ipv4_pseudo_header(packet: &[u8], protocol: u8) -> Vec<u8> {
+        let mut header: Vec<u8> = packet[12..20].to_vec();
+        header.extend_from_slice(&[0, protocol]);
+        header.extend_from_slice(&((packet.len() - IPV4_HEADER_LEN) as u16).to_be_bytes());
+        header
+    }
+
+    fn ipv6_pseudo_header(packet: &[u8], next_header: u8) -> Vec<u8> {
+        let mut header: Vec<u8> = packet[8..40].to_vec();
+        header.extend_from_slice(&((packet.len() - 40) as u32).to_be_bytes());
+        header.extend_from_slice(&[0, 0, 0, next_header]);
+        header
+    }
+
+    #[test]
+    fn tcp_options_are_padded() {
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::TCP).with_tcp_options(vec![
+            TemplateTcpOption::Mss(1460),
+            TemplateTcpOption::WindowScale(7),
+        ]);
+        let packet: Vec<u8> = build_template_packet(&ipv4_setting(), &template).unwrap();
+        let segment: &[u8] = &packet[IPV4_HEADER_LEN..];
+        // 7 bytes of options padded to 8
+        assert_eq!(segment[12] >> 4, 7);
+        assert_eq!(segment.len(), TCP_HEADER_LEN + 8);
+        assert_eq!(&segment[TCP_HEADER_LEN..], &[2, 4, 0x05, 0xb4, 3, 3, 7, 0]);
+        // Already aligned options are not padded
+        let template: ProbeTemplate = template.with_tcp_options(vec![TemplateTcpOption::Mss(1460)]);
+        let packet: Vec<u8> = build_template_packet(&ipv4_setting(), &template).unwrap();
+        assert_eq!(packet.len(), IPV4_HEADER_LEN + TCP_HEADER_LEN + 4);
+    }
+
+    #[test]
+    fn tcp_options_over_40_bytes() {
+        let options: Vec<TemplateTcpOption> = vec![TemplateTcpOption::Nop; 41];
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::TCP).with_tcp_options(options);
+        assert!(build_template_packet(&ipv4_setting(), &template).is_err());
+        let options: Vec<TemplateTcpOption> = vec![TemplateTcpOption::Nop; 40];
+        let template: ProbeTemplate = template.with_tcp_options(options);
+        assert!(build_template_packet(&ipv4_setting(), &template).is_ok());
+    }
+
+    #[test]
+    fn ipv4_checksums() {
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::TCP)
+            .with_tcp_options(vec![TemplateTcpOption::Timestamp(1, 0)])
+            .with_payload(vec![1, 2, 3]);
+        let packet: Vec<u8> = build_template_packet(&ipv4_setting(), &template).unwrap();
+        assert_eq!(checksum(&[&packet[..IPV4_HEADER_LEN]]), 0);
+        let pseudo_header: Vec<u8> = ipv4_pseudo_header(&packet, NEXT_HEADER_TCP);
+        assert_eq!(checksum(&[&pseudo_header, &packet[IPV4_HEADER_LEN..]]), 0);
+
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::UDP).with_payload(vec![1; 5]);
+        let packet: Vec<u8> = build_template_packet(&ipv4_setting(), &template).unwrap();
+        let pseudo_header: Vec<u8> = ipv4_pseudo_header(&packet, NEXT_HEADER_UDP);
+        assert_eq!(checksum(&[&pseudo_header, &packet[IPV4_HEADER_LEN..]]), 0);
+
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::ICMP).with_icmp_id(7);
+        let packet: Vec<u8> = build_template_packet(&ipv4_setting(), &template).unwrap();
+        assert_eq!(packet[IPV4_HEADER_LEN], ICMP_ECHO_REQUEST);
+        assert_eq!(checksum(&[&packet[IPV4_HEADER_LEN..]]), 0);
+    }
+
+    #[test]
+    fn ipv6_checksums() {
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::TCP).with_payload(vec![1; 3]);
+        let packet: Vec<u8> = build_template_packet(&ipv6_setting(), &template).unwrap();
+        let pseudo_header: Vec<u8> = ipv6_pseudo_header(&packet, NEXT_HEADER_TCP);
+        assert_eq!(checksum(&[&pseudo_header, &packet[40..]]), 0);
+
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::ICMP).with_payload(vec![1; 3]);
+        let packet: Vec<u8> = build_template_packet(&ipv6_setting(), &template).unwrap();
+        assert_eq!(packet[6], NEXT_HEADER_ICMPV6);
+        assert_eq!(packet[40], ICMPV6_ECHO_REQUEST);
+        let pseudo_header: Vec<u8> = ipv6_pseudo_header(&packet, NEXT_HEADER_ICMPV6);
+        assert_eq!(checksum(&[&pseudo_header, &packet[40..]]), 0);
+    }
+
+    #[test]
+    fn ipv6_traffic_class() {
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::UDP)
+            .with_tos(0xb8)
+            .with_flow_label(0x12345);
+        let packet: Vec<u8> = build_template_packet(&ipv6_setting(), &template).unwrap();
+        let first_word: u32 = u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
+        assert_eq!(first_word >> 28, 6);
+        assert_eq!((first_word >> 20) & 0xff, 0xb8);
+        assert_eq!(first_word & 0xfffff, 0x12345);
+    }
+
+    #[test]
+    fn ipv4_header_fields() {
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::UDP)
+            .with_tos(0xb8)
+            .with_ttl(3)
+            .with_ip_id(0x1234)
+            .with_dont_fragment(true);
+        let packet: Vec<u8> = build_template_packet(&ipv4_setting(), &template).unwrap();
+        assert_eq!(packet[1], 0xb8);
+        assert_eq!(&packet[4..8], &[0x12, 0x34, 0x40, 0x00]);
+        assert_eq!(packet[8], 3);
+        assert_eq!(packet[9], NEXT_HEADER_UDP);
+    }
+
+    #[test]
+    fn address_family_mismatch() {
+        let mut setting: ProbeSetting = ipv4_setting();
+        setting.dst_ip = ipv6_setting().dst_ip;
+        let template: ProbeTemplate = ProbeTemplate::new(Protocol::UDP);
+        assert!(build_template_packet(&setting, &template).is_err());
+    }
+}
@@ -8,8 +8,8 @@ use xenet::util::packet_builder::ipv4::Ipv4PacketBuilder;
 use xenet::util::packet_builder::ipv6::Ipv6PacketBuilder;
 use xenet::util::packet_builder::udp::UdpPacketBuilder;
 
-pub(crate) const UDP_DEFAULT_SRC_PORT: u16 = 53445;
-pub(crate) const UDP_BASE_DST_PORT: u16 = 33435;
+pub const UDP_DEFAULT_SRC_PORT: u16 = 53445;
+pub const UDP_BASE_DST_PORT: u16 = 33435;
 
 /// Build UDP packet
 pub fn build_udp_packet(setting: ProbeSetting, hop_limit: Option<u8>) -> Vec<u8> {
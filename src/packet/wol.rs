@@ -0,0 +1,87 @@
+use crate::setting::ProbeSetting;
+use std::net::{IpAddr, SocketAddr};
+use xenet::net::mac::MacAddr;
+use xenet::packet::ethernet::EtherType;
+use xenet::packet::ip::IpNextLevelProtocol;
+use xenet::packet::ipv4::IPV4_HEADER_LEN;
+use xenet::packet::udp::UDP_HEADER_LEN;
+use xenet::util::packet_builder::builder::PacketBuilder;
+use xenet::util::packet_builder::ethernet::EthernetPacketBuilder;
+use xenet::util::packet_builder::ipv4::Ipv4PacketBuilder;
+use xenet::util::packet_builder::udp::UdpPacketBuilder;
+
+/// Discard port, commonly used for Wake-on-LAN
+pub(crate) const WOL_DEFAULT_DST_PORT: u16 = 9;
+pub(crate) const WOL_DEFAULT_SRC_PORT: u16 = 40000;
+
+/// Build magic packet payload: 6 bytes of 0xFF, target MAC 16 times, then SecureOn password
+pub fn build_magic_packet(target_mac: MacAddr, password: Option<&[u8]>) -> Vec<u8> {
+    let mut payload: Vec<u8> = vec![0xff; 6];
+    for _ in 0..16 {
+        payload.extend_from_slice(&target_mac.octets());
+    }
+    if let Some(password) = password {
+        payload.extend_from_slice(password);
+    }
+    payload
+}
+
+/// Build Wake-on-LAN Ethernet frame (ethertype 0x0842)
+pub fn build_wol_ethernet_packet(
+    setting: ProbeSetting,
+    target_mac: MacAddr,
+    password: Option<&[u8]>,
+) -> Vec<u8> {
+    let mut packet_builder = PacketBuilder::new();
+    let ethernet_packet_builder = EthernetPacketBuilder {
+        src_mac: setting.src_mac,
+        dst_mac: setting.dst_mac,
+        ether_type: EtherType::WakeOnLan,
+    };
+    packet_builder.set_ethernet(ethernet_packet_builder);
+    let mut packet: Vec<u8> = packet_builder.packet();
+    packet.extend_from_slice(&build_magic_packet(target_mac, password));
+    packet
+}
+
+/// Build Wake-on-LAN UDP broadcast packet
+pub fn build_wol_udp_packet(
+    setting: ProbeSetting,
+    target_mac: MacAddr,
+    password: Option<&[u8]>,
+) -> Vec<u8> {
+    let mut packet_builder = PacketBuilder::new();
+
+    // Ethernet Header
+    let ethernet_packet_builder = EthernetPacketBuilder {
+        src_mac: setting.src_mac,
+        dst_mac: setting.dst_mac,
+        ether_type: EtherType::Ipv4,
+    };
+    packet_builder.set_ethernet(ethernet_packet_builder);
+
+    if let (IpAddr::V4(src_ipv4), IpAddr::V4(dst_ipv4)) = (setting.src_ip, setting.dst_ip) {
+        let payload: Vec<u8> = build_magic_packet(target_mac, password);
+        // IP Header
+        let mut ipv4_packet_builder =
+            Ipv4PacketBuilder::new(src_ipv4, dst_ipv4, IpNextLevelProtocol::Udp);
+        ipv4_packet_builder.ttl = Some(setting.hop_limit);
+        ipv4_packet_builder.total_length =
+            Some((IPV4_HEADER_LEN + UDP_HEADER_LEN + payload.len()) as u16);
+        packet_builder.set_ipv4(ipv4_packet_builder);
+        // UDP Header
+        let mut udp_packet_builder = UdpPacketBuilder::new(
+            SocketAddr::new(
+                setting.src_ip,
+                setting.src_port.unwrap_or(WOL_DEFAULT_SRC_PORT),
+            ),
+            SocketAddr::new(
+                setting.dst_ip,
+                setting.dst_port.unwrap_or(WOL_DEFAULT_DST_PORT),
+            ),
+        );
+        udp_packet_builder.payload = payload;
+        packet_builder.set_udp(udp_packet_builder);
+    }
+    packet_builder.packet()
+}
@@ -72,6 +72,9 @@ pub(crate) fn icmp_ping(
                                         node_type: NodeType::Destination,
                                         sent_packet_size: icmp_packet.len(),
                                         received_packet_size: packet.len(),
+                                        reply_frame: None,
+                                        unreachable_reason: None,
+                                        service: None,
                                     };
                                     responses.push(probe_result.clone());
                                     match msg_tx.lock() {
@@ -109,6 +112,9 @@ pub(crate) fn icmp_ping(
                                         node_type: NodeType::Destination,
                                         sent_packet_size: icmp_packet.len(),
                                         received_packet_size: packet.len(),
+                                        reply_frame: None,
+                                        unreachable_reason: None,
+                                        service: None,
                                     };
                                     responses.push(probe_result.clone());
                                     match msg_tx.lock() {
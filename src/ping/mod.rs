@@ -1,20 +1,24 @@
 pub(crate) mod icmp;
 pub(crate) mod tcp;
+pub(crate) mod template;
 pub(crate) mod udp;
 
 use crate::result::{PingResult, ProbeResult};
 use crate::setting::ProbeSetting;
+use crate::template::ProbeTemplate;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use xenet::net::interface::Interface;
 
 /// Pinger structure.
 ///
-/// Supports ICMP Ping, TCP Ping, UDP Ping.
+/// Supports ICMP Ping, TCP Ping, UDP Ping and user-defined probe templates.
 #[derive(Clone, Debug)]
 pub struct Pinger {
     /// Probe Setting
     pub probe_setting: ProbeSetting,
+    /// Probe template sent instead of the built-in packet of `probe_setting.protocol`
+    pub template: Option<ProbeTemplate>,
     /// Sender for progress messaging
     tx: Arc<Mutex<Sender<ProbeResult>>>,
     /// Receiver for progress messaging
@@ -36,14 +40,21 @@ impl Pinger {
         let (tx, rx) = channel();
         let pinger = Pinger {
             probe_setting: setting,
+            template: None,
             tx: Arc::new(Mutex::new(tx)),
             rx: Arc::new(Mutex::new(rx)),
         };
         return Ok(pinger);
     }
+    /// Send `template` instead of the built-in probe.
+    /// Each `ProbeResult` then carries the matched reply frame.
+    pub fn with_template(mut self, template: ProbeTemplate) -> Pinger {
+        self.template = Some(template);
+        self
+    }
     /// Run ping
     pub fn ping(&self) -> Result<PingResult, String> {
-        run_ping(&self.probe_setting, &self.tx)
+        run_ping(&self.probe_setting, &self.template, &self.tx)
     }
     /// Get progress receiver
     pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<ProbeResult>>> {
@@ -53,6 +64,7 @@ impl Pinger {
 
 fn run_ping(
     setting: &ProbeSetting,
+    template: &Option<ProbeTemplate>,
     msg_tx: &Arc<Mutex<Sender<ProbeResult>>>,
 ) -> Result<PingResult, String> {
     let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
@@ -80,6 +92,9 @@ fn run_ping(
         Ok(_) => return Err("run_ping: unable to create channel".to_string()),
         Err(e) => return Err(format!("run_ping: unable to create channel: {}", e)),
     };
+    if let Some(template) = template {
+        return template::template_ping(&mut tx, &mut rx, setting, template, msg_tx);
+    }
     match setting.protocol {
         crate::setting::Protocol::ICMP => {
             let result = icmp::icmp_ping(&mut tx, &mut rx, setting, msg_tx);
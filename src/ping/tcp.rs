@@ -1,3 +1,4 @@
+use crate::packet::decode::IcmpError;
 use crate::result::{NodeType, PingResult, PingStat, PortStatus, ProbeResult, ProbeStatus};
 use crate::setting::{ProbeSetting, Protocol};
 use std::net::IpAddr;
@@ -49,15 +50,56 @@ pub(crate) fn tcp_ping(
                     // So deep nested... but this is simplest way to check TCP packet safely.
                     if let Some(ip_layer) = &frame.ip {
                         if let Some(ipv4_header) = &ip_layer.ipv4 {
-                            if IpAddr::V4(ipv4_header.source) != setting.dst_ip || IpAddr::V4(ipv4_header.destination) != setting.src_ip {
+                            if IpAddr::V4(ipv4_header.destination) != setting.src_ip {
                                 continue;
                             }
                         }
                         if let Some(ipv6_header) = &ip_layer.ipv6 {
-                            if IpAddr::V6(ipv6_header.source) != setting.dst_ip || IpAddr::V6(ipv6_header.destination) != setting.src_ip {
+                            if IpAddr::V6(ipv6_header.destination) != setting.src_ip {
                                 continue;
                             }
                         }
+                        // Destination Unreachable about our probe, possibly sent by a router
+                        if let Some(icmp_error) = IcmpError::from_frame(&frame) {
+                            if !icmp_error.quotes_probe(setting) {
+                                continue;
+                            }
+                            let reason = match icmp_error.unreachable_reason() {
+                                Some(reason) => reason,
+                                None => continue,
+                            };
+                            let ttl: u8 = if let Some(ipv4_header) = &ip_layer.ipv4 {
+                                ipv4_header.ttl
+                            } else if let Some(ipv6_header) = &ip_layer.ipv6 {
+                                ipv6_header.hop_limit
+                            } else {
+                                0
+                            };
+                            let probe_result: ProbeResult = ProbeResult {
+                                seq,
+                                mac_addr,
+                                ip_addr: setting.dst_ip,
+                                host_name: setting.dst_hostname.clone(),
+                                port_number: setting.dst_port,
+                                port_status: Some(reason.port_status(&Protocol::TCP)),
+                                ttl,
+                                hop: crate::ip::guess_initial_ttl(ttl) - ttl,
+                                rtt: recv_time,
+                                probe_status: ProbeStatus::new(),
+                                protocol: Protocol::TCP,
+                                node_type: NodeType::Destination,
+                                sent_packet_size: tcp_packet.len(),
+                                received_packet_size: packet.len(),
+                                reply_frame: None,
+                                unreachable_reason: Some(reason),
+                                service: None,
+                            };
+                            responses.push(probe_result.clone());
+                            if let Ok(lr) = msg_tx.lock() {
+                                let _ = lr.send(probe_result);
+                            }
+                            break;
+                        }
                         if let Some(transport_layer) = &frame.transport {
                             if let Some(tcp_header) = &transport_layer.tcp {
                                 if let Some(port) = setting.dst_port {
@@ -80,6 +122,9 @@ pub(crate) fn tcp_ping(
                                     node_type: NodeType::Destination,
                                     sent_packet_size: tcp_packet.len(),
                                     received_packet_size: packet.len(),
+                                    reply_frame: None,
+                                    unreachable_reason: None,
+                                    service: None,
                                 };
                                 if tcp_header.flags == TcpFlags::SYN | TcpFlags::ACK {
                                     probe_result.port_status = Some(PortStatus::Open);
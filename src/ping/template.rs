@@ -0,0 +1,178 @@
+use crate::packet::decode::IcmpError;
+use crate::result::{NodeType, PingResult, PingStat, PortStatus, ProbeResult, ProbeStatus};
+use crate::result::UnreachableReason;
+use crate::setting::{ProbeSetting, Protocol};
+use crate::template::ProbeTemplate;
+use std::net::IpAddr;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use xenet::datalink::{DataLinkReceiver, DataLinkSender};
+use xenet::net::mac::MacAddr;
+use xenet::packet::frame::{Frame, ParseOption};
+use xenet::packet::tcp::TcpFlags;
+
+pub(crate) fn template_ping(
+    tx: &mut Box<dyn DataLinkSender>,
+    rx: &mut Box<dyn DataLinkReceiver>,
+    setting: &ProbeSetting,
+    template: &ProbeTemplate,
+    msg_tx: &Arc<Mutex<Sender<ProbeResult>>>,
+) -> Result<PingResult, String> {
+    let probe_packet: Vec<u8> = template.build(setting)?;
+    let mut result = PingResult::new();
+    result.protocol = template.protocol.clone();
+    let mut parse_option: ParseOption = ParseOption::default();
+    if setting.tunnel {
+        let payload_offset = if setting.loopback { 14 } else { 0 };
+        parse_option.from_ip_packet = true;
+        parse_option.offset = payload_offset;
+    }
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let mut responses: Vec<ProbeResult> = Vec::new();
+    for seq in 1..setting.count + 1 {
+        let send_time = Instant::now();
+        let _ = tx.send(&probe_packet);
+        let probe_result: ProbeResult = loop {
+            if let Ok(packet) = rx.next() {
+                let recv_time: Duration = Instant::now().duration_since(send_time);
+                let frame: Frame = Frame::from_bytes(packet, parse_option.clone());
+                if template.matches_reply(&frame, setting) {
+                    break reply_probe_result(
+                        seq,
+                        setting,
+                        template,
+                        frame,
+                        recv_time,
+                        probe_packet.len(),
+                        packet.len(),
+                    );
+                }
+            }
+            if Instant::now().duration_since(send_time) > setting.receive_timeout {
+                break ProbeResult::timeout(
+                    seq,
+                    setting.dst_ip,
+                    setting.dst_hostname.clone(),
+                    template.protocol.clone(),
+                    probe_packet.len(),
+                );
+            }
+        };
+        responses.push(probe_result.clone());
+        if let Ok(lr) = msg_tx.lock() {
+            let _ = lr.send(probe_result);
+        }
+        if seq < setting.count {
+            std::thread::sleep(setting.send_rate);
+        }
+    }
+    let probe_time = Instant::now().duration_since(start_time);
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = probe_time;
+    let replies: Vec<&ProbeResult> = responses
+        .iter()
+        .filter(|r| r.probe_status.kind == crate::result::ProbeStatusKind::Done)
+        .collect();
+    let received_count: usize = replies.len();
+    let ping_stat: PingStat = PingStat {
+        responses: responses.clone(),
+        probe_time,
+        transmitted_count: setting.count as usize,
+        received_count,
+        min: replies
+            .iter()
+            .map(|r| r.rtt)
+            .min()
+            .unwrap_or(Duration::from_millis(0)),
+        avg: replies
+            .iter()
+            .fold(Duration::from_millis(0), |acc, r| acc + r.rtt)
+            .checked_div(received_count as u32)
+            .unwrap_or(Duration::from_millis(0)),
+        max: replies
+            .iter()
+            .map(|r| r.rtt)
+            .max()
+            .unwrap_or(Duration::from_millis(0)),
+    };
+    result.stat = ping_stat;
+    result.probe_status = ProbeStatus::new();
+    Ok(result)
+}
+
+fn reply_probe_result(
+    seq: u8,
+    setting: &ProbeSetting,
+    template: &ProbeTemplate,
+    frame: Frame,
+    rtt: Duration,
+    sent_packet_size: usize,
+    received_packet_size: usize,
+) -> ProbeResult {
+    let mut mac_addr: MacAddr = MacAddr::zero();
+    if let Some(datalink_layer) = &frame.datalink {
+        if let Some(ethernet_header) = &datalink_layer.ethernet {
+            mac_addr = ethernet_header.source;
+        }
+    }
+    let mut ip_addr: IpAddr = setting.dst_ip;
+    let mut ttl: u8 = 0;
+    if let Some(ip_layer) = &frame.ip {
+        if let Some(ipv4_header) = &ip_layer.ipv4 {
+            ip_addr = IpAddr::V4(ipv4_header.source);
+            ttl = ipv4_header.ttl;
+        } else if let Some(ipv6_header) = &ip_layer.ipv6 {
+            ip_addr = IpAddr::V6(ipv6_header.source);
+            ttl = ipv6_header.hop_limit;
+        }
+    }
+    let mut port_number: Option<u16> = None;
+    let mut port_status: Option<PortStatus> = None;
+    if let Some(transport_layer) = &frame.transport {
+        if let Some(tcp_header) = &transport_layer.tcp {
+            port_number = Some(tcp_header.source);
+            if tcp_header.flags & (TcpFlags::SYN | TcpFlags::ACK) == TcpFlags::SYN | TcpFlags::ACK {
+                port_status = Some(PortStatus::Open);
+            } else if tcp_header.flags & TcpFlags::RST != 0 {
+                port_status = Some(PortStatus::Closed);
+            }
+        } else if let Some(udp_header) = &transport_layer.udp {
+            port_number = Some(udp_header.source);
+            port_status = Some(PortStatus::Open);
+        }
+    }
+    let unreachable_reason: Option<UnreachableReason> =
+        IcmpError::from_frame(&frame).and_then(|icmp_error| icmp_error.unreachable_reason());
+    if let Some(reason) = &unreachable_reason {
+        if matches!(template.protocol, Protocol::TCP | Protocol::UDP) {
+            port_number = Some(template.get_dst_port(setting));
+            port_status = Some(reason.port_status(&template.protocol));
+        }
+    }
+    let protocol: Protocol = if frame.transport.is_some() {
+        template.protocol.clone()
+    } else {
+        Protocol::ICMP
+    };
+    ProbeResult {
+        seq,
+        mac_addr,
+        ip_addr,
+        host_name: setting.dst_hostname.clone(),
+        port_number,
+        port_status,
+        ttl,
+        hop: crate::ip::guess_initial_ttl(ttl) - ttl,
+        rtt,
+        probe_status: ProbeStatus::new(),
+        protocol,
+        node_type: NodeType::Destination,
+        sent_packet_size,
+        received_packet_size,
+        reply_frame: Some(frame),
+        unreachable_reason,
+        service: None,
+    }
+}
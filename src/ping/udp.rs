@@ -1,4 +1,5 @@
-use crate::result::PortStatus;
+use crate::packet::decode::IcmpError;
+use crate::result::UnreachableReason;
 use crate::result::{NodeType, PingResult, PingStat, ProbeResult, ProbeStatus};
 use crate::setting::{ProbeSetting, Protocol};
 use std::net::IpAddr;
@@ -51,19 +52,24 @@ pub(crate) fn udp_ping(
                     if let Some(ip_layer) = &frame.ip {
                         // IPv4
                         if let Some(ipv4_header) = &ip_layer.ipv4 {
-                            if IpAddr::V4(ipv4_header.source) != setting.dst_ip || IpAddr::V4(ipv4_header.destination) != setting.src_ip {
+                            // Errors may come from a router, the quoted datagram identifies our probe
+                            if IpAddr::V4(ipv4_header.destination) != setting.src_ip {
                                 continue;
                             }
                             // ICMP
                             if let Some(icmp_header) = &ip_layer.icmp {
                                 if icmp_header.icmp_type == IcmpType::DestinationUnreachable {
+                                    let reason: Option<UnreachableReason> = match IcmpError::from_frame(&frame) {
+                                        Some(icmp_error) if icmp_error.quotes_probe(setting) => icmp_error.unreachable_reason(),
+                                        _ => continue,
+                                    };
                                     let probe_result: ProbeResult = ProbeResult {
                                         seq: seq,
                                         mac_addr: mac_addr,
                                         ip_addr: setting.dst_ip,
                                         host_name: setting.dst_hostname.clone(),
                                         port_number: setting.dst_port,
-                                        port_status: Some(PortStatus::Closed),
+                                        port_status: reason.map(|r| r.port_status(&Protocol::UDP)),
                                         ttl: ipv4_header.ttl,
                                         hop: crate::ip::guess_initial_ttl(ipv4_header.ttl)
                                             - ipv4_header.ttl,
@@ -73,6 +79,9 @@ pub(crate) fn udp_ping(
                                         node_type: NodeType::Destination,
                                         sent_packet_size: udp_packet.len(),
                                         received_packet_size: packet.len(),
+                                        reply_frame: None,
+                                        unreachable_reason: reason,
+                                        service: None,
                                     };
                                     responses.push(probe_result.clone());
                                     match msg_tx.lock() {
@@ -94,13 +103,17 @@ pub(crate) fn udp_ping(
                             // ICMPv6
                             if let Some(icmpv6_header) = &ip_layer.icmpv6 {
                                 if icmpv6_header.icmpv6_type == Icmpv6Type::DestinationUnreachable {
+                                    let reason: Option<UnreachableReason> = match IcmpError::from_frame(&frame) {
+                                        Some(icmp_error) if icmp_error.quotes_probe(setting) => icmp_error.unreachable_reason(),
+                                        _ => continue,
+                                    };
                                     let probe_result: ProbeResult = ProbeResult {
                                         seq: seq,
                                         mac_addr: mac_addr,
                                         ip_addr: setting.dst_ip,
                                         host_name: setting.dst_hostname.clone(),
                                         port_number: setting.dst_port,
-                                        port_status: Some(PortStatus::Closed),
+                                        port_status: reason.map(|r| r.port_status(&Protocol::UDP)),
                                         ttl: ipv6_header.hop_limit,
                                         hop: crate::ip::guess_initial_ttl(ipv6_header.hop_limit)
                                             - ipv6_header.hop_limit,
@@ -110,6 +123,9 @@ pub(crate) fn udp_ping(
                                         node_type: NodeType::Destination,
                                         sent_packet_size: udp_packet.len(),
                                         received_packet_size: packet.len(),
+                                        reply_frame: None,
+                                        unreachable_reason: reason,
+                                        service: None,
                                     };
                                     responses.push(probe_result.clone());
                                     match msg_tx.lock() {
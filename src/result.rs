@@ -1,17 +1,23 @@
-use crate::setting::Protocol;
-use std::net::IpAddr;
+use crate::dns::AddressPolicy;
+use crate::setting::{Protocol, ScanType, WakeOnLanMethod};
+use std::net::{IpAddr, Ipv6Addr};
 use std::time::Duration;
 use xenet::net::mac::MacAddr;
+use xenet::packet::frame::Frame;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PortStatus {
     Open,
     Closed,
     Filtered,
+    /// Reachable, but open or closed is unknown (ACK scan)
+    Unfiltered,
+    /// No reply to a UDP probe. Open, or the probe was dropped
+    OpenFiltered,
     Unknown,
 }
 
@@ -21,6 +27,8 @@ impl PortStatus {
             PortStatus::Open => String::from("open"),
             PortStatus::Closed => String::from("closed"),
             PortStatus::Filtered => String::from("filtered"),
+            PortStatus::Unfiltered => String::from("unfiltered"),
+            PortStatus::OpenFiltered => String::from("open|filtered"),
             PortStatus::Unknown => String::from("unknown"),
         }
     }
@@ -29,11 +37,139 @@ impl PortStatus {
             PortStatus::Open => String::from("Open"),
             PortStatus::Closed => String::from("Closed"),
             PortStatus::Filtered => String::from("Filtered"),
+            PortStatus::Unfiltered => String::from("Unfiltered"),
+            PortStatus::OpenFiltered => String::from("Open|Filtered"),
             PortStatus::Unknown => String::from("Unknown"),
         }
     }
 }
 
+/// Reason carried by an ICMP or ICMPv6 Destination Unreachable
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnreachableReason {
+    /// No route to the network (ICMPv4 codes 0, 6, 11. ICMPv6 code 0)
+    NetUnreachable,
+    /// Host did not answer address resolution (ICMPv4 codes 1, 7, 12. ICMPv6 code 3)
+    HostUnreachable,
+    /// Transport protocol not supported by the host (ICMPv4 code 2)
+    ProtocolUnreachable,
+    /// No socket bound to the port (ICMPv4 code 3. ICMPv6 code 4)
+    PortUnreachable,
+    /// Fragmentation needed but DF set (ICMPv4 code 4)
+    FragmentationNeeded,
+    /// Network or host administratively prohibited (ICMPv4 codes 9, 10. ICMPv6 code 1)
+    AdminProhibited,
+    /// Communication administratively filtered (ICMPv4 code 13. ICMPv6 codes 5, 6)
+    CommunicationFiltered,
+    /// Any other code
+    Other(u8),
+}
+
+impl UnreachableReason {
+    /// Decode the code of an ICMPv4 Destination Unreachable (type 3)
+    pub fn from_icmpv4_code(code: u8) -> UnreachableReason {
+        match code {
+            0 | 6 | 11 => UnreachableReason::NetUnreachable,
+            1 | 7 | 12 => UnreachableReason::HostUnreachable,
+            2 => UnreachableReason::ProtocolUnreachable,
+            3 => UnreachableReason::PortUnreachable,
+            4 => UnreachableReason::FragmentationNeeded,
+            9 | 10 => UnreachableReason::AdminProhibited,
+            13 => UnreachableReason::CommunicationFiltered,
+            _ => UnreachableReason::Other(code),
+        }
+    }
+    /// Decode the code of an ICMPv6 Destination Unreachable (type 1)
+    pub fn from_icmpv6_code(code: u8) -> UnreachableReason {
+        match code {
+            0 => UnreachableReason::NetUnreachable,
+            1 => UnreachableReason::AdminProhibited,
+            3 => UnreachableReason::HostUnreachable,
+            4 => UnreachableReason::PortUnreachable,
+            5 | 6 => UnreachableReason::CommunicationFiltered,
+            _ => UnreachableReason::Other(code),
+        }
+    }
+    /// Status of the probed port. Only a UDP probe answered with Port Unreachable proves the port closed.
+    pub fn port_status(&self, protocol: &Protocol) -> PortStatus {
+        match (self, protocol) {
+            (UnreachableReason::PortUnreachable, Protocol::UDP) => PortStatus::Closed,
+            _ => PortStatus::Filtered,
+        }
+    }
+    pub fn id(&self) -> String {
+        match *self {
+            UnreachableReason::NetUnreachable => String::from("net_unreachable"),
+            UnreachableReason::HostUnreachable => String::from("host_unreachable"),
+            UnreachableReason::ProtocolUnreachable => String::from("protocol_unreachable"),
+            UnreachableReason::PortUnreachable => String::from("port_unreachable"),
+            UnreachableReason::FragmentationNeeded => String::from("fragmentation_needed"),
+            UnreachableReason::AdminProhibited => String::from("admin_prohibited"),
+            UnreachableReason::CommunicationFiltered => String::from("communication_filtered"),
+            UnreachableReason::Other(_) => String::from("other"),
+        }
+    }
+    pub fn name(&self) -> String {
+        match *self {
+            UnreachableReason::NetUnreachable => String::from("Network Unreachable"),
+            UnreachableReason::HostUnreachable => String::from("Host Unreachable"),
+            UnreachableReason::ProtocolUnreachable => String::from("Protocol Unreachable"),
+            UnreachableReason::PortUnreachable => String::from("Port Unreachable"),
+            UnreachableReason::FragmentationNeeded => String::from("Fragmentation Needed"),
+            UnreachableReason::AdminProhibited => String::from("Administratively Prohibited"),
+            UnreachableReason::CommunicationFiltered => String::from("Communication Filtered"),
+            UnreachableReason::Other(code) => format!("Unreachable (code {})", code),
+        }
+    }
+}
+
+/// Service identified on a TCP port
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ServiceInfo {
+    /// Service name, such as ssh or http. "unknown" if the response matched no rule
+    pub name: String,
+    pub product: Option<String>,
+    pub version: Option<String>,
+    /// Extra information, such as the protocol version
+    pub info: Option<String>,
+    pub hostname: Option<String>,
+    pub os: Option<String>,
+    pub device_type: Option<String>,
+    /// Only the service is known, not the product (softmatch rule)
+    pub soft_match: bool,
+    /// Name of the probe that got the response
+    pub probe: String,
+    /// Response to the probe
+    pub banner: Vec<u8>,
+}
+
+impl ServiceInfo {
+    pub fn new(name: String, probe: String, banner: Vec<u8>) -> ServiceInfo {
+        ServiceInfo {
+            name,
+            product: None,
+            version: None,
+            info: None,
+            hostname: None,
+            os: None,
+            device_type: None,
+            soft_match: false,
+            probe,
+            banner,
+        }
+    }
+    /// Banner with non-printable bytes escaped
+    pub fn banner_text(&self) -> String {
+        self.banner
+            .iter()
+            .flat_map(|b| std::ascii::escape_default(*b))
+            .map(char::from)
+            .collect()
+    }
+}
+
 /// Node type
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -141,6 +277,12 @@ pub struct ProbeResult {
     pub sent_packet_size: usize,
     /// Received packet size
     pub received_packet_size: usize,
+    /// Reply frame, kept by template probes
+    pub reply_frame: Option<Frame>,
+    /// Reason of a Destination Unreachable reply
+    pub unreachable_reason: Option<UnreachableReason>,
+    /// Service identified on the port
+    pub service: Option<ServiceInfo>,
 }
 
 impl ProbeResult {
@@ -160,6 +302,9 @@ impl ProbeResult {
             node_type: NodeType::Destination,
             sent_packet_size: 0,
             received_packet_size: 0,
+            reply_frame: None,
+            unreachable_reason: None,
+            service: None,
         }
     }
     pub fn timeout(
@@ -187,6 +332,9 @@ impl ProbeResult {
             node_type: NodeType::Destination,
             sent_packet_size: sent_packet_size,
             received_packet_size: 0,
+            reply_frame: None,
+            unreachable_reason: None,
+            service: None,
         }
     }
     pub fn trace_timeout(
@@ -213,6 +361,9 @@ impl ProbeResult {
             node_type: node_type,
             sent_packet_size: sent_packet_size,
             received_packet_size: 0,
+            reply_frame: None,
+            unreachable_reason: None,
+            service: None,
         }
     }
 }
@@ -304,10 +455,62 @@ impl TracerouteResult {
     }
 }
 
+/// State of a kernel neighbor cache entry
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NeighborState {
+    Incomplete,
+    Reachable,
+    Stale,
+    Delay,
+    Probe,
+    Failed,
+    NoArp,
+    Permanent,
+    /// Entry is complete but the system does not expose its reachability state
+    Unknown,
+}
+
+impl NeighborState {
+    pub fn name(&self) -> String {
+        match *self {
+            NeighborState::Incomplete => String::from("INCOMPLETE"),
+            NeighborState::Reachable => String::from("REACHABLE"),
+            NeighborState::Stale => String::from("STALE"),
+            NeighborState::Delay => String::from("DELAY"),
+            NeighborState::Probe => String::from("PROBE"),
+            NeighborState::Failed => String::from("FAILED"),
+            NeighborState::NoArp => String::from("NOARP"),
+            NeighborState::Permanent => String::from("PERMANENT"),
+            NeighborState::Unknown => String::from("UNKNOWN"),
+        }
+    }
+    /// Whether the entry holds a link-layer address that can be used without probing
+    pub fn is_usable(&self) -> bool {
+        !matches!(self, NeighborState::Incomplete | NeighborState::Failed)
+    }
+}
+
+/// Entry of the system neighbor cache (ARP table / NDP neighbor table)
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NeighborEntry {
+    /// IP address
+    pub ip_addr: IpAddr,
+    /// MAC address
+    pub mac_addr: MacAddr,
+    /// Interface index
+    pub if_index: u32,
+    /// Entry state
+    pub state: NeighborState,
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DeviceResolveResult {
     pub results: Vec<ProbeResult>,
+    /// Neighbor cache entry used instead of active ARP/NDP, if any
+    pub cache_entry: Option<NeighborEntry>,
     pub probe_status: ProbeStatus,
     /// start-time in RFC 3339 and ISO 8601 date and time string
     pub start_time: String,
@@ -322,6 +525,7 @@ impl DeviceResolveResult {
     pub fn new() -> DeviceResolveResult {
         DeviceResolveResult {
             results: Vec::new(),
+            cache_entry: None,
             probe_status: ProbeStatus::new(),
             start_time: String::new(),
             end_time: String::new(),
@@ -330,3 +534,1014 @@ impl DeviceResolveResult {
         }
     }
 }
+
+/// Default router preference (RFC 4191)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RouterPreference {
+    High,
+    Medium,
+    Low,
+    Reserved,
+}
+
+impl RouterPreference {
+    pub fn from_flags(flags: u8) -> RouterPreference {
+        match (flags >> 3) & 0b11 {
+            0b01 => RouterPreference::High,
+            0b00 => RouterPreference::Medium,
+            0b11 => RouterPreference::Low,
+            _ => RouterPreference::Reserved,
+        }
+    }
+    pub fn name(&self) -> String {
+        match *self {
+            RouterPreference::High => String::from("High"),
+            RouterPreference::Medium => String::from("Medium"),
+            RouterPreference::Low => String::from("Low"),
+            RouterPreference::Reserved => String::from("Reserved"),
+        }
+    }
+}
+
+/// Prefix Information option of Router Advertisement
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PrefixInfo {
+    /// Prefix
+    pub prefix: Ipv6Addr,
+    /// Prefix length
+    pub prefix_len: u8,
+    /// On-link flag (L)
+    pub on_link: bool,
+    /// Autonomous address-configuration flag (A)
+    pub autonomous: bool,
+    /// Valid lifetime (seconds)
+    pub valid_lifetime: u32,
+    /// Preferred lifetime (seconds)
+    pub preferred_lifetime: u32,
+}
+
+/// Router learned from Router Advertisement
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RouterInfo {
+    /// MAC address (Ethernet source)
+    pub mac_addr: MacAddr,
+    /// IP address (IPv6 source)
+    pub ip_addr: IpAddr,
+    /// Source Link-Layer Address option
+    pub link_layer_addr: Option<MacAddr>,
+    /// Hop limit of the received packet
+    pub ttl: u8,
+    /// Cur Hop Limit advertised for outgoing packets
+    pub cur_hop_limit: u8,
+    /// Managed address configuration flag (M)
+    pub managed: bool,
+    /// Other configuration flag (O)
+    pub other_config: bool,
+    /// Default router preference
+    pub preference: RouterPreference,
+    /// Router lifetime (seconds). 0 means not a default router
+    pub router_lifetime: u16,
+    /// Reachable time (milliseconds)
+    pub reachable_time: u32,
+    /// Retrans timer (milliseconds)
+    pub retrans_timer: u32,
+    /// Link MTU
+    pub mtu: Option<u32>,
+    /// Prefix Information options
+    pub prefixes: Vec<PrefixInfo>,
+    /// Recursive DNS servers (RDNSS)
+    pub dns_servers: Vec<Ipv6Addr>,
+    /// DNS search list (DNSSL)
+    pub search_domains: Vec<String>,
+    /// Number of advertisements received from this router
+    pub advertisement_count: usize,
+    /// Round Trip Time of the first advertisement
+    pub rtt: Duration,
+    /// The advertisement is invalid (RFC 4861 § 6.1.2) or the router is not trusted
+    pub rogue: bool,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RouterDiscoveryResult {
+    pub routers: Vec<RouterInfo>,
+    /// More than one router answered on the link
+    pub multiple_routers: bool,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+    pub protocol: Protocol,
+}
+
+impl RouterDiscoveryResult {
+    pub fn new() -> RouterDiscoveryResult {
+        RouterDiscoveryResult {
+            routers: Vec::new(),
+            multiple_routers: false,
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+            protocol: Protocol::NDP,
+        }
+    }
+}
+
+impl Default for RouterDiscoveryResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// DHCP server learned from DHCPOFFER or DHCPv6 ADVERTISE
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DhcpServerInfo {
+    /// MAC address (Ethernet source)
+    pub mac_addr: MacAddr,
+    /// IP address (IP source)
+    pub ip_addr: IpAddr,
+    /// Server Identifier (DHCPv4 option 54 address or DHCPv6 DUID in hex)
+    pub server_id: String,
+    /// Relay agent address (giaddr) if the offer was relayed
+    pub relay_addr: Option<IpAddr>,
+    /// Offered address (yiaddr or IA_NA address)
+    pub offered_addr: Option<IpAddr>,
+    /// Subnet mask
+    pub subnet_mask: Option<IpAddr>,
+    /// Default gateways (Router option). Always empty for DHCPv6
+    pub gateways: Vec<IpAddr>,
+    /// DNS servers
+    pub dns_servers: Vec<IpAddr>,
+    /// Domain name or DNS search list
+    pub domain_names: Vec<String>,
+    /// Lease time (seconds). Valid lifetime for DHCPv6
+    pub lease_time: Option<u32>,
+    /// Number of offers received from this server
+    pub offer_count: usize,
+    /// Round Trip Time of the first offer
+    pub rtt: Duration,
+    /// The server is not trusted
+    pub rogue: bool,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DhcpDiscoveryResult {
+    pub servers: Vec<DhcpServerInfo>,
+    /// More than one server answered on the link
+    pub multiple_servers: bool,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+    pub protocol: Protocol,
+}
+
+impl DhcpDiscoveryResult {
+    pub fn new() -> DhcpDiscoveryResult {
+        DhcpDiscoveryResult {
+            servers: Vec::new(),
+            multiple_servers: false,
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+            protocol: Protocol::UDP,
+        }
+    }
+}
+
+impl Default for DhcpDiscoveryResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Switch or neighbor device learned from LLDP or CDP advertisement
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LinkNeighbor {
+    /// LLDP or CDP
+    pub protocol: Protocol,
+    /// MAC address (Ethernet source)
+    pub mac_addr: MacAddr,
+    /// Chassis ID (LLDP) or Device ID (CDP)
+    pub chassis_id: String,
+    /// Port ID of the neighbor port our host is connected to
+    pub port_id: String,
+    pub port_description: Option<String>,
+    pub system_name: Option<String>,
+    /// System description (LLDP) or software version (CDP)
+    pub system_description: Option<String>,
+    /// Hardware platform (CDP only)
+    pub platform: Option<String>,
+    pub management_addrs: Vec<IpAddr>,
+    /// Port VLAN ID (LLDP) or native VLAN (CDP)
+    pub vlan_id: Option<u16>,
+    /// Supported capabilities
+    pub capabilities: Vec<String>,
+    /// Enabled capabilities. Same as capabilities for CDP
+    pub enabled_capabilities: Vec<String>,
+    /// Time to live / hold time (seconds)
+    pub ttl: u16,
+    /// Number of advertisements received from this neighbor
+    pub frame_count: usize,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LinkDiscoveryResult {
+    pub neighbors: Vec<LinkNeighbor>,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+    pub protocol: Protocol,
+}
+
+impl LinkDiscoveryResult {
+    pub fn new() -> LinkDiscoveryResult {
+        LinkDiscoveryResult {
+            neighbors: Vec::new(),
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+            protocol: Protocol::LLDP,
+        }
+    }
+    /// The switch port our host is plugged into (first advertisement received)
+    pub fn switch_port(&self) -> Option<&LinkNeighbor> {
+        self.neighbors.first()
+    }
+}
+
+impl Default for LinkDiscoveryResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WakeOnLanResult {
+    /// MAC address of the host to wake
+    pub target_mac: MacAddr,
+    pub method: WakeOnLanMethod,
+    /// Number of magic packets sent
+    pub sent_count: usize,
+    pub sent_packet_size: usize,
+    /// Reply to the ARP/NDP verification probe, if the host answered
+    pub verify_result: Option<ProbeResult>,
+    /// Time from the first magic packet until the host answered
+    pub wake_time: Option<Duration>,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl WakeOnLanResult {
+    pub fn new() -> WakeOnLanResult {
+        WakeOnLanResult {
+            target_mac: MacAddr::zero(),
+            method: WakeOnLanMethod::Ethernet,
+            sent_count: 0,
+            sent_packet_size: 0,
+            verify_result: None,
+            wake_time: None,
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+}
+
+impl Default for WakeOnLanResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of one port
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PortResult {
+    pub ip_addr: IpAddr,
+    pub port: u16,
+    pub port_status: PortStatus,
+    /// Round Trip Time of the answered probe
+    pub rtt: Duration,
+    /// TTL or hop limit of the reply, 0 if unknown
+    pub ttl: u8,
+    /// Number of probes sent to the port
+    pub attempts: u8,
+}
+
+/// Ports of one scanned host
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HostScanResult {
+    pub ip_addr: IpAddr,
+    pub host_name: String,
+    pub ports: Vec<PortResult>,
+}
+
+impl HostScanResult {
+    /// Ports with the given status
+    pub fn ports_with_status(&self, port_status: PortStatus) -> Vec<u16> {
+        self.ports
+            .iter()
+            .filter(|p| p.port_status == port_status)
+            .map(|p| p.port)
+            .collect()
+    }
+    pub fn open_ports(&self) -> Vec<u16> {
+        self.ports_with_status(PortStatus::Open)
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PortScanResult {
+    pub hosts: Vec<HostScanResult>,
+    pub scan_type: ScanType,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl PortScanResult {
+    pub fn new() -> PortScanResult {
+        PortScanResult {
+            hosts: Vec::new(),
+            scan_type: ScanType::TcpSyn,
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Result of a host
+    pub fn get(&self, ip_addr: IpAddr) -> Option<&HostScanResult> {
+        self.hosts.iter().find(|h| h.ip_addr == ip_addr)
+    }
+}
+
+impl Default for PortScanResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Services of a host
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ServiceDetectionResult {
+    pub ip_addr: IpAddr,
+    pub host_name: String,
+    /// One result per port, in the order of the detector ports
+    pub results: Vec<ProbeResult>,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl ServiceDetectionResult {
+    pub fn new() -> ServiceDetectionResult {
+        ServiceDetectionResult {
+            ip_addr: IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+            host_name: String::new(),
+            results: Vec::new(),
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Result of a port
+    pub fn get(&self, port: u16) -> Option<&ProbeResult> {
+        self.results.iter().find(|r| r.port_number == Some(port))
+    }
+}
+
+impl Default for ServiceDetectionResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Time spent in each phase of an HTTP request
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HttpTiming {
+    /// Host name resolution. Zero if the host is an IP address
+    pub dns_lookup: Duration,
+    /// TCP connection establishment
+    pub tcp_connect: Duration,
+    /// Writing the request
+    pub request_send: Duration,
+    /// From the end of the request to the first byte of the response (server think time)
+    pub first_byte: Duration,
+    /// From the first byte of the response to the end of the body
+    pub content_transfer: Duration,
+    /// Whole exchange, DNS lookup included
+    pub total: Duration,
+}
+
+/// Response to one HTTP request
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HttpResponse {
+    /// Requested URL
+    pub url: String,
+    pub ip_addr: IpAddr,
+    pub port: u16,
+    /// Version of the status line, such as HTTP/1.1
+    pub version: String,
+    pub status_code: u16,
+    pub reason_phrase: String,
+    /// Header fields in received order
+    pub headers: Vec<(String, String)>,
+    /// Body size, without chunked transfer coding
+    pub body_size: usize,
+    pub timing: HttpTiming,
+}
+
+impl HttpResponse {
+    /// Value of the first header field named `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status_code, 301 | 302 | 303 | 307 | 308)
+    }
+}
+
+/// Result of an HTTP probe
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HttpProbeResult {
+    /// Responses in order, one per followed redirect
+    pub responses: Vec<HttpResponse>,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl HttpProbeResult {
+    pub fn new() -> HttpProbeResult {
+        HttpProbeResult {
+            responses: Vec::new(),
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Last response, after redirects
+    pub fn final_response(&self) -> Option<&HttpResponse> {
+        self.responses.last()
+    }
+}
+
+impl Default for HttpProbeResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Certificate presented in a TLS handshake
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CertificateInfo {
+    /// Distinguished name, such as "CN=example.com, O=Example"
+    pub subject: String,
+    pub issuer: String,
+    /// Hex serial number
+    pub serial_number: String,
+    /// Subject Alternative Names, such as "DNS:example.com" or "IP:192.0.2.1"
+    pub subject_alt_names: Vec<String>,
+    /// Start of validity in RFC 3339
+    pub not_before: String,
+    /// End of validity in RFC 3339
+    pub not_after: String,
+    /// Whole days until `not_after`. Negative once expired
+    pub days_to_expiry: i64,
+    pub is_ca: bool,
+}
+
+/// Result of a TLS handshake probe
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TlsProbeResult {
+    pub ip_addr: IpAddr,
+    pub port: u16,
+    /// Name verified against the certificate, sent as SNI unless it is an IP address
+    pub server_name: String,
+    /// Negotiated version, such as "TLS 1.3"
+    pub version: String,
+    /// Negotiated cipher suite, such as "TLS13_AES_256_GCM_SHA384"
+    pub cipher_suite: String,
+    /// Protocol selected by ALPN
+    pub alpn_protocol: Option<String>,
+    /// Certificate chain sent by the server, leaf first
+    pub certificates: Vec<CertificateInfo>,
+    /// The chain is valid for `server_name` and anchored in the bundled Mozilla roots
+    pub verified: bool,
+    /// Reason the chain could not be verified
+    pub verify_error: Option<String>,
+    /// Host name resolution. Zero if the host is an IP address
+    pub dns_lookup: Duration,
+    /// TCP connection establishment
+    pub tcp_connect: Duration,
+    /// From the ClientHello to the end of the handshake
+    pub handshake: Duration,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl TlsProbeResult {
+    pub fn new() -> TlsProbeResult {
+        TlsProbeResult {
+            ip_addr: IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+            port: 0,
+            server_name: String::new(),
+            version: String::new(),
+            cipher_suite: String::new(),
+            alpn_protocol: None,
+            certificates: Vec::new(),
+            verified: false,
+            verify_error: None,
+            dns_lookup: Duration::from_millis(0),
+            tcp_connect: Duration::from_millis(0),
+            handshake: Duration::from_millis(0),
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Certificate of the server itself
+    pub fn leaf_certificate(&self) -> Option<&CertificateInfo> {
+        self.certificates.first()
+    }
+    /// Days until the first certificate of the chain expires
+    pub fn days_to_expiry(&self) -> Option<i64> {
+        self.certificates.iter().map(|c| c.days_to_expiry).min()
+    }
+}
+
+impl Default for TlsProbeResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resource record of a DNS response
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DnsRecord {
+    pub name: String,
+    /// Record type, such as A or MX
+    pub record_type: String,
+    /// Class, usually IN
+    pub class: String,
+    pub ttl: u32,
+    /// Record data in presentation format
+    pub data: String,
+}
+
+/// Outcome of one DNS query
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DnsResponse {
+    /// Sequence number
+    pub seq: u8,
+    /// Server address
+    pub ip_addr: IpAddr,
+    pub port: u16,
+    /// Transport of the answer. TCP after a truncated UDP response
+    pub protocol: Protocol,
+    /// Message ID
+    pub id: u16,
+    /// Round Trip Time. Includes the TCP retry of a truncated response
+    pub rtt: Duration,
+    /// Response code
+    pub rcode: u16,
+    /// Response code mnemonic, such as NOERROR or NXDOMAIN
+    pub rcode_name: String,
+    /// Header flags that are set, such as qr, aa, tc, rd, ra, ad and cd
+    pub flags: Vec<String>,
+    /// The UDP response was truncated
+    pub truncated: bool,
+    pub answers: Vec<DnsRecord>,
+    pub authorities: Vec<DnsRecord>,
+    pub additionals: Vec<DnsRecord>,
+    /// Sent query size
+    pub sent_packet_size: usize,
+    /// Received response size
+    pub received_packet_size: usize,
+    /// Status
+    pub probe_status: ProbeStatus,
+}
+
+impl DnsResponse {
+    pub fn new() -> DnsResponse {
+        DnsResponse {
+            seq: 0,
+            ip_addr: IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+            port: 0,
+            protocol: Protocol::UDP,
+            id: 0,
+            rtt: Duration::from_millis(0),
+            rcode: 0,
+            rcode_name: String::new(),
+            flags: Vec::new(),
+            truncated: false,
+            answers: Vec::new(),
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+            sent_packet_size: 0,
+            received_packet_size: 0,
+            probe_status: ProbeStatus::new(),
+        }
+    }
+}
+
+impl Default for DnsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Statistics of a DNS probe
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DnsStat {
+    /// Query results, including unanswered ones
+    pub responses: Vec<DnsResponse>,
+    /// The entire probe time
+    pub probe_time: Duration,
+    /// Sent queries
+    pub transmitted_count: usize,
+    /// Received responses
+    pub received_count: usize,
+    /// Minimum RTT
+    pub min: Duration,
+    /// Average RTT
+    pub avg: Duration,
+    /// Maximum RTT
+    pub max: Duration,
+}
+
+impl DnsStat {
+    pub fn new() -> DnsStat {
+        DnsStat {
+            responses: Vec::new(),
+            probe_time: Duration::from_millis(0),
+            transmitted_count: 0,
+            received_count: 0,
+            min: Duration::from_millis(0),
+            avg: Duration::from_millis(0),
+            max: Duration::from_millis(0),
+        }
+    }
+    /// Percentage of queries without response
+    pub fn loss_rate(&self) -> f64 {
+        if self.transmitted_count == 0 {
+            return 0.0;
+        }
+        (self.transmitted_count - self.received_count) as f64 * 100.0
+            / self.transmitted_count as f64
+    }
+}
+
+impl Default for DnsStat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of a DNS probe
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DnsProbeResult {
+    pub stat: DnsStat,
+    pub probe_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl DnsProbeResult {
+    pub fn new() -> DnsProbeResult {
+        DnsProbeResult {
+            stat: DnsStat::new(),
+            probe_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+}
+
+impl Default for DnsProbeResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Destination address chosen for a host name
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResolvedTarget {
+    pub host_name: String,
+    /// Chosen address
+    pub ip_addr: IpAddr,
+    /// Candidate addresses in the order of the policy
+    pub addresses: Vec<IpAddr>,
+    pub policy: AddressPolicy,
+    /// Time of the address lookups
+    pub lookup_time: Duration,
+    /// Time from the first connection attempt to the winning connection, when they were raced
+    pub connect_time: Option<Duration>,
+}
+
+/// Result of a dual-stack probe
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DualStackResult {
+    pub host_name: String,
+    pub port: u16,
+    /// Probed IPv4 address. None if the name has none
+    pub ipv4_addr: Option<IpAddr>,
+    /// Probed IPv6 address. None if the name has none
+    pub ipv6_addr: Option<IpAddr>,
+    /// TCP connect RTT statistics of the IPv4 address
+    pub ipv4_stat: PingStat,
+    /// TCP connect RTT statistics of the IPv6 address
+    pub ipv6_stat: PingStat,
+    /// Why the IPv4 address was not probed, or Done
+    pub ipv4_status: ProbeStatus,
+    /// Why the IPv6 address was not probed, or Done
+    pub ipv6_status: ProbeStatus,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl DualStackResult {
+    pub fn new() -> DualStackResult {
+        DualStackResult {
+            host_name: String::new(),
+            port: 0,
+            ipv4_addr: None,
+            ipv6_addr: None,
+            ipv4_stat: PingStat::new(),
+            ipv6_stat: PingStat::new(),
+            ipv4_status: ProbeStatus::new(),
+            ipv6_status: ProbeStatus::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Address with the lower average RTT, among the ones that answered
+    pub fn faster_addr(&self) -> Option<IpAddr> {
+        match (self.ipv4_answered(), self.ipv6_answered()) {
+            (Some(ipv4_addr), Some(ipv6_addr)) => {
+                if self.ipv6_stat.avg < self.ipv4_stat.avg {
+                    Some(ipv6_addr)
+                } else {
+                    Some(ipv4_addr)
+                }
+            }
+            (ipv4_addr, ipv6_addr) => ipv4_addr.or(ipv6_addr),
+        }
+    }
+    /// IPv6 average RTT minus IPv4 average RTT in milliseconds. Negative if IPv6 is faster.
+    /// None unless both families answered.
+    pub fn rtt_difference_ms(&self) -> Option<f64> {
+        self.ipv4_answered()?;
+        self.ipv6_answered()?;
+        Some((self.ipv6_stat.avg.as_secs_f64() - self.ipv4_stat.avg.as_secs_f64()) * 1000.0)
+    }
+    fn ipv4_answered(&self) -> Option<IpAddr> {
+        self.ipv4_addr.filter(|_| self.ipv4_stat.received_count > 0)
+    }
+    fn ipv6_answered(&self) -> Option<IpAddr> {
+        self.ipv6_addr.filter(|_| self.ipv6_stat.received_count > 0)
+    }
+}
+
+impl Default for DualStackResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Summary of a stateless scan. Port results are only streamed through the progress receiver.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StatelessScanResult {
+    /// Probes sent
+    pub sent_count: u64,
+    /// Replies that carried a valid cookie
+    pub validated_count: u64,
+    /// Validated replies reporting an open port
+    pub open_count: u64,
+    /// Replies to our source port whose cookie did not match
+    pub invalid_count: u64,
+    pub probe_status: ProbeStatus,
+    /// Time spent sending probes
+    pub send_duration: Duration,
+    /// start-time in RFC 3339 and ISO 8601 date and time string
+    pub start_time: String,
+    /// end-time in RFC 3339 and ISO 8601 date and time string
+    pub end_time: String,
+    /// Elapsed time
+    pub elapsed_time: Duration,
+}
+
+impl StatelessScanResult {
+    pub fn new() -> StatelessScanResult {
+        StatelessScanResult {
+            sent_count: 0,
+            validated_count: 0,
+            open_count: 0,
+            invalid_count: 0,
+            probe_status: ProbeStatus::new(),
+            send_duration: Duration::from_millis(0),
+            start_time: String::new(),
+            end_time: String::new(),
+            elapsed_time: Duration::from_millis(0),
+        }
+    }
+    /// Probes sent per second
+    pub fn send_rate(&self) -> f64 {
+        if self.send_duration.is_zero() {
+            return 0.0;
+        }
+        self.sent_count as f64 / self.send_duration.as_secs_f64()
+    }
+}
+
+impl Default for StatelessScanResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icmpv4_unreachable_codes() {
+        let table: [(u8, UnreachableReason); 17] = [
+            (0, UnreachableReason::NetUnreachable),
+            (1, UnreachableReason::HostUnreachable),
+            (2, UnreachableReason::ProtocolUnreachable),
+            (3, UnreachableReason::PortUnreachable),
+            (4, UnreachableReason::FragmentationNeeded),
+            (5, UnreachableReason::Other(5)),
+            (6, UnreachableReason::NetUnreachable),
+            (7, UnreachableReason::HostUnreachable),
+            (8, UnreachableReason::Other(8)),
+            (9, UnreachableReason::AdminProhibited),
+            (10, UnreachableReason::AdminProhibited),
+            (11, UnreachableReason::NetUnreachable),
+            (12, UnreachableReason::HostUnreachable),
+            (13, UnreachableReason::CommunicationFiltered),
+            (14, UnreachableReason::Other(14)),
+            (15, UnreachableReason::Other(15)),
+            (16, UnreachableReason::Other(16)),
+        ];
+        for (code, reason) in table {
+            assert_eq!(
+                UnreachableReason::from_icmpv4_code(code),
+                reason,
+                "code {}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn icmpv6_unreachable_codes() {
+        let table: [(u8, UnreachableReason); 10] = [
+            (0, UnreachableReason::NetUnreachable),
+            (1, UnreachableReason::AdminProhibited),
+            (2, UnreachableReason::Other(2)),
+            (3, UnreachableReason::HostUnreachable),
+            (4, UnreachableReason::PortUnreachable),
+            (5, UnreachableReason::CommunicationFiltered),
+            (6, UnreachableReason::CommunicationFiltered),
+            (7, UnreachableReason::Other(7)),
+            (8, UnreachableReason::Other(8)),
+            (9, UnreachableReason::Other(9)),
+        ];
+        for (code, reason) in table {
+            assert_eq!(
+                UnreachableReason::from_icmpv6_code(code),
+                reason,
+                "code {}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn unreachable_port_status() {
+        let reasons: [UnreachableReason; 8] = [
+            UnreachableReason::NetUnreachable,
+            UnreachableReason::HostUnreachable,
+            UnreachableReason::ProtocolUnreachable,
+            UnreachableReason::PortUnreachable,
+            UnreachableReason::FragmentationNeeded,
+            UnreachableReason::AdminProhibited,
+            UnreachableReason::CommunicationFiltered,
+            UnreachableReason::Other(5),
+        ];
+        for reason in reasons {
+            let udp_status: PortStatus = if reason == UnreachableReason::PortUnreachable {
+                PortStatus::Closed
+            } else {
+                PortStatus::Filtered
+            };
+            assert_eq!(
+                reason.port_status(&Protocol::UDP),
+                udp_status,
+                "{:?}",
+                reason
+            );
+            // A TCP port never answers with ICMP, any unreachable means a filter
+            assert_eq!(
+                reason.port_status(&Protocol::TCP),
+                PortStatus::Filtered,
+                "{:?}",
+                reason
+            );
+        }
+        // UDP port unreachable from either address family
+        for reason in [
+            UnreachableReason::from_icmpv4_code(3),
+            UnreachableReason::from_icmpv6_code(4),
+        ] {
+            assert_eq!(reason.port_status(&Protocol::UDP), PortStatus::Closed);
+        }
+        // Administratively prohibited from either address family
+        for reason in [
+            UnreachableReason::from_icmpv4_code(13),
+            UnreachableReason::from_icmpv6_code(1),
+        ] {
+            assert_eq!(reason.port_status(&Protocol::UDP), PortStatus::Filtered);
+            assert_eq!(reason.port_status(&Protocol::TCP), PortStatus::Filtered);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use super::PortScanner;
+use crate::result::{PortResult, PortScanResult, PortStatus, ProbeStatus};
+use std::collections::{HashMap, VecDeque};
+use std::io::ErrorKind;
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub(crate) fn connect_scan(
+    scanner: &PortScanner,
+    msg_tx: &Arc<Mutex<Sender<PortResult>>>,
+) -> Result<PortScanResult, String> {
+    let setting = &scanner.probe_setting;
+    let mut result = PortScanResult::new();
+    result.scan_type = scanner.scan_type;
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let queue: Mutex<VecDeque<(IpAddr, u16)>> = Mutex::new(
+        scanner
+            .targets
+            .iter()
+            .flat_map(|ip| scanner.ports.iter().map(move |port| (*ip, *port)))
+            .collect(),
+    );
+    let workers: usize = scanner
+        .concurrency
+        .min(scanner.targets.len() * scanner.ports.len());
+    let port_results: Mutex<HashMap<(IpAddr, u16), PortResult>> = Mutex::new(HashMap::new());
+    // Start time of the next connection attempt, shared by all workers
+    let next_slot: Mutex<Instant> = Mutex::new(Instant::now());
+    std::thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                if start_time.elapsed() > setting.probe_timeout {
+                    break;
+                }
+                let (ip_addr, port) = match queue.lock() {
+                    Ok(mut queue) => match queue.pop_front() {
+                        Some(next) => next,
+                        None => break,
+                    },
+                    Err(_) => break,
+                };
+                let mut port_result = PortResult {
+                    ip_addr,
+                    port,
+                    port_status: PortStatus::Filtered,
+                    rtt: Duration::from_millis(0),
+                    ttl: 0,
+                    attempts: 0,
+                };
+                for _ in 0..=scanner.retries {
+                    wait_slot(&next_slot, setting.send_rate);
+                    port_result.attempts += 1;
+                    let connect_start = Instant::now();
+                    match TcpStream::connect_timeout(
+                        &SocketAddr::new(ip_addr, port),
+                        setting.receive_timeout,
+                    ) {
+                        Ok(stream) => {
+                            port_result.rtt = connect_start.elapsed();
+                            port_result.port_status = PortStatus::Open;
+                            let _ = stream.shutdown(Shutdown::Both);
+                            break;
+                        }
+                        Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                            port_result.rtt = connect_start.elapsed();
+                            port_result.port_status = PortStatus::Closed;
+                            break;
+                        }
+                        // No answer, try again
+                        Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                        // Unreachable or rejected by a filter
+                        Err(_) => break,
+                    }
+                }
+                if let Ok(lr) = msg_tx.lock() {
+                    let _ = lr.send(port_result.clone());
+                }
+                if let Ok(mut port_results) = port_results.lock() {
+                    port_results.insert((ip_addr, port), port_result);
+                }
+            });
+        }
+    });
+    let mut port_results: HashMap<(IpAddr, u16), PortResult> = match port_results.into_inner() {
+        Ok(port_results) => port_results,
+        Err(e) => e.into_inner(),
+    };
+    let timed_out: bool = port_results.len() < scanner.targets.len() * scanner.ports.len();
+    result.hosts = super::collect_host_results(scanner, &mut port_results);
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = start_time.elapsed();
+    result.probe_status = if timed_out {
+        ProbeStatus::with_timeout_message("connect_scan: probe timeout".to_string())
+    } else {
+        ProbeStatus::new()
+    };
+    Ok(result)
+}
+
+/// Sleep until this worker may start the next attempt
+fn wait_slot(next_slot: &Mutex<Instant>, send_rate: Duration) {
+    let wait: Duration = match next_slot.lock() {
+        Ok(mut next_slot) => {
+            let now = Instant::now();
+            let slot: Instant = (*next_slot).max(now);
+            *next_slot = slot + send_rate;
+            slot - now
+        }
+        Err(_) => Duration::from_millis(0),
+    };
+    if !wait.is_zero() {
+        std::thread::sleep(wait);
+    }
+}
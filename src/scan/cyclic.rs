@@ -0,0 +1,195 @@
+use rand::Rng;
+
+/// Random permutation of `0..size` walked through the multiplicative group of integers modulo
+/// a prime `p > size`, as zmap does. Needs O(1) memory whatever the size of the scan.
+///
+/// Starting from a random element, every multiplication by a random generator yields the next
+/// element. Elements above `size` are skipped.
+#[derive(Clone, Debug)]
+pub(crate) struct CyclicPermutation {
+    size: u64,
+    prime: u64,
+    generator: u64,
+    first: u64,
+    current: u64,
+    done: bool,
+}
+
+impl CyclicPermutation {
+    pub(crate) fn new<R: Rng>(size: u64, rng: &mut R) -> CyclicPermutation {
+        if size < 2 {
+            return CyclicPermutation {
+                size,
+                prime: 2,
+                generator: 1,
+                first: 1,
+                current: 1,
+                done: size == 0,
+            };
+        }
+        let prime: u64 = next_prime(size + 1);
+        let factors: Vec<u64> = prime_factors(prime - 1);
+        let generator: u64 = loop {
+            let candidate: u64 = rng.gen_range(2..prime);
+            if factors
+                .iter()
+                .all(|q| pow_mod(candidate, (prime - 1) / q, prime) != 1)
+            {
+                break candidate;
+            }
+        };
+        let first: u64 = rng.gen_range(1..prime);
+        CyclicPermutation {
+            size,
+            prime,
+            generator,
+            first,
+            current: first,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for CyclicPermutation {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        while !self.done {
+            let element: u64 = self.current;
+            self.current = mul_mod(self.current, self.generator, self.prime);
+            if self.current == self.first {
+                self.done = true;
+            }
+            if element <= self.size {
+                return Some(element - 1);
+            }
+        }
+        None
+    }
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result: u64 = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Deterministic Miller-Rabin for 64 bit integers
+fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let mut d: u64 = n - 1;
+    let mut r: u32 = 0;
+    while d & 1 == 0 {
+        d >>= 1;
+        r += 1;
+    }
+    'witness: for a in WITNESSES {
+        let mut x: u64 = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..r {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+fn next_prime(mut n: u64) -> u64 {
+    while !is_prime(n) {
+        n += 1;
+    }
+    n
+}
+
+/// Distinct prime factors, by trial division
+fn prime_factors(mut n: u64) -> Vec<u64> {
+    let mut factors: Vec<u64> = Vec::new();
+    let mut d: u64 = 2;
+    while d * d <= n {
+        if n.is_multiple_of(d) {
+            factors.push(d);
+            while n.is_multiple_of(d) {
+                n /= d;
+            }
+        }
+        d += if d == 2 { 1 } else { 2 };
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every index in `0..size` must come out exactly once, whatever the random start
+    fn assert_permutation(size: u64) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let mut seen: Vec<bool> = vec![false; size as usize];
+            for index in CyclicPermutation::new(size, &mut rng) {
+                assert!(index < size, "size {}: index {} out of range", size, index);
+                assert!(
+                    !seen[index as usize],
+                    "size {}: index {} repeated",
+                    size, index
+                );
+                seen[index as usize] = true;
+            }
+            assert!(seen.iter().all(|s| *s), "size {}: index missing", size);
+        }
+    }
+
+    #[test]
+    fn permutation_covers_every_index() {
+        // 13 is a prime, 16 is a prime minus one
+        for size in [0, 1, 2, 13, 16, 1000] {
+            assert_permutation(size);
+        }
+    }
+
+    #[test]
+    fn permutation_order_is_random() {
+        let mut rng = rand::thread_rng();
+        let orders: Vec<Vec<u64>> = (0..10)
+            .map(|_| CyclicPermutation::new(1000, &mut rng).collect())
+            .collect();
+        assert!(orders.iter().any(|order| *order != orders[0]));
+    }
+
+    #[test]
+    fn primes() {
+        let primes: Vec<u64> = (0..30).filter(|n| is_prime(*n)).collect();
+        assert_eq!(primes, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert!(is_prime(4_294_967_311));
+        assert!(!is_prime(4_294_967_297));
+        assert_eq!(next_prime(1001), 1009);
+        assert_eq!(prime_factors(1008), [2, 3, 7]);
+        assert_eq!(prime_factors(17), [17]);
+    }
+}
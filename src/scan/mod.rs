@@ -0,0 +1,217 @@
+pub(crate) mod connect;
+pub(crate) mod cyclic;
+pub mod payload;
+pub(crate) mod raw;
+pub mod stateless;
+pub(crate) mod tcp;
+pub(crate) mod udp;
+
+use crate::result::{HostScanResult, PortResult, PortScanResult, PortStatus};
+use crate::setting::{ProbeSetting, ScanType};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Target address and port of a probe
+pub(crate) type PortKey = (IpAddr, u16);
+
+/// Longest wait for a packet, so the receive loop notices the end of the send phase
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Port scanner structure.
+///
+/// Supports TCP SYN, TCP Connect, TCP ACK and UDP scan of one or many hosts.
+#[derive(Clone, Debug)]
+pub struct PortScanner {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    pub scan_type: ScanType,
+    /// Hosts to scan. Defaults to `probe_setting.dst_ip`
+    pub targets: Vec<IpAddr>,
+    /// Ports to scan on every host
+    pub ports: Vec<u16>,
+    /// Extra probes sent to ports that did not answer
+    pub retries: u8,
+    /// Simultaneous connections of the connect scan
+    pub concurrency: usize,
+    /// UDP payloads by port, used instead of the built-in ones of [`payload::udp_payload`]
+    pub udp_payloads: HashMap<u16, Vec<u8>>,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<PortResult>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<PortResult>>>,
+}
+
+impl PortScanner {
+    /// Create new PortScanner instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<PortScanner, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "PortScanner::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let (tx, rx) = channel();
+        let scanner = PortScanner {
+            targets: vec![setting.dst_ip],
+            ports: setting.dst_port.into_iter().collect(),
+            probe_setting: setting,
+            scan_type: ScanType::TcpSyn,
+            retries: 1,
+            concurrency: 64,
+            udp_payloads: HashMap::new(),
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(scanner)
+    }
+    pub fn with_scan_type(mut self, scan_type: ScanType) -> PortScanner {
+        self.scan_type = scan_type;
+        self
+    }
+    pub fn with_targets(mut self, targets: Vec<IpAddr>) -> PortScanner {
+        self.targets = targets;
+        self
+    }
+    pub fn with_ports(mut self, ports: Vec<u16>) -> PortScanner {
+        self.ports = ports;
+        self
+    }
+    /// Scan `start..=end`
+    pub fn with_port_range(mut self, start: u16, end: u16) -> PortScanner {
+        self.ports = (start..=end).collect();
+        self
+    }
+    pub fn with_retries(mut self, retries: u8) -> PortScanner {
+        self.retries = retries;
+        self
+    }
+    pub fn with_concurrency(mut self, concurrency: usize) -> PortScanner {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Send `payload` in UDP probes to `port`
+    pub fn with_udp_payload(mut self, port: u16, payload: Vec<u8>) -> PortScanner {
+        self.udp_payloads.insert(port, payload);
+        self
+    }
+    /// Limit probes (or connection attempts) per second
+    pub fn with_rate(mut self, packets_per_second: u32) -> PortScanner {
+        self.probe_setting.send_rate = Duration::from_secs(1) / packets_per_second.max(1);
+        self
+    }
+    /// Run scan
+    pub fn scan(&self) -> Result<PortScanResult, String> {
+        if self.ports.is_empty() {
+            return Err("PortScanner::scan: no ports to scan".to_string());
+        }
+        if self.targets.is_empty() {
+            return Err("PortScanner::scan: no targets to scan".to_string());
+        }
+        match self.scan_type {
+            ScanType::TcpSyn | ScanType::TcpAck | ScanType::Udp => {
+                if let Some(target) = self
+                    .targets
+                    .iter()
+                    .find(|t| t.is_ipv4() != self.probe_setting.src_ip.is_ipv4())
+                {
+                    return Err(format!(
+                        "PortScanner::scan: address family of {} does not match source {}",
+                        target, self.probe_setting.src_ip
+                    ));
+                }
+                match self.scan_type {
+                    ScanType::Udp => udp::udp_scan(self, &self.tx),
+                    _ => tcp::tcp_scan(self, &self.tx),
+                }
+            }
+            ScanType::TcpConnect => connect::connect_scan(self, &self.tx),
+        }
+    }
+    /// Get progress receiver
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<PortResult>>> {
+        self.rx.clone()
+    }
+}
+
+/// Parse a port list such as "22,80,8000-8100". Duplicates are removed, order is kept.
+pub fn parse_ports(spec: &str) -> Result<Vec<u16>, String> {
+    let mut ports: Vec<u16> = Vec::new();
+    let mut seen: HashSet<u16> = HashSet::new();
+    for part in spec.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+        let start: u16 = start
+            .parse::<u16>()
+            .map_err(|_| format!("parse_ports: invalid port {}", start))?;
+        let end: u16 = end
+            .parse::<u16>()
+            .map_err(|_| format!("parse_ports: invalid port {}", end))?;
+        if start > end {
+            return Err(format!("parse_ports: invalid range {}", part));
+        }
+        for port in start..=end {
+            if seen.insert(port) {
+                ports.push(port);
+            }
+        }
+    }
+    if ports.is_empty() {
+        return Err("parse_ports: no ports".to_string());
+    }
+    Ok(ports)
+}
+
+/// Cookie (TCP sequence number or IPv4 identification) tying a reply to the probe that caused it,
+/// without keeping per-probe state
+pub(crate) fn probe_cookie(secret: u64, ip_addr: IpAddr, port: u16) -> u32 {
+    let mut hasher = DefaultHasher::new();
+    secret.hash(&mut hasher);
+    ip_addr.hash(&mut hasher);
+    port.hash(&mut hasher);
+    hasher.finish() as u32
+}
+
+/// Group port results by target, in the order of `targets` and `ports`.
+/// Ports never probed (the scan timed out) are reported as Unknown.
+pub(crate) fn collect_host_results(
+    scanner: &PortScanner,
+    port_results: &mut HashMap<(IpAddr, u16), PortResult>,
+) -> Vec<HostScanResult> {
+    scanner
+        .targets
+        .iter()
+        .map(|ip_addr| HostScanResult {
+            ip_addr: *ip_addr,
+            host_name: if *ip_addr == scanner.probe_setting.dst_ip {
+                scanner.probe_setting.dst_hostname.clone()
+            } else {
+                ip_addr.to_string()
+            },
+            ports: scanner
+                .ports
+                .iter()
+                .map(|port| match port_results.remove(&(*ip_addr, *port)) {
+                    Some(port_result) => port_result,
+                    None => PortResult {
+                        ip_addr: *ip_addr,
+                        port: *port,
+                        port_status: PortStatus::Unknown,
+                        rtt: Duration::from_millis(0),
+                        ttl: 0,
+                        attempts: 0,
+                    },
+                })
+                .collect(),
+        })
+        .collect()
+}
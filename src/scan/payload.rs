@@ -0,0 +1,220 @@
+//! Protocol-specific UDP probe payloads.
+//!
+//! Most UDP services ignore an empty or malformed datagram, so a port that is open
+//! looks exactly like one whose probe was dropped. A well-formed request for the
+//! service usually expected on the port makes it answer.
+
+/// Well-known UDP ports with a dedicated probe payload
+pub const UDP_PAYLOAD_PORTS: [u16; 14] = [
+    53, 69, 111, 123, 137, 161, 500, 623, 1194, 1900, 4500, 5060, 5353, 11211,
+];
+
+/// Payload of the UDP probe sent to `port`. Empty if no protocol is known for the port.
+pub fn udp_payload(port: u16) -> Vec<u8> {
+    match port {
+        53 => dns_version_bind(),
+        69 => tftp_read_request(),
+        111 => rpc_portmap_null(),
+        123 => ntp_client_request(),
+        137 => netbios_status_query(),
+        161 => snmp_get_sys_descr(),
+        500 => ike_main_mode(false),
+        623 => ipmi_rmcp_ping(),
+        1194 => openvpn_hard_reset(),
+        1900 => ssdp_m_search(),
+        4500 => ike_main_mode(true),
+        5060 => sip_options(),
+        5353 => mdns_services_query(),
+        11211 => memcached_stats(),
+        _ => Vec::new(),
+    }
+}
+
+/// Encode a domain name into DNS labels
+fn dns_name(name: &str) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    buf
+}
+
+/// DNS query with a single question
+fn dns_query(id: u16, flags: u16, name: &str, qtype: u16, qclass: u16) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&flags.to_be_bytes());
+    // QDCOUNT 1, ANCOUNT/NSCOUNT/ARCOUNT 0
+    buf.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    buf.extend_from_slice(&dns_name(name));
+    buf.extend_from_slice(&qtype.to_be_bytes());
+    buf.extend_from_slice(&qclass.to_be_bytes());
+    buf
+}
+
+/// TXT CHAOS query for version.bind, answered (or refused) by most DNS servers
+fn dns_version_bind() -> Vec<u8> {
+    dns_query(0x1234, 0x0100, "version.bind", 16, 3)
+}
+
+/// PTR query for the DNS-SD service list
+fn mdns_services_query() -> Vec<u8> {
+    dns_query(0x0000, 0x0000, "_services._dns-sd._udp.local", 12, 1)
+}
+
+/// NBSTAT query for the wildcard name "*"
+fn netbios_status_query() -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![
+        0x80, 0xf0, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    // First-level encoding of "*" padded with NUL to 16 characters
+    buf.push(32);
+    buf.extend_from_slice(b"CK");
+    buf.extend_from_slice(&[b'A'; 30]);
+    buf.push(0);
+    // NBSTAT, IN
+    buf.extend_from_slice(&[0x00, 0x21, 0x00, 0x01]);
+    buf
+}
+
+/// NTPv4 client mode request
+fn ntp_client_request() -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![0u8; 48];
+    // LI 3 (unsynchronized), version 4, mode 3 (client)
+    buf[0] = 0xe3;
+    buf
+}
+
+/// SNMPv1 GetRequest for sysDescr.0 with community "public"
+fn snmp_get_sys_descr() -> Vec<u8> {
+    // sysDescr.0 = 1.3.6.1.2.1.1.1.0
+    let oid: [u8; 8] = [0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00];
+    let mut var_bind: Vec<u8> = vec![0x06, oid.len() as u8];
+    var_bind.extend_from_slice(&oid);
+    var_bind.extend_from_slice(&[0x05, 0x00]);
+    let var_bind: Vec<u8> = ber_sequence(0x30, &var_bind);
+    let var_bind_list: Vec<u8> = ber_sequence(0x30, &var_bind);
+    // request-id, error-status, error-index
+    let mut pdu: Vec<u8> = vec![
+        0x02, 0x04, 0x4e, 0x50, 0x52, 0x42, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00,
+    ];
+    pdu.extend_from_slice(&var_bind_list);
+    let pdu: Vec<u8> = ber_sequence(0xa0, &pdu);
+    // version 1 (0), community
+    let mut message: Vec<u8> = vec![0x02, 0x01, 0x00, 0x04, 0x06];
+    message.extend_from_slice(b"public");
+    message.extend_from_slice(&pdu);
+    ber_sequence(0x30, &message)
+}
+
+/// BER constructed value with a short-form length
+fn ber_sequence(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![tag, content.len() as u8];
+    buf.extend_from_slice(content);
+    buf
+}
+
+/// IKEv1 Main Mode proposal (3DES, SHA1, PSK, MODP1024).
+/// On port 4500 the message is prefixed with the 4 byte non-ESP marker.
+fn ike_main_mode(nat_traversal: bool) -> Vec<u8> {
+    // Transform: KEY_IKE with four basic attributes
+    let attributes: [u8; 16] = [
+        0x80, 0x01, 0x00, 0x05, // Encryption: 3DES-CBC
+        0x80, 0x02, 0x00, 0x02, // Hash: SHA1
+        0x80, 0x03, 0x00, 0x01, // Authentication: pre-shared key
+        0x80, 0x04, 0x00, 0x02, // Group: MODP1024
+    ];
+    let transform_len: u16 = 8 + attributes.len() as u16;
+    let proposal_len: u16 = 8 + transform_len;
+    let sa_len: u16 = 12 + proposal_len;
+    let total_len: u16 = 28 + sa_len;
+    let mut buf: Vec<u8> = Vec::new();
+    if nat_traversal {
+        buf.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+    }
+    // ISAKMP header
+    buf.extend_from_slice(&rand::random::<u64>().to_be_bytes());
+    buf.extend_from_slice(&[0u8; 8]);
+    // Next payload SA, version 1.0, Identity Protection, no flags, message ID 0
+    buf.extend_from_slice(&[0x01, 0x10, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    buf.extend_from_slice(&(total_len as u32).to_be_bytes());
+    // SA payload, DOI IPsec, situation identity only
+    buf.extend_from_slice(&[0x00, 0x00]);
+    buf.extend_from_slice(&sa_len.to_be_bytes());
+    buf.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01]);
+    // Proposal 1, PROTO_ISAKMP, no SPI, one transform
+    buf.extend_from_slice(&[0x00, 0x00]);
+    buf.extend_from_slice(&proposal_len.to_be_bytes());
+    buf.extend_from_slice(&[0x01, 0x01, 0x00, 0x01]);
+    // Transform 1, KEY_IKE
+    buf.extend_from_slice(&[0x00, 0x00]);
+    buf.extend_from_slice(&transform_len.to_be_bytes());
+    buf.extend_from_slice(&[0x01, 0x01, 0x00, 0x00]);
+    buf.extend_from_slice(&attributes);
+    buf
+}
+
+/// TFTP read request in octet mode
+fn tftp_read_request() -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![0x00, 0x01];
+    buf.extend_from_slice(b"netprobe.txt\0octet\0");
+    buf
+}
+
+/// ONC RPC call of the NULL procedure of portmapper (100000) version 2
+fn rpc_portmap_null() -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    buf.extend_from_slice(&rand::random::<u32>().to_be_bytes());
+    for word in [0u32, 2, 100000, 2, 0, 0, 0, 0, 0] {
+        buf.extend_from_slice(&word.to_be_bytes());
+    }
+    buf
+}
+
+/// OpenVPN P_CONTROL_HARD_RESET_CLIENT_V2 without TLS auth
+fn openvpn_hard_reset() -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![0x38];
+    buf.extend_from_slice(&rand::random::<u64>().to_be_bytes());
+    // No acknowledgements, packet ID 0
+    buf.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00]);
+    buf
+}
+
+/// SSDP discovery of all devices and services
+fn ssdp_m_search() -> Vec<u8> {
+    b"M-SEARCH * HTTP/1.1\r\n\
+HOST: 239.255.255.250:1900\r\n\
+MAN: \"ssdp:discover\"\r\n\
+MX: 1\r\n\
+ST: ssdp:all\r\n\r\n"
+        .to_vec()
+}
+
+/// SIP OPTIONS request
+fn sip_options() -> Vec<u8> {
+    b"OPTIONS sip:nm SIP/2.0\r\n\
+Via: SIP/2.0/UDP nm;branch=z9hG4bKnetprobe\r\n\
+From: <sip:nm@nm>;tag=netprobe\r\n\
+To: <sip:nm2@nm2>\r\n\
+Call-ID: netprobe\r\n\
+CSeq: 1 OPTIONS\r\n\
+Max-Forwards: 70\r\n\
+Content-Length: 0\r\n\r\n"
+        .to_vec()
+}
+
+/// memcached "stats" with the UDP frame header
+fn memcached_stats() -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00];
+    buf.extend_from_slice(b"stats\r\n");
+    buf
+}
+
+/// RMCP presence ping (ASF), answered by IPMI BMCs
+fn ipmi_rmcp_ping() -> Vec<u8> {
+    vec![
+        0x06, 0x00, 0xff, 0x06, 0x00, 0x00, 0x11, 0xbe, 0x80, 0x00, 0x00, 0x00,
+    ]
+}
@@ -0,0 +1,182 @@
+use super::{PortKey, PortScanner, POLL_INTERVAL};
+use crate::result::{PortResult, PortScanResult, PortStatus, ProbeStatus};
+use crate::setting::ProbeSetting;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use xenet::net::interface::Interface;
+use xenet::packet::frame::Frame;
+
+/// Send one probe per pending port and match replies over a raw channel, in rounds.
+///
+/// `build` creates the probe from target, port and cookie. `match_reply` returns the port,
+/// status and TTL of a reply to one of our probes. Ports that never answer get `no_reply_status`,
+/// ports not sent before the probe timeout are left to `collect_host_results`.
+pub(crate) fn raw_scan<B, M>(
+    scanner: &PortScanner,
+    msg_tx: &Arc<Mutex<Sender<PortResult>>>,
+    build: B,
+    match_reply: M,
+    no_reply_status: PortStatus,
+) -> Result<PortScanResult, String>
+where
+    B: Fn(IpAddr, u16, u32) -> Result<Vec<u8>, String> + Sync,
+    M: Fn(&Frame, u64) -> Option<(PortKey, PortStatus, u8)>,
+{
+    let setting: &ProbeSetting = &scanner.probe_setting;
+    let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
+        Some(interface) => interface,
+        None => {
+            return Err(format!(
+                "raw_scan: unable to get interface by index {}",
+                setting.if_index
+            ))
+        }
+    };
+    let config = xenet::datalink::Config {
+        write_buffer_size: 4096,
+        read_buffer_size: 4096,
+        read_timeout: Some(setting.receive_timeout.min(POLL_INTERVAL)),
+        write_timeout: None,
+        channel_type: xenet::datalink::ChannelType::Layer2,
+        bpf_fd_attempts: 1000,
+        linux_fanout: None,
+        promiscuous: false,
+    };
+    // Create a channel to send/receive packet
+    let (mut tx, mut rx) = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+        Ok(_) => return Err("raw_scan: unable to create channel".to_string()),
+        Err(e) => return Err(format!("raw_scan: unable to create channel: {}", e)),
+    };
+    let mut result = PortScanResult::new();
+    result.scan_type = scanner.scan_type;
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let secret: u64 = rand::random::<u64>();
+    let mut port_results: HashMap<PortKey, PortResult> = HashMap::new();
+    let attempts: Mutex<HashMap<PortKey, u8>> = Mutex::new(HashMap::new());
+    let send_times: Mutex<HashMap<PortKey, Instant>> = Mutex::new(HashMap::new());
+    let mut timed_out: bool = false;
+    for _ in 0..=scanner.retries {
+        let pending: Vec<PortKey> = scanner
+            .targets
+            .iter()
+            .flat_map(|ip| scanner.ports.iter().map(move |port| (*ip, *port)))
+            .filter(|key| !port_results.contains_key(key))
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+        let sending_done: AtomicBool = AtomicBool::new(false);
+        let stop: AtomicBool = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for (i, (ip_addr, port)) in pending.iter().enumerate() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let cookie: u32 = super::probe_cookie(secret, *ip_addr, *port);
+                    if let Ok(packet) = build(*ip_addr, *port, cookie) {
+                        if let Ok(mut send_times) = send_times.lock() {
+                            send_times.insert((*ip_addr, *port), Instant::now());
+                        }
+                        let _ = tx.send(&packet);
+                        if let Ok(mut attempts) = attempts.lock() {
+                            *attempts.entry((*ip_addr, *port)).or_insert(0) += 1;
+                        }
+                    }
+                    if i + 1 < pending.len() {
+                        std::thread::sleep(setting.send_rate);
+                    }
+                }
+                sending_done.store(true, Ordering::Relaxed);
+            });
+            let mut answered: usize = 0;
+            let mut send_end: Option<Instant> = None;
+            loop {
+                if let Ok(packet) = rx.next() {
+                    let frame: Frame = crate::packet::decode::parse_frame(packet, setting);
+                    if let Some((key, port_status, ttl)) = match_reply(&frame, secret) {
+                        // Send time is set before the probe is sent, unlike the attempt count
+                        let send_time: Option<Instant> = match send_times.lock() {
+                            Ok(_) if port_results.contains_key(&key) => None,
+                            Ok(send_times) => send_times.get(&key).copied(),
+                            Err(_) => None,
+                        };
+                        if let Some(send_time) = send_time {
+                            let attempt_count: u8 = match attempts.lock() {
+                                Ok(attempts) => attempts.get(&key).copied().unwrap_or(1),
+                                Err(_) => 1,
+                            };
+                            let port_result = PortResult {
+                                ip_addr: key.0,
+                                port: key.1,
+                                port_status,
+                                rtt: send_time.elapsed(),
+                                ttl,
+                                attempts: attempt_count,
+                            };
+                            if let Ok(lr) = msg_tx.lock() {
+                                let _ = lr.send(port_result.clone());
+                            }
+                            port_results.insert(key, port_result);
+                            answered += 1;
+                        }
+                    }
+                }
+                if sending_done.load(Ordering::Relaxed) {
+                    if answered >= pending.len() {
+                        break;
+                    }
+                    let send_end: Instant = *send_end.get_or_insert_with(Instant::now);
+                    if send_end.elapsed() > setting.receive_timeout {
+                        break;
+                    }
+                }
+                if start_time.elapsed() > setting.probe_timeout {
+                    stop.store(true, Ordering::Relaxed);
+                    timed_out = true;
+                    break;
+                }
+            }
+        });
+        if timed_out {
+            break;
+        }
+    }
+    // No reply after all attempts. Ports never sent stay out and are reported as Unknown.
+    let attempts: HashMap<PortKey, u8> = attempts.into_inner().unwrap_or_default();
+    for (key, attempt_count) in &attempts {
+        if port_results.contains_key(key) {
+            continue;
+        }
+        let port_result = PortResult {
+            ip_addr: key.0,
+            port: key.1,
+            port_status: no_reply_status.clone(),
+            rtt: Duration::from_millis(0),
+            ttl: 0,
+            attempts: *attempt_count,
+        };
+        if let Ok(lr) = msg_tx.lock() {
+            let _ = lr.send(port_result.clone());
+        }
+        port_results.insert(*key, port_result);
+    }
+    result.hosts = super::collect_host_results(scanner, &mut port_results);
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = start_time.elapsed();
+    result.probe_status = if timed_out {
+        ProbeStatus::with_timeout_message(format!(
+            "{} scan: probe timeout",
+            scanner.scan_type.name()
+        ))
+    } else {
+        ProbeStatus::new()
+    };
+    Ok(result)
+}
@@ -0,0 +1,349 @@
+use super::cyclic::CyclicPermutation;
+use super::{PortKey, POLL_INTERVAL};
+use crate::result::{PortResult, PortStatus, ProbeStatus, StatelessScanResult};
+use crate::setting::{ProbeSetting, ScanType};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use xenet::net::interface::Interface;
+use xenet::net::ipnet::IpNet;
+use xenet::packet::frame::Frame;
+
+/// Largest IPv6 network that can be enumerated (/64)
+const MAX_IPV6_HOST_BITS: u8 = 63;
+
+/// Stateless SYN scanner for large target spaces, in the way of zmap.
+///
+/// Targets and ports are visited in a random order generated by a cyclic group, so no list
+/// of probes is kept. The sequence number of every SYN is a keyed hash of the target address
+/// and port, which lets replies be validated without per-probe state.
+/// Results are streamed through the progress receiver as replies arrive.
+#[derive(Clone, Debug)]
+pub struct StatelessScanner {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Networks to scan. A single host is a /32 or /128 network
+    pub targets: Vec<IpNet>,
+    /// Ports to scan on every host
+    pub ports: Vec<u16>,
+    /// Probes per second over the whole scan. 0 sends as fast as possible
+    pub rate: u32,
+    /// Seed of the target and port order. Random if None
+    pub seed: Option<u64>,
+    /// Report every host and port only once, even if the reply is retransmitted
+    pub dedup: bool,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<PortResult>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<PortResult>>>,
+}
+
+impl StatelessScanner {
+    /// Create new StatelessScanner instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<StatelessScanner, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "StatelessScanner::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let (tx, rx) = channel();
+        let scanner = StatelessScanner {
+            targets: vec![IpNet::from(setting.dst_ip)],
+            ports: setting.dst_port.into_iter().collect(),
+            probe_setting: setting,
+            rate: 10000,
+            seed: None,
+            dedup: true,
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(scanner)
+    }
+    pub fn with_targets(mut self, targets: Vec<IpAddr>) -> StatelessScanner {
+        self.targets = targets.into_iter().map(IpNet::from).collect();
+        self
+    }
+    pub fn with_networks(mut self, networks: Vec<IpNet>) -> StatelessScanner {
+        self.targets = networks;
+        self
+    }
+    pub fn with_ports(mut self, ports: Vec<u16>) -> StatelessScanner {
+        self.ports = ports;
+        self
+    }
+    /// Scan `start..=end`
+    pub fn with_port_range(mut self, start: u16, end: u16) -> StatelessScanner {
+        self.ports = (start..=end).collect();
+        self
+    }
+    /// Limit probes per second. 0 removes the limit
+    pub fn with_rate(mut self, packets_per_second: u32) -> StatelessScanner {
+        self.rate = packets_per_second;
+        self
+    }
+    pub fn with_seed(mut self, seed: u64) -> StatelessScanner {
+        self.seed = Some(seed);
+        self
+    }
+    pub fn with_dedup(mut self, dedup: bool) -> StatelessScanner {
+        self.dedup = dedup;
+        self
+    }
+    /// Run scan. Waits `receive_timeout` for late replies once every probe is sent.
+    pub fn scan(&self) -> Result<StatelessScanResult, String> {
+        let setting: &ProbeSetting = &self.probe_setting;
+        if self.ports.is_empty() {
+            return Err("StatelessScanner::scan: no ports to scan".to_string());
+        }
+        let space: TargetSpace = TargetSpace::new(&self.targets, &self.ports)?;
+        if let Some(target) = self
+            .targets
+            .iter()
+            .find(|t| t.addr().is_ipv4() != setting.src_ip.is_ipv4())
+        {
+            return Err(format!(
+                "StatelessScanner::scan: address family of {} does not match source {}",
+                target.addr(),
+                setting.src_ip
+            ));
+        }
+        let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index)
+        {
+            Some(interface) => interface,
+            None => {
+                return Err(format!(
+                    "StatelessScanner::scan: unable to get interface by index {}",
+                    setting.if_index
+                ))
+            }
+        };
+        let config = xenet::datalink::Config {
+            write_buffer_size: 4096,
+            read_buffer_size: 4096,
+            read_timeout: Some(setting.receive_timeout.min(POLL_INTERVAL)),
+            write_timeout: None,
+            channel_type: xenet::datalink::ChannelType::Layer2,
+            bpf_fd_attempts: 1000,
+            linux_fanout: None,
+            promiscuous: false,
+        };
+        // Create a channel to send/receive packet
+        let (mut tx, mut rx) = match xenet::datalink::channel(&interface, config) {
+            Ok(xenet::datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+            Ok(_) => return Err("StatelessScanner::scan: unable to create channel".to_string()),
+            Err(e) => {
+                return Err(format!(
+                    "StatelessScanner::scan: unable to create channel: {}",
+                    e
+                ))
+            }
+        };
+        let src_port: u16 = setting
+            .src_port
+            .unwrap_or(crate::packet::tcp::TCP_DEFAULT_SRC_PORT);
+        // Built once, then only destination and sequence number change
+        let mut base_setting: ProbeSetting = setting.clone();
+        base_setting.src_port = Some(src_port);
+        base_setting.dst_ip = space.get(0).0;
+        let mut packet: Vec<u8> = crate::packet::tcp::build_tcp_packet(base_setting, None);
+        let mut rng: StdRng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let permutation: CyclicPermutation = CyclicPermutation::new(space.len(), &mut rng);
+        let secret: u64 = rand::random::<u64>();
+        let rate: u32 = self.rate;
+        let mut result = StatelessScanResult::new();
+        result.start_time = crate::sys::get_sysdate();
+        let start_time = Instant::now();
+        let sending_done: AtomicBool = AtomicBool::new(false);
+        let stop: AtomicBool = AtomicBool::new(false);
+        let mut seen: HashSet<PortKey> = HashSet::new();
+        let mut timed_out: bool = false;
+        let (sent_count, send_duration) = std::thread::scope(|s| {
+            let sender = s.spawn(|| {
+                let send_start = Instant::now();
+                let mut sent: u64 = 0;
+                for index in permutation {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let (ip_addr, port) = space.get(index);
+                    let cookie: u32 = super::probe_cookie(secret, ip_addr, port);
+                    if crate::packet::tcp::rewrite_tcp_packet(
+                        &mut packet,
+                        setting.tunnel,
+                        ip_addr,
+                        port,
+                        cookie,
+                    )
+                    .is_err()
+                    {
+                        continue;
+                    }
+                    let _ = tx.send(&packet);
+                    sent += 1;
+                    // Global rate limit. Sleeping against the schedule keeps the average exact
+                    // even when the OS sleeps longer than asked.
+                    if rate > 0 {
+                        let due: Duration = Duration::from_nanos(
+                            (sent as u128 * 1_000_000_000 / rate as u128).min(u64::MAX as u128)
+                                as u64,
+                        );
+                        let elapsed: Duration = send_start.elapsed();
+                        if due > elapsed {
+                            std::thread::sleep(due - elapsed);
+                        }
+                    }
+                }
+                sending_done.store(true, Ordering::Relaxed);
+                (sent, send_start.elapsed())
+            });
+            let mut send_end: Option<Instant> = None;
+            loop {
+                if let Ok(packet) = rx.next() {
+                    let frame: Frame = crate::packet::decode::parse_frame(packet, setting);
+                    match super::tcp::match_reply(
+                        &frame,
+                        setting,
+                        ScanType::TcpSyn,
+                        secret,
+                        src_port,
+                    ) {
+                        Some((key, port_status, ttl)) => {
+                            result.validated_count += 1;
+                            if self.dedup && !seen.insert(key) {
+                                continue;
+                            }
+                            if port_status == PortStatus::Open {
+                                result.open_count += 1;
+                            }
+                            let port_result = PortResult {
+                                ip_addr: key.0,
+                                port: key.1,
+                                port_status,
+                                rtt: Duration::from_millis(0),
+                                ttl,
+                                attempts: 1,
+                            };
+                            if let Ok(lr) = self.tx.lock() {
+                                let _ = lr.send(port_result);
+                            }
+                        }
+                        None if is_reply_to_port(&frame, src_port) => {
+                            result.invalid_count += 1;
+                        }
+                        None => {}
+                    }
+                }
+                if sending_done.load(Ordering::Relaxed) {
+                    let send_end: Instant = *send_end.get_or_insert_with(Instant::now);
+                    if send_end.elapsed() > setting.receive_timeout {
+                        break;
+                    }
+                }
+                if start_time.elapsed() > setting.probe_timeout {
+                    stop.store(true, Ordering::Relaxed);
+                    timed_out = true;
+                    break;
+                }
+            }
+            sender.join().unwrap_or((0, Duration::from_millis(0)))
+        });
+        result.sent_count = sent_count;
+        result.send_duration = send_duration;
+        result.end_time = crate::sys::get_sysdate();
+        result.elapsed_time = start_time.elapsed();
+        result.probe_status = if timed_out {
+            ProbeStatus::with_timeout_message("StatelessScanner::scan: probe timeout".to_string())
+        } else {
+            ProbeStatus::new()
+        };
+        Ok(result)
+    }
+    /// Get progress receiver
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<PortResult>>> {
+        self.rx.clone()
+    }
+}
+
+/// TCP segment to our source port
+fn is_reply_to_port(frame: &Frame, src_port: u16) -> bool {
+    match frame.transport.as_ref().and_then(|t| t.tcp.as_ref()) {
+        Some(tcp_header) => tcp_header.destination == src_port,
+        None => false,
+    }
+}
+
+/// Every (address, port) pair of the scan, addressed by index
+struct TargetSpace<'a> {
+    networks: &'a [IpNet],
+    /// Index past the last host of every network
+    ends: Vec<u64>,
+    ports: &'a [u16],
+    len: u64,
+}
+
+impl<'a> TargetSpace<'a> {
+    fn new(networks: &'a [IpNet], ports: &'a [u16]) -> Result<TargetSpace<'a>, String> {
+        if networks.is_empty() {
+            return Err("StatelessScanner::scan: no targets to scan".to_string());
+        }
+        let mut ends: Vec<u64> = Vec::with_capacity(networks.len());
+        let mut hosts: u64 = 0;
+        for network in networks {
+            let host_bits: u8 = network
+                .max_prefix_len()
+                .saturating_sub(network.prefix_len());
+            if host_bits > MAX_IPV6_HOST_BITS {
+                return Err(format!(
+                    "StatelessScanner::scan: network {}/{} is too large",
+                    network.addr(),
+                    network.prefix_len()
+                ));
+            }
+            hosts = hosts
+                .checked_add(1u64 << host_bits)
+                .ok_or("StatelessScanner::scan: too many targets".to_string())?;
+            ends.push(hosts);
+        }
+        let len: u64 = hosts
+            .checked_mul(ports.len() as u64)
+            .ok_or("StatelessScanner::scan: too many probes".to_string())?;
+        Ok(TargetSpace {
+            networks,
+            ends,
+            ports,
+            len,
+        })
+    }
+    fn len(&self) -> u64 {
+        self.len
+    }
+    fn get(&self, index: u64) -> (IpAddr, u16) {
+        let port_count: u64 = self.ports.len() as u64;
+        let port: u16 = self.ports[(index % port_count) as usize];
+        let host: u64 = index / port_count;
+        let i: usize = self.ends.partition_point(|end| *end <= host);
+        let start: u64 = if i == 0 { 0 } else { self.ends[i - 1] };
+        let offset: u64 = host - start;
+        let ip_addr: IpAddr = match self.networks[i].network() {
+            IpAddr::V4(network) => IpAddr::V4(Ipv4Addr::from(
+                u32::from(network).wrapping_add(offset as u32),
+            )),
+            IpAddr::V6(network) => IpAddr::V6(Ipv6Addr::from(
+                u128::from(network).wrapping_add(offset as u128),
+            )),
+        };
+        (ip_addr, port)
+    }
+}
@@ -0,0 +1,245 @@
+use super::{PortKey, PortScanner};
+use crate::packet::decode::{IcmpError, NEXT_HEADER_TCP};
+use crate::result::{PortResult, PortScanResult, PortStatus, UnreachableReason};
+use crate::setting::{ProbeSetting, Protocol, ScanType};
+use crate::template::{ProbeTemplate, TemplateTcpOption};
+use std::net::IpAddr;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use xenet::packet::frame::Frame;
+use xenet::packet::tcp::TcpFlags;
+
+const SCAN_WINDOW: u16 = 1024;
+const SCAN_MSS: u16 = 1460;
+
+pub(crate) fn tcp_scan(
+    scanner: &PortScanner,
+    msg_tx: &Arc<Mutex<Sender<PortResult>>>,
+) -> Result<PortScanResult, String> {
+    let setting: &ProbeSetting = &scanner.probe_setting;
+    let src_port: u16 = setting
+        .src_port
+        .unwrap_or(crate::packet::tcp::TCP_DEFAULT_SRC_PORT);
+    super::raw::raw_scan(
+        scanner,
+        msg_tx,
+        |ip_addr, port, cookie| {
+            build_scan_packet(setting, scanner.scan_type, ip_addr, port, cookie)
+        },
+        |frame, secret| match_reply(frame, setting, scanner.scan_type, secret, src_port),
+        PortStatus::Filtered,
+    )
+}
+
+/// SYN, or ACK for the ACK scan. The cookie is used as sequence (and acknowledgement) number.
+fn build_scan_packet(
+    setting: &ProbeSetting,
+    scan_type: ScanType,
+    ip_addr: IpAddr,
+    port: u16,
+    cookie: u32,
+) -> Result<Vec<u8>, String> {
+    let mut probe_setting: ProbeSetting = setting.clone();
+    probe_setting.dst_ip = ip_addr;
+    probe_setting.dst_port = Some(port);
+    let template: ProbeTemplate = match scan_type {
+        ScanType::TcpAck => ProbeTemplate::new(Protocol::TCP)
+            .with_tcp_flags(TcpFlags::ACK)
+            .with_tcp_ack(cookie),
+        _ => ProbeTemplate::new(Protocol::TCP)
+            .with_tcp_flags(TcpFlags::SYN)
+            .with_tcp_options(vec![TemplateTcpOption::Mss(SCAN_MSS)]),
+    };
+    template
+        .with_tcp_seq(cookie)
+        .with_tcp_window(SCAN_WINDOW)
+        .build(&probe_setting)
+}
+
+/// Port, status and TTL of a reply to one of our probes
+pub(crate) fn match_reply(
+    frame: &Frame,
+    setting: &ProbeSetting,
+    scan_type: ScanType,
+    secret: u64,
+    src_port: u16,
+) -> Option<(PortKey, PortStatus, u8)> {
+    let ip_layer = frame.ip.as_ref()?;
+    let (source, ttl) = if let Some(ipv4_header) = &ip_layer.ipv4 {
+        (IpAddr::V4(ipv4_header.source), ipv4_header.ttl)
+    } else if let Some(ipv6_header) = &ip_layer.ipv6 {
+        (IpAddr::V6(ipv6_header.source), ipv6_header.hop_limit)
+    } else {
+        return None;
+    };
+    if let Some(tcp_header) = frame.transport.as_ref().and_then(|t| t.tcp.as_ref()) {
+        if tcp_header.destination != src_port {
+            return None;
+        }
+        let cookie: u32 = super::probe_cookie(secret, source, tcp_header.source);
+        let key: PortKey = (source, tcp_header.source);
+        let is_rst: bool = tcp_header.flags & TcpFlags::RST != 0;
+        return match scan_type {
+            ScanType::TcpAck if is_rst && tcp_header.sequence == cookie => {
+                Some((key, PortStatus::Unfiltered, ttl))
+            }
+            ScanType::TcpAck => None,
+            _ if tcp_header.acknowledgement != cookie.wrapping_add(1) => None,
+            _ if is_rst => Some((key, PortStatus::Closed, ttl)),
+            _ if tcp_header.flags & (TcpFlags::SYN | TcpFlags::ACK)
+                == TcpFlags::SYN | TcpFlags::ACK =>
+            {
+                Some((key, PortStatus::Open, ttl))
+            }
+            _ => None,
+        };
+    }
+    let icmp_error: IcmpError = IcmpError::from_frame(frame)?;
+    let reason: UnreachableReason = icmp_error.unreachable_reason()?;
+    let quoted = icmp_error.quoted.as_ref()?;
+    if quoted.src_ip != setting.src_ip
+        || quoted.next_protocol != NEXT_HEADER_TCP
+        || quoted.src_port != Some(src_port)
+    {
+        return None;
+    }
+    let port: u16 = quoted.dst_port?;
+    if quoted.tcp_seq != Some(super::probe_cookie(secret, quoted.dst_ip, port)) {
+        return None;
+    }
+    Some((
+        (quoted.dst_ip, port),
+        reason.port_status(&Protocol::TCP),
+        ttl,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::decode::parse_ip_packet;
+    use std::net::Ipv4Addr;
+
+    const SECRET: u64 = 0x5eed;
+    const SRC_PORT: u16 = 40000;
+    const DST_PORT: u16 = 80;
+
+    fn scanner_setting() -> ProbeSetting {
+        ProbeSetting {
+            src_ip: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            src_port: Some(SRC_PORT),
+            tunnel: true,
+            ..ProbeSetting::new()
+        }
+    }
+
+    fn target() -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))
+    }
+
+    /// Segment sent by the target port to the scanner
+    fn reply(flags: u8, seq: u32, ack: u32) -> Frame {
+        let setting = ProbeSetting {
+            src_ip: target(),
+            dst_ip: scanner_setting().src_ip,
+            src_port: Some(DST_PORT),
+            dst_port: Some(SRC_PORT),
+            tunnel: true,
+            ..ProbeSetting::new()
+        };
+        let packet: Vec<u8> = ProbeTemplate::new(Protocol::TCP)
+            .with_tcp_flags(flags)
+            .with_tcp_seq(seq)
+            .with_tcp_ack(ack)
+            .build(&setting)
+            .unwrap();
+        parse_ip_packet(&packet)
+    }
+
+    fn syn_scan_reply(frame: &Frame) -> Option<(PortKey, PortStatus, u8)> {
+        match_reply(
+            frame,
+            &scanner_setting(),
+            ScanType::TcpSyn,
+            SECRET,
+            SRC_PORT,
+        )
+    }
+
+    #[test]
+    fn probe_carries_cookie() {
+        let cookie: u32 = crate::scan::probe_cookie(SECRET, target(), DST_PORT);
+        assert_ne!(
+            cookie,
+            crate::scan::probe_cookie(SECRET, target(), DST_PORT + 1)
+        );
+        assert_ne!(
+            cookie,
+            crate::scan::probe_cookie(SECRET + 1, target(), DST_PORT)
+        );
+        let packet: Vec<u8> = build_scan_packet(
+            &scanner_setting(),
+            ScanType::TcpSyn,
+            target(),
+            DST_PORT,
+            cookie,
+        )
+        .unwrap();
+        let frame: Frame = parse_ip_packet(&packet);
+        let tcp_header = frame.transport.unwrap().tcp.unwrap();
+        assert_eq!(tcp_header.sequence, cookie);
+        assert_eq!(tcp_header.destination, DST_PORT);
+        assert_eq!(tcp_header.flags, TcpFlags::SYN);
+    }
+
+    #[test]
+    fn syn_ack_acknowledges_cookie() {
+        let cookie: u32 = crate::scan::probe_cookie(SECRET, target(), DST_PORT);
+        let syn_ack: u8 = TcpFlags::SYN | TcpFlags::ACK;
+        assert_eq!(
+            syn_scan_reply(&reply(syn_ack, 1, cookie.wrapping_add(1))),
+            Some(((target(), DST_PORT), PortStatus::Open, 64))
+        );
+        assert_eq!(syn_scan_reply(&reply(syn_ack, 1, cookie)), None);
+        assert_eq!(
+            syn_scan_reply(&reply(
+                TcpFlags::RST | TcpFlags::ACK,
+                0,
+                cookie.wrapping_add(1)
+            )),
+            Some(((target(), DST_PORT), PortStatus::Closed, 64))
+        );
+        // Reply to another source port
+        let frame: Frame = reply(syn_ack, 1, cookie.wrapping_add(1));
+        let other_port: Option<(PortKey, PortStatus, u8)> = match_reply(
+            &frame,
+            &scanner_setting(),
+            ScanType::TcpSyn,
+            SECRET,
+            SRC_PORT + 1,
+        );
+        assert_eq!(other_port, None);
+    }
+
+    #[test]
+    fn ack_scan_rst_echoes_cookie() {
+        let cookie: u32 = crate::scan::probe_cookie(SECRET, target(), DST_PORT);
+        let ack_scan_reply = |frame: &Frame| {
+            match_reply(
+                frame,
+                &scanner_setting(),
+                ScanType::TcpAck,
+                SECRET,
+                SRC_PORT,
+            )
+        };
+        assert_eq!(
+            ack_scan_reply(&reply(TcpFlags::RST, cookie, 0)),
+            Some(((target(), DST_PORT), PortStatus::Unfiltered, 64))
+        );
+        assert_eq!(
+            ack_scan_reply(&reply(TcpFlags::RST, cookie.wrapping_add(1), 0)),
+            None
+        );
+    }
+}
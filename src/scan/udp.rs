@@ -0,0 +1,93 @@
+use super::{PortKey, PortScanner};
+use crate::packet::decode::{IcmpError, NEXT_HEADER_UDP};
+use crate::result::{PortResult, PortScanResult, PortStatus, UnreachableReason};
+use crate::setting::{ProbeSetting, Protocol};
+use crate::template::ProbeTemplate;
+use std::net::IpAddr;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use xenet::packet::frame::Frame;
+
+pub(crate) fn udp_scan(
+    scanner: &PortScanner,
+    msg_tx: &Arc<Mutex<Sender<PortResult>>>,
+) -> Result<PortScanResult, String> {
+    let setting: &ProbeSetting = &scanner.probe_setting;
+    let src_port: u16 = setting
+        .src_port
+        .unwrap_or(crate::packet::udp::UDP_DEFAULT_SRC_PORT);
+    super::raw::raw_scan(
+        scanner,
+        msg_tx,
+        |ip_addr, port, cookie| build_scan_packet(scanner, src_port, ip_addr, port, cookie),
+        |frame, secret| match_reply(frame, setting, secret, src_port),
+        // UDP services commonly stay silent, so no reply proves nothing
+        PortStatus::OpenFiltered,
+    )
+}
+
+/// UDP datagram with the payload for the port. The cookie is used as IPv4 identification.
+fn build_scan_packet(
+    scanner: &PortScanner,
+    src_port: u16,
+    ip_addr: IpAddr,
+    port: u16,
+    cookie: u32,
+) -> Result<Vec<u8>, String> {
+    let mut probe_setting: ProbeSetting = scanner.probe_setting.clone();
+    probe_setting.dst_ip = ip_addr;
+    probe_setting.dst_port = Some(port);
+    let payload: Vec<u8> = match scanner.udp_payloads.get(&port) {
+        Some(payload) => payload.clone(),
+        None => super::payload::udp_payload(port),
+    };
+    ProbeTemplate::new(Protocol::UDP)
+        .with_src_port(src_port)
+        .with_ip_id(cookie as u16)
+        .with_payload(payload)
+        .build(&probe_setting)
+}
+
+/// Port, status and TTL of a reply to one of our probes
+fn match_reply(
+    frame: &Frame,
+    setting: &ProbeSetting,
+    secret: u64,
+    src_port: u16,
+) -> Option<(PortKey, PortStatus, u8)> {
+    let ip_layer = frame.ip.as_ref()?;
+    let (source, ttl) = if let Some(ipv4_header) = &ip_layer.ipv4 {
+        (IpAddr::V4(ipv4_header.source), ipv4_header.ttl)
+    } else if let Some(ipv6_header) = &ip_layer.ipv6 {
+        (IpAddr::V6(ipv6_header.source), ipv6_header.hop_limit)
+    } else {
+        return None;
+    };
+    // Any datagram back from the probed port means a service is listening
+    if let Some(udp_header) = frame.transport.as_ref().and_then(|t| t.udp.as_ref()) {
+        if udp_header.destination != src_port {
+            return None;
+        }
+        return Some(((source, udp_header.source), PortStatus::Open, ttl));
+    }
+    let icmp_error: IcmpError = IcmpError::from_frame(frame)?;
+    let reason: UnreachableReason = icmp_error.unreachable_reason()?;
+    let quoted = icmp_error.quoted.as_ref()?;
+    if quoted.src_ip != setting.src_ip
+        || quoted.next_protocol != NEXT_HEADER_UDP
+        || quoted.src_port != Some(src_port)
+    {
+        return None;
+    }
+    let port: u16 = quoted.dst_port?;
+    if let Some(ip_id) = quoted.ip_id {
+        if ip_id != super::probe_cookie(secret, quoted.dst_ip, port) as u16 {
+            return None;
+        }
+    }
+    Some((
+        (quoted.dst_ip, port),
+        reason.port_status(&Protocol::UDP),
+        ttl,
+    ))
+}
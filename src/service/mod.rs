@@ -0,0 +1,247 @@
+pub mod probes;
+
+use crate::result::{PortStatus, ProbeResult, ProbeStatus, ServiceDetectionResult, ServiceInfo};
+use crate::setting::{ProbeSetting, Protocol};
+use probes::{ServiceDatabase, ServiceProbe};
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Largest response kept for matching
+const MAX_RESPONSE_SIZE: usize = 16 * 1024;
+
+/// Service detector structure.
+///
+/// Connects to TCP ports of `probe_setting.dst_ip`, reads the banner or the response to
+/// protocol-specific probes and identifies the service with the rules of a [`ServiceDatabase`].
+#[derive(Clone, Debug)]
+pub struct ServiceDetector {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// Ports to identify, usually the open ports of a port scan
+    pub ports: Vec<u16>,
+    /// Probes and match rules. Defaults to [`ServiceDatabase::builtin`]
+    pub database: Arc<ServiceDatabase>,
+    /// Send every probe to every port, not only the probes meant for the port
+    pub all_probes: bool,
+    /// Ports identified simultaneously
+    pub concurrency: usize,
+    /// Sender for progress messaging
+    tx: Arc<Mutex<Sender<ProbeResult>>>,
+    /// Receiver for progress messaging
+    rx: Arc<Mutex<Receiver<ProbeResult>>>,
+}
+
+impl ServiceDetector {
+    /// Create new ServiceDetector instance with setting
+    pub fn new(setting: ProbeSetting) -> Result<ServiceDetector, String> {
+        if setting.dst_ip.is_unspecified() {
+            return Err("ServiceDetector::new: destination address is unspecified".to_string());
+        }
+        let (tx, rx) = channel();
+        let detector = ServiceDetector {
+            ports: setting.dst_port.into_iter().collect(),
+            probe_setting: setting,
+            database: Arc::new(ServiceDatabase::builtin()),
+            all_probes: false,
+            concurrency: 16,
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Arc::new(Mutex::new(rx)),
+        };
+        Ok(detector)
+    }
+    pub fn with_ports(mut self, ports: Vec<u16>) -> ServiceDetector {
+        self.ports = ports;
+        self
+    }
+    /// Use `database` instead of the built-in probes
+    pub fn with_database(mut self, database: ServiceDatabase) -> ServiceDetector {
+        self.database = Arc::new(database);
+        self
+    }
+    pub fn with_all_probes(mut self, all_probes: bool) -> ServiceDetector {
+        self.all_probes = all_probes;
+        self
+    }
+    pub fn with_concurrency(mut self, concurrency: usize) -> ServiceDetector {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Run service detection
+    pub fn detect(&self) -> Result<ServiceDetectionResult, String> {
+        if self.ports.is_empty() {
+            return Err("ServiceDetector::detect: no ports to probe".to_string());
+        }
+        let setting: &ProbeSetting = &self.probe_setting;
+        let mut result = ServiceDetectionResult::new();
+        result.ip_addr = setting.dst_ip;
+        result.host_name = setting.dst_hostname.clone();
+        result.start_time = crate::sys::get_sysdate();
+        let start_time = Instant::now();
+        let queue: Mutex<VecDeque<(usize, u16)>> =
+            Mutex::new(self.ports.iter().copied().enumerate().collect());
+        let port_results: Mutex<Vec<Option<ProbeResult>>> =
+            Mutex::new(vec![None; self.ports.len()]);
+        std::thread::scope(|s| {
+            for _ in 0..self.concurrency.min(self.ports.len()) {
+                s.spawn(|| loop {
+                    if start_time.elapsed() > setting.probe_timeout {
+                        break;
+                    }
+                    let (index, port) = match queue.lock() {
+                        Ok(mut queue) => match queue.pop_front() {
+                            Some(next) => next,
+                            None => break,
+                        },
+                        Err(_) => break,
+                    };
+                    let probe_result: ProbeResult = self.detect_port(port);
+                    if let Ok(lr) = self.tx.lock() {
+                        let _ = lr.send(probe_result.clone());
+                    }
+                    if let Ok(mut port_results) = port_results.lock() {
+                        port_results[index] = Some(probe_result);
+                    }
+                });
+            }
+        });
+        let port_results: Vec<Option<ProbeResult>> = match port_results.into_inner() {
+            Ok(port_results) => port_results,
+            Err(e) => e.into_inner(),
+        };
+        let timed_out: bool = port_results.iter().any(|r| r.is_none());
+        result.results = port_results.into_iter().flatten().collect();
+        result.end_time = crate::sys::get_sysdate();
+        result.elapsed_time = start_time.elapsed();
+        result.probe_status = if timed_out {
+            ProbeStatus::with_timeout_message("service_detection: probe timeout".to_string())
+        } else {
+            ProbeStatus::new()
+        };
+        Ok(result)
+    }
+    /// Get progress receiver
+    pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<ProbeResult>>> {
+        self.rx.clone()
+    }
+    /// Send the probes for `port` in turn, each over a new connection, until a rule matches.
+    /// A softmatch, or else the first response, is kept if no rule identifies the version.
+    fn detect_port(&self, port: u16) -> ProbeResult {
+        let setting: &ProbeSetting = &self.probe_setting;
+        let socket_addr = SocketAddr::new(setting.dst_ip, port);
+        let mut probe_result = ProbeResult {
+            ip_addr: setting.dst_ip,
+            host_name: setting.dst_hostname.clone(),
+            port_number: Some(port),
+            protocol: Protocol::TCP,
+            ..ProbeResult::new()
+        };
+        let mut fallback: Option<ServiceInfo> = None;
+        for (i, probe) in self
+            .database
+            .probes_for_port(port, self.all_probes)
+            .into_iter()
+            .enumerate()
+        {
+            let connect_start = Instant::now();
+            let stream: TcpStream =
+                match TcpStream::connect_timeout(&socket_addr, setting.receive_timeout) {
+                    Ok(stream) => stream,
+                    Err(e) if i == 0 => {
+                        let (port_status, probe_status) = match e.kind() {
+                            ErrorKind::ConnectionRefused => (
+                                PortStatus::Closed,
+                                ProbeStatus::with_error_message(format!("{}: {}", socket_addr, e)),
+                            ),
+                            ErrorKind::TimedOut => (
+                                PortStatus::Filtered,
+                                ProbeStatus::with_timeout_message(format!(
+                                    "Connection timeout for {}",
+                                    socket_addr
+                                )),
+                            ),
+                            _ => (
+                                PortStatus::Filtered,
+                                ProbeStatus::with_error_message(format!("{}: {}", socket_addr, e)),
+                            ),
+                        };
+                        probe_result.rtt = connect_start.elapsed();
+                        probe_result.port_status = Some(port_status);
+                        probe_result.probe_status = probe_status;
+                        return probe_result;
+                    }
+                    Err(_) => continue,
+                };
+            if i == 0 {
+                probe_result.rtt = connect_start.elapsed();
+                probe_result.port_status = Some(PortStatus::Open);
+            }
+            let (response, service) = self.exchange(stream, probe);
+            probe_result.received_packet_size =
+                probe_result.received_packet_size.max(response.len());
+            match service {
+                Some(service) if !service.soft_match => {
+                    probe_result.service = Some(service);
+                    return probe_result;
+                }
+                // Keep the first softmatch over an unmatched response
+                Some(service) if !fallback.as_ref().is_some_and(|f| f.soft_match) => {
+                    fallback = Some(service);
+                }
+                None if !response.is_empty() && fallback.is_none() => {
+                    fallback = Some(ServiceInfo::new(
+                        String::from("unknown"),
+                        probe.name.clone(),
+                        response,
+                    ));
+                }
+                _ => {}
+            }
+        }
+        probe_result.service = fallback;
+        probe_result
+    }
+    /// Send the probe payload and read until a rule matches, the server closes the
+    /// connection or the wait time of the probe runs out
+    fn exchange(
+        &self,
+        mut stream: TcpStream,
+        probe: &ServiceProbe,
+    ) -> (Vec<u8>, Option<ServiceInfo>) {
+        let wait: Duration = probe
+            .total_wait
+            .unwrap_or(self.probe_setting.receive_timeout);
+        let deadline: Instant = Instant::now() + wait;
+        let mut response: Vec<u8> = Vec::new();
+        let mut service: Option<ServiceInfo> = None;
+        if !probe.payload.is_empty() && stream.write_all(&probe.payload).is_err() {
+            return (response, service);
+        }
+        let mut buf = [0u8; 4096];
+        loop {
+            let remaining: Duration = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || stream.set_read_timeout(Some(remaining)).is_err() {
+                break;
+            }
+            match stream.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    response.extend_from_slice(&buf[..n]);
+                    service = self.database.match_response(probe, &response);
+                    if service.as_ref().is_some_and(|s| !s.soft_match)
+                        || response.len() >= MAX_RESPONSE_SIZE
+                    {
+                        break;
+                    }
+                }
+                // Timeout or reset
+                Err(_) => break,
+            }
+        }
+        let _ = stream.shutdown(Shutdown::Both);
+        (response, service)
+    }
+}
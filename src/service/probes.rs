@@ -0,0 +1,536 @@
+use crate::result::ServiceInfo;
+use regex::bytes::{Captures, Regex, RegexBuilder};
+use std::path::Path;
+use std::time::Duration;
+
+/// Bundled service probes
+const BUILTIN_SERVICE_PROBES: &str = include_str!("service-probes");
+
+/// Name of the probe that sends nothing and waits for a banner
+pub const NULL_PROBE: &str = "NULL";
+
+/// Rule classifying the response to a probe
+#[derive(Clone, Debug)]
+pub struct ServiceMatch {
+    pub service: String,
+    pub pattern: Regex,
+    /// softmatch rule, identifies the service only
+    pub soft: bool,
+    /// Templates of the version fields. `$1` to `$9` refer to capture groups
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub info: Option<String>,
+    pub hostname: Option<String>,
+    pub os: Option<String>,
+    pub device_type: Option<String>,
+}
+
+impl ServiceMatch {
+    /// Service described by this rule, if `response` matches
+    pub fn apply(&self, probe: &str, response: &[u8]) -> Option<ServiceInfo> {
+        let captures: Captures = self.pattern.captures(response)?;
+        let field = |template: &Option<String>| -> Option<String> {
+            template
+                .as_ref()
+                .map(|t| substitute(t, &captures).trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        Some(ServiceInfo {
+            product: field(&self.product),
+            version: field(&self.version),
+            info: field(&self.info),
+            hostname: field(&self.hostname),
+            os: field(&self.os),
+            device_type: field(&self.device_type),
+            soft_match: self.soft,
+            ..ServiceInfo::new(self.service.clone(), probe.to_string(), response.to_vec())
+        })
+    }
+}
+
+/// Data sent to a TCP port, with the rules for its response
+#[derive(Clone, Debug)]
+pub struct ServiceProbe {
+    pub name: String,
+    pub payload: Vec<u8>,
+    /// Ports the probe is meant for
+    pub ports: Vec<u16>,
+    /// Time to wait for the response. Defaults to the receive timeout
+    pub total_wait: Option<Duration>,
+    /// 1 (common) to 9 (rare). Probes are sent in rarity order
+    pub rarity: u8,
+    /// Probes whose rules are also tried on the response
+    pub fallback: Vec<String>,
+    pub matches: Vec<ServiceMatch>,
+}
+
+impl ServiceProbe {
+    fn new(name: String, payload: Vec<u8>) -> ServiceProbe {
+        ServiceProbe {
+            name,
+            payload,
+            ports: Vec::new(),
+            total_wait: None,
+            rarity: 5,
+            fallback: Vec::new(),
+            matches: Vec::new(),
+        }
+    }
+}
+
+/// Service probes and match rules in nmap-service-probes format
+#[derive(Clone, Debug)]
+pub struct ServiceDatabase {
+    pub probes: Vec<ServiceProbe>,
+    /// Rules skipped because the regex crate does not support the pattern
+    /// (look-around, back-references)
+    pub skipped_rules: usize,
+}
+
+impl ServiceDatabase {
+    /// Create empty database
+    pub fn new() -> ServiceDatabase {
+        ServiceDatabase {
+            probes: Vec::new(),
+            skipped_rules: 0,
+        }
+    }
+    /// Database bundled with netprobe
+    pub fn builtin() -> ServiceDatabase {
+        // The bundled file is known to be valid
+        ServiceDatabase::parse(BUILTIN_SERVICE_PROBES).unwrap_or_default()
+    }
+    /// Load database from file. The nmap-service-probes file of Nmap can be loaded as is.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ServiceDatabase, String> {
+        match std::fs::read_to_string(path.as_ref()) {
+            Ok(content) => ServiceDatabase::parse(&content),
+            Err(e) => Err(format!(
+                "ServiceDatabase::load: unable to read {}: {}",
+                path.as_ref().display(),
+                e
+            )),
+        }
+    }
+    /// Parse database text.
+    ///
+    /// Reads `Probe`, `match`, `softmatch`, `ports`, `totalwaitms`, `rarity` and `fallback`.
+    /// UDP probes and SSL-only directives are skipped. Lines starting with `#` are comments.
+    pub fn parse(content: &str) -> Result<ServiceDatabase, String> {
+        let mut db: ServiceDatabase = ServiceDatabase::new();
+        // None before the first probe, Some(false) inside a skipped UDP probe
+        let mut in_tcp_probe: Option<bool> = None;
+        for (i, line) in content.lines().enumerate() {
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (directive, value) = match line.split_once(char::is_whitespace) {
+                Some((directive, value)) => (directive, value.trim()),
+                None => (line, ""),
+            };
+            if directive == "Exclude" {
+                continue;
+            }
+            if directive == "Probe" {
+                let probe: Option<ServiceProbe> = match parse_probe(value) {
+                    Ok(probe) => probe,
+                    Err(e) => return Err(format!("ServiceDatabase::parse: line {}: {}", i + 1, e)),
+                };
+                in_tcp_probe = Some(probe.is_some());
+                db.probes.extend(probe);
+                continue;
+            }
+            match in_tcp_probe {
+                Some(true) => {}
+                Some(false) => continue,
+                None => {
+                    return Err(format!(
+                        "ServiceDatabase::parse: line {}: {} before Probe",
+                        i + 1,
+                        directive
+                    ))
+                }
+            }
+            let probe: &mut ServiceProbe = match db.probes.last_mut() {
+                Some(probe) => probe,
+                None => continue,
+            };
+            let result: Result<(), String> = match directive {
+                "match" | "softmatch" => match parse_match(value, directive == "softmatch") {
+                    Ok(Some(service_match)) => {
+                        probe.matches.push(service_match);
+                        Ok(())
+                    }
+                    Ok(None) => {
+                        db.skipped_rules += 1;
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                },
+                "ports" => crate::scan::parse_ports(value).map(|ports| probe.ports = ports),
+                "totalwaitms" => match value.parse::<u64>() {
+                    Ok(ms) => {
+                        probe.total_wait = Some(Duration::from_millis(ms));
+                        Ok(())
+                    }
+                    Err(_) => Err(format!("invalid totalwaitms {}", value)),
+                },
+                "rarity" => match value.parse::<u8>() {
+                    Ok(rarity) => {
+                        probe.rarity = rarity;
+                        Ok(())
+                    }
+                    Err(_) => Err(format!("invalid rarity {}", value)),
+                },
+                "fallback" => {
+                    probe.fallback = value.split(',').map(|f| f.trim().to_string()).collect();
+                    Ok(())
+                }
+                // TLS is not wrapped, the timeout of tcpwrapped is the receive timeout
+                "sslports" | "tcpwrappedms" => Ok(()),
+                _ => Err(format!("unknown directive {}", directive)),
+            };
+            if let Err(e) = result {
+                return Err(format!("ServiceDatabase::parse: line {}: {}", i + 1, e));
+            }
+        }
+        Ok(db)
+    }
+    pub fn get_probe(&self, name: &str) -> Option<&ServiceProbe> {
+        self.probes.iter().find(|p| p.name == name)
+    }
+    /// Probes to send to `port`: the NULL probe, then the probes meant for the port
+    /// (or every probe if `all_probes`), by rarity.
+    pub fn probes_for_port(&self, port: u16, all_probes: bool) -> Vec<&ServiceProbe> {
+        let mut probes: Vec<&ServiceProbe> = self
+            .probes
+            .iter()
+            .filter(|p| p.name != NULL_PROBE && (all_probes || p.ports.contains(&port)))
+            .collect();
+        probes.sort_by_key(|p| p.rarity);
+        if let Some(null_probe) = self.get_probe(NULL_PROBE) {
+            probes.insert(0, null_probe);
+        }
+        probes
+    }
+    /// Classify the response to `probe`.
+    ///
+    /// The rules of the probe are tried first, then those of its fallback probes and of the
+    /// NULL probe. The first match wins, but a softmatch only if no other rule matches.
+    pub fn match_response(&self, probe: &ServiceProbe, response: &[u8]) -> Option<ServiceInfo> {
+        let mut rule_sets: Vec<&ServiceProbe> = vec![probe];
+        for name in probe
+            .fallback
+            .iter()
+            .map(|f| f.as_str())
+            .chain([NULL_PROBE])
+        {
+            if let Some(fallback) = self.get_probe(name) {
+                if !rule_sets.iter().any(|p| p.name == fallback.name) {
+                    rule_sets.push(fallback);
+                }
+            }
+        }
+        let mut soft: Option<ServiceInfo> = None;
+        for service_match in rule_sets.iter().flat_map(|p| p.matches.iter()) {
+            if soft.is_some() && service_match.soft {
+                continue;
+            }
+            if let Some(info) = service_match.apply(&probe.name, response) {
+                if !info.soft_match {
+                    return Some(info);
+                }
+                soft = Some(info);
+            }
+        }
+        soft
+    }
+}
+
+impl Default for ServiceDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `TCP <name> q|<payload>|`. None for UDP probes.
+fn parse_probe(value: &str) -> Result<Option<ServiceProbe>, String> {
+    let mut parts = value.splitn(3, char::is_whitespace);
+    let protocol: &str = parts.next().unwrap_or("");
+    let name: &str = parts.next().ok_or("missing probe name")?;
+    let payload: &str = parts.next().ok_or("missing probe string")?.trim();
+    match protocol {
+        "TCP" => {}
+        "UDP" => return Ok(None),
+        _ => return Err(format!("invalid protocol {}", protocol)),
+    }
+    let payload: &str = match payload.strip_prefix('q') {
+        Some(quoted) => take_delimited(quoted).ok_or("unterminated probe string")?.0,
+        None => return Err(format!("invalid probe string {}", payload)),
+    };
+    Ok(Some(ServiceProbe::new(
+        name.to_string(),
+        unescape(payload)?,
+    )))
+}
+
+/// `<service> m|<pattern>|[is] [p/product/] [v/version/] [i/info/] [h/host/] [o/os/] [d/type/]`.
+/// None if the pattern does not compile.
+fn parse_match(value: &str, soft: bool) -> Result<Option<ServiceMatch>, String> {
+    let (service, spec) = value
+        .split_once(char::is_whitespace)
+        .ok_or(format!("invalid match {}", value))?;
+    let spec: &str = spec
+        .trim_start()
+        .strip_prefix('m')
+        .ok_or(format!("invalid pattern {}", spec))?;
+    let (pattern, rest) = take_delimited(spec).ok_or("unterminated pattern")?;
+    let flags_end: usize = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    let (flags, mut rest) = rest.split_at(flags_end);
+    let mut builder = RegexBuilder::new(pattern);
+    builder.unicode(false).octal(true);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            's' => builder.dot_matches_new_line(true),
+            _ => return Err(format!("invalid pattern flag {}", flag)),
+        };
+    }
+    let pattern: Regex = match builder.build() {
+        Ok(pattern) => pattern,
+        Err(_) => return Ok(None),
+    };
+    let mut service_match = ServiceMatch {
+        service: service.to_string(),
+        pattern,
+        soft,
+        product: None,
+        version: None,
+        info: None,
+        hostname: None,
+        os: None,
+        device_type: None,
+    };
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(cpe) = rest.strip_prefix("cpe:") {
+            let (_, after) = take_delimited(cpe).ok_or("unterminated cpe")?;
+            rest = after.strip_prefix('a').unwrap_or(after);
+            continue;
+        }
+        let field: char = rest.chars().next().unwrap_or(' ');
+        let (template, after) = take_delimited(&rest[field.len_utf8()..])
+            .ok_or(format!("unterminated {} field", field))?;
+        let template: Option<String> = Some(template.to_string());
+        match field {
+            'p' => service_match.product = template,
+            'v' => service_match.version = template,
+            'i' => service_match.info = template,
+            'h' => service_match.hostname = template,
+            'o' => service_match.os = template,
+            'd' => service_match.device_type = template,
+            _ => return Err(format!("unknown version field {}", field)),
+        }
+        rest = after;
+    }
+    Ok(Some(service_match))
+}
+
+/// Split `|content|rest` on its first character
+fn take_delimited(s: &str) -> Option<(&str, &str)> {
+    let delimiter: char = s.chars().next()?;
+    let body: &str = &s[delimiter.len_utf8()..];
+    let end: usize = body.find(delimiter)?;
+    Some((&body[..end], &body[end + delimiter.len_utf8()..]))
+}
+
+/// Decode the C-style escapes of a probe string
+fn unescape(s: &str) -> Result<Vec<u8>, String> {
+    let mut buf: Vec<u8> = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            buf.push(b);
+            continue;
+        }
+        let escaped: u8 = match bytes.next() {
+            Some(b'0') => 0,
+            Some(b'a') => 0x07,
+            Some(b'b') => 0x08,
+            Some(b'f') => 0x0c,
+            Some(b'n') => b'\n',
+            Some(b'r') => b'\r',
+            Some(b't') => b'\t',
+            Some(b'v') => 0x0b,
+            Some(b'x') => {
+                let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+                std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or(format!("invalid escape in {}", s))?
+            }
+            Some(other) => other,
+            None => return Err(format!("trailing backslash in {}", s)),
+        };
+        buf.push(escaped);
+    }
+    Ok(buf)
+}
+
+/// Fill a version template: `$1`..`$9`, `$P(n)` (printable characters only),
+/// `$SUBST(n,"from","to")` and `$I(n,">")` (unsigned integer, `>` big or `<` little endian)
+fn substitute(template: &str, captures: &Captures) -> String {
+    let group = |n: &str| -> Vec<u8> {
+        n.trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| captures.get(n))
+            .map(|m| m.as_bytes().to_vec())
+            .unwrap_or_default()
+    };
+    let mut out: String = String::new();
+    let mut rest: &str = template;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some(d) = rest.chars().next().filter(|c| c.is_ascii_digit()) {
+            out.push_str(&String::from_utf8_lossy(&group(&d.to_string())));
+            rest = &rest[1..];
+            continue;
+        }
+        let (function, args_end) = match (rest.find('('), rest.find(')')) {
+            (Some(open), Some(close)) if open < close => (&rest[..open], close),
+            _ => {
+                out.push('$');
+                continue;
+            }
+        };
+        let args: Vec<&str> = rest[function.len() + 1..args_end].split(',').collect();
+        let value: Option<String> = match (function, args.as_slice()) {
+            ("P", [n]) => Some(
+                group(n)
+                    .iter()
+                    .filter(|b| b.is_ascii_graphic() || **b == b' ')
+                    .map(|b| *b as char)
+                    .collect(),
+            ),
+            ("SUBST", [n, from, to]) => Some(
+                String::from_utf8_lossy(&group(n))
+                    .replace(from.trim_matches('"'), to.trim_matches('"')),
+            ),
+            ("I", [n, endian]) => {
+                let bytes: Vec<u8> = group(n);
+                let bytes: Box<dyn Iterator<Item = &u8>> = if endian.contains('<') {
+                    Box::new(bytes.iter().rev())
+                } else {
+                    Box::new(bytes.iter())
+                };
+                Some(
+                    bytes
+                        .fold(0u64, |acc, b| acc.wrapping_shl(8) | *b as u64)
+                        .to_string(),
+                )
+            }
+            _ => None,
+        };
+        match value {
+            Some(value) => {
+                out.push_str(&value);
+                rest = &rest[args_end + 1..];
+            }
+            None => out.push('$'),
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_database_parses() {
+        let db: ServiceDatabase = ServiceDatabase::parse(BUILTIN_SERVICE_PROBES).unwrap();
+        assert!(!db.probes.is_empty());
+        assert!(!db.get_probe(NULL_PROBE).unwrap().matches.is_empty());
+        assert_eq!(ServiceDatabase::builtin().probes.len(), db.probes.len());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_lines() {
+        assert!(ServiceDatabase::parse("match ssh m|^SSH|").is_err());
+        assert!(ServiceDatabase::parse("Probe SCTP x q||").is_err());
+        assert!(ServiceDatabase::parse("Probe TCP x q||\nrarity high").is_err());
+        // Rules of UDP probes are skipped
+        let db: ServiceDatabase =
+            ServiceDatabase::parse("Probe UDP x q||\nmatch dns m|^x|\nProbe TCP y q|\\r\\n|")
+                .unwrap();
+        assert_eq!(db.probes.len(), 1);
+        assert_eq!(db.probes[0].payload, b"\r\n");
+    }
+
+    #[test]
+    fn parse_match_flags_and_fields() {
+        let service_match: ServiceMatch = parse_match(
+            r"ssh m|^ssh-([\d.]+)-OpenSSH_(\S+).*\n(.*)$|is p/OpenSSH/ v/$2/ i/protocol $1/ h/$3/ o/Linux/ d/router/ cpe:/a:openbsd:openssh:$2/a",
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(service_match.service, "ssh");
+        assert!(!service_match.soft);
+        let info: ServiceInfo = service_match
+            .apply(NULL_PROBE, b"SSH-2.0-OpenSSH_9.6\r\nhost1")
+            .unwrap();
+        assert_eq!(info.product.as_deref(), Some("OpenSSH"));
+        assert_eq!(info.version.as_deref(), Some("9.6"));
+        assert_eq!(info.info.as_deref(), Some("protocol 2.0"));
+        assert_eq!(info.hostname.as_deref(), Some("host1"));
+        assert_eq!(info.os.as_deref(), Some("Linux"));
+        assert_eq!(info.device_type.as_deref(), Some("router"));
+        // Without `s` the dot does not match the line break
+        let service_match: ServiceMatch =
+            parse_match(r"ssh m|^SSH.*host1|", true).unwrap().unwrap();
+        assert!(service_match.soft);
+        assert!(service_match.pattern.captures(b"SSH-2.0\nhost1").is_none());
+        // Unknown flag and version field
+        assert!(parse_match("ssh m|^SSH|x", false).is_err());
+        assert!(parse_match("ssh m|^SSH| z/x/", false).is_err());
+        // Look-around is not supported by the regex crate
+        assert!(parse_match("ssh m|^SSH(?=-2)|", false).unwrap().is_none());
+    }
+
+    #[test]
+    fn unescape_probe_string() {
+        assert_eq!(
+            unescape(r"GET / HTTP/1.0\r\n\r\n").unwrap(),
+            b"GET / HTTP/1.0\r\n\r\n"
+        );
+        assert_eq!(
+            unescape(r"\x00\x01\x7f\xFF").unwrap(),
+            [0x00, 0x01, 0x7f, 0xff]
+        );
+        assert_eq!(unescape(r"\0\t\\\|").unwrap(), b"\0\t\\|");
+        assert!(unescape(r"\xZZ").is_err());
+        assert!(unescape(r"\x").is_err());
+        assert!(unescape("abc\\").is_err());
+    }
+
+    #[test]
+    fn substitute_templates() {
+        let pattern: Regex = Regex::new(r"(?s-u)^(\S+) (\S+) (.*)$").unwrap();
+        let captures: Captures = pattern.captures(b"1_2_3 a\x01b\xffc \x00\x01\x02").unwrap();
+        assert_eq!(substitute("v$1", &captures), "v1_2_3");
+        assert_eq!(substitute("$P(2)", &captures), "abc");
+        assert_eq!(substitute(r#"$SUBST(1,"_",".")"#, &captures), "1.2.3");
+        assert_eq!(substitute(r#"$I(3,">")"#, &captures), "258");
+        assert_eq!(substitute(r#"$I(3,"<")"#, &captures), "131328");
+        // Missing group, unknown function and lone dollar
+        assert_eq!(substitute("[$9]", &captures), "[]");
+        assert_eq!(substitute("$X(1) $", &captures), "$X(1) $");
+    }
+}
@@ -5,8 +5,9 @@ use std::time::Duration;
 pub use xenet::net::interface::Interface;
 pub use xenet::net::mac::MacAddr;
 
-use crate::dns::{lookup_host_name, lookup_ip_addr};
+use crate::dns::{lookup_host_name, lookup_ip_addr, AddressPolicy, TargetResolver};
 use crate::fp::FingerprintType;
+use crate::result::ResolvedTarget;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -17,11 +18,54 @@ use serde::{Deserialize, Serialize};
 pub enum Protocol {
     ARP,
     NDP,
+    LLDP,
+    CDP,
     ICMP,
     TCP,
     UDP,
 }
 
+/// Port scan technique
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ScanType {
+    /// Half-open SYN scan over a raw channel
+    TcpSyn,
+    /// Full TCP handshake with the OS socket API. Needs no privileges
+    TcpConnect,
+    /// ACK scan, tells filtered ports from unfiltered ones
+    TcpAck,
+    /// UDP scan with protocol-specific payloads
+    Udp,
+}
+
+impl ScanType {
+    pub fn name(&self) -> String {
+        match *self {
+            ScanType::TcpSyn => String::from("TCP SYN"),
+            ScanType::TcpConnect => String::from("TCP Connect"),
+            ScanType::TcpAck => String::from("TCP ACK"),
+            ScanType::Udp => String::from("UDP"),
+        }
+    }
+    pub fn protocol(&self) -> Protocol {
+        match *self {
+            ScanType::TcpSyn | ScanType::TcpConnect | ScanType::TcpAck => Protocol::TCP,
+            ScanType::Udp => Protocol::UDP,
+        }
+    }
+}
+
+/// How the Wake-on-LAN magic packet is delivered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WakeOnLanMethod {
+    /// Raw Ethernet frame with ethertype 0x0842
+    Ethernet,
+    /// UDP datagram to the broadcast address (port 9 by default)
+    Udp,
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ProbeSetting {
@@ -74,6 +118,9 @@ impl ProbeSetting {
         self.if_name = if_name;
         self
     }
+    /// Set `dst_hostname` and resolve it with `dns::default_config()`.
+    /// `dst_ip` is left unchanged if the name does not resolve.
+    #[deprecated(note = "use `with_dst_target`, which reports resolution failures")]
     pub fn with_dst_hostname(mut self, dst_hostname: String) -> ProbeSetting {
         self.dst_hostname = dst_hostname.clone();
         if let Some(ip) = lookup_host_name(dst_hostname) {
@@ -81,6 +128,22 @@ impl ProbeSetting {
         }
         self
     }
+    /// Set `dst_hostname` and `dst_ip` to the address chosen by `policy`.
+    /// `AddressPolicy::HappyEyeballs` races connections to `dst_port` if it is set.
+    pub fn with_dst_target(
+        mut self,
+        dst_hostname: String,
+        policy: AddressPolicy,
+    ) -> Result<ProbeSetting, String> {
+        let mut resolver = TargetResolver::new(&dst_hostname, policy);
+        if let Some(dst_port) = self.dst_port {
+            resolver = resolver.with_port(dst_port);
+        }
+        let target: ResolvedTarget = resolver.resolve()?;
+        self.dst_hostname = dst_hostname;
+        self.dst_ip = target.ip_addr;
+        Ok(self)
+    }
     pub fn with_dst_ip(mut self, dst_ip: IpAddr) -> ProbeSetting {
         self.dst_ip = dst_ip.clone();
         if let Some(hostname) = lookup_ip_addr(dst_ip) {
@@ -711,6 +774,185 @@ impl ProbeSetting {
         };
         Ok(setting)
     }
+    pub fn router_discovery(interface: Interface, count: u8) -> Result<ProbeSetting, String> {
+        let src_ip: IpAddr = match crate::interface::get_interface_link_local_ipv6(&interface) {
+            Some(ip) => ip,
+            None => {
+                return Err(format!(
+                    "RouterDiscovery: link-local IPv6 address not found on interface {}",
+                    interface.name
+                ))
+            }
+        };
+        if interface.is_tun() {
+            return Err(String::from("RouterDiscovery: tun interface is not supported"));
+        }
+        if interface.is_loopback() {
+            return Err(String::from("RouterDiscovery: loopback interface is not supported"));
+        }
+        let dst_ipv6_addr: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 2);
+
+        let setting = ProbeSetting {
+            if_index: interface.index,
+            if_name: interface.name.clone(),
+            src_mac: crate::interface::get_interface_macaddr(&interface),
+            dst_mac: MacAddr::new(0x33, 0x33, 0, 0, 0, 2),
+            src_ip,
+            src_port: None,
+            dst_ip: IpAddr::V6(dst_ipv6_addr),
+            dst_hostname: dst_ipv6_addr.to_string(),
+            dst_port: None,
+            hop_limit: u8::MAX,
+            count,
+            protocol: Protocol::NDP,
+            receive_timeout: Duration::from_secs(1),
+            probe_timeout: Duration::from_secs(30),
+            send_rate: Duration::from_secs(1),
+            tunnel: false,
+            loopback: false,
+        };
+        Ok(setting)
+    }
+    /// DHCP server discovery (DHCPDISCOVER broadcast)
+    pub fn dhcp_discovery(interface: Interface, count: u8) -> Result<ProbeSetting, String> {
+        if interface.is_tun() {
+            return Err(String::from("DhcpDiscovery: tun interface is not supported"));
+        }
+        if interface.is_loopback() {
+            return Err(String::from("DhcpDiscovery: loopback interface is not supported"));
+        }
+        let setting = ProbeSetting {
+            if_index: interface.index,
+            if_name: interface.name.clone(),
+            src_mac: crate::interface::get_interface_macaddr(&interface),
+            dst_mac: MacAddr::broadcast(),
+            src_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            src_port: Some(crate::packet::dhcp::DHCP_CLIENT_PORT),
+            dst_ip: IpAddr::V4(Ipv4Addr::BROADCAST),
+            dst_hostname: Ipv4Addr::BROADCAST.to_string(),
+            dst_port: Some(crate::packet::dhcp::DHCP_SERVER_PORT),
+            hop_limit: 64,
+            count,
+            protocol: Protocol::UDP,
+            receive_timeout: Duration::from_secs(3),
+            probe_timeout: Duration::from_secs(30),
+            send_rate: Duration::from_secs(1),
+            tunnel: false,
+            loopback: false,
+        };
+        Ok(setting)
+    }
+    /// DHCPv6 server discovery (Solicit to All_DHCP_Relay_Agents_and_Servers)
+    pub fn dhcpv6_discovery(interface: Interface, count: u8) -> Result<ProbeSetting, String> {
+        let src_ip: IpAddr = match crate::interface::get_interface_link_local_ipv6(&interface) {
+            Some(ip) => ip,
+            None => {
+                return Err(format!(
+                    "DhcpDiscovery: link-local IPv6 address not found on interface {}",
+                    interface.name
+                ))
+            }
+        };
+        if interface.is_tun() {
+            return Err(String::from("DhcpDiscovery: tun interface is not supported"));
+        }
+        if interface.is_loopback() {
+            return Err(String::from("DhcpDiscovery: loopback interface is not supported"));
+        }
+        let dst_ipv6_addr: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 1, 2);
+
+        let setting = ProbeSetting {
+            if_index: interface.index,
+            if_name: interface.name.clone(),
+            src_mac: crate::interface::get_interface_macaddr(&interface),
+            dst_mac: MacAddr::new(0x33, 0x33, 0, 1, 0, 2),
+            src_ip,
+            src_port: Some(crate::packet::dhcp::DHCPV6_CLIENT_PORT),
+            dst_ip: IpAddr::V6(dst_ipv6_addr),
+            dst_hostname: dst_ipv6_addr.to_string(),
+            dst_port: Some(crate::packet::dhcp::DHCPV6_SERVER_PORT),
+            hop_limit: 1,
+            count,
+            protocol: Protocol::UDP,
+            receive_timeout: Duration::from_secs(3),
+            probe_timeout: Duration::from_secs(30),
+            send_rate: Duration::from_secs(1),
+            tunnel: false,
+            loopback: false,
+        };
+        Ok(setting)
+    }
+    /// Wake-on-LAN. Magic packets are broadcast on the interface.
+    /// dst_ip is the directed broadcast address of the interface, used for the UDP method.
+    pub fn wake_on_lan(interface: Interface, count: u8) -> Result<ProbeSetting, String> {
+        if interface.is_tun() {
+            return Err(String::from("WakeOnLan: tun interface is not supported"));
+        }
+        if interface.is_loopback() {
+            return Err(String::from("WakeOnLan: loopback interface is not supported"));
+        }
+        let (src_ip, dst_ip): (IpAddr, IpAddr) = match interface.ipv4.first() {
+            Some(ipv4) => (IpAddr::V4(ipv4.addr), IpAddr::V4(ipv4.broadcast())),
+            None => (
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                IpAddr::V4(Ipv4Addr::BROADCAST),
+            ),
+        };
+        let setting = ProbeSetting {
+            if_index: interface.index,
+            if_name: interface.name.clone(),
+            src_mac: crate::interface::get_interface_macaddr(&interface),
+            dst_mac: MacAddr::broadcast(),
+            src_ip,
+            src_port: None,
+            dst_ip,
+            dst_hostname: dst_ip.to_string(),
+            dst_port: Some(crate::packet::wol::WOL_DEFAULT_DST_PORT),
+            hop_limit: 64,
+            count,
+            protocol: Protocol::UDP,
+            receive_timeout: Duration::from_secs(1),
+            probe_timeout: Duration::from_secs(120),
+            send_rate: Duration::from_secs(1),
+            tunnel: false,
+            loopback: false,
+        };
+        Ok(setting)
+    }
+    /// Passive LLDP and CDP listening.
+    /// probe_timeout is the listening window. LLDP is usually sent every 30s and CDP every 60s.
+    pub fn link_discovery(interface: Interface) -> Result<ProbeSetting, String> {
+        if interface.is_tun() {
+            return Err(String::from("LinkDiscovery: tun interface is not supported"));
+        }
+        if interface.is_loopback() {
+            return Err(String::from("LinkDiscovery: loopback interface is not supported"));
+        }
+        let src_ip: IpAddr = match crate::interface::get_interface_ipv4(&interface) {
+            Some(ip) => ip,
+            None => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        };
+        let setting = ProbeSetting {
+            if_index: interface.index,
+            if_name: interface.name.clone(),
+            src_mac: crate::interface::get_interface_macaddr(&interface),
+            dst_mac: MacAddr::new(0x01, 0x80, 0xc2, 0, 0, 0x0e),
+            src_ip,
+            src_port: None,
+            dst_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            dst_hostname: String::new(),
+            dst_port: None,
+            hop_limit: 1,
+            count: 1,
+            protocol: Protocol::LLDP,
+            receive_timeout: Duration::from_secs(1),
+            probe_timeout: Duration::from_secs(60),
+            send_rate: Duration::from_secs(1),
+            tunnel: false,
+            loopback: false,
+        };
+        Ok(setting)
+    }
     pub fn fingerprinting(interface: Interface, ip_addr: IpAddr, port: Option<u16>, probe_type: FingerprintType) -> Result<ProbeSetting, String> {
         let src_ip: IpAddr = match ip_addr {
             IpAddr::V4(_) => match crate::interface::get_interface_ipv4(&interface) {
@@ -771,4 +1013,102 @@ impl ProbeSetting {
         };
         Ok(setting)
     }
+    /// Port scan of `dst_ip_addr`. Use `PortScanner::with_targets` to scan more hosts of the same address family.
+    /// send_rate is the interval between probes, receive_timeout the wait for late replies.
+    pub fn port_scan(interface: Interface, dst_ip_addr: IpAddr, scan_type: ScanType) -> Result<ProbeSetting, String> {
+        let src_ip: IpAddr = match dst_ip_addr {
+            IpAddr::V4(_) => match crate::interface::get_interface_ipv4(&interface) {
+                Some(ip) => ip,
+                None => return Err(String::from("IPv4 address not found on default interface.")),
+            },
+            IpAddr::V6(ipv6_addr) => {
+                if xenet::net::ipnet::is_global_ipv6(&ipv6_addr) {
+                    match crate::interface::get_interface_global_ipv6(&interface) {
+                        Some(ip) => ip,
+                        None => {
+                            return Err(String::from(
+                                "Global IPv6 address not found on default interface.",
+                            ))
+                        }
+                    }
+                } else {
+                    match crate::interface::get_interface_local_ipv6(&interface) {
+                        Some(ip) => ip,
+                        None => {
+                            return Err(String::from(
+                                "Local IPv6 address not found on default interface.",
+                            ))
+                        }
+                    }
+                }
+            }
+        };
+        let use_tun = interface.is_tun();
+        let loopback = interface.is_loopback();
+        let setting = ProbeSetting {
+            if_index: interface.index,
+            if_name: interface.name.clone(),
+            src_mac: if use_tun {
+                MacAddr::zero()
+            } else {
+                crate::interface::get_interface_macaddr(&interface)
+            },
+            dst_mac: if use_tun {
+                MacAddr::zero()
+            } else {
+                crate::interface::get_gateway_macaddr(&interface)
+            },
+            src_ip,
+            src_port: match scan_type {
+                ScanType::Udp => Some(crate::packet::udp::UDP_DEFAULT_SRC_PORT),
+                _ => Some(crate::packet::tcp::TCP_DEFAULT_SRC_PORT),
+            },
+            dst_ip: dst_ip_addr,
+            dst_hostname: dst_ip_addr.to_string(),
+            dst_port: None,
+            hop_limit: 64,
+            count: 1,
+            protocol: scan_type.protocol(),
+            receive_timeout: Duration::from_secs(1),
+            probe_timeout: Duration::from_secs(300),
+            send_rate: Duration::from_millis(1),
+            tunnel: use_tun,
+            loopback,
+        };
+        Ok(setting)
+    }
+    /// Passive TCP SYN and SYN/ACK fingerprinting of all hosts.
+    /// probe_timeout is the listening window. Use with_dst_ip or with_dst_port to observe a single host or service.
+    pub fn passive_fingerprinting(interface: Interface, duration: Duration) -> Result<ProbeSetting, String> {
+        let src_ip: IpAddr = match crate::interface::get_interface_ipv4(&interface) {
+            Some(ip) => ip,
+            None => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        };
+        let use_tun = interface.is_tun();
+        let loopback = interface.is_loopback();
+        let setting = ProbeSetting {
+            if_index: interface.index,
+            if_name: interface.name.clone(),
+            src_mac: if use_tun {
+                MacAddr::zero()
+            } else {
+                crate::interface::get_interface_macaddr(&interface)
+            },
+            dst_mac: MacAddr::zero(),
+            src_ip,
+            src_port: None,
+            dst_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            dst_hostname: String::new(),
+            dst_port: None,
+            hop_limit: 64,
+            count: 1,
+            protocol: Protocol::TCP,
+            receive_timeout: Duration::from_millis(500),
+            probe_timeout: duration,
+            send_rate: Duration::from_secs(1),
+            tunnel: use_tun,
+            loopback,
+        };
+        Ok(setting)
+    }
 }
@@ -1,4 +1,27 @@
+use crate::result::ProbeStatus;
+use std::io::ErrorKind;
+use std::time::Duration;
+
 pub fn get_sysdate() -> String {
     let now = chrono::Local::now();
     now.to_rfc3339()
 }
+
+/// Probe status of a failed socket operation
+pub fn io_status(context: &str, e: std::io::Error) -> ProbeStatus {
+    match e.kind() {
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => {
+            ProbeStatus::with_timeout_message(format!("{}: timeout", context))
+        }
+        _ => ProbeStatus::with_error_message(format!("{}: {}", context, e)),
+    }
+}
+
+/// Minimum, average and maximum of `rtts`. Zero if empty.
+pub fn rtt_stat(rtts: &[Duration]) -> (Duration, Duration, Duration) {
+    (
+        rtts.iter().min().copied().unwrap_or_default(),
+        rtts.iter().sum::<Duration>() / (rtts.len().max(1) as u32),
+        rtts.iter().max().copied().unwrap_or_default(),
+    )
+}
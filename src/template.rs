@@ -0,0 +1,426 @@
+use crate::setting::{ProbeSetting, Protocol};
+use std::net::IpAddr;
+use xenet::packet::frame::Frame;
+use xenet::packet::PrimitiveValues;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// TCP option carried by a probe template
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TemplateTcpOption {
+    /// End of option list
+    Eol,
+    Nop,
+    Mss(u16),
+    WindowScale(u8),
+    SackPermitted,
+    /// Timestamp value and echo reply
+    Timestamp(u32, u32),
+    /// Any other kind, with its data. The length byte is computed.
+    Raw(u8, Vec<u8>),
+}
+
+impl TemplateTcpOption {
+    /// Option in wire format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            TemplateTcpOption::Eol => vec![0],
+            TemplateTcpOption::Nop => vec![1],
+            TemplateTcpOption::Mss(mss) => {
+                let mut bytes: Vec<u8> = vec![2, 4];
+                bytes.extend_from_slice(&mss.to_be_bytes());
+                bytes
+            }
+            TemplateTcpOption::WindowScale(shift) => vec![3, 3, *shift],
+            TemplateTcpOption::SackPermitted => vec![4, 2],
+            TemplateTcpOption::Timestamp(value, echo_reply) => {
+                let mut bytes: Vec<u8> = vec![8, 10];
+                bytes.extend_from_slice(&value.to_be_bytes());
+                bytes.extend_from_slice(&echo_reply.to_be_bytes());
+                bytes
+            }
+            TemplateTcpOption::Raw(kind, data) => {
+                let mut bytes: Vec<u8> = vec![*kind, (data.len() + 2) as u8];
+                bytes.extend_from_slice(data);
+                bytes
+            }
+        }
+    }
+}
+
+/// Rule deciding which received packet is the reply to a template probe
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReplyMatch {
+    /// Reply must come from the probed address
+    pub from_destination: bool,
+    /// Protocol of the reply. `Protocol::ICMP` matches both ICMP and ICMPv6.
+    pub protocol: Option<Protocol>,
+    /// TCP or UDP reply must come from the probed port and go to the probing port
+    pub mirror_ports: bool,
+    /// Required TCP flags, compared after applying `tcp_flags_mask`
+    pub tcp_flags: Option<u8>,
+    pub tcp_flags_mask: u8,
+    /// ICMP type, for IPv4 replies
+    pub icmp_type: Option<u8>,
+    /// ICMPv6 type, for IPv6 replies
+    pub icmpv6_type: Option<u8>,
+    /// ICMP or ICMPv6 code
+    pub icmp_code: Option<u8>,
+}
+
+impl ReplyMatch {
+    /// Match any packet from the probed address
+    pub fn new() -> ReplyMatch {
+        ReplyMatch {
+            from_destination: true,
+            protocol: None,
+            mirror_ports: false,
+            tcp_flags: None,
+            tcp_flags_mask: 0xff,
+            icmp_type: None,
+            icmpv6_type: None,
+            icmp_code: None,
+        }
+    }
+    /// Any TCP segment from the probed port
+    pub fn tcp() -> ReplyMatch {
+        ReplyMatch::new()
+            .with_protocol(Protocol::TCP)
+            .with_mirror_ports(true)
+    }
+    /// Any UDP datagram from the probed port
+    pub fn udp() -> ReplyMatch {
+        ReplyMatch::new()
+            .with_protocol(Protocol::UDP)
+            .with_mirror_ports(true)
+    }
+    /// ICMP Echo Reply or ICMPv6 Echo Reply
+    pub fn icmp_echo_reply() -> ReplyMatch {
+        ReplyMatch::new()
+            .with_protocol(Protocol::ICMP)
+            .with_icmp_type(0)
+            .with_icmpv6_type(129)
+    }
+    /// ICMP or ICMPv6 Destination Unreachable
+    pub fn icmp_unreachable() -> ReplyMatch {
+        ReplyMatch::new()
+            .with_protocol(Protocol::ICMP)
+            .with_icmp_type(3)
+            .with_icmpv6_type(1)
+    }
+    pub fn with_from_destination(mut self, from_destination: bool) -> ReplyMatch {
+        self.from_destination = from_destination;
+        self
+    }
+    pub fn with_protocol(mut self, protocol: Protocol) -> ReplyMatch {
+        self.protocol = Some(protocol);
+        self
+    }
+    pub fn with_mirror_ports(mut self, mirror_ports: bool) -> ReplyMatch {
+        self.mirror_ports = mirror_ports;
+        self
+    }
+    /// Require exactly these TCP flags
+    pub fn with_tcp_flags(mut self, tcp_flags: u8) -> ReplyMatch {
+        self.tcp_flags = Some(tcp_flags);
+        self.tcp_flags_mask = 0xff;
+        self
+    }
+    /// Require `tcp_flags` among the bits selected by `mask`
+    pub fn with_tcp_flags_mask(mut self, tcp_flags: u8, mask: u8) -> ReplyMatch {
+        self.tcp_flags = Some(tcp_flags & mask);
+        self.tcp_flags_mask = mask;
+        self
+    }
+    pub fn with_icmp_type(mut self, icmp_type: u8) -> ReplyMatch {
+        self.icmp_type = Some(icmp_type);
+        self
+    }
+    pub fn with_icmpv6_type(mut self, icmpv6_type: u8) -> ReplyMatch {
+        self.icmpv6_type = Some(icmpv6_type);
+        self
+    }
+    pub fn with_icmp_code(mut self, icmp_code: u8) -> ReplyMatch {
+        self.icmp_code = Some(icmp_code);
+        self
+    }
+    /// Check a received frame against the rule.
+    /// `src_port` and `dst_port` are the ports of the probe.
+    pub fn matches(
+        &self,
+        frame: &Frame,
+        setting: &ProbeSetting,
+        src_port: u16,
+        dst_port: u16,
+    ) -> bool {
+        let ip_layer = match &frame.ip {
+            Some(ip_layer) => ip_layer,
+            None => return false,
+        };
+        let (source, destination) = if let Some(ipv4_header) = &ip_layer.ipv4 {
+            (
+                IpAddr::V4(ipv4_header.source),
+                IpAddr::V4(ipv4_header.destination),
+            )
+        } else if let Some(ipv6_header) = &ip_layer.ipv6 {
+            (
+                IpAddr::V6(ipv6_header.source),
+                IpAddr::V6(ipv6_header.destination),
+            )
+        } else {
+            return false;
+        };
+        if destination != setting.src_ip {
+            return false;
+        }
+        if self.from_destination && source != setting.dst_ip {
+            return false;
+        }
+        let tcp_header = frame.transport.as_ref().and_then(|t| t.tcp.as_ref());
+        let udp_header = frame.transport.as_ref().and_then(|t| t.udp.as_ref());
+        let icmp_present: bool = ip_layer.icmp.is_some() || ip_layer.icmpv6.is_some();
+        match self.protocol {
+            Some(Protocol::TCP) if tcp_header.is_none() => return false,
+            Some(Protocol::UDP) if udp_header.is_none() => return false,
+            Some(Protocol::ICMP) if !icmp_present => return false,
+            _ => {}
+        }
+        if self.mirror_ports {
+            let ports: Option<(u16, u16)> = match (tcp_header, udp_header) {
+                (Some(tcp), _) => Some((tcp.source, tcp.destination)),
+                (None, Some(udp)) => Some((udp.source, udp.destination)),
+                (None, None) => None,
+            };
+            if let Some((source_port, destination_port)) = ports {
+                if source_port != dst_port || destination_port != src_port {
+                    return false;
+                }
+            }
+        }
+        if let Some(tcp_flags) = self.tcp_flags {
+            match tcp_header {
+                Some(tcp) if tcp.flags & self.tcp_flags_mask == tcp_flags => {}
+                _ => return false,
+            }
+        }
+        if let Some(icmp_header) = &ip_layer.icmp {
+            if let Some(icmp_type) = self.icmp_type {
+                if icmp_header.icmp_type.to_primitive_values().0 != icmp_type {
+                    return false;
+                }
+            }
+            if let Some(icmp_code) = self.icmp_code {
+                if icmp_header.icmp_code.0 != icmp_code {
+                    return false;
+                }
+            }
+        } else if let Some(icmpv6_header) = &ip_layer.icmpv6 {
+            if let Some(icmpv6_type) = self.icmpv6_type {
+                if icmpv6_header.icmpv6_type.to_primitive_values().0 != icmpv6_type {
+                    return false;
+                }
+            }
+            if let Some(icmp_code) = self.icmp_code {
+                if icmpv6_header.icmpv6_code.0 != icmp_code {
+                    return false;
+                }
+            }
+        } else if self.icmp_type.is_some() || self.icmpv6_type.is_some() {
+            return false;
+        }
+        true
+    }
+}
+
+impl Default for ReplyMatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// User-defined probe packet.
+///
+/// Addresses and MAC addresses come from the `ProbeSetting` the template is sent with.
+/// Fields left as `None` fall back to the setting or to the built-in probe defaults.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProbeTemplate {
+    /// TCP, UDP or ICMP (ICMPv6 for IPv6 destinations)
+    pub protocol: Protocol,
+    /// IPv4 TTL or IPv6 hop limit. Defaults to `ProbeSetting::hop_limit`
+    pub ttl: Option<u8>,
+    /// IPv4 TOS or IPv6 traffic class
+    pub tos: u8,
+    /// IPv4 identification. Random if not set
+    pub ip_id: Option<u16>,
+    /// IPv4 Don't Fragment flag
+    pub dont_fragment: bool,
+    /// IPv6 flow label (20 bits)
+    pub flow_label: u32,
+    /// Defaults to `ProbeSetting::src_port`
+    pub src_port: Option<u16>,
+    /// Defaults to `ProbeSetting::dst_port`
+    pub dst_port: Option<u16>,
+    pub tcp_flags: u8,
+    pub tcp_window: u16,
+    pub tcp_seq: u32,
+    pub tcp_ack: u32,
+    pub tcp_urgent_ptr: u16,
+    /// Padded to a multiple of 4 bytes with EOL
+    pub tcp_options: Vec<TemplateTcpOption>,
+    /// ICMP type. Defaults to Echo Request of the address family
+    pub icmp_type: Option<u8>,
+    pub icmp_code: u8,
+    pub icmp_id: u16,
+    pub icmp_seq: u16,
+    /// Bytes after the transport header
+    pub payload: Vec<u8>,
+    /// Rule selecting the reply
+    pub reply_match: ReplyMatch,
+}
+
+impl ProbeTemplate {
+    /// Create new template. TCP templates start as a bare SYN,
+    /// the reply rule defaults to the usual answer for the protocol.
+    pub fn new(protocol: Protocol) -> ProbeTemplate {
+        let reply_match: ReplyMatch = match protocol {
+            Protocol::TCP => ReplyMatch::tcp(),
+            Protocol::UDP => ReplyMatch::icmp_unreachable(),
+            _ => ReplyMatch::icmp_echo_reply(),
+        };
+        ProbeTemplate {
+            protocol,
+            ttl: None,
+            tos: 0,
+            ip_id: None,
+            dont_fragment: false,
+            flow_label: 0,
+            src_port: None,
+            dst_port: None,
+            tcp_flags: xenet::packet::tcp::TcpFlags::SYN,
+            tcp_window: 1024,
+            tcp_seq: rand::random::<u32>(),
+            tcp_ack: 0,
+            tcp_urgent_ptr: 0,
+            tcp_options: Vec::new(),
+            icmp_type: None,
+            icmp_code: 0,
+            icmp_id: rand::random::<u16>(),
+            icmp_seq: 1,
+            payload: Vec::new(),
+            reply_match,
+        }
+    }
+    pub fn with_ttl(mut self, ttl: u8) -> ProbeTemplate {
+        self.ttl = Some(ttl);
+        self
+    }
+    pub fn with_tos(mut self, tos: u8) -> ProbeTemplate {
+        self.tos = tos;
+        self
+    }
+    pub fn with_ip_id(mut self, ip_id: u16) -> ProbeTemplate {
+        self.ip_id = Some(ip_id);
+        self
+    }
+    pub fn with_dont_fragment(mut self, dont_fragment: bool) -> ProbeTemplate {
+        self.dont_fragment = dont_fragment;
+        self
+    }
+    pub fn with_flow_label(mut self, flow_label: u32) -> ProbeTemplate {
+        self.flow_label = flow_label & 0xfffff;
+        self
+    }
+    pub fn with_src_port(mut self, src_port: u16) -> ProbeTemplate {
+        self.src_port = Some(src_port);
+        self
+    }
+    pub fn with_dst_port(mut self, dst_port: u16) -> ProbeTemplate {
+        self.dst_port = Some(dst_port);
+        self
+    }
+    pub fn with_tcp_flags(mut self, tcp_flags: u8) -> ProbeTemplate {
+        self.tcp_flags = tcp_flags;
+        self
+    }
+    pub fn with_tcp_window(mut self, tcp_window: u16) -> ProbeTemplate {
+        self.tcp_window = tcp_window;
+        self
+    }
+    pub fn with_tcp_seq(mut self, tcp_seq: u32) -> ProbeTemplate {
+        self.tcp_seq = tcp_seq;
+        self
+    }
+    pub fn with_tcp_ack(mut self, tcp_ack: u32) -> ProbeTemplate {
+        self.tcp_ack = tcp_ack;
+        self
+    }
+    pub fn with_tcp_urgent_ptr(mut self, tcp_urgent_ptr: u16) -> ProbeTemplate {
+        self.tcp_urgent_ptr = tcp_urgent_ptr;
+        self
+    }
+    pub fn with_tcp_options(mut self, tcp_options: Vec<TemplateTcpOption>) -> ProbeTemplate {
+        self.tcp_options = tcp_options;
+        self
+    }
+    pub fn with_icmp_type(mut self, icmp_type: u8) -> ProbeTemplate {
+        self.icmp_type = Some(icmp_type);
+        self
+    }
+    pub fn with_icmp_code(mut self, icmp_code: u8) -> ProbeTemplate {
+        self.icmp_code = icmp_code;
+        self
+    }
+    pub fn with_icmp_id(mut self, icmp_id: u16) -> ProbeTemplate {
+        self.icmp_id = icmp_id;
+        self
+    }
+    pub fn with_icmp_seq(mut self, icmp_seq: u16) -> ProbeTemplate {
+        self.icmp_seq = icmp_seq;
+        self
+    }
+    pub fn with_payload(mut self, payload: Vec<u8>) -> ProbeTemplate {
+        self.payload = payload;
+        self
+    }
+    pub fn with_reply_match(mut self, reply_match: ReplyMatch) -> ProbeTemplate {
+        self.reply_match = reply_match;
+        self
+    }
+    /// Source port used for the probe
+    pub fn get_src_port(&self, setting: &ProbeSetting) -> u16 {
+        match self.src_port.or(setting.src_port) {
+            Some(port) => port,
+            None => match self.protocol {
+                Protocol::UDP => crate::packet::udp::UDP_DEFAULT_SRC_PORT,
+                _ => crate::packet::tcp::TCP_DEFAULT_SRC_PORT,
+            },
+        }
+    }
+    /// Destination port used for the probe
+    pub fn get_dst_port(&self, setting: &ProbeSetting) -> u16 {
+        match self.dst_port.or(setting.dst_port) {
+            Some(port) => port,
+            None => match self.protocol {
+                Protocol::UDP => crate::packet::udp::UDP_BASE_DST_PORT,
+                _ => crate::packet::tcp::TCP_DEFAULT_DST_PORT,
+            },
+        }
+    }
+    /// Build the probe packet for the addresses in `setting`
+    pub fn build(&self, setting: &ProbeSetting) -> Result<Vec<u8>, String> {
+        crate::packet::template::build_template_packet(setting, self)
+    }
+    /// Check whether `frame` is the reply to this probe
+    pub fn matches_reply(&self, frame: &Frame, setting: &ProbeSetting) -> bool {
+        self.reply_match.matches(
+            frame,
+            setting,
+            self.get_src_port(setting),
+            self.get_dst_port(setting),
+        )
+    }
+}
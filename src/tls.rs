@@ -0,0 +1,295 @@
+use crate::result::{CertificateInfo, ProbeStatus, TlsProbeResult};
+use crate::sys::io_status;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+
+pub const TLS_DEFAULT_PORT: u16 = 443;
+
+/// TLS handshake prober.
+///
+/// Connects to `host:port`, completes a TLS 1.2 or 1.3 handshake and reports the negotiated
+/// parameters with the certificate chain of the server. The chain is verified against the
+/// bundled Mozilla roots, but the handshake goes on if it is invalid, so expired or
+/// self-signed certificates can still be inspected.
+#[derive(Clone, Debug)]
+pub struct TlsProber {
+    /// Host name or IP address to connect to
+    pub host: String,
+    pub port: u16,
+    /// Name sent as SNI and verified against the certificate. Defaults to `host`
+    pub server_name: String,
+    /// Protocols offered with ALPN, such as "h2" and "http/1.1"
+    pub alpn_protocols: Vec<String>,
+    /// Address to connect to instead of resolving `host`
+    pub dst_ip: Option<IpAddr>,
+    pub connect_timeout: Duration,
+    /// Timeout of every read and write of the handshake
+    pub receive_timeout: Duration,
+}
+
+impl TlsProber {
+    /// Create new TlsProber instance for `host:port`
+    pub fn new(host: &str, port: u16) -> Result<TlsProber, String> {
+        let host: String = host.trim_matches(['[', ']']).to_ascii_lowercase();
+        if host.is_empty() {
+            return Err("TlsProber::new: missing host".to_string());
+        }
+        let prober = TlsProber {
+            server_name: host.clone(),
+            host,
+            port,
+            alpn_protocols: Vec::new(),
+            dst_ip: None,
+            connect_timeout: Duration::from_secs(5),
+            receive_timeout: Duration::from_secs(5),
+        };
+        Ok(prober)
+    }
+    pub fn with_server_name(mut self, server_name: &str) -> TlsProber {
+        self.server_name = server_name.to_ascii_lowercase();
+        self
+    }
+    pub fn with_alpn_protocols(mut self, alpn_protocols: Vec<String>) -> TlsProber {
+        self.alpn_protocols = alpn_protocols;
+        self
+    }
+    pub fn with_dst_ip(mut self, dst_ip: IpAddr) -> TlsProber {
+        self.dst_ip = Some(dst_ip);
+        self
+    }
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> TlsProber {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+    pub fn with_receive_timeout(mut self, receive_timeout: Duration) -> TlsProber {
+        self.receive_timeout = receive_timeout;
+        self
+    }
+    /// Run probe
+    pub fn probe(&self) -> Result<TlsProbeResult, String> {
+        let server_name: ServerName<'static> = ServerName::try_from(self.server_name.clone())
+            .map_err(|e| {
+                format!(
+                    "TlsProber::probe: invalid server name {}: {}",
+                    self.server_name, e
+                )
+            })?;
+        let provider: Arc<CryptoProvider> = Arc::new(rustls::crypto::ring::default_provider());
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let webpki_verifier =
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .map_err(|e| format!("TlsProber::probe: {}", e))?;
+        let verifier = Arc::new(RecordingVerifier {
+            inner: webpki_verifier,
+            chain: Mutex::new(Vec::new()),
+            verify_error: Mutex::new(None),
+        });
+        let mut config: ClientConfig = ClientConfig::builder_with_provider(provider)
+            .with_protocol_versions(&[&rustls::version::TLS13, &rustls::version::TLS12])
+            .map_err(|e| format!("TlsProber::probe: {}", e))?
+            .dangerous()
+            .with_custom_certificate_verifier(verifier.clone())
+            .with_no_client_auth();
+        config.alpn_protocols = self
+            .alpn_protocols
+            .iter()
+            .map(|p| p.as_bytes().to_vec())
+            .collect();
+
+        let mut result = TlsProbeResult::new();
+        result.port = self.port;
+        result.server_name = self.server_name.clone();
+        result.start_time = crate::sys::get_sysdate();
+        let start_time = Instant::now();
+        match self.handshake(Arc::new(config), server_name, &mut result) {
+            Ok(()) => {}
+            Err(probe_status) => result.probe_status = probe_status,
+        }
+        if let Ok(chain) = verifier.chain.lock() {
+            result.certificates = chain
+                .iter()
+                .filter_map(|der| certificate_info(der))
+                .collect();
+        }
+        if let Ok(verify_error) = verifier.verify_error.lock() {
+            result.verify_error = verify_error.clone();
+        }
+        result.verified = !result.certificates.is_empty() && result.verify_error.is_none();
+        result.end_time = crate::sys::get_sysdate();
+        result.elapsed_time = start_time.elapsed();
+        Ok(result)
+    }
+    fn handshake(
+        &self,
+        config: Arc<ClientConfig>,
+        server_name: ServerName<'static>,
+        result: &mut TlsProbeResult,
+    ) -> Result<(), ProbeStatus> {
+        let lookup_start = Instant::now();
+        let ip_addr: IpAddr = match (self.dst_ip, self.host.parse::<IpAddr>()) {
+            (Some(dst_ip), _) => dst_ip,
+            (None, Ok(ip_addr)) => ip_addr,
+            (None, Err(_)) => crate::dns::lookup_host_name(self.host.clone()).ok_or(
+                ProbeStatus::with_error_message(format!("Unable to resolve {}", self.host)),
+            )?,
+        };
+        result.dns_lookup = lookup_start.elapsed();
+        result.ip_addr = ip_addr;
+        let socket_addr = SocketAddr::new(ip_addr, self.port);
+        let connect_start = Instant::now();
+        let mut stream: TcpStream = TcpStream::connect_timeout(&socket_addr, self.connect_timeout)
+            .map_err(|e| io_status(&format!("Connect to {}", socket_addr), e))?;
+        result.tcp_connect = connect_start.elapsed();
+        let _ = stream.set_read_timeout(Some(self.receive_timeout));
+        let _ = stream.set_write_timeout(Some(self.receive_timeout));
+        let mut conn: ClientConnection = ClientConnection::new(config, server_name)
+            .map_err(|e| ProbeStatus::with_error_message(format!("TLS: {}", e)))?;
+        let handshake_start = Instant::now();
+        while conn.is_handshaking() {
+            match conn.complete_io(&mut stream) {
+                Ok((0, 0)) => {
+                    return Err(ProbeStatus::with_error_message(String::from(
+                        "Connection closed during TLS handshake",
+                    )))
+                }
+                Ok(_) => {}
+                Err(e) => return Err(io_status("TLS handshake", e)),
+            }
+        }
+        result.handshake = handshake_start.elapsed();
+        result.version = match conn.protocol_version() {
+            Some(rustls::ProtocolVersion::TLSv1_3) => String::from("TLS 1.3"),
+            Some(rustls::ProtocolVersion::TLSv1_2) => String::from("TLS 1.2"),
+            Some(version) => format!("{:?}", version),
+            None => String::new(),
+        };
+        result.cipher_suite = conn
+            .negotiated_cipher_suite()
+            .map(|suite| format!("{:?}", suite.suite()))
+            .unwrap_or_default();
+        result.alpn_protocol = conn
+            .alpn_protocol()
+            .map(|p| String::from_utf8_lossy(p).to_string());
+        conn.send_close_notify();
+        let _ = conn.complete_io(&mut stream);
+        let _ = stream.shutdown(Shutdown::Both);
+        Ok(())
+    }
+}
+
+/// Verifies the chain with webpki, but only records the outcome so that the handshake
+/// completes whatever the certificate
+#[derive(Debug)]
+struct RecordingVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    chain: Mutex<Vec<CertificateDer<'static>>>,
+    verify_error: Mutex<Option<String>>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Ok(mut chain) = self.chain.lock() {
+            *chain = std::iter::once(end_entity)
+                .chain(intermediates)
+                .map(|der| der.clone().into_owned())
+                .collect();
+        }
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        );
+        if let (Err(e), Ok(mut verify_error)) = (verified, self.verify_error.lock()) {
+            *verify_error = Some(e.to_string());
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Decode a DER certificate. None if it is malformed.
+pub fn certificate_info(der: &[u8]) -> Option<CertificateInfo> {
+    let (_, cert): (_, X509Certificate) = x509_parser::parse_x509_certificate(der).ok()?;
+    let subject_alt_names: Vec<String> = match cert.subject_alternative_name() {
+        Ok(Some(san)) => san
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(dns_name) => Some(format!("DNS:{}", dns_name)),
+                GeneralName::IPAddress(octets) => {
+                    ip_from_octets(octets).map(|ip| format!("IP:{}", ip))
+                }
+                GeneralName::RFC822Name(email) => Some(format!("email:{}", email)),
+                GeneralName::URI(uri) => Some(format!("URI:{}", uri)),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let not_before: i64 = cert.validity().not_before.timestamp();
+    let not_after: i64 = cert.validity().not_after.timestamp();
+    let rfc3339 = |timestamp: i64| -> String {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default()
+    };
+    Some(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        serial_number: cert.raw_serial_as_string(),
+        subject_alt_names,
+        not_before: rfc3339(not_before),
+        not_after: rfc3339(not_after),
+        days_to_expiry: (not_after - chrono::Utc::now().timestamp()).div_euclid(86400),
+        is_ca: cert.is_ca(),
+    })
+}
+
+fn ip_from_octets(octets: &[u8]) -> Option<IpAddr> {
+    match octets.len() {
+        4 => <[u8; 4]>::try_from(octets).ok().map(IpAddr::from),
+        16 => <[u8; 16]>::try_from(octets).ok().map(IpAddr::from),
+        _ => None,
+    }
+}
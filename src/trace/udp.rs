@@ -1,4 +1,5 @@
-use crate::result::PortStatus;
+use crate::packet::decode::IcmpError;
+use crate::result::UnreachableReason;
 use crate::result::{NodeType, ProbeResult, ProbeStatus, TracerouteResult};
 use crate::setting::{ProbeSetting, Protocol};
 use std::net::IpAddr;
@@ -80,6 +81,9 @@ pub(crate) fn udp_trace(
                                             },
                                             sent_packet_size: udp_packet.len(),
                                             received_packet_size: packet.len(),
+                                            reply_frame: None,
+                                            unreachable_reason: None,
+                                            service: None,
                                         };
                                         responses.push(probe_result.clone());
                                         match msg_tx.lock() {
@@ -92,22 +96,34 @@ pub(crate) fn udp_trace(
                                         break;
                                     }
                                     IcmpType::DestinationUnreachable => {
+                                        // Errors about other datagrams do not end the trace
+                                        let reason: Option<UnreachableReason> = match IcmpError::from_frame(&frame) {
+                                            Some(icmp_error) if icmp_error.quotes_probe(setting) => icmp_error.unreachable_reason(),
+                                            _ => continue,
+                                        };
                                         let probe_result: ProbeResult = ProbeResult {
                                             seq: seq_ttl,
                                             mac_addr: mac_addr,
                                             ip_addr: IpAddr::V4(ipv4_header.source),
                                             host_name: ipv4_header.source.to_string(),
                                             port_number: setting.dst_port,
-                                            port_status: Some(PortStatus::Closed),
+                                            port_status: reason.map(|r| r.port_status(&Protocol::UDP)),
                                             ttl: ipv4_header.ttl,
                                             hop: crate::ip::guess_initial_ttl(ipv4_header.ttl)
                                                 - ipv4_header.ttl,
                                             rtt: recv_time,
                                             probe_status: ProbeStatus::new(),
                                             protocol: Protocol::UDP,
-                                            node_type: NodeType::Destination,
+                                            node_type: if IpAddr::V4(ipv4_header.source) == setting.dst_ip {
+                                                NodeType::Destination
+                                            } else {
+                                                NodeType::Relay
+                                            },
                                             sent_packet_size: udp_packet.len(),
                                             received_packet_size: packet.len(),
+                                            reply_frame: None,
+                                            unreachable_reason: reason,
+                                            service: None,
                                         };
                                         responses.push(probe_result.clone());
                                         match msg_tx.lock() {
@@ -154,6 +170,9 @@ pub(crate) fn udp_trace(
                                             },
                                             sent_packet_size: udp_packet.len(),
                                             received_packet_size: packet.len(),
+                                            reply_frame: None,
+                                            unreachable_reason: None,
+                                            service: None,
                                         };
                                         responses.push(probe_result.clone());
                                         match msg_tx.lock() {
@@ -166,13 +185,18 @@ pub(crate) fn udp_trace(
                                         break;
                                     }
                                     Icmpv6Type::DestinationUnreachable => {
+                                        // Errors about other datagrams do not end the trace
+                                        let reason: Option<UnreachableReason> = match IcmpError::from_frame(&frame) {
+                                            Some(icmp_error) if icmp_error.quotes_probe(setting) => icmp_error.unreachable_reason(),
+                                            _ => continue,
+                                        };
                                         let probe_result: ProbeResult = ProbeResult {
                                             seq: seq_ttl,
                                             mac_addr: mac_addr,
                                             ip_addr: IpAddr::V6(ipv6_header.source),
                                             host_name: ipv6_header.source.to_string(),
                                             port_number: setting.dst_port,
-                                            port_status: Some(PortStatus::Closed),
+                                            port_status: reason.map(|r| r.port_status(&Protocol::UDP)),
                                             ttl: ipv6_header.hop_limit,
                                             hop: crate::ip::guess_initial_ttl(
                                                 ipv6_header.hop_limit,
@@ -180,9 +204,16 @@ pub(crate) fn udp_trace(
                                             rtt: recv_time,
                                             probe_status: ProbeStatus::new(),
                                             protocol: Protocol::UDP,
-                                            node_type: NodeType::Destination,
+                                            node_type: if IpAddr::V6(ipv6_header.source) == setting.dst_ip {
+                                                NodeType::Destination
+                                            } else {
+                                                NodeType::Relay
+                                            },
                                             sent_packet_size: udp_packet.len(),
                                             received_packet_size: packet.len(),
+                                            reply_frame: None,
+                                            unreachable_reason: reason,
+                                            service: None,
                                         };
                                         responses.push(probe_result.clone());
                                         match msg_tx.lock() {
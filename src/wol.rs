@@ -0,0 +1,166 @@
+use std::net::IpAddr;
+use std::time::Instant;
+use xenet::net::interface::Interface;
+use xenet::net::mac::MacAddr;
+
+use crate::neighbor::DeviceResolver;
+use crate::result::{ProbeStatus, ProbeStatusKind, WakeOnLanResult};
+use crate::setting::ProbeSetting;
+pub use crate::setting::WakeOnLanMethod;
+
+/// Wake-on-LAN structure.
+///
+/// Sends magic packets to a target MAC and optionally verifies wake-up with ARP/NDP.
+#[derive(Clone, Debug)]
+pub struct WakeOnLan {
+    /// Probe Setting
+    pub probe_setting: ProbeSetting,
+    /// MAC address of the host to wake
+    pub target_mac: MacAddr,
+    pub method: WakeOnLanMethod,
+    /// SecureOn password (4 or 6 bytes)
+    pub password: Option<Vec<u8>>,
+    /// IP address of the target. If set, the host is ARP/NDP-pinged until it answers
+    pub verify_ip: Option<IpAddr>,
+}
+
+impl WakeOnLan {
+    /// Create new WakeOnLan instance with setting
+    pub fn new(setting: ProbeSetting, target_mac: MacAddr) -> Result<WakeOnLan, String> {
+        // Check interface
+        if crate::interface::get_interface_by_index(setting.if_index).is_none()
+            && crate::interface::get_interface_by_name(setting.if_name.clone()).is_none()
+        {
+            return Err(format!(
+                "WakeOnLan::new: unable to get interface. index: {}, name: {}",
+                setting.if_index, setting.if_name
+            ));
+        }
+        let wol = WakeOnLan {
+            probe_setting: setting,
+            target_mac,
+            method: WakeOnLanMethod::Ethernet,
+            password: None,
+            verify_ip: None,
+        };
+        Ok(wol)
+    }
+    /// Set delivery method
+    pub fn with_method(mut self, method: WakeOnLanMethod) -> WakeOnLan {
+        self.method = method;
+        self
+    }
+    /// Set SecureOn password
+    pub fn with_password(mut self, password: Vec<u8>) -> WakeOnLan {
+        self.password = Some(password);
+        self
+    }
+    /// Verify wake-up by ARP (IPv4) or NDP (IPv6) until the host answers or probe_timeout elapses
+    pub fn with_verify_ip(mut self, verify_ip: IpAddr) -> WakeOnLan {
+        self.verify_ip = Some(verify_ip);
+        self
+    }
+    /// Send magic packets
+    pub fn wake(&self) -> Result<WakeOnLanResult, String> {
+        run_wake_on_lan(self)
+    }
+}
+
+fn run_wake_on_lan(wol: &WakeOnLan) -> Result<WakeOnLanResult, String> {
+    let setting: &ProbeSetting = &wol.probe_setting;
+    if let Some(password) = &wol.password {
+        if password.len() != 4 && password.len() != 6 {
+            return Err(format!(
+                "run_wake_on_lan: SecureOn password must be 4 or 6 bytes, got {}",
+                password.len()
+            ));
+        }
+    }
+    if wol.method == WakeOnLanMethod::Udp && !setting.dst_ip.is_ipv4() {
+        return Err("run_wake_on_lan: UDP method requires IPv4 destination".to_string());
+    }
+    let interface: Interface = match crate::interface::get_interface_by_index(setting.if_index) {
+        Some(interface) => interface,
+        None => {
+            return Err(format!(
+                "run_wake_on_lan: unable to get interface by index {}",
+                setting.if_index
+            ))
+        }
+    };
+    let config = xenet::datalink::Config {
+        write_buffer_size: 4096,
+        read_buffer_size: 4096,
+        read_timeout: Some(setting.receive_timeout),
+        write_timeout: None,
+        channel_type: xenet::datalink::ChannelType::Layer2,
+        bpf_fd_attempts: 1000,
+        linux_fanout: None,
+        promiscuous: false,
+    };
+    // Create a channel to send packet
+    let mut tx = match xenet::datalink::channel(&interface, config) {
+        Ok(xenet::datalink::Channel::Ethernet(tx, _rx)) => tx,
+        Ok(_) => return Err("run_wake_on_lan: unable to create channel".to_string()),
+        Err(e) => return Err(format!("run_wake_on_lan: unable to create channel: {}", e)),
+    };
+    let mut result = WakeOnLanResult::new();
+    result.target_mac = wol.target_mac;
+    result.method = wol.method;
+    result.start_time = crate::sys::get_sysdate();
+    let start_time = Instant::now();
+    let password: Option<&[u8]> = wol.password.as_deref();
+    let packet: Vec<u8> = match wol.method {
+        WakeOnLanMethod::Ethernet => {
+            crate::packet::wol::build_wol_ethernet_packet(setting.clone(), wol.target_mac, password)
+        }
+        WakeOnLanMethod::Udp => {
+            crate::packet::wol::build_wol_udp_packet(setting.clone(), wol.target_mac, password)
+        }
+    };
+    result.sent_packet_size = packet.len();
+    for seq in 1..setting.count + 1 {
+        match tx.send(&packet) {
+            Some(Ok(_)) => result.sent_count += 1,
+            Some(Err(e)) => {
+                return Err(format!("run_wake_on_lan: unable to send packet: {}", e));
+            }
+            None => {}
+        }
+        if seq < setting.count {
+            std::thread::sleep(setting.send_rate);
+        }
+    }
+    if let Some(verify_ip) = wol.verify_ip {
+        let verify_setting: ProbeSetting = match verify_ip {
+            IpAddr::V4(ipv4) => ProbeSetting::arp(interface, ipv4, 1)?,
+            IpAddr::V6(ipv6) => ProbeSetting::ndp(interface, ipv6, 1)?,
+        };
+        let resolver: DeviceResolver = DeviceResolver::new(verify_setting)?;
+        // The host may take a while to boot. Keep probing until it answers.
+        loop {
+            // Receive timeouts are reported as results too, only a reply from the target counts
+            let reply = resolver.resolve().ok().and_then(|resolve_result| {
+                resolve_result.results.into_iter().find(|r| {
+                    r.probe_status.kind == ProbeStatusKind::Done && r.mac_addr == wol.target_mac
+                })
+            });
+            if let Some(reply) = reply {
+                result.wake_time = Some(Instant::now().duration_since(start_time));
+                result.verify_result = Some(reply);
+                break;
+            }
+            if Instant::now().duration_since(start_time) > setting.probe_timeout {
+                result.probe_status = ProbeStatus::with_timeout_message(format!(
+                    "Host {} did not answer after wake-up",
+                    verify_ip
+                ));
+                break;
+            }
+            std::thread::sleep(setting.send_rate);
+        }
+    }
+    result.end_time = crate::sys::get_sysdate();
+    result.elapsed_time = Instant::now().duration_since(start_time);
+    Ok(result)
+}